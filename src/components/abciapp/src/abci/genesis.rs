@@ -0,0 +1,213 @@
+//!
+//! # Genesis export and import
+//!
+//! `findorad export-genesis` dumps the state of a stopped node into the `app_state`
+//! of a tendermint genesis file, the ledger, the staking and the EVM state included,
+//! then a new chain started from that file imports the state in `InitChain`.
+//!
+//! It is used by hard-fork upgrades and by local networks forked from mainnet,
+//! the heights of the exported state are rebased on the new chain.
+//!
+//! A forked network, created by `fn dev create --fork-from`, also replaces
+//! the validators of the exported state with the validators of its genesis file.
+//!
+
+use {
+    crate::abci::staking::validator_limit,
+    abci::ValidatorUpdate,
+    baseapp::BaseApp,
+    ledger::{
+        staking::{td_addr_to_string, td_pubkey_to_td_addr},
+        store::{genesis::LedgerGenesis, LedgerState},
+    },
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    serde_json::{json, Value},
+    std::{collections::BTreeMap, fs, path::Path},
+    zei::xfr::sig::XfrPublicKey,
+};
+
+/// Version of the genesis format, bump it on incompatible changes.
+pub const GENESIS_VERSION: u64 = 1;
+
+/// The `app_state` of an exported genesis file.
+#[derive(Serialize, Deserialize)]
+pub struct AppGenesis {
+    /// version of the format
+    pub version: u64,
+    /// the height at which the state was exported
+    pub height: u64,
+    /// UTXOs, asset types and staking
+    pub ledger: LedgerGenesis,
+    /// module name => state of the module
+    pub modules: BTreeMap<String, Value>,
+    /// only set by a local network forked from the exported state
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fork: Option<ForkGenesis>,
+}
+
+/// The settings of a local network forked from an exported state.
+#[derive(Serialize, Deserialize)]
+pub struct ForkGenesis {
+    /// The ids of the new validators, they are paired in turn with
+    /// the validators of the genesis file sorted by their addresses.
+    pub validator_ids: Vec<XfrPublicKey>,
+}
+
+impl AppGenesis {
+    /// Export the state of the node stored in `ledger_dir`,
+    /// the node must be stopped.
+    ///
+    /// Only the latest state is kept by the node, so the export height
+    /// must be the current one, roll back the node to export an older state.
+    pub fn export(ledger_dir: &str, height: Option<u64>) -> Result<Self> {
+        let ledger = LedgerState::load_or_init(ledger_dir).c(d!())?;
+
+        let cur = ledger.get_tendermint_height();
+        if let Some(h) = height.filter(|h| *h != cur) {
+            return Err(eg!(format!(
+                "the node is at height {}, run `{} {}` first",
+                cur, "findorad node --snapshot-rollback-to-exact", h
+            )));
+        }
+        let chain_state = BaseApp::chain_state_height(Path::new(ledger_dir)).c(d!())?;
+        if chain_state != cur {
+            return Err(eg!(format!(
+                "the chain state is at height {}, but {} in the ledger, {}",
+                chain_state, cur, "run `findorad doctor` first"
+            )));
+        }
+
+        let modules = BaseApp::export_genesis(Path::new(ledger_dir))
+            .c(d!())?
+            .into_iter()
+            .map(|(name, gs)| {
+                let v = if gs.is_empty() {
+                    Value::Null
+                } else {
+                    serde_json::from_slice(&gs).c(d!())?
+                };
+                Ok((name, v))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(AppGenesis {
+            version: GENESIS_VERSION,
+            height: cur,
+            ledger: ledger.export_genesis().c(d!())?,
+            modules,
+            fork: None,
+        })
+    }
+
+    /// Write the genesis file of the new chain to `output`, the other fields,
+    /// such as the chain id, are taken from the genesis file `template`.
+    pub fn write_tendermint_genesis(&self, template: &str, output: &str) -> Result<()> {
+        let mut genesis = fs::read(template)
+            .c(d!())
+            .and_then(|g| serde_json::from_slice::<Value>(&g).c(d!()))?;
+
+        let mut vs = self
+            .ledger
+            .staking
+            .get_validators()
+            .c(d!())?
+            .body
+            .values()
+            .filter(|v| 0 < v.td_power)
+            .collect::<Vec<_>>();
+        vs.sort_by(|a, b| b.td_power.cmp(&a.td_power));
+        let validators = vs
+            .into_iter()
+            .take(validator_limit(1))
+            .map(|v| {
+                json!({
+                    "address": td_addr_to_string(&v.td_addr),
+                    "pub_key": {
+                        "type": "tendermint/PubKeyEd25519",
+                        "value": base64::encode(&v.td_pubkey),
+                    },
+                    "power": v.td_power.to_string(),
+                    "name": v.memo.name,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let g = genesis.as_object_mut().c(d!("invalid genesis file"))?;
+        g.insert("validators".to_owned(), Value::Array(validators));
+        g.insert("app_hash".to_owned(), Value::String(String::new()));
+        g.insert("app_state".to_owned(), serde_json::to_value(self).c(d!())?);
+
+        serde_json::to_vec_pretty(&genesis)
+            .c(d!())
+            .and_then(|g| fs::write(output, g).c(d!()))
+    }
+
+    /// Parse the `app_state` of `InitChain`,
+    /// `None` if the chain does not start from an exported state.
+    pub fn from_app_state(app_state: &[u8]) -> Result<Option<Self>> {
+        let v = if app_state.is_empty() {
+            Value::Null
+        } else {
+            serde_json::from_slice::<Value>(app_state).c(d!())?
+        };
+        match &v {
+            Value::Null => return Ok(None),
+            Value::String(s) if s.is_empty() => return Ok(None),
+            Value::Object(m) if m.is_empty() => return Ok(None),
+            _ => {}
+        }
+
+        let genesis = serde_json::from_value::<AppGenesis>(v).c(d!())?;
+        if GENESIS_VERSION != genesis.version {
+            return Err(eg!(format!(
+                "unsupported genesis version: {}, expected {}",
+                genesis.version, GENESIS_VERSION
+            )));
+        }
+        Ok(Some(genesis))
+    }
+
+    /// Import the state into a fresh node,
+    /// it is committed along with the first block.
+    ///
+    /// `validators` are the validators in the genesis file.
+    pub fn import(
+        mut self,
+        validators: &[ValidatorUpdate],
+        ledger: &mut LedgerState,
+        app: &mut BaseApp,
+    ) -> Result<()> {
+        if let Some(fork) = self.fork.take() {
+            let mut td_pubkeys = validators
+                .iter()
+                .map(|v| v.get_pub_key().get_data().to_vec())
+                .collect::<Vec<_>>();
+            td_pubkeys.sort_by_key(|pk| td_pubkey_to_td_addr(pk));
+            if fork.validator_ids.len() != td_pubkeys.len() {
+                return Err(eg!(format!(
+                    "{} validator ids for {} validators",
+                    fork.validator_ids.len(),
+                    td_pubkeys.len()
+                )));
+            }
+            self.ledger
+                .staking
+                .replace_validators(
+                    fork.validator_ids.into_iter().zip(td_pubkeys).collect(),
+                )
+                .c(d!())?;
+        }
+
+        let modules = self
+            .modules
+            .into_iter()
+            .filter(|(_, v)| !v.is_null())
+            .map(|(name, v)| Ok((name, serde_json::to_vec(&v).c(d!())?)))
+            .collect::<Result<BTreeMap<_, _>>>()?;
+
+        ledger.import_genesis(self.ledger).c(d!())?;
+        app.import_genesis(&modules).c(d!())
+    }
+}
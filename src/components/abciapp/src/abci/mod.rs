@@ -5,9 +5,12 @@
 #![deny(warnings)]
 #![allow(clippy::needless_borrow)]
 
+pub mod genesis;
 mod server;
 pub mod staking;
 
+pub use server::ABCISubmissionServer;
+
 use {
     crate::api::{
         query_server::query_api, submission_server::submission_api::SubmissionApi,
@@ -47,7 +50,12 @@ pub fn run() -> Result<()> {
     env::set_var("BNC_DATA_DIR", format!("{}/__bnc__", &config.ledger_dir));
 
     if CFG.enable_query_service {
-        env::set_var("FINDORAD_KEEP_HIST", "1");
+        if CFG.disable_api_cache {
+            // the histories may still be built by standalone indexers
+            env::set_var("FINDORAD_KEEP_HIST_EVENTS", "1");
+        } else {
+            env::set_var("FINDORAD_KEEP_HIST", "1");
+        }
     }
 
     let app = server::ABCISubmissionServer::new(
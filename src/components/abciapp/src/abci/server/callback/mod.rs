@@ -6,9 +6,15 @@ mod utils;
 
 use {
     crate::{
-        abci::{server::ABCISubmissionServer, staking, IN_SAFE_ITV, IS_EXITING, POOL},
+        abci::{
+            genesis::AppGenesis, server::ABCISubmissionServer, staking, IN_SAFE_ITV,
+            IS_EXITING, POOL,
+        },
         api::{
-            query_server::BLOCK_CREATED,
+            query_server::{
+                APP_HASH_PARTS, APP_HASH_PARTS_KEPT, BLOCK_CREATED, STAKING_HIST_EVENTS,
+                STAKING_HIST_EVENTS_KEPT,
+            },
             submission_server::{convert_tx, try_tx_catalog, TxCatalog},
         },
     },
@@ -19,11 +25,11 @@ use {
         ResponseEndBlock, ResponseInfo, ResponseInitChain, ResponseQuery,
     },
     config::abci::global_cfg::CFG,
-    fp_storage::hash::{Sha256, StorageHasher},
     lazy_static::lazy_static,
     ledger::{
         converter::is_convert_account,
-        staking::KEEP_HIST,
+        data_model::AppHashParts,
+        staking::{StakingHistEvents, KEEP_HIST, KEEP_HIST_EVENTS},
         store::{
             api_cache,
             fbnc::{new_mapx, Mapx},
@@ -77,8 +83,7 @@ pub fn info(s: &mut ABCISubmissionServer, req: &RequestInfo) -> ResponseInfo {
     let mut la = s.la.write();
     let state = la.get_committed_state().write();
 
-    let commitment = state.get_state_commitment();
-    let la_hash = commitment.0.as_ref().to_vec();
+    let commitment = state.get_state_commitment().0;
 
     let h = state.get_tendermint_height() as i64;
     TENDERMINT_BLOCK_HEIGHT.swap(h, Ordering::Relaxed);
@@ -87,10 +92,14 @@ pub fn info(s: &mut ABCISubmissionServer, req: &RequestInfo) -> ResponseInfo {
         if CFG.checkpoint.disable_evm_block_height < h
             && h < CFG.checkpoint.enable_frc20_height
         {
-            resp.set_last_block_app_hash(la_hash);
+            resp.set_last_block_app_hash(commitment.0.as_ref().to_vec());
         } else {
             let cs_hash = s.account_base_app.write().info(req).last_block_app_hash;
-            resp.set_last_block_app_hash(app_hash("info", h, la_hash, cs_hash));
+            let parts = AppHashParts {
+                ledger: commitment,
+                evm: cs_hash,
+            };
+            resp.set_last_block_app_hash(app_hash("info", h, &parts));
         }
     }
 
@@ -113,7 +122,25 @@ pub fn init_chain(
     s: &mut ABCISubmissionServer,
     req: &RequestInitChain,
 ) -> ResponseInitChain {
-    s.account_base_app.write().init_chain(req)
+    let resp = s.account_base_app.write().init_chain(req);
+
+    // start from an exported state, if any
+    if let Some(genesis) = pnk!(AppGenesis::from_app_state(req.get_app_state_bytes())) {
+        info!(
+            target: "abciapp",
+            "======== Import the genesis state exported at height {} ========",
+            genesis.height
+        );
+        let la = s.la.write();
+        let mut ledger = la.get_committed_state().write();
+        pnk!(genesis.import(
+            req.get_validators(),
+            &mut ledger,
+            &mut s.account_base_app.write()
+        ));
+    }
+
+    resp
 }
 
 /// any new tx will trigger this callback before it can enter the mem-pool of tendermint
@@ -430,7 +457,15 @@ pub fn commit(s: &mut ABCISubmissionServer, req: &RequestCommit) -> ResponseComm
     state.set_tendermint_height(td_height as u64);
 
     // cache last block for QueryServer
-    pnk!(api_cache::update_api_cache(&mut state));
+    let hist_events = StakingHistEvents::take();
+    pnk!(api_cache::update_api_cache(&mut state, &hist_events));
+
+    // keep the staking changes for indexers, only for the latest heights
+    if *KEEP_HIST_EVENTS {
+        let mut events = STAKING_HIST_EVENTS.write();
+        events.insert(td_height, hist_events);
+        events.remove(&(td_height - STAKING_HIST_EVENTS_KEPT));
+    }
 
     // snapshot them finally
     let path = format!("{}/{}", &CFG.ledger_dir, &state.get_status().snapshot_file);
@@ -439,15 +474,33 @@ pub fn commit(s: &mut ABCISubmissionServer, req: &RequestCommit) -> ResponseComm
         .and_then(|s| fs::write(&path, s).c(d!(path))));
 
     let mut r = ResponseCommit::new();
-    let la_hash = state.get_state_commitment().0.as_ref().to_vec();
+    let commitment = state.get_state_commitment().0;
     let cs_hash = s.account_base_app.write().commit(req).data;
 
-    if CFG.checkpoint.disable_evm_block_height < td_height
+    let parts = if CFG.checkpoint.disable_evm_block_height < td_height
         && td_height < CFG.checkpoint.enable_frc20_height
     {
-        r.set_data(la_hash);
+        let parts = AppHashParts {
+            ledger: commitment,
+            evm: vec![],
+        };
+        r.set_data(parts.app_hash());
+        parts
     } else {
-        r.set_data(app_hash("commit", td_height, la_hash, cs_hash));
+        let parts = AppHashParts {
+            ledger: commitment,
+            evm: cs_hash,
+        };
+        r.set_data(app_hash("commit", td_height, &parts));
+        parts
+    };
+
+    // keep the preimage of the app hash for light clients,
+    // only for the latest heights
+    {
+        let mut app_hash_parts = APP_HASH_PARTS.write();
+        app_hash_parts.insert(td_height, parts);
+        app_hash_parts.remove(&(td_height - APP_HASH_PARTS_KEPT));
     }
 
     IN_SAFE_ITV.store(false, Ordering::Release);
@@ -574,25 +627,14 @@ pub fn commit(s: &mut ABCISubmissionServer, req: &RequestCommit) -> ResponseComm
 
 /// Combines ledger state hash and EVM chain state hash
 /// and print app hashes for debugging
-fn app_hash(
-    when: &str,
-    height: i64,
-    mut la_hash: Vec<u8>,
-    mut cs_hash: Vec<u8>,
-) -> Vec<u8> {
+fn app_hash(when: &str, height: i64, parts: &AppHashParts) -> Vec<u8> {
     info!(target: "abciapp",
         "app_hash_{}: {}_{}, height: {}",
         when,
-        hex::encode(la_hash.clone()),
-        hex::encode(cs_hash.clone()),
+        hex::encode(parts.ledger.0.as_ref()),
+        hex::encode(&parts.evm),
         height
     );
 
-    // append ONLY non-empty EVM chain state hash
-    if !cs_hash.is_empty() {
-        la_hash.append(&mut cs_hash);
-        Sha256::hash(la_hash.as_slice()).to_vec()
-    } else {
-        la_hash
-    }
+    parts.app_hash()
 }
@@ -22,7 +22,7 @@ use {
                 governance::{governance_penalty_tendermint_auto, ByzantineKind},
                 mint_fra::{MintEntry, MintFraOps, MintKind},
             },
-            td_addr_to_string, Staking, VALIDATOR_UPDATE_BLOCK_ITV,
+            td_addr_to_string, BlockHeight, Staking, VALIDATOR_UPDATE_BLOCK_ITV,
         },
         store::LedgerState,
     },
@@ -41,6 +41,16 @@ const VALIDATOR_LIMIT: usize = 58;
 // Modify the validator's validator line to 100
 const VALIDATOR_LIMIT_V2: usize = 100;
 
+// The max number of official validators at a specified height.
+#[inline(always)]
+pub(crate) fn validator_limit(h: BlockHeight) -> usize {
+    if CFG.checkpoint.validators_limit_v2_height > h {
+        VALIDATOR_LIMIT
+    } else {
+        VALIDATOR_LIMIT_V2
+    }
+}
+
 lazy_static! {
     /// Tendermint node address, sha256(pubkey)[:20]
     pub static ref TD_NODE_SELF_ADDR: Vec<u8> = pnk!(whoami::get_self_addr());
@@ -125,16 +135,11 @@ pub fn get_validators(
     // reverse sort
     vs.sort_by(|a, b| b.1.cmp(&a.1));
 
-    let validator_limit =
-        if CFG.checkpoint.validators_limit_v2_height > staking.cur_height() {
-            VALIDATOR_LIMIT
-        } else {
-            VALIDATOR_LIMIT_V2
-        };
+    let limit = validator_limit(staking.cur_height());
 
     // set the power of every extra validators to zero,
     // then tendermint can remove them from consensus logic.
-    vs.iter_mut().skip(validator_limit).for_each(|(k, power)| {
+    vs.iter_mut().skip(limit).for_each(|(k, power)| {
         alt!(cur_entries.contains_key(k), *power = 0, *power = -1);
     });
 
@@ -279,7 +284,7 @@ fn system_governance(staking: &mut Staking, bz: &ByzantineInfo) -> Result<()> {
     governance_penalty_tendermint_auto(staking, bz.addr, &kind).c(d!())
 }
 
-/// Pay for freed 'Delegations' and 'FraDistributions'.
+/// Pay for freed 'Delegations', 'FraDistributions' and released 'Vestings'.
 pub fn system_mint_pay(
     la: &LedgerState,
     account_base_app: &mut AccountBaseApp,
@@ -322,6 +327,9 @@ pub fn system_mint_pay(
                     MintEntry::new(MintKind::Claim, k, None, n, ASSET_TYPE_FRA)
                 }),
         )
+        .chain(staking.vesting_get_plan().iter().map(|(k, n)| {
+            MintEntry::new(MintKind::Vesting, *k, None, *n, ASSET_TYPE_FRA)
+        }))
         .take(NUM_TO_PAY)
         .collect::<Vec<_>>();
 
@@ -345,6 +353,18 @@ pub fn system_mint_pay(
 
     mint_entries.append(&mut mints);
 
+    if let Some(account_mint) = account_base_app.consume_confidential_mint() {
+        mint_entries.extend(account_mint.into_iter().map(|mint| {
+            MintEntry::with_record(
+                MintKind::Other,
+                mint.record.public_key,
+                mint.amount_proof.amount,
+                mint.record,
+                mint.owner_memo,
+            )
+        }));
+    }
+
     if mint_entries.is_empty() {
         None
     } else {
@@ -376,14 +396,9 @@ fn gen_offline_punish_list(
         .collect::<Vec<_>>();
     vs.sort_by(|a, b| b.1.cmp(&a.1));
 
-    let validator_limit =
-        if CFG.checkpoint.validators_limit_v2_height > staking.cur_height() {
-            VALIDATOR_LIMIT
-        } else {
-            VALIDATOR_LIMIT_V2
-        };
+    let limit = validator_limit(staking.cur_height());
 
-    vs.iter_mut().skip(validator_limit).for_each(|(_, power)| {
+    vs.iter_mut().skip(limit).for_each(|(_, power)| {
         *power = 0;
     });
 
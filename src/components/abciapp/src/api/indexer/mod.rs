@@ -0,0 +1,594 @@
+//!
+//! # Standalone indexer
+//!
+//! Follows the committed transactions of a node through its ledger service,
+//! builds the same indices as the `ApiCache` of the node into its own store,
+//! and serves `QueryServerRoutes` with them,
+//! so the node itself can run without history indexing.
+//!
+//! Transactions are indexed by their `TxnSID` in order,
+//! the progress is stored, so the indexer can be restarted at any time.
+//! A new indexer can start from a block height instead of the genesis,
+//! the history before it is not indexed.
+//!
+//! The staking histories can not be built from the transactions,
+//! they are built from the staking changes the node records at every commit,
+//! block by block as the `ApiCache` of the node does.
+//! The node keeps the changes of its latest `STAKING_HIST_EVENTS_KEPT` heights,
+//! a new indexer starts from the last committed height of the node.
+//! The rewards of delegations are not indexed.
+//!
+
+use {
+    super::query_server::query_api::{
+        indexed_routes,
+        ledger_api::{ping, ApiRoutes},
+        server::IndexedData,
+        version, QueryServerRoutes,
+    },
+    actix_cors::Cors,
+    actix_web::{middleware, web, App, HttpServer},
+    finutils::api::{NetworkRoute, StakingValidators},
+    globutils::wallet,
+    ledger::{
+        data_model::{
+            FinalizedTransaction, Operation, TxnSID, TxoRef, TxoSID, XfrAddress,
+        },
+        staking::{BlockHeight, StakingHistEvents, TendermintAddrRef},
+        store::{
+            api_cache::ApiCache,
+            fbnc::{self, new_mapx, Mapx},
+        },
+    },
+    parking_lot::RwLock,
+    ruc::*,
+    std::{collections::HashSet, sync::Arc, thread, time::Duration},
+    tracing::info,
+    zei::xfr::sig::XfrPublicKey,
+};
+
+const PREFIX: &str = "indexer_";
+const NEXT_TXN_SID: &str = "next_txn_sid";
+const NEXT_STAKING_HEIGHT: &str = "next_staking_height";
+const COMMITS: &str = "commits";
+
+/// The indices and the progress of indexing
+pub struct Indexer {
+    /// url of the ledger service of a node, eg. `http://127.0.0.1:8668`
+    node: String,
+    cache: ApiCache,
+    progress: Mapx<String, u64>,
+    /// the validators of the last poll
+    validators: StakingValidators,
+}
+
+impl Indexer {
+    /// Open the store of the indexer, or create a new one.
+    pub fn new(node: &str) -> Self {
+        Indexer {
+            node: node.trim_end_matches('/').to_owned(),
+            cache: ApiCache::new(PREFIX),
+            progress: new_mapx!("indexer/progress"),
+            validators: StakingValidators::default(),
+        }
+    }
+
+    /// No transaction has been indexed
+    #[inline(always)]
+    pub fn is_new(&self) -> bool {
+        self.progress.get(&NEXT_TXN_SID.to_owned()).is_none()
+    }
+
+    /// Start indexing from a transaction instead of the genesis,
+    /// only a new indexer can be moved.
+    pub fn start_from(&mut self, sid: TxnSID) -> Result<()> {
+        if !self.is_new() {
+            return Err(eg!(format!(
+                "already indexed up to transaction {}",
+                self.next_txn_sid().0
+            )));
+        }
+        self.progress.insert(NEXT_TXN_SID.to_owned(), sid.0 as u64);
+        Ok(())
+    }
+
+    /// The next transaction to be indexed
+    #[inline(always)]
+    pub fn next_txn_sid(&self) -> TxnSID {
+        TxnSID(self.progress.get(&NEXT_TXN_SID.to_owned()).unwrap_or(0) as usize)
+    }
+
+    /// Index a committed transaction, it must be the next one.
+    pub fn index_txn(&mut self, ftx: &FinalizedTransaction) -> Result<()> {
+        if ftx.tx_id != self.next_txn_sid() {
+            return Err(eg!(format!(
+                "expected transaction {}, got {}",
+                self.next_txn_sid().0,
+                ftx.tx_id.0
+            )));
+        }
+
+        let addresses = txo_owners(ftx)
+            .into_iter()
+            .map(|key| XfrAddress { key })
+            .collect::<Vec<_>>();
+        if addresses.len() != ftx.txo_ids.len() {
+            return Err(eg!(format!(
+                "outputs of transaction {} mismatch",
+                ftx.tx_id.0
+            )));
+        }
+
+        self.cache
+            .cache_txn(ftx.tx_id, &ftx.txn, &ftx.txo_ids, &addresses);
+        self.progress
+            .insert(NEXT_TXN_SID.to_owned(), ftx.tx_id.0 as u64 + 1);
+
+        Ok(())
+    }
+
+    /// The next height whose staking changes are to be indexed,
+    /// `None` if no height has been indexed.
+    #[inline(always)]
+    pub fn next_staking_height(&self) -> Option<BlockHeight> {
+        self.progress.get(&NEXT_STAKING_HEIGHT.to_owned())
+    }
+
+    /// Index the staking changes of a height, it must be the next one.
+    pub fn index_staking(
+        &mut self,
+        height: BlockHeight,
+        events: &StakingHistEvents,
+    ) -> Result<()> {
+        if let Some(h) = self.next_staking_height().filter(|h| *h != height) {
+            return Err(eg!(format!(
+                "expected the staking changes of height {h}, got {height}"
+            )));
+        }
+
+        self.cache.cache_hist_data(events);
+        self.progress
+            .insert(NEXT_STAKING_HEIGHT.to_owned(), height + 1);
+
+        Ok(())
+    }
+
+    /// Update the validators to look up.
+    #[inline(always)]
+    pub fn set_validators(&mut self, validators: StakingValidators) {
+        self.validators = validators;
+    }
+
+    // An error is returned if the data does not exist on the node,
+    // eg. the node has pruned it or has not caught up.
+    fn get<T: serde::de::DeserializeOwned>(&self, path: &str) -> Result<T> {
+        self.try_get(path)
+            .c(d!())?
+            .c(d!(format!("{path} not found on {}", self.node)))
+    }
+
+    // Same as `get`, but `None` is returned if the data does not exist yet.
+    fn try_get<T: serde::de::DeserializeOwned>(&self, path: &str) -> Result<Option<T>> {
+        let resp = attohttpc::get(format!("{}{}", self.node, path))
+            .send()
+            .c(d!())?;
+        if resp.status() == attohttpc::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        resp.error_for_status()
+            .c(d!())?
+            .bytes()
+            .c(d!())
+            .and_then(|b| serde_json::from_slice(&b).c(d!()))
+            .map(Some)
+    }
+}
+
+impl IndexedData for Indexer {
+    #[inline(always)]
+    fn api_cache(&self) -> Option<&ApiCache> {
+        Some(&self.cache)
+    }
+
+    fn get_transaction_light(&self, sid: TxnSID) -> Result<FinalizedTransaction> {
+        self.get(&ApiRoutes::TxnSidLight.with_arg(&sid.0)).c(d!())
+    }
+
+    fn get_owned_utxos(&self, pk: &XfrPublicKey) -> Result<HashSet<TxoSID>> {
+        self.get(
+            &QueryServerRoutes::GetOwnedUtxos
+                .with_arg(&wallet::public_key_to_base64(pk)),
+        )
+        .c(d!())
+    }
+
+    #[inline(always)]
+    fn get_commits(&self) -> u64 {
+        self.progress.get(&COMMITS.to_owned()).unwrap_or(0)
+    }
+
+    #[inline(always)]
+    fn get_staking_height(&self) -> BlockHeight {
+        self.next_staking_height().unwrap_or(1).saturating_sub(1)
+    }
+
+    fn get_validator_start(
+        &self,
+        addr: TendermintAddrRef,
+    ) -> Result<(XfrPublicKey, BlockHeight)> {
+        self.validators
+            .validators
+            .iter()
+            .find(|v| v.addr == addr)
+            .map(|v| (v.id, v.start_height))
+            .c(d!("Not a validator or non-existing node address"))
+    }
+}
+
+// Owners of the outputs which are not spent in the same transaction,
+// in the order of `txo_ids`.
+fn txo_owners(ftx: &FinalizedTransaction) -> Vec<XfrPublicKey> {
+    let mut txos = vec![];
+    for op in ftx.txn.body.operations.iter() {
+        match op {
+            Operation::TransferAsset(t) => {
+                for i in t.body.inputs.iter() {
+                    if let TxoRef::Relative(offs) = i {
+                        if let Some(ix) = txos.len().checked_sub(1 + *offs as usize) {
+                            txos[ix] = None;
+                        }
+                    }
+                }
+                txos.extend(t.body.outputs.iter().map(|o| Some(o.record.public_key)));
+            }
+            Operation::IssueAsset(i) => {
+                txos.extend(
+                    i.body
+                        .records
+                        .iter()
+                        .map(|(o, _)| Some(o.record.public_key)),
+                );
+            }
+            Operation::MintFra(i) => {
+                txos.extend(i.entries.iter().map(|e| Some(e.utxo.record.public_key)));
+            }
+            _ => {}
+        }
+    }
+    txos.into_iter().flatten().collect()
+}
+
+// Index all the transactions and the staking changes committed by the node,
+// the node is not locked while fetching them.
+fn sync(indexer: &RwLock<Indexer>) -> Result<()> {
+    let commits = indexer
+        .read()
+        .get::<(serde_json::Value, u64, String)>(&ApiRoutes::GlobalState.route())
+        .c(d!())?
+        .1;
+    let validators = indexer
+        .read()
+        .get::<StakingValidators>(&ApiRoutes::StakingValidators.route())
+        .c(d!())?;
+
+    loop {
+        let sid = indexer.read().next_txn_sid();
+        let ftx = indexer
+            .read()
+            .try_get::<FinalizedTransaction>(&ApiRoutes::TxnSidLight.with_arg(&sid.0))
+            .c(d!())?;
+        if let Some(ftx) = ftx {
+            indexer.write().index_txn(&ftx).c(d!())?;
+        } else {
+            break;
+        }
+    }
+
+    // a new indexer starts from the last committed height
+    let mut h = indexer
+        .read()
+        .next_staking_height()
+        .unwrap_or_else(|| validators.height.saturating_sub(1));
+    while h <= validators.height {
+        let events = indexer
+            .read()
+            .try_get::<StakingHistEvents>(&ApiRoutes::StakingHistEvents.with_arg(&h))
+            .c(d!())?;
+        match events {
+            Some(events) => indexer.write().index_staking(h, &events).c(d!())?,
+            // the last height may be queried before its changes are kept
+            None if h == validators.height => break,
+            None => {
+                return Err(eg!(format!(
+                    "the staking changes of height {h} are not kept by the node"
+                )));
+            }
+        }
+        h += 1;
+    }
+
+    let mut indexer = indexer.write();
+    indexer.set_validators(validators);
+    // all the transactions of these blocks have been indexed
+    indexer.progress.insert(COMMITS.to_owned(), commits);
+    fbnc::flush_data();
+
+    Ok(())
+}
+
+/// Follow the node every `itv` seconds,
+/// and serve `QueryServerRoutes` at `addrs`.
+///
+/// A new indexer starts from the ledger block `start_height` if it is set.
+pub fn start_indexer(
+    node: &str,
+    itv: u64,
+    start_height: Option<u64>,
+    addrs: &[(&str, u16)],
+) -> Result<()> {
+    let mut indexer = Indexer::new(node);
+    if let Some(h) = start_height.filter(|_| indexer.is_new()) {
+        let sid = indexer
+            .get::<TxnSID>(&ApiRoutes::BlockTxnSid.with_arg(&h))
+            .c(d!())?;
+        indexer.start_from(sid).c(d!())?;
+    }
+    let indexer = Arc::new(RwLock::new(indexer));
+    info!(
+        "Indexing from transaction {}",
+        indexer.read().next_txn_sid().0
+    );
+
+    let hdr = Arc::clone(&indexer);
+    thread::spawn(move || loop {
+        if let Err(e) = sync(&hdr) {
+            e.print(None);
+        }
+        thread::sleep(Duration::from_secs(itv));
+    });
+
+    let sys = actix_rt::System::new("findora indexer");
+
+    let mut srv = HttpServer::new(move || {
+        App::new()
+            .wrap(middleware::Logger::default())
+            .wrap(Cors::permissive().supports_credentials())
+            .data(Arc::clone(&indexer))
+            .route("/ping", web::get().to(ping))
+            .route("/version", web::get().to(version))
+            .configure(indexed_routes::<Indexer>)
+    });
+
+    for (host, port) in addrs.iter() {
+        srv = srv.bind(&format!("{host}:{port}")).c(d!())?
+    }
+
+    srv.run();
+
+    info!("Indexer started");
+
+    sys.run().c(d!())
+}
+
+#[cfg(test)]
+#[allow(missing_docs)]
+mod test {
+    use {
+        super::*,
+        finutils::{
+            api::ValidatorStart,
+            txn_builder::{TransactionBuilder, TransferOperationBuilder},
+        },
+        ledger::data_model::{TransferType, ASSET_TYPE_FRA},
+        rand_chacha::ChaChaRng,
+        rand_core::SeedableRng,
+        zei::{
+            setup::PublicParams,
+            xfr::{
+                asset_record::{
+                    build_blind_asset_record, open_blind_asset_record,
+                    AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType,
+                },
+                sig::XfrKeyPair,
+                structs::{AssetRecordTemplate, OpenAssetRecord},
+            },
+        },
+    };
+
+    const NODE: &str = "http://127.0.0.1:8668";
+
+    fn transfer(
+        prng: &mut ChaChaRng,
+        input: TxoRef,
+        oar: OpenAssetRecord,
+        from: &XfrKeyPair,
+        outputs: &[(XfrPublicKey, u64)],
+    ) -> Operation {
+        let mut op = TransferOperationBuilder::new();
+        let am = oar.amount;
+        pnk!(op.add_input_with_rng(prng, input, oar, None, None, am));
+        for (pk, n) in outputs.iter() {
+            let template = AssetRecordTemplate::with_no_asset_tracing(
+                *n,
+                ASSET_TYPE_FRA,
+                NonConfidentialAmount_NonConfidentialAssetType,
+                *pk,
+            );
+            pnk!(op.add_output_with_rng(prng, &template, None, None, None));
+        }
+        pnk!(op
+            .create_with_rng(prng, TransferType::Standard)
+            .and_then(|op| op.sign(from))
+            .and_then(|op| op.transaction()))
+    }
+
+    #[test]
+    fn test_index_txn() {
+        fbnc::clear();
+        let mut prng = ChaChaRng::seed_from_u64(0);
+        let params = PublicParams::default();
+        let alice = XfrKeyPair::generate(&mut prng);
+        let bob = XfrKeyPair::generate(&mut prng);
+        let carol = XfrKeyPair::generate(&mut prng);
+
+        let ar = AssetRecordTemplate::with_no_asset_tracing(
+            1000,
+            ASSET_TYPE_FRA,
+            NonConfidentialAmount_NonConfidentialAssetType,
+            alice.get_pk(),
+        );
+        let (ba, _, memo) =
+            build_blind_asset_record(&mut prng, &params.pc_gens, &ar, vec![]);
+        let oar = pnk!(open_blind_asset_record(&ba, &memo, &alice));
+
+        // alice pays bob, then pays her change to carol in the same transaction
+        let op1 = transfer(
+            &mut prng,
+            TxoRef::Absolute(TxoSID(0)),
+            oar,
+            &alice,
+            &[(bob.get_pk(), 600), (alice.get_pk(), 400)],
+        );
+        let change = match &op1 {
+            Operation::TransferAsset(t) => t.body.outputs[1].record.clone(),
+            _ => unreachable!(),
+        };
+        let oar = pnk!(open_blind_asset_record(&change, &None, &alice));
+        let op2 = transfer(
+            &mut prng,
+            TxoRef::Relative(0),
+            oar,
+            &alice,
+            &[(carol.get_pk(), 400)],
+        );
+
+        let mut builder = TransactionBuilder::from_seq_id_with_rng(&mut prng, 1);
+        builder.add_operation(op1).add_operation(op2).sign(&alice);
+        let ftx = FinalizedTransaction {
+            txn: builder.take_transaction(),
+            tx_id: TxnSID(0),
+            txo_ids: vec![TxoSID(1), TxoSID(2)],
+            merkle_id: 0,
+        };
+
+        // the change spent by the second transfer is not an output
+        assert_eq!(txo_owners(&ftx), vec![bob.get_pk(), carol.get_pk()]);
+
+        let mut indexer = Indexer::new(NODE);
+        assert!(indexer.is_new());
+
+        // transactions are indexed in order
+        let mut later = ftx.clone();
+        later.tx_id = TxnSID(1);
+        assert!(indexer.index_txn(&later).is_err());
+
+        // every output must have an owner
+        let mut short = ftx.clone();
+        short.txo_ids.pop();
+        assert!(indexer.index_txn(&short).is_err());
+        assert!(indexer.is_new());
+
+        pnk!(indexer.index_txn(&ftx));
+        assert_eq!(indexer.next_txn_sid(), TxnSID(1));
+        assert!(indexer.index_txn(&ftx).is_err());
+        assert!(indexer.start_from(TxnSID(5)).is_err());
+
+        let cache = pnk!(indexer.api_cache());
+        assert_eq!(
+            cache.utxos_to_map_index.get(&TxoSID(1)),
+            Some(XfrAddress { key: bob.get_pk() })
+        );
+        assert_eq!(
+            cache.utxos_to_map_index.get(&TxoSID(2)),
+            Some(XfrAddress {
+                key: carol.get_pk()
+            })
+        );
+        for kp in [&alice, &bob, &carol] {
+            let related = pnk!(cache
+                .related_transactions
+                .get(&XfrAddress { key: kp.get_pk() }));
+            assert!(related.get(&TxnSID(0)).is_some());
+        }
+    }
+
+    #[test]
+    fn test_start_from() {
+        fbnc::clear();
+        let mut indexer = Indexer::new(NODE);
+        pnk!(indexer.start_from(TxnSID(5)));
+        assert!(!indexer.is_new());
+        assert_eq!(indexer.next_txn_sid(), TxnSID(5));
+        assert!(indexer.start_from(TxnSID(6)).is_err());
+    }
+
+    #[test]
+    fn test_index_staking() {
+        fbnc::clear();
+        let id = XfrKeyPair::generate(&mut ChaChaRng::seed_from_u64(0)).get_pk();
+        let addr = "5F2E3C4D5E6F708192A3B4C5D6E7F8091A2B3C4D".to_owned();
+
+        let mut indexer = Indexer::new(NODE);
+        assert_eq!(indexer.next_staking_height(), None);
+        pnk!(indexer.index_staking(
+            10,
+            &StakingHistEvents {
+                global_rate: vec![(10, [1, 10])],
+                self_delegation: vec![(id, 10, 100)],
+                commission: vec![(id, 10, [1, 100])],
+                ..Default::default()
+            }
+        ));
+
+        // heights are indexed in order
+        assert!(indexer
+            .index_staking(12, &StakingHistEvents::default())
+            .is_err());
+        pnk!(indexer.index_staking(11, &StakingHistEvents::default()));
+        pnk!(indexer.index_staking(
+            12,
+            &StakingHistEvents {
+                global_rate: vec![(12, [2, 10])],
+                delegation_amount: vec![(id, 12, 50)],
+                ..Default::default()
+            }
+        ));
+        assert_eq!(indexer.next_staking_height(), Some(13));
+        assert_eq!(indexer.get_staking_height(), 12);
+
+        let cache = pnk!(indexer.api_cache());
+        assert_eq!(
+            cache.staking_global_rate_hist.iter().collect::<Vec<_>>(),
+            vec![(10, [1, 10]), (12, [2, 10])]
+        );
+        assert_eq!(
+            pnk!(cache.staking_delegation_amount_hist.get(&id))
+                .iter()
+                .collect::<Vec<_>>(),
+            vec![(12, 50)]
+        );
+        assert_eq!(
+            pnk!(cache.staking_self_delegation_hist.get(&id))
+                .iter()
+                .collect::<Vec<_>>(),
+            vec![(10, 100)]
+        );
+        assert_eq!(
+            pnk!(cache.staking_commission_hist.get(&id))
+                .iter()
+                .collect::<Vec<_>>(),
+            vec![(10, [1, 100])]
+        );
+
+        indexer.set_validators(StakingValidators {
+            height: 12,
+            validators: vec![ValidatorStart {
+                addr: addr.clone(),
+                id,
+                start_height: 1,
+            }],
+        });
+        assert_eq!(pnk!(indexer.get_validator_start(&addr)), (id, 1));
+        assert!(indexer.get_validator_start("unknown").is_err());
+    }
+}
@@ -2,6 +2,9 @@
 //! # Services provided by api
 //!
 
+/// Index the history of a node out of process
+pub mod indexer;
+
 /// Provide query service for ledgerState
 pub mod query_server;
 
@@ -6,3 +6,9 @@ pub mod query_api;
 
 /// used to notify `query server` to do updating
 pub use query_api::server::BLOCK_CREATED;
+
+/// used to prove ledger state commitments to light clients
+pub use query_api::server::{APP_HASH_PARTS, APP_HASH_PARTS_KEPT};
+
+/// used to follow the staking histories by standalone indexers
+pub use query_api::server::{STAKING_HIST_EVENTS, STAKING_HIST_EVENTS_KEPT};
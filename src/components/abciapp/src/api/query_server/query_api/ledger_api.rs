@@ -3,22 +3,24 @@
 //!
 
 use {
-    super::server::QueryServer,
+    super::server::{IndexedData, QueryServer, APP_HASH_PARTS, STAKING_HIST_EVENTS},
     actix_web::{error, web},
     config::abci::global_cfg::CFG,
     finutils::api::{
-        DelegationInfo, DelegatorInfo, DelegatorList, NetworkRoute, Validator,
-        ValidatorDetail, ValidatorList,
+        DelegationInfo, DelegatorInfo, DelegatorList, NetworkRoute, StakingValidators,
+        Validator, ValidatorDetail, ValidatorList, ValidatorStart, VestingEntry,
+        VestingInfo,
     },
     globutils::HashOf,
     ledger::{
         data_model::{
-            AssetType, AssetTypeCode, AuthenticatedUtxo, StateCommitmentData, TxnSID,
-            TxoSID, UnAuthenticatedUtxo, Utxo,
+            AppHashParts, AssetType, AssetTypeCode, AuthenticatedUtxo, BlockSID,
+            StateCommitmentData, TxnSID, TxoSID, UnAuthenticatedUtxo, Utxo,
         },
         staking::{
-            DelegationRwdDetail, DelegationState, Staking, TendermintAddr,
-            TendermintAddrRef,
+            DelegationRwdDetail, DelegationState, Staking, StakingHistEvents,
+            TendermintAddr, TendermintAddrRef, ValidatorData,
+            COMMISSION_RATE_MAX_CHANGE,
         },
     },
     parking_lot::RwLock,
@@ -158,6 +160,10 @@ pub async fn query_txn(
         if let Ok(mut txn) = ruc::info!(ledger.get_transaction(TxnSID(txn_sid))) {
             txn.finalized_txn.set_txo_id();
             Ok(serde_json::to_string(&txn)?)
+        } else if ledger.is_pruned_txn(TxnSID(txn_sid)) {
+            Err(actix_web::error::ErrorGone(
+                "Specified transaction has been pruned.",
+            ))
         } else {
             Err(actix_web::error::ErrorNotFound(
                 "Specified transaction does not exist.",
@@ -181,6 +187,10 @@ pub async fn query_txn_light(
         if let Ok(mut txn) = ruc::info!(ledger.get_transaction_light(TxnSID(txn_sid))) {
             txn.set_txo_id();
             Ok(serde_json::to_string(&txn)?)
+        } else if ledger.is_pruned_txn(TxnSID(txn_sid)) {
+            Err(actix_web::error::ErrorGone(
+                "Specified transaction has been pruned.",
+            ))
         } else {
             Err(actix_web::error::ErrorNotFound(
                 "Specified transaction does not exist.",
@@ -193,6 +203,71 @@ pub async fn query_txn_light(
     }
 }
 
+/// query the first `TxnSID` committed at or after a block height
+pub async fn query_block_txn_sid(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    height: web::Path<usize>,
+) -> actix_web::Result<web::Json<TxnSID>> {
+    let qs = data.read();
+    let ledger = &qs.ledger_cloned;
+    if let Some(sid) = ledger.get_first_txn_since(BlockSID(*height)) {
+        Ok(web::Json(sid))
+    } else if ledger.is_pruned_block(BlockSID(*height)) {
+        Err(actix_web::error::ErrorGone(
+            "Specified block has been pruned.",
+        ))
+    } else {
+        Err(actix_web::error::ErrorNotFound(
+            "Specified block does not exist.",
+        ))
+    }
+}
+
+/// query the validators of current height
+pub async fn query_staking_validators(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+) -> web::Json<StakingValidators> {
+    let qs = data.read();
+    let staking = qs.ledger_cloned.get_staking();
+
+    let validators = staking
+        .validator_get_current()
+        .map(|vd| {
+            vd.get_validator_addr_map()
+                .iter()
+                .filter_map(|(addr, pk)| {
+                    let d = staking.delegation_get(pk)?;
+                    Some(ValidatorStart {
+                        addr: addr.clone(),
+                        id: *pk,
+                        start_height: d.start_height,
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    web::Json(StakingValidators {
+        height: staking.cur_height(),
+        validators,
+    })
+}
+
+/// query the staking changes committed at a tendermint height,
+/// indexers build the staking histories with them,
+/// only the latest `STAKING_HIST_EVENTS_KEPT` heights are kept
+pub async fn query_staking_hist_events(
+    height: web::Path<i64>,
+) -> actix_web::Result<web::Json<StakingHistEvents>> {
+    if let Some(events) = STAKING_HIST_EVENTS.read().get(&height) {
+        Ok(web::Json(events))
+    } else {
+        Err(error::ErrorNotFound(
+            "Specified height is not recorded by this node.",
+        ))
+    }
+}
+
 /// query global state, return (apphash, block count, apphash and block count signatures)
 #[allow(clippy::type_complexity)]
 pub async fn query_global_state(
@@ -216,6 +291,21 @@ pub async fn query_global_state_version(
     web::Json(hash)
 }
 
+/// query the parts of the app hash committed at a tendermint height,
+/// light clients verify them with the header of the next height,
+/// only the latest `APP_HASH_PARTS_KEPT` heights are kept
+pub async fn query_app_hash_parts(
+    height: web::Path<i64>,
+) -> actix_web::Result<web::Json<AppHashParts>> {
+    if let Some(parts) = APP_HASH_PARTS.read().get(&height) {
+        Ok(web::Json(parts))
+    } else {
+        Err(error::ErrorNotFound(
+            "Specified height is not committed by this node.",
+        ))
+    }
+}
+
 /// Query current validator list,
 /// validtors who have not completed self-deletagion will be filtered out.
 #[allow(unused)]
@@ -261,6 +351,20 @@ pub async fn query_validators(
     Ok(web::Json(ValidatorList::new(0, vec![])))
 }
 
+/// Query the data of the current validators,
+/// including their power and the rule of co-signatures.
+pub async fn query_validator_data(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+) -> actix_web::Result<web::Json<ValidatorData>> {
+    let qs = data.read();
+    qs.ledger_cloned
+        .get_staking()
+        .validator_get_current()
+        .cloned()
+        .map(web::Json)
+        .ok_or_else(|| error::ErrorNotFound("no validators"))
+}
+
 #[allow(missing_docs)]
 #[derive(Deserialize, Debug)]
 pub struct DelegationRwdQueryParams {
@@ -284,9 +388,7 @@ pub async fn get_delegation_reward(
         .ledger_cloned
         .api_cache
         .as_ref()
-        .unwrap()
-        .staking_delegation_rwd_hist
-        .get(&key)
+        .and_then(|api| api.staking_delegation_rwd_hist.get(&key))
         .c(d!())
         .map_err(|e| error::ErrorNotFound(e.to_string()))?;
 
@@ -319,46 +421,28 @@ pub struct ValidatorDelegation {
 }
 
 /// get history according to `ValidatorDelegationQueryParams`
-pub async fn get_validator_delegation_history(
-    data: web::Data<Arc<RwLock<QueryServer>>>,
+pub async fn get_validator_delegation_history<S: IndexedData>(
+    data: web::Data<Arc<RwLock<S>>>,
     web::Query(info): web::Query<ValidatorDelegationQueryParams>,
 ) -> actix_web::Result<web::Json<Vec<ValidatorDelegation>>> {
-    let qs = data.read();
-    let ledger = &qs.ledger_cloned;
-    let staking = ledger.get_staking();
+    let server = data.read();
 
-    let v_id = staking
-        .validator_td_addr_to_app_pk(&info.address)
+    let (v_id, start_height) = server
+        .get_validator_start(&info.address)
         .c(d!())
         .map_err(error::ErrorBadRequest)?;
 
-    let h = staking.cur_height();
+    let h = server.get_staking_height();
 
-    let start_height = staking
-        .delegation_get(&v_id)
-        .ok_or_else(|| error::ErrorBadRequest("not exists"))?
-        .start_height;
+    let api_cache = server
+        .api_cache()
+        .ok_or_else(|| error::ErrorNotFound("history is not kept on this node"))?;
 
-    let staking_global_rate_hist = &qs
-        .ledger_cloned
-        .api_cache
-        .as_ref()
-        .unwrap()
-        .staking_global_rate_hist;
+    let staking_global_rate_hist = &api_cache.staking_global_rate_hist;
 
-    let delegation_amount_hist = ledger
-        .api_cache
-        .as_ref()
-        .unwrap()
-        .staking_delegation_amount_hist
-        .get(&v_id);
+    let delegation_amount_hist = api_cache.staking_delegation_amount_hist.get(&v_id);
 
-    let self_delegation_amount_hist = ledger
-        .api_cache
-        .as_ref()
-        .unwrap()
-        .staking_self_delegation_hist
-        .get(&v_id);
+    let self_delegation_amount_hist = api_cache.staking_self_delegation_hist.get(&v_id);
 
     let mut esiz = info.epoch_size.unwrap_or(10);
     alt!(esiz > h, esiz = h);
@@ -556,12 +640,24 @@ pub async fn query_validator_detail(
                     .ok_or_else(|| error::ErrorBadRequest("not exists"))?;
                 fra_rewards += delegation.rwd_amount;
             }
+
+            let commission_hist = ledger
+                .api_cache
+                .as_ref()
+                .and_then(|api| api.staking_commission_hist.get(&v_pub_key))
+                .map(|hist| hist.iter().collect())
+                .unwrap_or_default();
+
             let resp = ValidatorDetail {
                 addr: addr.into_inner(),
                 is_online: v.signed_last_block,
                 voting_power: v.td_power,
                 voting_power_rank,
                 commission_rate,
+                max_commission_rate: v.get_max_commission_rate(),
+                commission_max_change: COMMISSION_RATE_MAX_CHANGE,
+                commission_next_change_height: v.commission_next_change_height(),
+                commission_hist,
                 self_staking: v_self_delegation
                     .delegations
                     .iter()
@@ -676,6 +772,66 @@ pub async fn query_delegation_info(
     Ok(web::Json(resp))
 }
 
+/// query vesting info according to `public_key`
+pub async fn query_vesting_info(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    address: web::Path<String>,
+) -> actix_web::Result<web::Json<VestingInfo>> {
+    let pk = globutils::wallet::public_key_from_base64(address.as_str())
+        .c(d!())
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+
+    let qs = data.read();
+    let ledger = &qs.ledger_cloned;
+    let staking = ledger.get_staking();
+    let h = staking.cur_height();
+
+    let mut resp = VestingInfo {
+        pending_payment: staking.vesting_get_plan().get(&pk).copied().unwrap_or(0),
+        current_height: h,
+        ..Default::default()
+    };
+
+    for v in staking.vesting_get(&pk).into_iter().flatten() {
+        resp.total += v.amount;
+        resp.vested += v.vested(h);
+        resp.unvested += v.unvested(h);
+        resp.released += v.released;
+        resp.delegated += v.delegated;
+        resp.releasable += v.releasable(h);
+        resp.entries.push(VestingEntry {
+            creator: globutils::wallet::public_key_to_base64(&v.creator),
+            amount: v.amount,
+            vested: v.vested(h),
+            released: v.released,
+            delegated: v.delegated,
+            start_height: v.start_height,
+            schedule: v.schedule.clone(),
+        });
+    }
+
+    Ok(web::Json(resp))
+}
+
+/// query the credential public key registered by an issuer address
+pub async fn query_cred_issuer(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    address: web::Path<String>,
+) -> actix_web::Result<String> {
+    let pk = globutils::wallet::public_key_from_base64(address.as_str())
+        .c(d!())
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+
+    let qs = data.read();
+    if let Some(key) = qs.ledger_cloned.get_cred_issuer(&pk) {
+        Ok(serde_json::to_string(&key)?)
+    } else {
+        Err(error::ErrorNotFound(
+            "Specified credential issuer is not registered.",
+        ))
+    }
+}
+
 /// query utxos according `public_key`
 pub async fn query_owned_utxos(
     data: web::Data<Arc<RwLock<QueryServer>>>,
@@ -702,9 +858,16 @@ pub enum ApiRoutes {
     GlobalStateVersion,
     OwnedUtxos,
     ValidatorList,
+    ValidatorData,
     DelegationInfo,
     DelegatorList,
     ValidatorDetail,
+    VestingInfo,
+    CredIssuer,
+    AppHashParts,
+    BlockTxnSid,
+    StakingValidators,
+    StakingHistEvents,
 }
 
 impl NetworkRoute for ApiRoutes {
@@ -721,9 +884,16 @@ impl NetworkRoute for ApiRoutes {
             ApiRoutes::GlobalStateVersion => "global_state_version",
             ApiRoutes::OwnedUtxos => "owned_utxos",
             ApiRoutes::ValidatorList => "validator_list",
+            ApiRoutes::ValidatorData => "validator_data",
             ApiRoutes::DelegationInfo => "delegation_info",
             ApiRoutes::DelegatorList => "delegator_list",
             ApiRoutes::ValidatorDetail => "validator_detail",
+            ApiRoutes::VestingInfo => "vesting_info",
+            ApiRoutes::CredIssuer => "cred_issuer",
+            ApiRoutes::AppHashParts => "app_hash_parts",
+            ApiRoutes::BlockTxnSid => "block_txn_sid",
+            ApiRoutes::StakingValidators => "staking_validators",
+            ApiRoutes::StakingHistEvents => "staking_hist_events",
         };
         "/".to_owned() + endpoint
     }
@@ -27,7 +27,7 @@ use {
     parking_lot::RwLock,
     ruc::*,
     serde::{Deserialize, Serialize},
-    server::QueryServer,
+    server::{IndexedData, QueryServer},
     std::{
         collections::{BTreeMap, HashSet},
         sync::Arc,
@@ -51,8 +51,8 @@ pub async fn version() -> actix_web::Result<String> {
 
 /// Queries the status of a transaction by its handle. Returns either a not committed message or a
 /// serialized TxnStatus.
-pub async fn get_address(
-    data: web::Data<Arc<RwLock<QueryServer>>>,
+pub async fn get_address<S: IndexedData>(
+    data: web::Data<Arc<RwLock<S>>>,
     info: web::Path<u64>,
 ) -> actix_web::Result<String, actix_web::error::Error> {
     let server = data.read();
@@ -67,8 +67,8 @@ pub async fn get_address(
 
 /// Returns the owner memo required to decrypt the asset record stored at given index, if it exists.
 #[allow(clippy::unnecessary_wraps)]
-pub async fn get_owner_memo(
-    data: web::Data<Arc<RwLock<QueryServer>>>,
+pub async fn get_owner_memo<S: IndexedData>(
+    data: web::Data<Arc<RwLock<S>>>,
     info: web::Path<u64>,
 ) -> actix_web::Result<web::Json<Option<OwnerMemo>>, actix_web::error::Error> {
     let server = data.read();
@@ -77,8 +77,8 @@ pub async fn get_owner_memo(
 
 /// Separate a string of `TxoSID` by ',' and query the corresponding memo
 #[allow(clippy::unnecessary_wraps)]
-pub async fn get_owner_memo_batch(
-    data: web::Data<Arc<RwLock<QueryServer>>>,
+pub async fn get_owner_memo_batch<S: IndexedData>(
+    data: web::Data<Arc<RwLock<S>>>,
     info: web::Path<String>,
 ) -> actix_web::Result<web::Json<Vec<Option<OwnerMemo>>>, actix_web::error::Error> {
     let ids = info
@@ -95,22 +95,18 @@ pub async fn get_owner_memo_batch(
 }
 
 /// Returns an array of the utxo sids currently spendable by a given address
-pub async fn get_owned_utxos(
-    data: web::Data<Arc<RwLock<QueryServer>>>,
+pub async fn get_owned_utxos<S: IndexedData>(
+    data: web::Data<Arc<RwLock<S>>>,
     owner: web::Path<String>,
 ) -> actix_web::Result<web::Json<HashSet<TxoSID>>> {
     let qs = data.read();
-    let ledger = &qs.ledger_cloned;
 
     let pk = wallet::public_key_from_base64(owner.as_str())
         .map_err(actix_web::error::ErrorServiceUnavailable)?;
 
-    let utxos = ledger
+    let utxos = qs
         .get_owned_utxos(&pk)
-        .map_err(actix_web::error::ErrorServiceUnavailable)?
-        .keys()
-        .copied()
-        .collect();
+        .map_err(actix_web::error::ErrorServiceUnavailable)?;
 
     Ok(web::Json(utxos))
 }
@@ -155,8 +151,8 @@ impl NetworkRoute for QueryServerRoutes {
 }
 
 /// Returns the list of assets created by a public key
-pub async fn get_created_assets(
-    data: web::Data<Arc<RwLock<QueryServer>>>,
+pub async fn get_created_assets<S: IndexedData>(
+    data: web::Data<Arc<RwLock<S>>>,
     info: web::Path<String>,
 ) -> actix_web::Result<web::Json<Vec<DefineAsset>>> {
     // Convert from base64 representation
@@ -173,8 +169,8 @@ pub async fn get_created_assets(
 
 /// Returns the list of records issued by a public key
 #[allow(clippy::type_complexity)]
-pub async fn get_issued_records(
-    data: web::Data<Arc<RwLock<QueryServer>>>,
+pub async fn get_issued_records<S: IndexedData>(
+    data: web::Data<Arc<RwLock<S>>>,
     info: web::Path<String>,
 ) -> actix_web::Result<web::Json<Vec<(TxOutput, Option<OwnerMemo>)>>> {
     // Convert from base64 representation
@@ -191,8 +187,8 @@ pub async fn get_issued_records(
 
 /// Returns the list of records issued by a token code
 #[allow(clippy::type_complexity)]
-pub async fn get_issued_records_by_code(
-    data: web::Data<Arc<RwLock<QueryServer>>>,
+pub async fn get_issued_records_by_code<S: IndexedData>(
+    data: web::Data<Arc<RwLock<S>>>,
     info: web::Path<String>,
 ) -> actix_web::Result<web::Json<Vec<(TxOutput, Option<OwnerMemo>)>>> {
     let server = data.read();
@@ -212,8 +208,8 @@ pub async fn get_issued_records_by_code(
 }
 
 /// Returns authenticated txn sid and hash
-pub async fn get_authenticated_txnid_hash(
-    data: web::Data<Arc<RwLock<QueryServer>>>,
+pub async fn get_authenticated_txnid_hash<S: IndexedData>(
+    data: web::Data<Arc<RwLock<S>>>,
     info: web::Path<u64>,
 ) -> actix_web::Result<web::Json<TxnIDHash>> {
     let server = data.read();
@@ -226,8 +222,8 @@ pub async fn get_authenticated_txnid_hash(
 }
 
 /// Returns txn hash by sid
-pub async fn get_transaction_hash(
-    data: web::Data<Arc<RwLock<QueryServer>>>,
+pub async fn get_transaction_hash<S: IndexedData>(
+    data: web::Data<Arc<RwLock<S>>>,
     info: web::Path<usize>,
 ) -> actix_web::Result<web::Json<String>> {
     let server = data.read();
@@ -240,8 +236,8 @@ pub async fn get_transaction_hash(
 }
 
 /// Returns txn sid by hash
-pub async fn get_transaction_sid(
-    data: web::Data<Arc<RwLock<QueryServer>>>,
+pub async fn get_transaction_sid<S: IndexedData>(
+    data: web::Data<Arc<RwLock<S>>>,
     info: web::Path<String>,
 ) -> actix_web::Result<web::Json<usize>> {
     let server = data.read();
@@ -255,8 +251,8 @@ pub async fn get_transaction_sid(
 
 /// Returns most recent commit count at server side
 /// Check this number to make sure server is in sync
-pub async fn get_commits(
-    data: web::Data<Arc<RwLock<QueryServer>>>,
+pub async fn get_commits<S: IndexedData>(
+    data: web::Data<Arc<RwLock<S>>>,
 ) -> actix_web::Result<web::Json<u64>> {
     let server = data.read();
     Ok(web::Json(server.get_commits()))
@@ -294,8 +290,8 @@ pub struct CoinbaseOperInfo {
 }
 
 /// paging Query delegators according to `WalletQueryParams`
-pub async fn get_coinbase_oper_list(
-    data: web::Data<Arc<RwLock<QueryServer>>>,
+pub async fn get_coinbase_oper_list<S: IndexedData>(
+    data: web::Data<Arc<RwLock<S>>>,
     web::Query(info): web::Query<WalletQueryParams>,
 ) -> actix_web::Result<web::Json<CoinbaseOperInfo>> {
     // Convert from base64 representation
@@ -345,8 +341,8 @@ pub async fn get_coinbase_oper_list(
 }
 
 /// Returns the list of claim transations of a given ledger address
-pub async fn get_claim_txns(
-    data: web::Data<Arc<RwLock<QueryServer>>>,
+pub async fn get_claim_txns<S: IndexedData>(
+    data: web::Data<Arc<RwLock<S>>>,
     web::Query(info): web::Query<WalletQueryParams>,
 ) -> actix_web::Result<web::Json<Vec<Option<Transaction>>>> {
     // Convert from base64 representation
@@ -383,8 +379,8 @@ pub async fn get_claim_txns(
 }
 
 /// Returns the list of transations associated with a given ledger address
-pub async fn get_related_txns(
-    data: web::Data<Arc<RwLock<QueryServer>>>,
+pub async fn get_related_txns<S: IndexedData>(
+    data: web::Data<Arc<RwLock<S>>>,
     info: web::Path<String>,
 ) -> actix_web::Result<web::Json<HashSet<TxnSID>>> {
     // Convert from base64 representation
@@ -401,8 +397,8 @@ pub async fn get_related_txns(
 }
 
 /// Returns the list of transfer transations associated with a given asset
-pub async fn get_related_xfrs(
-    data: web::Data<Arc<RwLock<QueryServer>>>,
+pub async fn get_related_xfrs<S: IndexedData>(
+    data: web::Data<Arc<RwLock<S>>>,
     info: web::Path<String>,
 ) -> actix_web::Result<web::Json<HashSet<TxnSID>>> {
     let server = data.read();
@@ -489,6 +485,74 @@ pub async fn get_checkpoint(
     Ok(web::Json(CFG.checkpoint.clone()))
 }
 
+/// Register the routes of `QueryServerRoutes`,
+/// the paged history of addresses and the delegation history of validators.
+pub fn indexed_routes<S: IndexedData + Send + Sync + 'static>(
+    cfg: &mut web::ServiceConfig,
+) {
+    cfg.route(
+        &QueryServerRoutes::GetAddress.with_arg_template("txo_sid"),
+        web::get().to(get_address::<S>),
+    )
+    .route(
+        &QueryServerRoutes::GetOwnedUtxos.with_arg_template("address"),
+        web::get().to(get_owned_utxos::<S>),
+    )
+    .route(
+        &QueryServerRoutes::GetOwnerMemo.with_arg_template("txo_sid"),
+        web::get().to(get_owner_memo::<S>),
+    )
+    .route(
+        &QueryServerRoutes::GetOwnerMemoBatch.with_arg_template("txo_sid_list"),
+        web::get().to(get_owner_memo_batch::<S>),
+    )
+    .route(
+        &QueryServerRoutes::GetRelatedTxns.with_arg_template("address"),
+        web::get().to(get_related_txns::<S>),
+    )
+    .service(web::resource("claim_history").route(web::get().to(get_claim_txns::<S>)))
+    .service(
+        web::resource("coinbase_history")
+            .route(web::get().to(get_coinbase_oper_list::<S>)),
+    )
+    .service(
+        web::resource("/validator_delegation")
+            .route(web::get().to(get_validator_delegation_history::<S>)),
+    )
+    .route(
+        &QueryServerRoutes::GetRelatedXfrs.with_arg_template("asset_token"),
+        web::get().to(get_related_xfrs::<S>),
+    )
+    .route(
+        &QueryServerRoutes::GetCreatedAssets.with_arg_template("address"),
+        web::get().to(get_created_assets::<S>),
+    )
+    .route(
+        &QueryServerRoutes::GetIssuedRecords.with_arg_template("address"),
+        web::get().to(get_issued_records::<S>),
+    )
+    .route(
+        &QueryServerRoutes::GetIssuedRecordsByCode.with_arg_template("asset_token"),
+        web::get().to(get_issued_records_by_code::<S>),
+    )
+    .route(
+        &QueryServerRoutes::GetAuthencatedTxnIDHash.with_arg_template("txo_sid"),
+        web::get().to(get_authenticated_txnid_hash::<S>),
+    )
+    .route(
+        &QueryServerRoutes::GetTransactionHash.with_arg_template("txn_sid"),
+        web::get().to(get_transaction_hash::<S>),
+    )
+    .route(
+        &QueryServerRoutes::GetTransactionSid.with_arg_template("txn_hash"),
+        web::get().to(get_transaction_sid::<S>),
+    )
+    .route(
+        &QueryServerRoutes::GetCommits.route(),
+        web::get().to(get_commits::<S>),
+    );
+}
+
 /// Structures exposed to the outside world
 pub struct QueryApi;
 
@@ -514,68 +578,7 @@ impl QueryApi {
                     web::resource("circulating_supply")
                         .route(web::get().to(get_circulating_supply)),
                 )
-                .route(
-                    &QueryServerRoutes::GetAddress.with_arg_template("txo_sid"),
-                    web::get().to(get_address),
-                )
-                .route(
-                    &QueryServerRoutes::GetOwnedUtxos.with_arg_template("address"),
-                    web::get().to(get_owned_utxos),
-                )
-                .route(
-                    &QueryServerRoutes::GetOwnerMemo.with_arg_template("txo_sid"),
-                    web::get().to(get_owner_memo),
-                )
-                .route(
-                    &QueryServerRoutes::GetOwnerMemoBatch
-                        .with_arg_template("txo_sid_list"),
-                    web::get().to(get_owner_memo_batch),
-                )
-                .route(
-                    &QueryServerRoutes::GetRelatedTxns.with_arg_template("address"),
-                    web::get().to(get_related_txns),
-                )
-                .service(
-                    web::resource("claim_history").route(web::get().to(get_claim_txns)),
-                )
-                .service(
-                    web::resource("coinbase_history")
-                        .route(web::get().to(get_coinbase_oper_list)),
-                )
-                .route(
-                    &QueryServerRoutes::GetRelatedXfrs.with_arg_template("asset_token"),
-                    web::get().to(get_related_xfrs),
-                )
-                .route(
-                    &QueryServerRoutes::GetCreatedAssets.with_arg_template("address"),
-                    web::get().to(get_created_assets),
-                )
-                .route(
-                    &QueryServerRoutes::GetIssuedRecords.with_arg_template("address"),
-                    web::get().to(get_issued_records),
-                )
-                .route(
-                    &QueryServerRoutes::GetIssuedRecordsByCode
-                        .with_arg_template("asset_token"),
-                    web::get().to(get_issued_records_by_code),
-                )
-                .route(
-                    &QueryServerRoutes::GetAuthencatedTxnIDHash
-                        .with_arg_template("txo_sid"),
-                    web::get().to(get_authenticated_txnid_hash),
-                )
-                .route(
-                    &QueryServerRoutes::GetTransactionHash.with_arg_template("txn_sid"),
-                    web::get().to(get_transaction_hash),
-                )
-                .route(
-                    &QueryServerRoutes::GetTransactionSid.with_arg_template("txn_hash"),
-                    web::get().to(get_transaction_sid),
-                )
-                .route(
-                    &QueryServerRoutes::GetCommits.route(),
-                    web::get().to(get_commits),
-                )
+                .configure(indexed_routes::<QueryServer>)
                 .route(
                     &ApiRoutes::UtxoSid.with_arg_template("sid"),
                     web::get().to(query_utxo),
@@ -620,6 +623,10 @@ impl QueryApi {
                     &ApiRoutes::ValidatorList.route(),
                     web::get().to(query_validators),
                 )
+                .route(
+                    &ApiRoutes::ValidatorData.route(),
+                    web::get().to(query_validator_data),
+                )
                 .route(
                     &ApiRoutes::DelegationInfo.with_arg_template("XfrPublicKey"),
                     web::get().to(query_delegation_info),
@@ -636,14 +643,34 @@ impl QueryApi {
                     web::resource("/delegation_rewards")
                         .route(web::get().to(get_delegation_reward)),
                 )
-                .service(
-                    web::resource("/validator_delegation")
-                        .route(web::get().to(get_validator_delegation_history)),
-                )
                 .route(
                     &ApiRoutes::ValidatorDetail.with_arg_template("NodeAddress"),
                     web::get().to(query_validator_detail),
                 )
+                .route(
+                    &ApiRoutes::VestingInfo.with_arg_template("XfrPublicKey"),
+                    web::get().to(query_vesting_info),
+                )
+                .route(
+                    &ApiRoutes::CredIssuer.with_arg_template("XfrPublicKey"),
+                    web::get().to(query_cred_issuer),
+                )
+                .route(
+                    &ApiRoutes::AppHashParts.with_arg_template("height"),
+                    web::get().to(query_app_hash_parts),
+                )
+                .route(
+                    &ApiRoutes::BlockTxnSid.with_arg_template("height"),
+                    web::get().to(query_block_txn_sid),
+                )
+                .route(
+                    &ApiRoutes::StakingValidators.route(),
+                    web::get().to(query_staking_validators),
+                )
+                .route(
+                    &ApiRoutes::StakingHistEvents.with_arg_template("height"),
+                    web::get().to(query_staking_hist_events),
+                )
                 .service(
                     web::resource("/display_checkpoint")
                         .route(web::get().to(get_checkpoint)),
@@ -6,86 +6,101 @@ use {
     lazy_static::lazy_static,
     ledger::{
         data_model::{
-            AssetTypeCode, DefineAsset, IssuerPublicKey, Transaction, TxOutput,
-            TxnIDHash, TxnSID, TxoSID, XfrAddress,
+            AppHashParts, AssetTypeCode, DefineAsset, FinalizedTransaction,
+            IssuerPublicKey, Transaction, TxOutput, TxnIDHash, TxnSID, TxoSID,
+            XfrAddress,
+        },
+        staking::{
+            ops::mint_fra::MintEntry, BlockHeight, StakingHistEvents, TendermintAddrRef,
+        },
+        store::{
+            api_cache::ApiCache,
+            fbnc::{new_mapx, Mapx},
+            LedgerState,
         },
-        staking::{ops::mint_fra::MintEntry, BlockHeight},
-        store::LedgerState,
     },
     parking_lot::{Condvar, Mutex, RwLock},
     ruc::*,
     std::{collections::HashSet, sync::Arc},
-    zei::xfr::structs::OwnerMemo,
+    zei::xfr::{sig::XfrPublicKey, structs::OwnerMemo},
 };
 
+/// The number of latest heights whose app hash parts are kept,
+/// about one week of blocks
+pub const APP_HASH_PARTS_KEPT: i64 = 40_000;
+
+/// The number of latest heights whose staking changes are kept,
+/// an indexer can not fall behind its node by more than this
+pub const STAKING_HIST_EVENTS_KEPT: i64 = 40_000;
+
 lazy_static! {
     /// the query_server will be notified every time
     /// a block is added to the ledgerState to update the data
     pub static ref BLOCK_CREATED: Arc<(Mutex<bool>, Condvar)> =
         Arc::new((Mutex::new(false), Condvar::new()));
-}
 
-/// A data container for API
-pub struct QueryServer {
-    pub(crate) ledger: Arc<RwLock<LedgerState>>,
-    pub(crate) ledger_cloned: LedgerState,
+    /// the parts of the app hash of the latest `APP_HASH_PARTS_KEPT` heights, for light clients
+    pub static ref APP_HASH_PARTS: Arc<RwLock<Mapx<i64, AppHashParts>>> =
+        Arc::new(RwLock::new(new_mapx!("app_hash_parts")));
+
+    /// the staking changes of the latest `STAKING_HIST_EVENTS_KEPT` heights, for indexers
+    pub static ref STAKING_HIST_EVENTS: Arc<RwLock<Mapx<i64, StakingHistEvents>>> =
+        Arc::new(RwLock::new(new_mapx!("staking_hist_events")));
 }
 
-impl QueryServer {
-    /// create query server
-    pub fn new(ledger: Arc<RwLock<LedgerState>>) -> QueryServer {
-        let ledger_cloned = ledger.read().clone();
-        QueryServer {
-            ledger,
-            ledger_cloned,
-        }
-    }
+/// The data behind `QueryServerRoutes`,
+/// it is provided by the query server of a node, or by a standalone indexer.
+pub trait IndexedData {
+    /// The indices of transactions, `None` if history indexing is disabled.
+    fn api_cache(&self) -> Option<&ApiCache>;
+
+    /// Returns a committed transaction.
+    fn get_transaction_light(&self, sid: TxnSID) -> Result<FinalizedTransaction>;
+
+    /// Returns the utxo sids currently spendable by a given public key.
+    fn get_owned_utxos(&self, pk: &XfrPublicKey) -> Result<HashSet<TxoSID>>;
+
+    /// Returns most recent commits at query_server side.
+    fn get_commits(&self) -> u64;
+
+    /// Returns current height of the staking state.
+    fn get_staking_height(&self) -> BlockHeight;
+
+    /// Returns the id of a validator and the height its delegation starts at.
+    fn get_validator_start(
+        &self,
+        addr: TendermintAddrRef,
+    ) -> Result<(XfrPublicKey, BlockHeight)>;
 
     /// Returns the set of records issued by a certain key.
     #[inline(always)]
-    pub fn get_issued_records(
+    fn get_issued_records(
         &self,
         issuer: &IssuerPublicKey,
     ) -> Option<Vec<(TxOutput, Option<OwnerMemo>)>> {
-        self.ledger_cloned
-            .api_cache
-            .as_ref()
-            .unwrap()
-            .issuances
-            .get(issuer)
+        self.api_cache()?.issuances.get(issuer)
     }
 
     /// Returns the set of records issued by a certain token code.
     #[inline(always)]
-    pub fn get_issued_records_by_code(
+    fn get_issued_records_by_code(
         &self,
         code: &AssetTypeCode,
     ) -> Option<Vec<(TxOutput, Option<OwnerMemo>)>> {
-        self.ledger_cloned
-            .api_cache
-            .as_ref()
-            .unwrap()
-            .token_code_issuances
-            .get(code)
+        self.api_cache()?.token_code_issuances.get(code)
     }
 
     /// return `DefineAsset` according to `IssuerPublicKey`
     #[inline(always)]
-    pub fn get_created_assets(
-        &self,
-        issuer: &IssuerPublicKey,
-    ) -> Option<Vec<DefineAsset>> {
-        self.ledger_cloned
-            .api_cache
-            .as_ref()
-            .unwrap()
+    fn get_created_assets(&self, issuer: &IssuerPublicKey) -> Option<Vec<DefineAsset>> {
+        self.api_cache()?
             .created_assets
             .get(issuer)
             .map(|d| d.iter().map(|(_, v)| v).collect())
     }
 
     /// get coinbase based on address and sorting rules and start and end position
-    pub fn get_coinbase_entries(
+    fn get_coinbase_entries(
         &self,
         address: &XfrAddress,
         start: usize,
@@ -93,12 +108,8 @@ impl QueryServer {
         order_desc: bool,
     ) -> Result<(u64, Vec<(u64, MintEntry)>)> {
         if let Some(hist) = self
-            .ledger_cloned
-            .api_cache
-            .as_ref()
-            .unwrap()
-            .coinbase_oper_hist
-            .get(address)
+            .api_cache()
+            .and_then(|c| c.coinbase_oper_hist.get(address))
         {
             let len = hist.len();
             if len > start {
@@ -139,7 +150,7 @@ impl QueryServer {
     }
 
     /// Returns a list of claim transactions of a given ledger address
-    pub fn get_claim_transactions(
+    fn get_claim_transactions(
         &self,
         address: &XfrAddress,
         start: usize,
@@ -147,12 +158,8 @@ impl QueryServer {
         order_desc: bool,
     ) -> Result<Vec<Option<Transaction>>> {
         if let Some(hist) = self
-            .ledger_cloned
-            .api_cache
-            .as_ref()
-            .unwrap()
-            .claim_hist_txns
-            .get(address)
+            .api_cache()
+            .and_then(|c| c.claim_hist_txns.get(address))
         {
             let len = hist.len();
             if len > start {
@@ -187,9 +194,7 @@ impl QueryServer {
                 return Ok(slice
                     .iter()
                     .map(|h| {
-                        if let Ok(tx) =
-                            ruc::info!(self.ledger_cloned.get_transaction_light(*h))
-                        {
+                        if let Ok(tx) = ruc::info!(self.get_transaction_light(*h)) {
                             Some(tx.txn)
                         } else {
                             None
@@ -210,14 +215,8 @@ impl QueryServer {
     /// 4. Signer of a kv_update txn
     /// 5. Signer of a memo_update txn
     #[inline(always)]
-    pub fn get_related_transactions(
-        &self,
-        address: &XfrAddress,
-    ) -> Option<HashSet<TxnSID>> {
-        self.ledger_cloned
-            .api_cache
-            .as_ref()
-            .unwrap()
+    fn get_related_transactions(&self, address: &XfrAddress) -> Option<HashSet<TxnSID>> {
+        self.api_cache()?
             .related_transactions
             .get(&address)
             .map(|d| d.iter().map(|(k, _)| k).collect())
@@ -226,14 +225,8 @@ impl QueryServer {
     /// Returns the set of transfer transactions that are associated with a given asset.
     /// The asset type must be nonconfidential.
     #[inline(always)]
-    pub fn get_related_transfers(
-        &self,
-        code: &AssetTypeCode,
-    ) -> Option<HashSet<TxnSID>> {
-        self.ledger_cloned
-            .api_cache
-            .as_ref()
-            .unwrap()
+    fn get_related_transfers(&self, code: &AssetTypeCode) -> Option<HashSet<TxnSID>> {
+        self.api_cache()?
             .related_transfers
             .get(&code)
             .map(|d| d.iter().map(|(k, _)| k).collect())
@@ -241,74 +234,55 @@ impl QueryServer {
 
     /// Returns the owner of a given txo_sid.
     #[inline(always)]
-    pub fn get_address_of_sid(&self, txo_sid: TxoSID) -> Option<XfrAddress> {
-        self.ledger_cloned
-            .api_cache
-            .as_ref()
-            .unwrap()
-            .utxos_to_map_index
-            .get(&txo_sid)
+    fn get_address_of_sid(&self, txo_sid: TxoSID) -> Option<XfrAddress> {
+        self.api_cache()?.utxos_to_map_index.get(&txo_sid)
     }
 
     /// Returns the authenticated txn (id, hash) of a given txo_sid.
     #[inline(always)]
-    pub fn get_authenticated_txnid(&self, txo_sid: TxoSID) -> Option<TxnIDHash> {
-        self.ledger_cloned
-            .api_cache
-            .as_ref()
-            .unwrap()
-            .txo_to_txnid
-            .get(&txo_sid)
+    fn get_authenticated_txnid(&self, txo_sid: TxoSID) -> Option<TxnIDHash> {
+        self.api_cache()?.txo_to_txnid.get(&txo_sid)
     }
 
     /// Returns the transaction hash of a given txn_sid.
     #[inline(always)]
-    pub fn get_transaction_hash(&self, txn_sid: TxnSID) -> Option<String> {
-        self.ledger_cloned
-            .api_cache
-            .as_ref()
-            .unwrap()
-            .txn_sid_to_hash
-            .get(&txn_sid)
+    fn get_transaction_hash(&self, txn_sid: TxnSID) -> Option<String> {
+        self.api_cache()?.txn_sid_to_hash.get(&txn_sid)
     }
 
     /// Returns the transaction sid of a given txn_hash.
     #[inline(always)]
-    pub fn get_transaction_sid(&self, txn_hash: String) -> Option<TxnSID> {
-        self.ledger_cloned
-            .api_cache
-            .as_ref()
-            .unwrap()
-            .txn_hash_to_sid
-            .get(&txn_hash)
-    }
-
-    /// Returns most recent commits at query_server side.
-    #[inline(always)]
-    pub fn get_commits(&self) -> u64 {
-        self.ledger_cloned.get_block_commit_count()
+    fn get_transaction_sid(&self, txn_hash: String) -> Option<TxnSID> {
+        self.api_cache()?.txn_hash_to_sid.get(&txn_hash)
     }
 
     /// Returns the owner memo required to decrypt the asset record stored at given index, if it exists.
     #[inline(always)]
-    pub fn get_owner_memo(&self, txo_sid: TxoSID) -> Option<OwnerMemo> {
-        self.ledger_cloned
-            .api_cache
-            .as_ref()
-            .unwrap()
-            .owner_memos
-            .get(&txo_sid)
+    fn get_owner_memo(&self, txo_sid: TxoSID) -> Option<OwnerMemo> {
+        self.api_cache()?.owner_memos.get(&txo_sid)
     }
 
     /// retrieve block reward rate at specified block height
     #[inline(always)]
-    pub fn query_block_rewards_rate(&self, height: &BlockHeight) -> Option<[u128; 2]> {
-        self.ledger_cloned
-            .api_cache
-            .as_ref()
-            .unwrap()
-            .staking_global_rate_hist
-            .get(height)
+    fn query_block_rewards_rate(&self, height: &BlockHeight) -> Option<[u128; 2]> {
+        self.api_cache()?.staking_global_rate_hist.get(height)
+    }
+}
+
+/// A data container for API
+pub struct QueryServer {
+    pub(crate) ledger: Arc<RwLock<LedgerState>>,
+    pub(crate) ledger_cloned: LedgerState,
+}
+
+impl QueryServer {
+    /// create query server
+    pub fn new(ledger: Arc<RwLock<LedgerState>>) -> QueryServer {
+        let ledger_cloned = ledger.read().clone();
+        QueryServer {
+            ledger,
+            ledger_cloned,
+        }
     }
 
     /// update after a new block is created
@@ -319,3 +293,45 @@ impl QueryServer {
         }
     }
 }
+
+impl IndexedData for QueryServer {
+    #[inline(always)]
+    fn api_cache(&self) -> Option<&ApiCache> {
+        self.ledger_cloned.api_cache.as_ref()
+    }
+
+    #[inline(always)]
+    fn get_transaction_light(&self, sid: TxnSID) -> Result<FinalizedTransaction> {
+        self.ledger_cloned.get_transaction_light(sid).c(d!())
+    }
+
+    #[inline(always)]
+    fn get_owned_utxos(&self, pk: &XfrPublicKey) -> Result<HashSet<TxoSID>> {
+        self.ledger_cloned
+            .get_owned_utxos(pk)
+            .c(d!())
+            .map(|utxos| utxos.keys().copied().collect())
+    }
+
+    #[inline(always)]
+    fn get_commits(&self) -> u64 {
+        self.ledger_cloned.get_block_commit_count()
+    }
+
+    #[inline(always)]
+    fn get_staking_height(&self) -> BlockHeight {
+        self.ledger_cloned.get_staking().cur_height()
+    }
+
+    fn get_validator_start(
+        &self,
+        addr: TendermintAddrRef,
+    ) -> Result<(XfrPublicKey, BlockHeight)> {
+        let staking = self.ledger_cloned.get_staking();
+        let v_id = staking.validator_td_addr_to_app_pk(addr).c(d!())?;
+        staking
+            .delegation_get(&v_id)
+            .map(|d| (v_id, d.start_height))
+            .c(d!("not exists"))
+    }
+}
@@ -8,15 +8,28 @@ use {
     fp_utils::tx::EVM_TX_TAG,
     ledger::{
         data_model::{BlockEffect, Transaction, TxnEffect, TxnSID, TxnTempSID, TxoSID},
-        store::LedgerState,
+        store::{simulation::TxnSimulation, LedgerState},
     },
-    parking_lot::RwLock,
+    parking_lot::{Mutex, RwLock},
     rand_core::{CryptoRng, RngCore},
     ruc::*,
     serde::{Deserialize, Serialize},
-    std::{collections::HashMap, fmt, sync::Arc},
+    std::{
+        collections::HashMap,
+        fmt,
+        sync::Arc,
+        time::{Duration, Instant},
+    },
 };
 
+/// At most this many transactions are simulated in a second,
+/// every simulation works on a full copy of the staking state.
+pub const SIMULATIONS_PER_SEC: usize = 10;
+
+/// The size limit of the body of a simulated transaction, in bytes,
+/// the proofs of larger transactions are too costly to verify for free.
+pub const SIMULATION_SIZE_LIMIT: usize = 128 * 1024;
+
 /// Query handle for user
 #[derive(Debug, Hash, Eq, PartialEq, Clone, Serialize, Deserialize)]
 pub struct TxnHandle(pub String);
@@ -75,6 +88,8 @@ where
     prng: RNG,
     commit_mode: CommitMode,
     txn_forwarder: TF,
+    // the start of current second and the simulations in it
+    simulations: Mutex<(Instant, usize)>,
 }
 
 impl<RNG, TF> SubmissionServer<RNG, TF>
@@ -98,6 +113,7 @@ where
             block_capacity,
             commit_mode: CommitMode::FullBlock,
             txn_forwarder,
+            simulations: Mutex::new((Instant::now(), 0)),
         })
     }
 
@@ -116,6 +132,7 @@ where
             block_capacity: 0,
             commit_mode: CommitMode::Manual,
             txn_forwarder,
+            simulations: Mutex::new((Instant::now(), 0)),
         })
     }
 
@@ -236,6 +253,28 @@ where
         }
     }
 
+    /// Dry-run a transaction against a copy of the current block,
+    /// neither the block nor the ledger will be changed.
+    ///
+    /// It fails if `SIMULATIONS_PER_SEC` has been reached.
+    pub fn simulate_transaction(&self, txn: Transaction) -> Result<TxnSimulation> {
+        {
+            let mut window = self.simulations.lock();
+            if window.0.elapsed() >= Duration::from_secs(1) {
+                *window = (Instant::now(), 0);
+            }
+            if window.1 >= SIMULATIONS_PER_SEC {
+                return Err(eg!("too many simulations, please retry later"));
+            }
+            window.1 += 1;
+        }
+
+        Ok(self
+            .committed_state
+            .read()
+            .simulate_transaction(self.block.as_ref(), txn))
+    }
+
     /// Handle the whole process when there's a new transaction
     pub fn handle_transaction(&mut self, txn: Transaction) -> Result<TxnHandle> {
         let txn_handle = TxnHandle::new(&txn);
@@ -3,11 +3,11 @@
 //!
 
 use {
-    super::{SubmissionServer, TxnForward, TxnHandle},
+    super::{SubmissionServer, TxnForward, TxnHandle, SIMULATION_SIZE_LIMIT},
     actix_cors::Cors,
     actix_web::{error, middleware, web, App, HttpServer},
     finutils::api::NetworkRoute,
-    ledger::data_model::Transaction,
+    ledger::{data_model::Transaction, store::simulation::TxnSimulation},
     parking_lot::RwLock,
     rand_core::{CryptoRng, RngCore},
     ruc::*,
@@ -53,6 +53,23 @@ where
         })
 }
 
+/// Dry-run a transaction without submitting it,
+/// the body is limited to `SIMULATION_SIZE_LIMIT` bytes
+pub async fn simulate_transaction<RNG, TF>(
+    data: web::Data<Arc<RwLock<SubmissionServer<RNG, TF>>>>,
+    body: web::Json<Transaction>,
+) -> StdResult<web::Json<TxnSimulation>, actix_web::error::Error>
+where
+    RNG: RngCore + CryptoRng,
+    TF: TxnForward + Sync + Send,
+{
+    let submission_server = data.read();
+    submission_server
+        .simulate_transaction(body.into_inner())
+        .map(web::Json)
+        .map_err(|e| error::ErrorTooManyRequests(e.to_string()))
+}
+
 /// Queries the status of a transaction by its handle. Returns either a not committed message or a
 /// serialized TxnStatus.
 pub async fn txn_status<RNG, TF>(
@@ -84,6 +101,7 @@ pub struct SubmissionApi;
 #[allow(missing_docs)]
 pub enum SubmissionRoutes {
     SubmitTransaction,
+    SimulateTransaction,
     TxnStatus,
     Ping,
     Version,
@@ -93,6 +111,7 @@ impl NetworkRoute for SubmissionRoutes {
     fn route(&self) -> String {
         let endpoint = match *self {
             SubmissionRoutes::SubmitTransaction => "submit_transaction",
+            SubmissionRoutes::SimulateTransaction => "simulate_transaction",
             SubmissionRoutes::TxnStatus => "txn_status",
             SubmissionRoutes::Ping => "ping",
             SubmissionRoutes::Version => "version",
@@ -123,6 +142,11 @@ impl SubmissionApi {
                     &SubmissionRoutes::SubmitTransaction.route(),
                     web::post().to(submit_transaction::<RNG, TF>),
                 )
+                .service(
+                    web::resource(&SubmissionRoutes::SimulateTransaction.route())
+                        .data(web::JsonConfig::default().limit(SIMULATION_SIZE_LIMIT))
+                        .route(web::post().to(simulate_transaction::<RNG, TF>)),
+                )
                 .route(&SubmissionRoutes::Ping.route(), web::get().to(ping))
                 .route(&SubmissionRoutes::Version.route(), web::get().to(version))
                 .route(
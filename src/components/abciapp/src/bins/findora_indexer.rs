@@ -0,0 +1,76 @@
+//!
+//! # Findora Indexer
+//!
+//! Builds the history indices of a node out of process,
+//! and serves them with the same routes as the query service of a node.
+//!
+
+#![deny(warnings)]
+
+use {
+    abciapp::api::indexer,
+    clap::{crate_authors, App},
+    ruc::*,
+    std::{env, fs},
+};
+
+fn main() {
+    globutils::logging::init_logging(None);
+    pnk!(run());
+}
+
+fn run() -> Result<()> {
+    let m = App::new("findora-indexer")
+        .version(env!("VERGEN_SHA"))
+        .author(crate_authors!())
+        .about("A standalone indexer of FindoraNetwork.")
+        .arg_from_usage("--node-url=[URL] 'ledger service of the node to follow, default to http://127.0.0.1:8668'")
+        .arg_from_usage("--host=[IP] 'default to 0.0.0.0'")
+        .arg_from_usage("--port=[Port] 'default to 8667'")
+        .arg_from_usage("-d, --data-dir=[Path]")
+        .arg_from_usage("--interval=[Secs] 'interval between adjacent polls, default to 3 seconds'")
+        .arg_from_usage("--start-height=[Height] 'ledger block height a new indexer starts from, default to the genesis'")
+        .get_matches();
+
+    let node = m
+        .value_of("node-url")
+        .map(|v| v.to_owned())
+        .or_else(|| env::var("INDEXER_NODE_URL").ok())
+        .unwrap_or_else(|| "http://127.0.0.1:8668".to_owned());
+    let host = m
+        .value_of("host")
+        .map(|v| v.to_owned())
+        .or_else(|| env::var("INDEXER_HOST").ok())
+        .unwrap_or_else(|| "0.0.0.0".to_owned());
+    let port = m
+        .value_of("port")
+        .map(|v| v.to_owned())
+        .or_else(|| env::var("INDEXER_PORT").ok())
+        .unwrap_or_else(|| "8667".to_owned())
+        .parse::<u16>()
+        .c(d!())?;
+    let dir = m
+        .value_of("data-dir")
+        .map(|v| v.to_owned())
+        .unwrap_or_else(|| {
+            env::var("INDEXER_DIR").unwrap_or_else(|_| {
+                format!("{}/.findora_indexer", pnk!(env::var("HOME")))
+            })
+        });
+    let itv = m
+        .value_of("interval")
+        .unwrap_or("3")
+        .parse::<u64>()
+        .c(d!())?;
+    let start_height = m
+        .value_of("start-height")
+        .map(|v| v.to_owned())
+        .or_else(|| env::var("INDEXER_START_HEIGHT").ok())
+        .map(|h| h.parse::<u64>().c(d!()))
+        .transpose()?;
+
+    fs::create_dir_all(&dir).c(d!())?;
+    env::set_var("BNC_DATA_DIR", format!("{}/__bnc__", &dir));
+
+    indexer::start_indexer(&node, itv, start_height, &[(&host, port)])
+}
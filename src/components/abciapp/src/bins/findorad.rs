@@ -8,8 +8,11 @@
 #![deny(warnings)]
 
 use {
+    abciapp::abci::genesis::AppGenesis,
+    baseapp::BaseApp,
     config::findora::{config::CFG, init},
     lazy_static::lazy_static,
+    ledger::store::doctor::{self, Diagnosis},
     nix::{
         sys::signal::{kill, Signal},
         unistd::{truncate, Pid},
@@ -22,7 +25,7 @@ use {
         io::{self, prelude::*, BufReader, Read, Seek, SeekFrom},
         mem::size_of,
         os::unix::fs::PermissionsExt,
-        path::PathBuf,
+        path::{Path, PathBuf},
         process::{Command, Stdio},
     },
 };
@@ -73,6 +76,7 @@ fn node_command() -> Result<()> {
 
     for (condition, action) in [
         (CFG.enable_query_service, "--enable-query-service"),
+        (CFG.disable_api_cache, "--disable-api-cache"),
         (CFG.enable_eth_api_service, "--enable-eth-api-service"),
         (CFG.disable_eth_empty_blocks, "--disable-eth-empty-blocks"),
         (CFG.enable_snapshot, "--enable-snapshot"),
@@ -87,6 +91,7 @@ fn node_command() -> Result<()> {
 
     convert_arg!(tendermint_node_self_addr);
     convert_arg!(tendermint_node_key_config_path);
+    convert_arg!(prune_keep_blocks);
     convert_arg!(snapshot_target);
     convert_arg!(snapshot_itv);
     convert_arg!(snapshot_cap);
@@ -248,6 +253,122 @@ fn unpack() -> Result<()> {
     .c(d!())
 }
 
+fn doctor() -> Result<()> {
+    // the same location as the node
+    env::set_var("BNC_DATA_DIR", format!("{}/__bnc__", &CFG.ledger_dir));
+
+    let recent = CFG
+        .doctor_blocks
+        .as_deref()
+        .unwrap_or("10")
+        .parse::<usize>()
+        .c(d!("invalid number of blocks"))?;
+
+    let diagnosis = doctor::diagnose(&CFG.ledger_dir, recent).c(d!())?;
+    let mut healthy = print_diagnosis(&diagnosis);
+    if diagnosis.repairs.is_empty() {
+        return if healthy {
+            Ok(())
+        } else {
+            Err(eg!("no safe repair, restore the node from a snapshot"))
+        };
+    }
+
+    println!("\nRepairs:");
+    for r in diagnosis.repairs.iter() {
+        println!("    {r:?}");
+    }
+    if !CFG.doctor_repair {
+        println!("\nRun with `--repair` to apply them.");
+        return Err(eg!("the ledger is inconsistent"));
+    }
+
+    doctor::repair(&CFG.ledger_dir, &diagnosis.repairs).c(d!())?;
+    println!("\nRepaired, checking again:\n");
+    healthy = print_diagnosis(&doctor::diagnose(&CFG.ledger_dir, recent).c(d!())?);
+
+    if healthy {
+        Ok(())
+    } else {
+        Err(eg!("the ledger is still inconsistent"))
+    }
+}
+
+// Print the checks, the chain state is checked here since it is outside of the ledger.
+fn print_diagnosis(diagnosis: &Diagnosis) -> bool {
+    let chain_state = BaseApp::chain_state_height(Path::new(&CFG.ledger_dir))
+        .c(d!())
+        .and_then(|h| {
+            if h == diagnosis.tendermint_height {
+                Ok(())
+            } else {
+                Err(eg!(format!(
+                    "height {}, but {} in the ledger",
+                    h, diagnosis.tendermint_height
+                )))
+            }
+        })
+        .err()
+        .map(|e| e.to_string());
+
+    let checks = diagnosis
+        .checks
+        .iter()
+        .map(|c| (c.name, &c.error))
+        .chain([("chain_state", &chain_state)]);
+    for (name, error) in checks {
+        if let Some(e) = error {
+            println!("[FAIL] {name}: {e}");
+        } else {
+            println!("[ OK ] {name}");
+        }
+    }
+
+    diagnosis.is_healthy() && chain_state.is_none()
+}
+
+fn index_logs() -> Result<()> {
+    let from = CFG
+        .index_logs_from
+        .as_deref()
+        .unwrap_or("0")
+        .parse::<u64>()
+        .c(d!("invalid height"))?;
+
+    let tail = BaseApp::backfill_log_index(Path::new(&CFG.ledger_dir), from).c(d!())?;
+    println!("The log index covers the EVM blocks from {tail}");
+    Ok(())
+}
+
+fn export_genesis() -> Result<()> {
+    // the same location as the node
+    env::set_var("BNC_DATA_DIR", format!("{}/__bnc__", &CFG.ledger_dir));
+
+    let height = CFG
+        .export_height
+        .as_deref()
+        .map(|h| h.parse::<u64>().c(d!("invalid height")))
+        .transpose()?;
+    let output = CFG.export_output.as_deref().unwrap_or("genesis.json");
+
+    let genesis = AppGenesis::export(&CFG.ledger_dir, height).c(d!())?;
+    genesis
+        .write_tendermint_genesis(
+            &(CFG.tendermint_home.clone() + "/config/genesis.json"),
+            output,
+        )
+        .c(d!())?;
+
+    println!(
+        "The state at height {} is exported to {}: {} UTXOs, {} asset types",
+        genesis.height,
+        output,
+        genesis.ledger.utxos.len(),
+        genesis.ledger.asset_types.len()
+    );
+    Ok(())
+}
+
 fn get_bin_path() -> Result<PathBuf> {
     let bin_path = env::current_exe().c(d!())?;
     let bin_size = metadata(&bin_path).c(d!())?.len() as usize;
@@ -262,7 +383,12 @@ fn main() {
         "init" => unpack().c(d!()).and_then(|_| init_command().c(d!())),
         "node" => unpack().c(d!()).and_then(|_| node_command().c(d!())),
         "pack" => pack().c(d!()),
-        _ => Err(eg!("The available options are 'node'/'init'")),
+        "doctor" => doctor().c(d!()),
+        "index-logs" => index_logs().c(d!()),
+        "export-genesis" => export_genesis().c(d!()),
+        _ => Err(eg!(
+            "The available options are 'node'/'init'/'doctor'/'index-logs'/'export-genesis'"
+        )),
     };
 
     pnk!(res);
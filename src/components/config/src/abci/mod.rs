@@ -90,6 +90,26 @@ pub struct CheckPointConfig {
     pub disable_delegate_frc20: i64,
 
     pub fix_exec_code: i64,
+
+    // Enforce the max-rate ceiling, the max change and
+    // the minimum interval of validator commission changes.
+    pub commission_rule_height: u64,
+
+    // Enable the time-locked vesting of FRAs.
+    pub vesting_height: u64,
+
+    // Allow spending from weighted multisig addresses.
+    pub multisig_address_height: u64,
+
+    // Allow credential issuer registrations and credential-gated assets.
+    pub cred_gated_asset_height: u64,
+
+    // Allow confidential amounts in the conversions between UTXOs and EVM accounts.
+    pub confidential_convert_height: i64,
+
+    // Enforce a gas budget on the EVM transactions of a block.
+    pub evm_block_gas_limit_height: i64,
+    pub evm_block_gas_limit: u64,
 }
 
 impl CheckPointConfig {
@@ -125,6 +145,13 @@ impl CheckPointConfig {
                                 evm_substate_v2_height: 0,
                                 disable_delegate_frc20: 0,
                                 fix_exec_code: 0,
+                                commission_rule_height: 0,
+                                vesting_height: 0,
+                                multisig_address_height: 0,
+                                cred_gated_asset_height: 0,
+                                confidential_convert_height: 0,
+                                evm_block_gas_limit_height: 0,
+                                evm_block_gas_limit: 30_000_000,
                             };
                             #[cfg(not(feature = "debug_env"))]
                             let config = CheckPointConfig {
@@ -150,6 +177,13 @@ impl CheckPointConfig {
                                 evm_substate_v2_height: 3351349,
                                 disable_delegate_frc20: 3401450,
                                 fix_exec_code: 3401450,
+                                commission_rule_height: 4004430,
+                                vesting_height: 4004430,
+                                multisig_address_height: 4004430,
+                                cred_gated_asset_height: 4004430,
+                                confidential_convert_height: 4004430,
+                                evm_block_gas_limit_height: 4004430,
+                                evm_block_gas_limit: 30_000_000,
                             };
                             let content = toml::to_string(&config).unwrap();
                             file.write_all(content.as_bytes()).unwrap();
@@ -295,6 +329,8 @@ pub mod global_cfg {
         pub submission_service_port: u16,
         pub ledger_service_port: u16,
         pub enable_query_service: bool,
+        pub disable_api_cache: bool,
+        pub prune_keep_blocks: Option<usize>,
         pub disable_eth_empty_blocks: bool,
         pub enable_eth_api_service: bool,
         pub evm_http_port: u16,
@@ -330,6 +366,8 @@ pub mod global_cfg {
             .arg_from_usage("--submission-service-port=[Submission Service Port]")
             .arg_from_usage("--ledger-service-port=[Ledger Service Port]")
             .arg_from_usage("-q, --enable-query-service")
+            .arg_from_usage("--disable-api-cache 'serve the query service without history indices, eg. when they are served by findora-indexer'")
+            .arg_from_usage("--prune-keep-blocks=[Number] 'keep the transactions of the last N blocks only, the older ones are pruned a bounded number per block'")
             .arg_from_usage("--disable-eth-empty-blocks 'not generate empty ethereum blocks when no evm transaction'")
             .arg_from_usage("--enable-eth-api-service")
             .arg_from_usage("--evm-http-port=[EVM Web3 Http Port]")
@@ -429,6 +467,19 @@ pub mod global_cfg {
             .c(d!())?;
         let eqs = m.is_present("enable-query-service")
             || env::var("ENABLE_QUERY_SERVICE").is_ok();
+        let dac =
+            m.is_present("disable-api-cache") || env::var("DISABLE_API_CACHE").is_ok();
+        let pkb = m
+            .value_of("prune-keep-blocks")
+            .map(|v| v.to_owned())
+            .or_else(|| env::var("PRUNE_KEEP_BLOCKS").ok())
+            .map(|v| {
+                v.parse::<usize>()
+                    .c(d!())
+                    .and_then(|n| alt!(0 < n, Ok(n), Err(eg!("at least 1 block"))))
+                    .c(d!("invalid prune-keep-blocks"))
+            })
+            .transpose()?;
         let tnsa = m
             .value_of("tendermint-node-self-addr")
             .map(|v| v.to_owned())
@@ -478,6 +529,8 @@ pub mod global_cfg {
             submission_service_port: ssp,
             ledger_service_port: lsp,
             enable_query_service: eqs,
+            disable_api_cache: dac,
+            prune_keep_blocks: pkb,
             disable_eth_empty_blocks: eeb,
             enable_eth_api_service: eas,
             evm_http_port: ehp,
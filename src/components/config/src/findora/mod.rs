@@ -156,6 +156,8 @@ pub mod config {
         pub submission_service_port: u16,
         pub ledger_service_port: u16,
         pub enable_query_service: bool,
+        pub disable_api_cache: bool,
+        pub prune_keep_blocks: Option<String>,
         pub enable_eth_api_service: bool,
         pub disable_eth_empty_blocks: bool,
         pub no_fast_sync: bool,
@@ -177,6 +179,11 @@ pub mod config {
         pub snapshot_rollback: bool,
         pub snapshot_rollback_to: Option<String>,
         pub snapshot_rollback_to_exact: Option<String>,
+        pub doctor_blocks: Option<String>,
+        pub doctor_repair: bool,
+        pub index_logs_from: Option<String>,
+        pub export_height: Option<String>,
+        pub export_output: Option<String>,
     }
 
     fn get_config() -> Result<Config> {
@@ -191,6 +198,8 @@ pub mod config {
                 .arg_from_usage("--submission-service-port=[Submission Service Port]")
                 .arg_from_usage("--ledger-service-port=[Ledger Service Port]")
                 .arg_from_usage("-q, --enable-query-service")
+                .arg_from_usage("--disable-api-cache 'serve the query service without history indices, eg. when they are served by findora-indexer'")
+                .arg_from_usage("--prune-keep-blocks=[Number] 'keep the transactions of the last N blocks only, the older ones are pruned a bounded number per block'")
                 .arg_from_usage("--enable-eth-api-service")
                 .arg_from_usage("--disable-eth-empty-blocks")
                 .arg_from_usage("-N, --no-fast-sync")
@@ -227,6 +236,32 @@ pub mod config {
 
             let pack = SubCommand::with_name("pack");
 
+            let doctor = SubCommand::with_name("doctor")
+                .about("Check the consistency of the ledger data, the node must be stopped.")
+                .arg_from_usage("-d, --ledger-dir=[Path]")
+                .arg_from_usage(
+                    "-b, --base-dir=[DIR] 'The root directory for tendermint config, aka $TENDERMINT_HOME'",
+                )
+                .arg_from_usage("-n, --blocks=[N] 'number of recent blocks to verify, default to 10'")
+                .arg_from_usage("--repair 'apply the safe repairs, the damaged files will be backed up'");
+
+            let index_logs = SubCommand::with_name("index-logs")
+                .about("Add the EVM blocks stored before the log index to it, the node must be stopped.")
+                .arg_from_usage("-d, --ledger-dir=[Path]")
+                .arg_from_usage(
+                    "-b, --base-dir=[DIR] 'The root directory for tendermint config, aka $TENDERMINT_HOME'",
+                )
+                .arg_from_usage("--from=[HEIGHT] 'the first block to index, default to 0'");
+
+            let export_genesis = SubCommand::with_name("export-genesis")
+                .about("Export the state of the node into a genesis file for a new chain, the node must be stopped.")
+                .arg_from_usage("-d, --ledger-dir=[Path]")
+                .arg_from_usage(
+                    "-b, --base-dir=[DIR] 'The root directory for tendermint config, aka $TENDERMINT_HOME'",
+                )
+                .arg_from_usage("--height=[HEIGHT] 'the height to export, must be the current one, default to the current one'")
+                .arg_from_usage("-o, --output=[Path] 'where to write the genesis file, default to ./genesis.json'");
+
             App::new("findorad")
                 .version(env!("VERGEN_SHA"))
                 .author(crate_authors!())
@@ -234,6 +269,9 @@ pub mod config {
                 .subcommand(node)
                 .subcommand(init)
                 .subcommand(pack)
+                .subcommand(doctor)
+                .subcommand(index_logs)
+                .subcommand(export_genesis)
                 .arg(Arg::with_name("_a").long("ignored").hidden(true))
                 .arg(Arg::with_name("_b").long("nocapture").hidden(true))
                 .arg(Arg::with_name("_c").long("test-threads").hidden(true))
@@ -329,6 +367,12 @@ pub mod config {
             .c(d!())?;
         let eqs = m.is_present("enable-query-service")
             || env::var("ENABLE_QUERY_SERVICE").is_ok();
+        let dac =
+            m.is_present("disable-api-cache") || env::var("DISABLE_API_CACHE").is_ok();
+        let pkb = m
+            .value_of("prune-keep-blocks")
+            .map(|v| v.to_owned())
+            .or_else(|| env::var("PRUNE_KEEP_BLOCKS").ok());
         let eeas = m.is_present("enable-eth-api-service")
             || env::var("ENABLE_ETH_API_SERVICE").is_ok();
         let deeb = m.is_present("disable-eth-empty-blocks")
@@ -378,6 +422,8 @@ pub mod config {
             submission_service_port: ssp,
             ledger_service_port: lsp,
             enable_query_service: eqs,
+            disable_api_cache: dac,
+            prune_keep_blocks: pkb,
             enable_eth_api_service: eeas,
             disable_eth_empty_blocks: deeb,
             no_fast_sync: nfs,
@@ -404,6 +450,11 @@ pub mod config {
             snapshot_rollback_to_exact: m
                 .value_of("snapshot-rollback-to-exact")
                 .map(|v| v.to_owned()),
+            doctor_blocks: m.value_of("blocks").map(|v| v.to_owned()),
+            doctor_repair: m.is_present("repair"),
+            index_logs_from: m.value_of("from").map(|v| v.to_owned()),
+            export_height: m.value_of("height").map(|v| v.to_owned()),
+            export_output: m.value_of("output").map(|v| v.to_owned()),
         };
 
         Ok(res)
@@ -2,7 +2,11 @@ use crate::extensions::SignedExtra;
 use abci::*;
 use fp_core::context::RunTxMode;
 use fp_evm::BlockId;
-use fp_types::assemble::convert_unchecked_transaction;
+use fp_traits::evm::FeeCalculator;
+use fp_types::{
+    actions::{ethereum::Action as EthereumAction, evm::Action as EvmAction, Action},
+    assemble::{convert_unchecked_transaction, UncheckedTransaction},
+};
 use fp_utils::tx::EvmRawTxWrapper;
 use primitive_types::U256;
 use ruc::*;
@@ -74,6 +78,7 @@ impl crate::BaseApp {
         };
 
         if let Ok(tx) = convert_unchecked_transaction::<SignedExtra>(raw_tx) {
+            resp.priority = Self::tx_priority(&tx);
             #[cfg(feature = "enterprise-web3")]
             let tmp_tx = tx.clone();
             let check_fn = |mode: RunTxMode| {
@@ -82,7 +87,7 @@ impl crate::BaseApp {
                     ctx.run_mode = mode;
                     ctx
                 };
-                let result = self.modules.process_tx::<SignedExtra>(ctx, tx);
+                let result = self.modules.process_tx(ctx, tx);
                 match result {
                     Ok(ar) => {
                         #[cfg(feature = "enterprise-web3")]
@@ -165,6 +170,24 @@ impl crate::BaseApp {
         resp
     }
 
+    /// The priority of a transaction in the mempool, the gas price in gwei of the EVM transactions.
+    fn tx_priority(tx: &UncheckedTransaction<SignedExtra>) -> i64 {
+        let gas_price = match &tx.function {
+            Action::Ethereum(EthereumAction::Transact(tx)) => tx.gas_price,
+            Action::Evm(action) => match action {
+                EvmAction::Call(args) => args.gas_price,
+                EvmAction::Create(args) => args.gas_price,
+                EvmAction::Create2(args) => args.gas_price,
+            }
+            .unwrap_or_else(<Self as module_evm::Config>::FeeCalculator::min_gas_price),
+            _ => U256::zero(),
+        };
+
+        (gas_price / U256::exp10(9))
+            .min(U256::from(i64::MAX))
+            .as_u64() as i64
+    }
+
     /// init_chain implements the ABCI interface.
     pub fn init_chain(&mut self, req: &RequestInitChain) -> ResponseInitChain {
         let mut init_header: Header = Default::default();
@@ -214,7 +237,7 @@ impl crate::BaseApp {
             let ctx = self.retrieve_context(RunTxMode::Deliver).clone();
             #[cfg(feature = "enterprise-web3")]
             let tmp_tx = tx.clone();
-            let ret = self.modules.process_tx::<SignedExtra>(ctx, tx);
+            let ret = self.modules.process_tx(ctx, tx);
             match ret {
                 Ok(ar) => {
                     #[cfg(feature = "enterprise-web3")]
@@ -1,7 +1,8 @@
 ///! Transaction signature extension for transaction verification and validity check.
 use crate::BaseApp;
+use config::abci::global_cfg::CFG;
 use fp_core::{
-    context::Context,
+    context::{Context, RunTxMode},
     transaction::{ActionResult, SignedExtension},
 };
 use fp_traits::account::{AccountAsset, FeeCalculator};
@@ -55,6 +56,44 @@ impl SignedExtension for CheckNonce {
     }
 }
 
+/// The nonce check of the EVM actions, whose nonce is increased by the EVM
+/// like the ethereum transactions, except in `CheckTx` where they are not executed.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct CheckEvmNonce(U256);
+
+impl From<CheckNonce> for CheckEvmNonce {
+    fn from(nonce: CheckNonce) -> Self {
+        CheckEvmNonce(nonce.0)
+    }
+}
+
+impl SignedExtension for CheckEvmNonce {
+    type AccountId = Address;
+    type Pre = ();
+
+    fn validate(&self, ctx: &Context, who: &Self::AccountId) -> Result<()> {
+        CheckNonce(self.0).validate(ctx, who)
+    }
+
+    fn pre_execute(self, ctx: &Context, who: &Self::AccountId) -> Result<Self::Pre> {
+        if ctx.header.height >= CFG.checkpoint.evm_checktx_nonce
+            && ctx.run_mode == RunTxMode::Check
+        {
+            return CheckNonce(self.0).pre_execute(ctx, who);
+        }
+
+        let nonce = module_account::App::<BaseApp>::nonce(ctx, who);
+        if self.0 != nonce {
+            #[cfg(not(feature = "benchmark"))]
+            return Err(eg!(format!(
+                "InvalidNonce, expected: {}, actual: {}",
+                nonce, self.0
+            )));
+        }
+        Ok(())
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct CheckFee(Option<U256>);
 
@@ -13,6 +13,7 @@ mod notify;
 use crate::modules::ModuleManager;
 use abci::Header;
 use ethereum::BlockV0 as Block;
+use ethereum_types::Bloom;
 use evm_precompile::{self, FindoraPrecompiles};
 use fin_db::{FinDB, RocksDB};
 use fp_core::context::Context as Context2;
@@ -28,14 +29,18 @@ use fp_traits::{
     base::BaseProvider,
     evm::{EthereumAddressMapping, EthereumDecimalsMapping},
 };
-use fp_types::{actions::xhub::NonConfidentialOutput, actions::Action, crypto::Address};
+use fp_types::{
+    actions::xhub::{ConfidentialOutput, NonConfidentialOutput},
+    actions::Action,
+    crypto::Address,
+};
 use lazy_static::lazy_static;
 use ledger::data_model::Transaction as FindoraTransaction;
 use notify::*;
 use parking_lot::RwLock;
 use primitive_types::{H160, H256, U256};
 use ruc::{eg, Result};
-use std::{borrow::BorrowMut, path::Path, sync::Arc};
+use std::{borrow::BorrowMut, collections::BTreeMap, path::Path, sync::Arc};
 use storage::state::{ChainState, ChainStateOpts};
 use tracing::info;
 
@@ -127,6 +132,7 @@ impl<I: From<FindoraPrecompiles<BaseApp>>> fp_core::macros::Get2<I, Context2>
 
 impl module_evm::Config for BaseApp {
     type AccountAsset = module_account::App<Self>;
+    type ActionRecorder = module_ethereum::App<Self>;
     type AddressMapping = EthereumAddressMapping;
     type BlockGasLimit = BlockGasLimit;
     type BlockHashMapping = module_ethereum::App<Self>;
@@ -206,6 +212,66 @@ impl BaseApp {
         })
     }
 
+    /// Height of the chain state stored in `basedir`, the node must be stopped.
+    pub fn chain_state_height(basedir: &Path) -> Result<u64> {
+        let fdb = FinDB::open(basedir.join(CHAIN_STATE_PATH).as_path())?;
+        ChainState::new(fdb, "findora_db".to_owned(), 0).height()
+    }
+
+    /// Index the logs blooms of the blocks from `from` to the start of the log index,
+    /// the node must be stopped, returns the first indexed block.
+    pub fn backfill_log_index(basedir: &Path, from: u64) -> Result<U256> {
+        let fdb = FinDB::open(basedir.join(CHAIN_STATE_PATH).as_path())?;
+        let chain_state = Arc::new(RwLock::new(ChainState::new(
+            fdb,
+            "findora_db".to_owned(),
+            0,
+        )));
+        let rdb = RocksDB::open(basedir.join(CHAIN_HISTORY_DATA_PATH).as_path())?;
+        let chain_db =
+            Arc::new(RwLock::new(ChainState::new(rdb, "rocks_db".to_owned(), 0)));
+
+        let ctx = Context::new(chain_state, chain_db);
+        let height = ctx.db.read().height()?;
+        let from = U256::from(from);
+
+        // commit each section to keep the memory usage low
+        loop {
+            let tail = module_ethereum::App::<Self>::backfill_log_index(
+                &ctx,
+                from,
+                module_ethereum::BLOOM_SECTION_SIZE,
+            )?;
+            ctx.db.write().commit(height)?;
+            if tail <= from {
+                return Ok(tail);
+            }
+        }
+    }
+
+    /// Export the genesis state of every module from the chain state stored
+    /// in `basedir`, the node must be stopped.
+    pub fn export_genesis(basedir: &Path) -> Result<BTreeMap<String, Vec<u8>>> {
+        let fdb = FinDB::open(basedir.join(CHAIN_STATE_PATH).as_path())?;
+        let chain_state = Arc::new(RwLock::new(ChainState::new(
+            fdb,
+            "findora_db".to_owned(),
+            0,
+        )));
+        let rdb = RocksDB::open(basedir.join(CHAIN_HISTORY_DATA_PATH).as_path())?;
+        let chain_db =
+            Arc::new(RwLock::new(ChainState::new(rdb, "rocks_db".to_owned(), 0)));
+
+        let ctx = Context::new(chain_state, chain_db);
+        ModuleManager::default().export_genesis(&ctx)
+    }
+
+    /// Write the module states exported by `export_genesis` into the deliver state,
+    /// they are committed with the first block.
+    pub fn import_genesis(&mut self, genesis: &BTreeMap<String, Vec<u8>>) -> Result<()> {
+        self.modules.init_genesis(&mut self.deliver_state, genesis)
+    }
+
     pub fn derive_app(&self) -> Self {
         let chain_state = self.chain_state.clone();
         let chain_db = self.chain_db.clone();
@@ -354,9 +420,28 @@ impl BaseApp {
         self.modules.process_findora_tx(&self.deliver_state, tx)
     }
 
+    /// The first block covered by the log index.
+    pub fn log_index_tail(&self) -> Option<U256> {
+        let ctx = self.create_query_context(Some(0), false).ok()?;
+        module_ethereum::App::<Self>::log_index_tail(&ctx)
+    }
+
+    /// Bitset of the blocks in the section of the log index whose logs bloom contains `bloom`,
+    /// all the blocks are candidates if the index is unavailable.
+    pub fn bloom_section(&self, section: u64, bloom: &Bloom) -> Vec<u8> {
+        match self.create_query_context(Some(0), false) {
+            Ok(ctx) => module_ethereum::App::<Self>::bloom_section(&ctx, section, bloom),
+            Err(_) => vec![0xff; (module_ethereum::BLOOM_SECTION_SIZE / 8) as usize],
+        }
+    }
+
     pub fn consume_mint(&mut self) -> Option<Vec<NonConfidentialOutput>> {
         module_xhub::App::<BaseApp>::consume_mint(&self.deliver_state)
     }
+
+    pub fn consume_confidential_mint(&mut self) -> Option<Vec<ConfidentialOutput>> {
+        module_xhub::App::<BaseApp>::consume_confidential_mint(&self.deliver_state)
+    }
 }
 
 impl BaseProvider for BaseApp {
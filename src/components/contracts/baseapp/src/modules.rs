@@ -1,23 +1,24 @@
 use super::*;
+use crate::extensions::{CheckEvmNonce, SignedExtra};
 use abci::*;
 use fp_core::{
     context::Context,
-    module::AppModule,
+    module::{AppModule, AppModuleBasic},
     transaction::{
         ActionResult, Applyable, Executable, SignedExtension, ValidateUnsigned,
     },
 };
-use fp_traits::evm::DecimalsMapping;
+use fp_traits::evm::{AddressMapping, DecimalsMapping};
 use fp_types::{
     actions,
     assemble::{convert_unsigned_transaction, CheckedTransaction, UncheckedTransaction},
-    crypto::Address,
+    crypto::{xfr_evm_address, Address, MultiSignature},
 };
 use ledger::{
     converter::check_convert_account, data_model::Transaction as FindoraTransaction,
 };
 use ruc::*;
-use serde::Serialize;
+use std::collections::BTreeMap;
 
 #[derive(Default, Clone)]
 pub struct ModuleManager {
@@ -89,21 +90,47 @@ impl ModuleManager {
         resp
     }
 
-    pub fn process_tx<
-        Extra: Clone + Serialize + SignedExtension<AccountId = Address>,
-    >(
+    pub fn process_tx(
         &self,
         ctx: Context,
-        tx: UncheckedTransaction<Extra>,
+        tx: UncheckedTransaction<SignedExtra>,
     ) -> Result<ActionResult> {
         let checked = tx.clone().check()?;
         match tx.function.clone() {
             actions::Action::Ethereum(action) => Self::dispatch::<
                 actions::ethereum::Action,
                 module_ethereum::App<BaseApp>,
-                Extra,
+                SignedExtra,
             >(&ctx, action, checked),
-            _ => Self::dispatch::<actions::Action, BaseApp, Extra>(
+            actions::Action::Evm(action) => {
+                // The EVM actions are signed by Findora (Xfr) keys and run as
+                // the EVM account of the key (`xfr_evm_address`), not the account
+                // credited by converting UTXOs to the key itself.
+                // The gas pays for them, so `CheckFee` is left out,
+                // and the EVM increases the nonce.
+                let signed = match (tx.signature, checked.signed) {
+                    (Some((_, MultiSignature::Xfr(_), _)), Some((who, (nonce, _)))) => {
+                        let who = EthereumAddressMapping::convert_to_account_id(
+                            xfr_evm_address(&who),
+                        );
+                        Some((who, CheckEvmNonce::from(nonce)))
+                    }
+                    _ => return Err(eg!("evm actions must be signed by xfr keys")),
+                };
+                Self::dispatch::<
+                    actions::evm::Action,
+                    module_evm::App<BaseApp>,
+                    CheckEvmNonce,
+                >(
+                    &ctx,
+                    action,
+                    CheckedTransaction {
+                        signed,
+                        function: checked.function,
+                    },
+                )
+            }
+            _ => Self::dispatch::<actions::Action, BaseApp, SignedExtra>(
                 &ctx,
                 tx.function,
                 checked,
@@ -121,6 +148,59 @@ impl ModuleManager {
             .ok_or_else(|| eg!("The transfer to account amount is too large"))?;
         module_account::App::<BaseApp>::mint(ctx, &Address::from(owner), balance)
     }
+
+    /// Export the genesis state of every module, keyed by the module name.
+    pub fn export_genesis(&self, ctx: &Context) -> Result<BTreeMap<String, Vec<u8>>> {
+        // Note: adding new modules need to be updated.
+        let mut genesis = BTreeMap::new();
+        genesis.insert(
+            module_account::MODULE_NAME.to_owned(),
+            self.account_module.export_genesis(ctx).c(d!())?,
+        );
+        genesis.insert(
+            module_ethereum::MODULE_NAME.to_owned(),
+            self.ethereum_module.export_genesis(ctx).c(d!())?,
+        );
+        genesis.insert(
+            module_evm::MODULE_NAME.to_owned(),
+            self.evm_module.export_genesis(ctx).c(d!())?,
+        );
+        genesis.insert(
+            module_xhub::MODULE_NAME.to_owned(),
+            self.xhub_module.export_genesis(ctx).c(d!())?,
+        );
+        genesis.insert(
+            module_template::MODULE_NAME.to_owned(),
+            self.template_module.export_genesis(ctx).c(d!())?,
+        );
+        Ok(genesis)
+    }
+
+    /// Initialize the modules from the states returned by `export_genesis`,
+    /// the modules missing from `genesis` are left empty.
+    pub fn init_genesis(
+        &self,
+        ctx: &mut Context,
+        genesis: &BTreeMap<String, Vec<u8>>,
+    ) -> Result<()> {
+        // Note: adding new modules need to be updated.
+        for (name, gs) in genesis.iter().filter(|(_, gs)| !gs.is_empty()) {
+            if name == module_account::MODULE_NAME {
+                self.account_module.init_genesis(ctx, gs).c(d!())?;
+            } else if name == module_ethereum::MODULE_NAME {
+                self.ethereum_module.init_genesis(ctx, gs).c(d!())?;
+            } else if name == module_evm::MODULE_NAME {
+                self.evm_module.init_genesis(ctx, gs).c(d!())?;
+            } else if name == module_xhub::MODULE_NAME {
+                self.xhub_module.init_genesis(ctx, gs).c(d!())?;
+            } else if name == module_template::MODULE_NAME {
+                self.template_module.init_genesis(ctx, gs).c(d!())?;
+            } else {
+                return Err(eg!(format!("Invalid genesis module: {name}!")));
+            }
+        }
+        Ok(())
+    }
 }
 
 impl ModuleManager {
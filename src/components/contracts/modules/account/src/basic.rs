@@ -1,6 +1,19 @@
-use super::{App, Config, MODULE_NAME};
-use fp_core::module::AppModuleBasic;
-use ruc::Result;
+use super::{storage::*, App, Config, MODULE_NAME};
+use fp_core::{account::SmartAccount, context::Context, module::AppModuleBasic};
+use fp_storage::{Borrow, BorrowMut};
+use fp_types::crypto::Address;
+use primitive_types::U256;
+use ruc::*;
+use serde::{Deserialize, Serialize};
+
+/// Genesis state of the account module.
+#[derive(Default, Serialize, Deserialize)]
+pub struct GenesisState {
+    pub accounts: Vec<(Address, SmartAccount)>,
+    pub total_issuance: U256,
+    /// owner => spender => amount
+    pub allowances: Vec<(Address, Address, U256)>,
+}
 
 impl<C: Config> AppModuleBasic for App<C> {
     fn name() -> String {
@@ -8,18 +21,34 @@ impl<C: Config> AppModuleBasic for App<C> {
     }
 
     fn default_genesis(&self) -> Vec<u8> {
-        todo!()
+        serde_json::to_vec(&GenesisState::default()).unwrap_or_default()
     }
 
-    fn init_genesis(&self) {
-        todo!()
+    fn init_genesis(&self, ctx: &mut Context, genesis: &[u8]) -> Result<()> {
+        let gs = serde_json::from_slice::<GenesisState>(genesis).c(d!())?;
+
+        let mut state = ctx.state.write();
+        for (who, sa) in gs.accounts.iter() {
+            AccountStore::insert(state.borrow_mut(), who, sa).c(d!())?;
+        }
+        TotalIssuance::put(state.borrow_mut(), &gs.total_issuance).c(d!())?;
+        for (owner, spender, amount) in gs.allowances.iter() {
+            Allowances::insert(state.borrow_mut(), owner, spender, amount).c(d!())?;
+        }
+        Ok(())
     }
 
     fn validate_genesis(&self) -> Result<()> {
         todo!()
     }
 
-    fn export_genesis(&self) {
-        todo!()
+    fn export_genesis(&self, ctx: &Context) -> Result<Vec<u8>> {
+        let state = ctx.state.read();
+        let gs = GenesisState {
+            accounts: AccountStore::iterate(state.borrow()),
+            total_issuance: TotalIssuance::get(state.borrow()).unwrap_or_default(),
+            allowances: Allowances::iterate(state.borrow()),
+        };
+        serde_json::to_vec(&gs).c(d!())
     }
 }
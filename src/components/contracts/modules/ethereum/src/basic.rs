@@ -1,6 +1,19 @@
-use super::{App, Config, MODULE_NAME};
-use fp_core::module::AppModuleBasic;
-use ruc::Result;
+use super::{storage::*, App, Config, MODULE_NAME};
+use fp_core::{context::Context, module::AppModuleBasic};
+use fp_storage::{Borrow, BorrowMut};
+use fp_types::crypto::HA160;
+use ruc::*;
+use serde::{Deserialize, Serialize};
+
+/// Genesis state of the ethereum module.
+#[derive(Default, Serialize, Deserialize)]
+pub struct GenesisState {
+    /// The accounts that can send transactions without signing them,
+    /// only set by `fn dev create --impersonate`
+    /// and only accepted by the `debug_env` builds.
+    #[serde(default)]
+    pub impersonated: Vec<HA160>,
+}
 
 impl<C: Config> AppModuleBasic for App<C> {
     fn name() -> String {
@@ -11,15 +24,31 @@ impl<C: Config> AppModuleBasic for App<C> {
         todo!()
     }
 
-    fn init_genesis(&self) {
-        todo!()
+    fn init_genesis(&self, ctx: &mut Context, genesis: &[u8]) -> Result<()> {
+        let gs = serde_json::from_slice::<GenesisState>(genesis).c(d!())?;
+        if !gs.impersonated.is_empty() && !cfg!(feature = "debug_env") {
+            return Err(eg!("impersonated accounts need a `debug_env` build"));
+        }
+
+        let mut state = ctx.state.write();
+        for address in gs.impersonated.iter() {
+            Impersonated::insert(state.borrow_mut(), address, &true).c(d!())?;
+        }
+        Ok(())
     }
 
     fn validate_genesis(&self) -> Result<()> {
         todo!()
     }
 
-    fn export_genesis(&self) {
-        todo!()
+    fn export_genesis(&self, ctx: &Context) -> Result<Vec<u8>> {
+        let impersonated = Impersonated::iterate(ctx.state.read().borrow())
+            .into_iter()
+            .map(|(address, _)| address)
+            .collect::<Vec<_>>();
+        if impersonated.is_empty() {
+            return Ok(vec![]);
+        }
+        serde_json::to_vec(&GenesisState { impersonated }).c(d!())
     }
 }
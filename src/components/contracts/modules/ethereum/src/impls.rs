@@ -2,11 +2,11 @@ use crate::storage::*;
 use crate::{App, Config, ContractLog, TransactionExecuted};
 use config::abci::global_cfg::CFG;
 use ethereum::{
-    BlockV0 as Block, LegacyTransactionMessage, ReceiptV0 as Receipt,
-    TransactionV0 as Transaction,
+    BlockV0 as Block, LegacyTransactionMessage, ReceiptV0 as Receipt, TransactionAction,
+    TransactionSignature, TransactionV0 as Transaction,
 };
 use ethereum_types::{Bloom, BloomInput, H160, H256, H64, U256};
-use evm::{ExitFatal, ExitReason};
+use evm::{ExitError, ExitFatal, ExitReason};
 use fp_core::{
     context::{Context, RunTxMode},
     macros::Get,
@@ -18,7 +18,7 @@ use fp_evm::{BlockId, CallOrCreateInfo, Runner, TransactionStatus};
 use fp_storage::{Borrow, BorrowMut};
 use fp_types::{
     actions::evm as EvmAction,
-    crypto::{secp256k1_ecdsa_recover, HA256},
+    crypto::{secp256k1_ecdsa_recover, HA160, HA256},
 };
 use fp_utils::{proposer_converter, timestamp_converter};
 use ruc::*;
@@ -28,7 +28,40 @@ use tracing::{debug, info};
 #[cfg(feature = "web3_service")]
 use enterprise_web3::{TxState, BLOCK, RECEIPTS, TXS, WEB3_SERVICE_START_HEIGHT};
 
+/// The signature of a transaction sent from an impersonated account,
+/// `r` holds the sender and `s` is `1`, which a real signature never looks like.
+pub fn impersonated_signature(
+    sender: H160,
+    chain_id: u64,
+) -> Option<TransactionSignature> {
+    let mut r = H256::zero();
+    r.as_bytes_mut()[12..].copy_from_slice(sender.as_bytes());
+    TransactionSignature::new(chain_id * 2 + 35, r, H256::from_low_u64_be(1))
+}
+
 impl<C: Config> App<C> {
+    /// The sender of a transaction signed by `impersonated_signature`,
+    /// if it is one of the impersonated accounts of the chain.
+    ///
+    /// Always `None` outside the `debug_env` builds.
+    pub fn impersonated_signer(
+        ctx: &Context,
+        transaction: &Transaction,
+    ) -> Option<H160> {
+        if !cfg!(feature = "debug_env") {
+            return None;
+        }
+
+        let sig = &transaction.signature;
+        if H256::from_low_u64_be(1) != *sig.s() || sig.r()[..12] != [0u8; 12] {
+            return None;
+        }
+
+        let sender = H160::from_slice(&sig.r()[12..]);
+        Impersonated::contains_key(ctx.state.read().borrow(), &HA160(sender))
+            .then_some(sender)
+    }
+
     pub fn recover_signer_fast(
         ctx: &Context,
         transaction: &Transaction,
@@ -55,10 +88,12 @@ impl<C: Config> App<C> {
         let mut txn_signers = ctx.eth_cache.current.write();
         match txn_signers.get(&transaction_hash) {
             Some(signer) => *signer,
-            None => Self::recover_signer(transaction).map(|signer| {
-                txn_signers.insert(transaction_hash, Some(signer));
-                signer
-            }),
+            None => Self::impersonated_signer(ctx, transaction)
+                .or_else(|| Self::recover_signer(transaction))
+                .map(|signer| {
+                    txn_signers.insert(transaction_hash, Some(signer));
+                    signer
+                }),
         }
     }
 
@@ -133,7 +168,7 @@ impl<C: Config> App<C> {
             logs_bloom,
             difficulty: U256::zero(),
             number: block_number,
-            gas_limit: C::BlockGasLimit::get(),
+            gas_limit: Self::block_gas_limit(ctx),
             gas_used: receipts
                 .clone()
                 .into_iter()
@@ -148,6 +183,10 @@ impl<C: Config> App<C> {
 
         CurrentBlockNumber::put(ctx.db.write().borrow_mut(), &block_number)?;
         BlockHash::insert(ctx.db.write().borrow_mut(), &block_number, &block_hash)?;
+        if Self::log_index_tail(ctx).is_none() {
+            LogIndexTail::put(ctx.db.write().borrow_mut(), &block_number)?;
+        }
+        Self::index_logs_bloom(ctx, block_number, &block.header.logs_bloom)?;
         if is_store_block {
             CurrentBlock::insert(ctx.db.write().borrow_mut(), &block_hash, &block)?;
             CurrentReceipts::insert(
@@ -214,7 +253,6 @@ impl<C: Config> App<C> {
         debug!(target: "ethereum", "transact ethereum transaction: {:?}", transaction);
 
         let mut events = vec![];
-        let just_check = ctx.run_mode != RunTxMode::Deliver;
 
         let source = Self::recover_signer_fast(ctx, &transaction)
             .ok_or_else(|| eg!("ExecuteTransaction: InvalidSignature"))?;
@@ -222,15 +260,12 @@ impl<C: Config> App<C> {
         let transaction_hash =
             H256::from_slice(Keccak256::digest(&rlp::encode(&transaction)).as_slice());
 
-        let transaction_index = if just_check {
-            0
-        } else {
-            let txns = DELIVER_PENDING_TRANSACTIONS.lock().c(d!())?;
-            txns.len() as u32
-        };
-
         let gas_limit = transaction.gas_limit;
 
+        if let Some(res) = Self::check_block_gas(ctx, &transaction, source)? {
+            return Ok(res);
+        }
+
         let execute_ret = Self::execute_transaction(
             ctx,
             source,
@@ -270,6 +305,128 @@ impl<C: Config> App<C> {
 
         let (to, contract_address, info) = execute_ret.unwrap();
 
+        Self::transaction_executed(
+            ctx,
+            transaction,
+            source,
+            to,
+            contract_address,
+            info,
+            events,
+            false,
+        )
+    }
+
+    /// Add an action executed by the EVM module to the pending block,
+    /// as the transaction built by `native_transaction`.
+    pub fn record_native_action(
+        ctx: &Context,
+        action: EvmAction::Action,
+        info: CallOrCreateInfo,
+    ) -> Result<ActionResult> {
+        let (source, transaction) = Self::native_transaction(action).c(d!())?;
+
+        let (to, contract_address) = match (&transaction.action, &info) {
+            (TransactionAction::Call(target), _) => (Some(*target), None),
+            (TransactionAction::Create, CallOrCreateInfo::Create(info)) => {
+                (None, Some(info.value))
+            }
+            _ => (None, None),
+        };
+
+        Self::transaction_executed(
+            ctx,
+            transaction,
+            source,
+            to,
+            contract_address,
+            info,
+            vec![],
+            true,
+        )
+    }
+
+    /// The transaction standing for an action of the EVM module.
+    ///
+    /// The action is not signed: the signature fields only hold the source address and the nonce,
+    /// so that the hashes of these transactions are unique. Its status is marked `native`
+    /// and it is served over the RPC with a zero signature.
+    fn native_transaction(action: EvmAction::Action) -> Result<(H160, Transaction)> {
+        let (source, action, input, value, gas_limit, gas_price, nonce) = match action {
+            EvmAction::Action::Call(args) => (
+                args.source,
+                TransactionAction::Call(args.target),
+                args.input,
+                args.value,
+                args.gas_limit,
+                args.gas_price,
+                args.nonce,
+            ),
+            EvmAction::Action::Create(args) => (
+                args.source,
+                TransactionAction::Create,
+                args.init,
+                args.value,
+                args.gas_limit,
+                args.gas_price,
+                args.nonce,
+            ),
+            EvmAction::Action::Create2(args) => (
+                args.source,
+                TransactionAction::Create,
+                args.init,
+                args.value,
+                args.gas_limit,
+                args.gas_price,
+                args.nonce,
+            ),
+        };
+        let nonce = nonce.unwrap_or_default();
+
+        let signature = TransactionSignature::new(
+            C::ChainId::get() * 2 + 35,
+            H256::from(source),
+            H256::from_low_u64_be(nonce.low_u64().saturating_add(1)),
+        )
+        .c(d!("invalid source address"))?;
+        let transaction = Transaction {
+            nonce,
+            gas_price: gas_price.unwrap_or_default(),
+            gas_limit: U256::from(gas_limit),
+            action,
+            value,
+            input,
+            signature,
+        };
+
+        Ok((source, transaction))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    /// Build the receipt of an executed transaction and add it to the pending block,
+    /// `native` for an action of the EVM module.
+    fn transaction_executed(
+        ctx: &Context,
+        transaction: Transaction,
+        source: H160,
+        to: Option<H160>,
+        contract_address: Option<H160>,
+        info: CallOrCreateInfo,
+        mut events: Vec<abci::Event>,
+        native: bool,
+    ) -> Result<ActionResult> {
+        let just_check = ctx.run_mode != RunTxMode::Deliver;
+        let gas_limit = transaction.gas_limit;
+        let transaction_hash =
+            H256::from_slice(Keccak256::digest(&rlp::encode(&transaction)).as_slice());
+
+        let transaction_index = if just_check {
+            0
+        } else {
+            let txns = DELIVER_PENDING_TRANSACTIONS.lock().c(d!())?;
+            txns.len() as u32
+        };
+
         let (reason, data, status, used_gas) = match info.clone() {
             CallOrCreateInfo::Call(info) => (
                 info.exit_reason,
@@ -286,6 +443,7 @@ impl<C: Config> App<C> {
                         Self::logs_bloom(info.logs, &mut bloom);
                         bloom
                     },
+                    native,
                 },
                 info.used_gas,
             ),
@@ -304,6 +462,7 @@ impl<C: Config> App<C> {
                         Self::logs_bloom(info.logs, &mut bloom);
                         bloom
                     },
+                    native,
                 },
                 info.used_gas,
             ),
@@ -365,24 +524,7 @@ impl<C: Config> App<C> {
         };
 
         if !just_check {
-            {
-                let mut pending_txs = DELIVER_PENDING_TRANSACTIONS.lock().c(d!())?;
-                pending_txs.push((transaction, status, receipt));
-            }
-
-            if ctx.header.height < CFG.checkpoint.tx_revert_on_error_height {
-                TransactionIndex::insert(
-                    ctx.state.write().borrow_mut(),
-                    &HA256::new(transaction_hash),
-                    &(ctx.header.height.into(), transaction_index),
-                )?;
-            } else {
-                TransactionIndex::insert(
-                    ctx.db.write().borrow_mut(),
-                    &HA256::new(transaction_hash),
-                    &(ctx.header.height.into(), transaction_index),
-                )?;
-            }
+            Self::add_pending_transaction(ctx, transaction, status, receipt)?;
         }
 
         events.push(Event::emit_event(
@@ -455,6 +597,175 @@ impl<C: Config> App<C> {
         }
     }
 
+    /// The gas limit of the current block.
+    pub fn block_gas_limit(ctx: &Context) -> U256 {
+        if ctx.header.height < CFG.checkpoint.evm_block_gas_limit_height {
+            C::BlockGasLimit::get()
+        } else {
+            C::BlockGasLimit::get().min(CFG.checkpoint.evm_block_gas_limit.into())
+        }
+    }
+
+    /// The gas left in the current block for the pending transactions.
+    pub fn remaining_block_gas(ctx: &Context) -> Result<U256> {
+        let gas_used = DELIVER_PENDING_TRANSACTIONS
+            .lock()
+            .c(d!())?
+            .iter()
+            .fold(U256::zero(), |acc, (_, _, r)| {
+                acc.saturating_add(r.used_gas)
+            });
+        Ok(Self::block_gas_limit(ctx).saturating_sub(gas_used))
+    }
+
+    /// Reject a transaction whose gas limit exceeds the gas left in the block.
+    ///
+    /// The transaction is not executed, it is added to the block with a failed receipt
+    /// using no gas, like a reverted one, and can be sent again in a later block.
+    pub(crate) fn check_block_gas(
+        ctx: &Context,
+        transaction: &Transaction,
+        source: H160,
+    ) -> Result<Option<ActionResult>> {
+        match Self::block_gas_exceeded(ctx, transaction.gas_limit)? {
+            Some(remaining) => Self::reject_transaction(
+                ctx,
+                transaction.clone(),
+                source,
+                remaining,
+                false,
+            )
+            .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Same as `check_block_gas`, for an action of the EVM module,
+    /// `nonce` is the one used by its execution.
+    pub(crate) fn check_native_block_gas(
+        ctx: &Context,
+        action: &EvmAction::Action,
+        nonce: U256,
+    ) -> Result<Option<ActionResult>> {
+        let gas_limit = match action {
+            EvmAction::Action::Call(args) => args.gas_limit,
+            EvmAction::Action::Create(args) => args.gas_limit,
+            EvmAction::Action::Create2(args) => args.gas_limit,
+        };
+        let remaining = match Self::block_gas_exceeded(ctx, gas_limit.into())? {
+            Some(remaining) => remaining,
+            None => return Ok(None),
+        };
+
+        let mut action = action.clone();
+        match &mut action {
+            EvmAction::Action::Call(args) => args.nonce = Some(nonce),
+            EvmAction::Action::Create(args) => args.nonce = Some(nonce),
+            EvmAction::Action::Create2(args) => args.nonce = Some(nonce),
+        }
+        let (source, transaction) = Self::native_transaction(action).c(d!())?;
+        Self::reject_transaction(ctx, transaction, source, remaining, true).map(Some)
+    }
+
+    /// The gas left in the block if `gas_limit` exceeds it, only checked in deliver mode.
+    fn block_gas_exceeded(ctx: &Context, gas_limit: U256) -> Result<Option<U256>> {
+        if ctx.run_mode != RunTxMode::Deliver {
+            return Ok(None);
+        }
+
+        let remaining = Self::remaining_block_gas(ctx)?;
+        Ok((gas_limit > remaining).then_some(remaining))
+    }
+
+    fn reject_transaction(
+        ctx: &Context,
+        transaction: Transaction,
+        source: H160,
+        remaining: U256,
+        native: bool,
+    ) -> Result<ActionResult> {
+        let gas_limit = transaction.gas_limit;
+        info!(target: "ethereum", "block gas limit exceeded: gas limit {}, remaining gas {}", gas_limit, remaining);
+
+        let transaction_hash =
+            H256::from_slice(Keccak256::digest(&rlp::encode(&transaction)).as_slice());
+        let transaction_index =
+            DELIVER_PENDING_TRANSACTIONS.lock().c(d!())?.len() as u32;
+        let to = match transaction.action {
+            TransactionAction::Call(target) => Some(target),
+            TransactionAction::Create => None,
+        };
+        let status = TransactionStatus {
+            transaction_hash,
+            transaction_index,
+            from: source,
+            to,
+            contract_address: None,
+            logs: vec![],
+            logs_bloom: Bloom::default(),
+            native,
+        };
+        let receipt = Receipt {
+            state_root: H256::from_low_u64_le(0),
+            used_gas: U256::zero(),
+            logs_bloom: Bloom::default(),
+            logs: vec![],
+        };
+        Self::add_pending_transaction(ctx, transaction, status, receipt)?;
+
+        let reason = ExitReason::Error(ExitError::OutOfGas);
+        let events = vec![Event::emit_event(
+            Self::name(),
+            TransactionExecuted {
+                sender: source,
+                to: to.unwrap_or_default(),
+                contract_address: Default::default(),
+                transaction_hash,
+                reason,
+            },
+        )];
+
+        Ok(ActionResult {
+            code: 5,
+            log: format!(
+                "BlockGasLimitExceeded: gas limit {gas_limit}, remaining gas of the block {remaining}"
+            ),
+            gas_wanted: gas_limit.low_u64(),
+            events,
+            ..Default::default()
+        })
+    }
+
+    /// Add a delivered transaction and its receipt to the pending block.
+    fn add_pending_transaction(
+        ctx: &Context,
+        transaction: Transaction,
+        status: TransactionStatus,
+        receipt: Receipt,
+    ) -> Result<()> {
+        let transaction_hash = status.transaction_hash;
+        let transaction_index = status.transaction_index;
+        {
+            let mut pending_txs = DELIVER_PENDING_TRANSACTIONS.lock().c(d!())?;
+            pending_txs.push((transaction, status, receipt));
+        }
+
+        if ctx.header.height < CFG.checkpoint.tx_revert_on_error_height {
+            TransactionIndex::insert(
+                ctx.state.write().borrow_mut(),
+                &HA256::new(transaction_hash),
+                &(ctx.header.height.into(), transaction_index),
+            )?;
+        } else {
+            TransactionIndex::insert(
+                ctx.db.write().borrow_mut(),
+                &HA256::new(transaction_hash),
+                &(ctx.header.height.into(), transaction_index),
+            )?;
+        }
+        Ok(())
+    }
+
     /// Get the transaction status with given block id.
     pub fn current_transaction_statuses(
         &self,
@@ -521,7 +832,7 @@ impl<C: Config> App<C> {
         }
     }
 
-    fn get_hash(ctx: &Context, number: U256) -> Option<H256> {
+    pub(crate) fn get_hash(ctx: &Context, number: U256) -> Option<H256> {
         if let Some(hash) = BlockHash::get(ctx.db.read().borrow(), &number) {
             return Some(hash.h256());
         }
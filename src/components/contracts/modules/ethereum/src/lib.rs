@@ -3,6 +3,7 @@
 
 mod basic;
 mod impls;
+mod log_index;
 
 use abci::{RequestEndBlock, ResponseEndBlock};
 use config::abci::global_cfg::CFG;
@@ -17,15 +18,24 @@ use fp_core::{
     transaction::{ActionResult, Executable, ValidateUnsigned},
 };
 use fp_events::*;
-use fp_evm::{BlockId, Runner};
+use fp_evm::{BlockId, CallOrCreateInfo, Runner};
 use fp_traits::{
     account::AccountAsset,
-    evm::{AddressMapping, BlockHashMapping, DecimalsMapping, FeeCalculator},
+    evm::{
+        ActionRecorder, AddressMapping, BlockHashMapping, DecimalsMapping, FeeCalculator,
+    },
+};
+use fp_types::{
+    actions::{ethereum::Action, evm::Action as EvmAction},
+    crypto::Address,
 };
-use fp_types::{actions::ethereum::Action, crypto::Address};
 use ruc::*;
 use std::marker::PhantomData;
 
+pub use basic::GenesisState;
+pub use impls::impersonated_signature;
+pub use log_index::BLOOM_SECTION_SIZE;
+
 pub const MODULE_NAME: &str = "ethereum";
 
 static ISTANBUL_CONFIG: EvmConfig = EvmConfig::istanbul();
@@ -60,7 +70,7 @@ pub mod storage {
     use ethereum_types::U256;
     use fp_evm::TransactionStatus;
     use fp_storage::*;
-    use fp_types::crypto::HA256;
+    use fp_types::crypto::{HA160, HA256};
     use lazy_static::lazy_static;
     use std::sync::Mutex;
 
@@ -77,6 +87,12 @@ pub mod storage {
     generate_storage!(Ethereum, CurrentReceipts => Map<HA256, Vec<Receipt>>);
     // The ethereum history transaction statuses with block number.
     generate_storage!(Ethereum, CurrentTransactionStatuses => Map<HA256, Vec<TransactionStatus>>);
+    // The bitsets of the blocks having a bit of the logs bloom, by section and bit.
+    generate_storage!(Ethereum, BloomBits => DoubleMap<u64, u32, Vec<u8>>);
+    // The first block covered by the log index.
+    generate_storage!(Ethereum, LogIndexTail => Value<U256>);
+    // The accounts that can send transactions without signing them, dev chains only.
+    generate_storage!(Ethereum, Impersonated => Map<HA160, bool>);
 
     // The following data is stored in in-memory array
     // Current building block's transactions and receipts.
@@ -191,13 +207,13 @@ impl<C: Config> ValidateUnsigned for App<C> {
             .ok_or_else(|| eg!("ExecuteTransaction: InvalidSignature"))?;
 
         // Same as go ethereum, Min gas limit is 21000.
+        let block_gas_limit = Self::block_gas_limit(ctx);
         if transaction.gas_limit < U256::from(21000)
-            || transaction.gas_limit > C::BlockGasLimit::get()
+            || transaction.gas_limit > block_gas_limit
         {
             return Err(eg!(format!(
                 "InvalidGasLimit: got {}, the gas limit must be in range [21000, {}]",
-                transaction.gas_limit,
-                C::BlockGasLimit::get()
+                transaction.gas_limit, block_gas_limit
             )));
         }
 
@@ -240,3 +256,25 @@ impl<C: Config> BlockHashMapping for App<C> {
         Self::block_hash(ctx, Some(BlockId::Number(number)))
     }
 }
+
+impl<C: Config> ActionRecorder for App<C> {
+    fn block_gas_limit(ctx: &Context) -> U256 {
+        Self::block_gas_limit(ctx)
+    }
+
+    fn check_block_gas(
+        ctx: &Context,
+        action: &EvmAction,
+        nonce: U256,
+    ) -> Result<Option<ActionResult>> {
+        Self::check_native_block_gas(ctx, action, nonce)
+    }
+
+    fn record_action(
+        ctx: &Context,
+        action: EvmAction,
+        info: CallOrCreateInfo,
+    ) -> Result<ActionResult> {
+        Self::record_native_action(ctx, action, info)
+    }
+}
@@ -0,0 +1,121 @@
+//! Index of the logs blooms, in the style of the bloombits of geth.
+//!
+//! Blocks are grouped into sections of `BLOOM_SECTION_SIZE` blocks. For each bit of
+//! the 2048-bit logs bloom, a section stores a bitset of the blocks having that bit,
+//! so the blocks which may contain the logs of an address or a topic are found by
+//! AND-ing the bitsets of its 3 bits, without loading any block.
+//!
+//! The index covers the blocks from `LogIndexTail` to the current one,
+//! the older blocks are added by `backfill_log_index`.
+
+use crate::storage::*;
+use crate::{App, Config};
+use ethereum_types::{Bloom, U256};
+use fp_core::context::Context;
+use fp_storage::{Borrow, BorrowMut};
+use fp_types::crypto::HA256;
+use ruc::*;
+use std::collections::BTreeMap;
+use tracing::info;
+
+/// Number of blocks in a section of the index.
+pub const BLOOM_SECTION_SIZE: u64 = 4096;
+
+const SECTION_BYTES: usize = (BLOOM_SECTION_SIZE / 8) as usize;
+
+impl<C: Config> App<C> {
+    /// Add the logs bloom of a block to the index.
+    pub fn index_logs_bloom(ctx: &Context, number: U256, bloom: &Bloom) -> Result<()> {
+        Self::index_logs_blooms(ctx, &[(number.low_u64(), *bloom)])
+    }
+
+    /// Add the logs blooms of several blocks to the index,
+    /// the bitset of a bit is written once per section.
+    pub fn index_logs_blooms(ctx: &Context, blooms: &[(u64, Bloom)]) -> Result<()> {
+        let mut sections: BTreeMap<u64, BTreeMap<u32, Vec<u8>>> = BTreeMap::new();
+        for (number, bloom) in blooms {
+            let section = number / BLOOM_SECTION_SIZE;
+            let pos = (number % BLOOM_SECTION_SIZE) as usize;
+            let bitsets = sections.entry(section).or_default();
+            for bit in set_bits(bloom) {
+                let bits = bitsets.entry(bit).or_insert_with(|| {
+                    BloomBits::get(ctx.db.read().borrow(), &section, &bit)
+                        .unwrap_or_else(|| vec![0; SECTION_BYTES])
+                });
+                bits[pos / 8] |= 0x80 >> (pos % 8);
+            }
+        }
+
+        for (section, bitsets) in sections.iter() {
+            for (bit, bits) in bitsets.iter() {
+                BloomBits::insert(ctx.db.write().borrow_mut(), section, bit, bits)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The first block covered by the log index.
+    pub fn log_index_tail(ctx: &Context) -> Option<U256> {
+        LogIndexTail::get(ctx.db.read().borrow())
+    }
+
+    /// Bitset of the blocks in `section` whose logs bloom contains `bloom`,
+    /// the most significant bit of the first byte is the first block of the section.
+    pub fn bloom_section(ctx: &Context, section: u64, bloom: &Bloom) -> Vec<u8> {
+        let mut ret = vec![0xff; SECTION_BYTES];
+        for bit in set_bits(bloom) {
+            match BloomBits::get(ctx.db.read().borrow(), &section, &bit) {
+                Some(bits) => ret.iter_mut().zip(bits).for_each(|(r, b)| *r &= b),
+                None => return vec![0; SECTION_BYTES],
+            }
+        }
+        ret
+    }
+
+    /// Index at most `max_blocks` blocks before the tail of the index and not before `from`,
+    /// returns the new tail.
+    ///
+    /// Without any indexed block, the index starts from the block after the current one.
+    pub fn backfill_log_index(
+        ctx: &Context,
+        from: U256,
+        max_blocks: u64,
+    ) -> Result<U256> {
+        let tail = match Self::log_index_tail(ctx) {
+            Some(tail) => tail,
+            None => Self::current_block_number(ctx)
+                .c(d!("no ethereum block"))?
+                .saturating_add(U256::one()),
+        };
+
+        let mut number = tail;
+        let mut cnt = 0;
+        let mut blooms = vec![];
+        while number > from && cnt < max_blocks {
+            number = number.saturating_sub(U256::one());
+            cnt += 1;
+
+            let block = Self::get_hash(ctx, number).and_then(|hash| {
+                CurrentBlock::get(ctx.db.read().borrow(), &HA256::new(hash))
+            });
+            if let Some(block) = block {
+                blooms.push((number.low_u64(), block.header.logs_bloom));
+            }
+        }
+        Self::index_logs_blooms(ctx, &blooms)?;
+
+        if number != tail {
+            LogIndexTail::put(ctx.db.write().borrow_mut(), &number)?;
+            info!(target: "ethereum", "log index backfilled to block {}", number);
+        }
+        Ok(number)
+    }
+}
+
+fn set_bits(bloom: &Bloom) -> impl Iterator<Item = u32> + '_ {
+    bloom.as_bytes().iter().enumerate().flat_map(|(i, byte)| {
+        (0..8)
+            .filter(move |b| byte & (0x80 >> b) != 0)
+            .map(move |b| (i * 8 + b) as u32)
+    })
+}
@@ -0,0 +1,195 @@
+//! Per-block EVM gas budget tests.
+
+#![allow(clippy::field_reassign_with_default)]
+
+use abci::*;
+use baseapp::{BaseApp, ChainId};
+use config::abci::global_cfg::CFG;
+use ethereum::{ReceiptV0, TransactionV0};
+use ethereum_types::{Bloom, H160, H256, U256};
+use fp_evm::TransactionStatus;
+use fp_mocks::*;
+use fp_traits::evm::{ActionRecorder, FeeCalculator};
+use fp_types::{
+    actions::{
+        ethereum::Action as EthereumAction,
+        evm::{Action as EvmAction, Call},
+        Action,
+    },
+    assemble::UncheckedTransaction,
+};
+use fp_utils::tx::EvmRawTxWrapper;
+use module_ethereum::storage::DELIVER_PENDING_TRANSACTIONS;
+use sha3::{Digest, Keccak256};
+
+type EthereumApp = module_ethereum::App<BaseApp>;
+
+const GAS_LIMIT: u64 = 0x100000;
+
+fn build_transfer_transaction(to: H160, balance: U256, nonce: U256) -> TransactionV0 {
+    let tx = UnsignedTransaction {
+        nonce,
+        gas_price: <BaseApp as module_ethereum::Config>::FeeCalculator::min_gas_price(),
+        gas_limit: U256::from(GAS_LIMIT),
+        action: ethereum::TransactionAction::Call(to),
+        value: balance,
+        input: Vec::new(),
+    };
+
+    tx.sign(&ALICE_ECDSA.private_key, ChainId::get())
+}
+
+fn deliver_tx(tx: &TransactionV0) -> ResponseDeliverTx {
+    let function = Action::Ethereum(EthereumAction::Transact(tx.clone()));
+    let tx =
+        serde_json::to_vec(&UncheckedTransaction::<()>::new_unsigned(function)).unwrap();
+    let mut req = RequestDeliverTx::default();
+    req.tx = EvmRawTxWrapper::wrap(&tx);
+    BASE_APP.lock().unwrap().deliver_tx(&req)
+}
+
+fn begin_block(height: i64) {
+    let mut req = RequestBeginBlock::default();
+    req.hash = height.to_be_bytes().to_vec();
+    let mut header = Header::default();
+    header.height = height;
+    req.set_header(header);
+    let _ = BASE_APP.lock().unwrap().begin_block(&req);
+}
+
+fn end_block(height: i64) {
+    let mut req = RequestEndBlock::default();
+    req.height = height;
+    let _ = BASE_APP.lock().unwrap().end_block(&req);
+    let _ = BASE_APP.lock().unwrap().commit(&RequestCommit::new());
+}
+
+#[test]
+fn block_gas_budget() {
+    let height = CFG
+        .checkpoint
+        .evm_block_gas_limit_height
+        .max(CFG.checkpoint.tx_revert_on_error_height)
+        + 2;
+    test_mint_balance(
+        &ALICE_ECDSA.account_id,
+        100_0000_0000_0000_0000_u64.into(),
+        height as u64 - 1,
+    );
+
+    begin_block(height);
+
+    // a heavy transaction already used most of the gas of the block
+    let block_gas_limit = {
+        let app = BASE_APP.lock().unwrap();
+        let ctx = &app.deliver_state;
+        let block_gas_limit = EthereumApp::block_gas_limit(ctx);
+        assert!(block_gas_limit <= U256::from(CFG.checkpoint.evm_block_gas_limit));
+        block_gas_limit
+    };
+    let heavy_tx = build_transfer_transaction(H160::zero(), 0.into(), 1000.into());
+    DELIVER_PENDING_TRANSACTIONS.lock().unwrap().push((
+        heavy_tx,
+        TransactionStatus::default(),
+        ReceiptV0 {
+            state_root: H256::from_low_u64_be(1),
+            used_gas: block_gas_limit - GAS_LIMIT + 1,
+            logs_bloom: Bloom::default(),
+            logs: vec![],
+        },
+    ));
+
+    // the transfer does not fit in the gas left
+    let tx = build_transfer_transaction(BOB_ECDSA.address, 10.into(), 0.into());
+    let tx_hash = H256::from_slice(Keccak256::digest(&rlp::encode(&tx)).as_slice());
+    let resp = deliver_tx(&tx);
+    assert_eq!(resp.code, 5, "log: {}", resp.log);
+    assert!(resp.log.contains("BlockGasLimitExceeded"));
+    assert_eq!(resp.gas_used, 0);
+
+    // it is recorded with a failed receipt
+    {
+        let pending = DELIVER_PENDING_TRANSACTIONS.lock().unwrap();
+        assert_eq!(2, pending.len());
+        let (_, status, receipt) = pending.last().unwrap();
+        assert_eq!(status.transaction_hash, tx_hash);
+        assert_eq!(status.transaction_index, 1);
+        assert_eq!(status.to, Some(BOB_ECDSA.address));
+        assert!(!status.native);
+        assert_eq!(receipt.state_root, H256::zero());
+        assert_eq!(receipt.used_gas, U256::zero());
+    }
+
+    // an action of the EVM module that does not fit is recorded as a native transaction
+    {
+        let app = BASE_APP.lock().unwrap();
+        let action = EvmAction::Call(Call {
+            source: ALICE_ECDSA.address,
+            target: BOB_ECDSA.address,
+            input: vec![],
+            value: 10.into(),
+            gas_limit: GAS_LIMIT,
+            gas_price: None,
+            nonce: None,
+        });
+        let resp = <EthereumApp as ActionRecorder>::check_block_gas(
+            &app.deliver_state,
+            &action,
+            1.into(),
+        )
+        .unwrap();
+        assert!(resp.is_some());
+    }
+    {
+        let pending = DELIVER_PENDING_TRANSACTIONS.lock().unwrap();
+        assert_eq!(3, pending.len());
+        let (_, status, _) = pending.last().unwrap();
+        assert_eq!(status.from, ALICE_ECDSA.address);
+        assert_eq!(status.to, Some(BOB_ECDSA.address));
+        assert!(status.native);
+    }
+    {
+        let app = BASE_APP.lock().unwrap();
+        assert_eq!(
+            EthereumApp::transaction_index(&app.deliver_state, tx_hash),
+            Some((U256::from(height as u64), 1))
+        );
+        assert_eq!(
+            module_account::App::<BaseApp>::balance(
+                &app.deliver_state,
+                &BOB_ECDSA.account_id
+            ),
+            U256::zero()
+        );
+    }
+
+    end_block(height);
+
+    // the budget is reset in the next block, where the transaction can be sent again
+    begin_block(height + 1);
+    {
+        let app = BASE_APP.lock().unwrap();
+        assert_eq!(
+            EthereumApp::remaining_block_gas(&app.deliver_state).unwrap(),
+            block_gas_limit
+        );
+    }
+    let resp = deliver_tx(&tx);
+    assert_eq!(resp.code, 0, "log: {}", resp.log);
+    {
+        let app = BASE_APP.lock().unwrap();
+        assert_eq!(
+            EthereumApp::transaction_index(&app.deliver_state, tx_hash),
+            Some((U256::from(height as u64 + 1), 0))
+        );
+        assert_eq!(
+            module_account::App::<BaseApp>::balance(
+                &app.deliver_state,
+                &BOB_ECDSA.account_id
+            ),
+            10.into()
+        );
+    }
+
+    end_block(height + 1);
+}
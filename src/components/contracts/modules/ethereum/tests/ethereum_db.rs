@@ -1,10 +1,11 @@
 use baseapp::BaseApp;
 use ethereum::{TransactionAction, TransactionSignature, TransactionV0};
+use ethereum_types::{Bloom, BloomInput};
 use fin_db::{FinDB, RocksDB};
 use fp_core::context::Context;
 use fp_storage::{Borrow, BorrowMut, RwLock};
 use fp_types::crypto::HA256;
-use fp_types::{H256, U256};
+use fp_types::{H160, H256, U256};
 use module_ethereum::storage::TransactionIndex;
 use sha3::{Digest, Keccak256};
 use std::{env::temp_dir, sync::Arc, time::SystemTime};
@@ -78,3 +79,46 @@ fn test_eth_db_migrate_txn_index() {
         assert_eq!(value.unwrap(), txn.1);
     }
 }
+
+#[test]
+fn test_eth_db_log_index() {
+    let ctx = setup();
+    let section_size = module_ethereum::BLOOM_SECTION_SIZE;
+
+    let address = H160::random();
+    let topic = H256::random();
+    let mut bloom = Bloom::default();
+    bloom.accrue(BloomInput::Raw(&address[..]));
+    bloom.accrue(BloomInput::Raw(&topic[..]));
+
+    // the 2nd block of the 2nd section, then the last block of the same section
+    // and the 3rd block of the next section in a batch
+    module_ethereum::App::<BaseApp>::index_logs_bloom(
+        &ctx,
+        U256::from(section_size + 1),
+        &bloom,
+    )
+    .unwrap();
+    module_ethereum::App::<BaseApp>::index_logs_blooms(
+        &ctx,
+        &[(2 * section_size - 1, bloom), (2 * section_size + 2, bloom)],
+    )
+    .unwrap();
+
+    let blocks_of = |input: &[u8], section: u64| {
+        let bits = module_ethereum::App::<BaseApp>::bloom_section(
+            &ctx,
+            section,
+            &Bloom::from(BloomInput::Raw(input)),
+        );
+        (0..section_size)
+            .filter(|i| bits[(i / 8) as usize] & (0x80 >> (i % 8)) != 0)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(blocks_of(&address[..], 1), vec![1, section_size - 1]);
+    assert_eq!(blocks_of(&topic[..], 1), vec![1, section_size - 1]);
+    assert_eq!(blocks_of(&address[..], 2), vec![2]);
+    assert!(blocks_of(&address[..], 0).is_empty());
+    assert!(blocks_of(&H160::random()[..], 1).is_empty());
+}
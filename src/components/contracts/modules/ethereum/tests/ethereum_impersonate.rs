@@ -0,0 +1,78 @@
+//! Impersonated accounts of a dev chain, only enabled by the `debug_env` builds.
+
+use baseapp::BaseApp;
+use ethereum::{TransactionAction, TransactionV0};
+use fin_db::{FinDB, RocksDB};
+use fp_core::{context::Context, module::AppModuleBasic};
+use fp_storage::RwLock;
+use fp_types::{crypto::HA160, H160, U256};
+use module_ethereum::{impersonated_signature, GenesisState};
+use std::{env::temp_dir, sync::Arc, time::SystemTime};
+use storage::state::ChainState;
+
+type EthereumApp = module_ethereum::App<BaseApp>;
+
+fn setup() -> Context {
+    let time = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    let mut path = temp_dir();
+    path.push(format!("temp-findora-db–{time}"));
+
+    let fdb = FinDB::open(path).unwrap();
+    let chain_state = Arc::new(RwLock::new(ChainState::new(
+        fdb,
+        "temp_db".to_string(),
+        100,
+    )));
+
+    let mut rocks_path = temp_dir();
+    rocks_path.push(format!("temp-rocks-db–{time}"));
+
+    let rdb = RocksDB::open(rocks_path).unwrap();
+    let chain_db = Arc::new(RwLock::new(ChainState::new(
+        rdb,
+        "temp_rocks_db".to_string(),
+        0,
+    )));
+
+    Context::new(chain_state, chain_db)
+}
+
+fn unsigned_transaction(from: H160) -> TransactionV0 {
+    TransactionV0 {
+        nonce: U256::zero(),
+        gas_price: Default::default(),
+        gas_limit: Default::default(),
+        action: TransactionAction::Create,
+        value: Default::default(),
+        input: vec![],
+        signature: impersonated_signature(from, 2152).unwrap(),
+    }
+}
+
+#[test]
+fn test_impersonated_genesis() {
+    let mut ctx = setup();
+    let app = EthereumApp::default();
+    let sender = H160::from_low_u64_be(0xf00d);
+    let genesis = serde_json::to_vec(&GenesisState {
+        impersonated: vec![HA160(sender)],
+    })
+    .unwrap();
+
+    let tx = unsigned_transaction(sender);
+    if cfg!(feature = "debug_env") {
+        app.init_genesis(&mut ctx, &genesis).unwrap();
+        assert_eq!(EthereumApp::impersonated_signer(&ctx, &tx), Some(sender));
+        let other = unsigned_transaction(H160::from_low_u64_be(0xbeef));
+        assert_eq!(EthereumApp::impersonated_signer(&ctx, &other), None);
+        assert_eq!(app.export_genesis(&ctx).unwrap(), genesis);
+    } else {
+        // a release node never accepts unsigned transactions
+        assert!(app.init_genesis(&mut ctx, &genesis).is_err());
+        assert_eq!(EthereumApp::impersonated_signer(&ctx, &tx), None);
+        assert!(app.export_genesis(&ctx).unwrap().is_empty());
+    }
+}
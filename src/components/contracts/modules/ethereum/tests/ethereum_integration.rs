@@ -83,6 +83,10 @@ fn test_abci_check_tx() {
         "check tx failed, code: {}, log: {}",
         resp.code, resp.log
     );
+
+    // the priority is the gas price in gwei
+    let gas_price = <BaseApp as module_ethereum::Config>::FeeCalculator::min_gas_price();
+    assert_eq!(resp.priority, (gas_price / U256::exp10(9)).as_u64() as i64);
 }
 
 fn test_abci_begin_block() {
@@ -1,6 +1,19 @@
-use super::{App, Config, MODULE_NAME};
-use fp_core::module::AppModuleBasic;
-use ruc::Result;
+use super::{storage::*, App, Config, MODULE_NAME};
+use ethereum_types::H256;
+use fp_core::{context::Context, module::AppModuleBasic};
+use fp_storage::{Borrow, BorrowMut};
+use fp_types::crypto::{HA160, HA256};
+use ruc::*;
+use serde::{Deserialize, Serialize};
+
+/// Genesis state of the evm module.
+#[derive(Default, Serialize, Deserialize)]
+pub struct GenesisState {
+    /// Contract address => code
+    pub codes: Vec<(HA160, Vec<u8>)>,
+    /// Contract address => index => value
+    pub storages: Vec<(HA160, HA256, H256)>,
+}
 
 impl<C: Config> AppModuleBasic for App<C> {
     fn name() -> String {
@@ -8,18 +21,34 @@ impl<C: Config> AppModuleBasic for App<C> {
     }
 
     fn default_genesis(&self) -> Vec<u8> {
-        todo!()
+        serde_json::to_vec(&GenesisState::default()).unwrap_or_default()
     }
 
-    fn init_genesis(&self) {
-        todo!()
+    fn init_genesis(&self, ctx: &mut Context, genesis: &[u8]) -> Result<()> {
+        let gs = serde_json::from_slice::<GenesisState>(genesis).c(d!())?;
+
+        let mut state = ctx.state.write();
+        for (address, code) in gs.codes.iter() {
+            AccountCodes::insert_bytes(state.borrow_mut(), address, code.clone())
+                .c(d!())?;
+        }
+        for (address, index, value) in gs.storages.iter() {
+            AccountStorages::insert(state.borrow_mut(), address, index, value)
+                .c(d!())?;
+        }
+        Ok(())
     }
 
     fn validate_genesis(&self) -> Result<()> {
         todo!()
     }
 
-    fn export_genesis(&self) {
-        todo!()
+    fn export_genesis(&self, ctx: &Context) -> Result<Vec<u8>> {
+        let state = ctx.state.read();
+        let gs = GenesisState {
+            codes: AccountCodes::iterate_bytes(state.borrow()),
+            storages: AccountStorages::iterate(state.borrow()),
+        };
+        serde_json::to_vec(&gs).c(d!())
     }
 }
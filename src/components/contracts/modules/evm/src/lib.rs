@@ -10,21 +10,25 @@ pub mod runtime;
 
 use abci::{RequestQuery, ResponseQuery};
 use ethereum_types::U256;
-use evm::executor::stack::PrecompileSet as EvmPrecompileSet;
+use evm::{executor::stack::PrecompileSet as EvmPrecompileSet, Config as EvmConfig};
 use fp_core::{
     context::Context,
+    ensure,
     macros::Get,
     macros::Get2,
     module::AppModule,
-    transaction::{ActionResult, Executable},
+    transaction::{ActionResult, Executable, ValidateUnsigned},
 };
+use fp_evm::{CallOrCreateInfo, Runner};
 use fp_storage::Borrow;
 use fp_traits::{
     account::AccountAsset,
-    evm::{AddressMapping, BlockHashMapping, DecimalsMapping, FeeCalculator},
+    evm::{
+        ActionRecorder, AddressMapping, BlockHashMapping, DecimalsMapping, FeeCalculator,
+    },
 };
 use fp_types::{
-    actions::evm::Action,
+    actions::evm::{Action, Call, Create, Create2},
     crypto::{Address, HA160},
 };
 use precompile::PrecompileSet;
@@ -35,9 +39,13 @@ pub use runtime::*;
 
 pub const MODULE_NAME: &str = "evm";
 
+static ISTANBUL_CONFIG: EvmConfig = EvmConfig::istanbul();
+
 pub trait Config {
     /// Account module interface to read/write account assets.
     type AccountAsset: AccountAsset<Address>;
+    /// Recorder of the executed actions in the ethereum blocks.
+    type ActionRecorder: ActionRecorder;
     /// Mapping from address to account id.
     type AddressMapping: AddressMapping;
     /// The block gas limit. Can be a simple constant, or an adjustment algorithm in another pallet.
@@ -54,6 +62,10 @@ pub trait Config {
     type Precompiles: PrecompileSet;
     type PrecompilesType: EvmPrecompileSet;
     type PrecompilesValue: Get2<Self::PrecompilesType, Context>;
+    /// EVM config used in the module.
+    fn config() -> &'static EvmConfig {
+        &ISTANBUL_CONFIG
+    }
 }
 
 pub mod storage {
@@ -96,7 +108,7 @@ impl<C: Config> AppModule for App<C> {
         match path[0] {
             "contract-number" => {
                 let contracts: Vec<(HA160, Vec<u8>)> =
-                    storage::AccountCodes::iterate(ctx.state.read().borrow());
+                    storage::AccountCodes::iterate_bytes(ctx.state.read().borrow());
                 resp.value = serde_json::to_vec(&contracts.len()).unwrap_or_default();
                 resp
             }
@@ -109,11 +121,101 @@ impl<C: Config> Executable for App<C> {
     type Origin = Address;
     type Call = Action;
 
+    /// Execute an action signed by a Findora (Xfr) key.
+    ///
+    /// The nonce is checked by the signed extensions on the account of the source,
+    /// and the gas is paid as in ethereum transactions instead of a transaction fee.
     fn execute(
-        _origin: Option<Self::Origin>,
-        _call: Self::Call,
-        _ctx: &Context,
+        origin: Option<Self::Origin>,
+        call: Self::Call,
+        ctx: &Context,
     ) -> Result<ActionResult> {
-        Err(eg!("Unsupported evm action!"))
+        let (source, gas_limit) = match &call {
+            Action::Call(args) => (args.source, args.gas_limit),
+            Action::Create(args) => (args.source, args.gas_limit),
+            Action::Create2(args) => (args.source, args.gas_limit),
+        };
+
+        let origin = origin.c(d!("InvalidTransaction: unsigned evm action"))?;
+        let account_id = C::AddressMapping::convert_to_account_id(source);
+        ensure!(origin == account_id, "InvalidTransaction: IllegalOrigin");
+
+        // Same as go ethereum, Min gas limit is 21000.
+        let block_gas_limit = C::ActionRecorder::block_gas_limit(ctx);
+        ensure!(
+            21000 <= gas_limit && U256::from(gas_limit) <= block_gas_limit,
+            format!(
+                "InvalidGasLimit: got {}, the gas limit must be in range [21000, {}]",
+                gas_limit, block_gas_limit
+            )
+        );
+
+        // The nonce has been checked by the signed extensions,
+        // and is increased by the EVM.
+        let nonce = C::AccountAsset::nonce(ctx, &account_id);
+
+        if let Some(res) = C::ActionRecorder::check_block_gas(ctx, &call, nonce)? {
+            return Ok(res);
+        }
+        // Without a gas price, pay the minimum one instead of estimating the gas.
+        let gas_price = |price: Option<U256>| {
+            Some(price.unwrap_or_else(C::FeeCalculator::min_gas_price))
+        };
+
+        let (call, info) = match call {
+            Action::Call(mut args) => {
+                args.gas_price = gas_price(args.gas_price);
+                let info = runner::ActionRunner::<C>::call(
+                    ctx,
+                    Call {
+                        nonce: None,
+                        ..args.clone()
+                    },
+                    C::config(),
+                )?;
+                args.nonce = Some(nonce);
+                (Action::Call(args), CallOrCreateInfo::Call(info))
+            }
+            Action::Create(mut args) => {
+                args.gas_price = gas_price(args.gas_price);
+                let info = runner::ActionRunner::<C>::create(
+                    ctx,
+                    Create {
+                        nonce: None,
+                        ..args.clone()
+                    },
+                    C::config(),
+                )?;
+                args.nonce = Some(nonce);
+                (Action::Create(args), CallOrCreateInfo::Create(info))
+            }
+            Action::Create2(mut args) => {
+                args.gas_price = gas_price(args.gas_price);
+                let info = runner::ActionRunner::<C>::create2(
+                    ctx,
+                    Create2 {
+                        nonce: None,
+                        ..args.clone()
+                    },
+                    C::config(),
+                )?;
+                args.nonce = Some(nonce);
+                (Action::Create2(args), CallOrCreateInfo::Create(info))
+            }
+        };
+
+        C::ActionRecorder::record_action(ctx, call, info)
+    }
+}
+
+impl<C: Config> ValidateUnsigned for App<C> {
+    type Call = Action;
+
+    fn pre_execute(_ctx: &Context, _call: &Self::Call) -> Result<()> {
+        Ok(())
+    }
+
+    fn validate_unsigned(_ctx: &Context, _call: &Self::Call) -> Result<()> {
+        Err(eg!("InvalidTransaction: unsigned evm action"))
     }
 }
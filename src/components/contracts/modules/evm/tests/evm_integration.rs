@@ -8,9 +8,13 @@ use ethereum_types::{H160, H256, U256};
 use fp_evm::{CallOrCreateInfo, Runner};
 use fp_mocks::*;
 use fp_storage::Borrow;
+use fp_traits::evm::{AddressMapping, EthereumAddressMapping, FeeCalculator};
 use fp_types::{
-    actions::ethereum::Action as EthereumAction, actions::evm::Call, actions::Action,
+    actions::ethereum::Action as EthereumAction,
+    actions::evm::{Action as EvmAction, Call},
+    actions::Action,
     assemble::UncheckedTransaction,
+    crypto::{xfr_evm_address, Address, MultiSigner},
 };
 use fp_utils::tx::EvmRawTxWrapper;
 use module_evm::storage::*;
@@ -76,6 +80,11 @@ fn erc20_works() {
         1,
     );
     test_mint_balance(&BOB_ECDSA.account_id, 100_0000_0000_0000_0000_u64.into(), 1);
+    test_mint_balance(
+        &EthereumAddressMapping::convert_to_account_id(alice_xfr_address()),
+        100_0000_0000_0000_0000_u64.into(),
+        1,
+    );
 
     // erc20 initialize
     test_deploy_check_tx();
@@ -99,9 +108,22 @@ fn erc20_works() {
         9900.into()
     );
     assert_eq!(
-        test_balance_of_with_eth_call(erc20_instance, ALICE_ECDSA.address),
+        test_balance_of_with_eth_call(erc20_instance.clone(), ALICE_ECDSA.address),
         100.into()
     );
+
+    // erc20 balanceOf signed by a xfr key
+    assert_eq!(
+        test_balance_of_xfr_signed_deliver_tx(erc20_instance, BOB_ECDSA.address),
+        9900.into()
+    );
+
+    // convert to a xfr key, then send from the same key
+    test_convert_then_xfr_signed_transfer();
+}
+
+fn alice_xfr_address() -> H160 {
+    xfr_evm_address(&ALICE_XFR.get_pk().into())
 }
 
 fn test_deploy_check_tx() {
@@ -301,3 +323,110 @@ fn test_balance_of_with_eth_call(contract: ERC20, who: H160) -> U256 {
 
     U256::from_big_endian(info.value.as_ref())
 }
+
+fn test_balance_of_xfr_signed_deliver_tx(contract: ERC20, who: H160) -> U256 {
+    let input = contract
+        .0
+        .abi
+        .function("balanceOf")
+        .unwrap()
+        .encode_input(&[ethabi::Token::Address(who)])
+        .unwrap();
+
+    let function = Action::Evm(EvmAction::Call(Call {
+        source: alice_xfr_address(),
+        target: contract.0.address,
+        input,
+        value: U256::zero(),
+        gas_limit: DEFAULT_GAS_LIMIT,
+        gas_price: None,
+        nonce: None,
+    }));
+
+    let mut req = RequestDeliverTx::default();
+    let tx =
+        serde_json::to_vec(&build_signed_transaction(function, &ALICE_XFR, 0.into()))
+            .unwrap();
+    req.tx = EvmRawTxWrapper::wrap(&tx);
+    let resp = BASE_APP.lock().unwrap().deliver_tx(&req);
+    assert_eq!(
+        resp.code, 0,
+        "xfr signed call failed, code: {}, log: {}",
+        resp.code, resp.log
+    );
+
+    let info = serde_json::from_slice::<CallOrCreateInfo>(&resp.data).unwrap();
+    if let CallOrCreateInfo::Call(info) = info {
+        assert!(
+            info.exit_reason.is_succeed(),
+            "query erc20 balance failed: {:?}",
+            info.exit_reason
+        );
+
+        U256::from_big_endian(info.value.as_ref())
+    } else {
+        panic!("not expected result: {info:?}")
+    }
+}
+
+fn test_convert_then_xfr_signed_transfer() {
+    // the EVM address of a findora key, funded by converting UTXOs to it explicitly,
+    // credited by the ConvertAccount operations as `Address::from(receiver)`
+    let receiver = MultiSigner::Ethereum(alice_xfr_address());
+    let alice = Address::from(receiver);
+    assert_eq!(
+        alice,
+        EthereumAddressMapping::convert_to_account_id(alice_xfr_address())
+    );
+    let balance = |who: &Address| {
+        module_account::App::<BaseApp>::balance(
+            &BASE_APP.lock().unwrap().deliver_state,
+            who,
+        )
+    };
+    let converted = 1_0000_0000_0000_0000_u64.into();
+    assert!(module_account::App::<BaseApp>::mint(
+        &BASE_APP.lock().unwrap().deliver_state,
+        &alice,
+        converted
+    )
+    .is_ok());
+    let alice_balance = balance(&alice);
+    let bob_balance = balance(&BOB_ECDSA.account_id);
+
+    let function = Action::Evm(EvmAction::Call(Call {
+        source: alice_xfr_address(),
+        target: BOB_ECDSA.address,
+        input: vec![],
+        value: 10.into(),
+        gas_limit: DEFAULT_GAS_LIMIT,
+        gas_price: None,
+        nonce: None,
+    }));
+    let mut req = RequestDeliverTx::default();
+    let tx =
+        serde_json::to_vec(&build_signed_transaction(function, &ALICE_XFR, 1.into()))
+            .unwrap();
+    req.tx = EvmRawTxWrapper::wrap(&tx);
+    let resp = BASE_APP.lock().unwrap().deliver_tx(&req);
+    assert_eq!(
+        resp.code, 0,
+        "xfr signed transfer failed, code: {}, log: {}",
+        resp.code, resp.log
+    );
+
+    let info = serde_json::from_slice::<CallOrCreateInfo>(&resp.data).unwrap();
+    let used_gas = match info {
+        CallOrCreateInfo::Call(info) => {
+            assert!(info.exit_reason.is_succeed(), "{:?}", info.exit_reason);
+            info.used_gas
+        }
+        _ => panic!("not expected result: {info:?}"),
+    };
+
+    // only the gas is paid, no transaction fee on top of it
+    let gas_fee =
+        used_gas * <BaseApp as module_evm::Config>::FeeCalculator::min_gas_price();
+    assert_eq!(balance(&alice), alice_balance - U256::from(10) - gas_fee);
+    assert_eq!(balance(&BOB_ECDSA.account_id), bob_balance + U256::from(10));
+}
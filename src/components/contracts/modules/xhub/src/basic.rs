@@ -1,5 +1,5 @@
 use super::{App, Config, MODULE_NAME};
-use fp_core::module::AppModuleBasic;
+use fp_core::{context::Context, module::AppModuleBasic};
 use ruc::Result;
 
 impl<C: Config> AppModuleBasic for App<C> {
@@ -11,15 +11,15 @@ impl<C: Config> AppModuleBasic for App<C> {
         todo!()
     }
 
-    fn init_genesis(&self) {
-        todo!()
+    fn init_genesis(&self, _ctx: &mut Context, _genesis: &[u8]) -> Result<()> {
+        Ok(())
     }
 
     fn validate_genesis(&self) -> Result<()> {
         todo!()
     }
 
-    fn export_genesis(&self) {
-        todo!()
+    fn export_genesis(&self, _ctx: &Context) -> Result<Vec<u8>> {
+        Ok(vec![])
     }
 }
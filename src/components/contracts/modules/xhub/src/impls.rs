@@ -3,9 +3,11 @@ use crate::{App, Config};
 use fp_core::{context::Context, ensure, transaction::ActionResult};
 use fp_storage::{Borrow, BorrowMut};
 use fp_traits::{account::AccountAsset, evm::DecimalsMapping};
-use fp_types::actions::xhub::NonConfidentialTransfer;
+use fp_types::actions::xhub::{
+    ConfidentialOutput, ConfidentialTransfer, NonConfidentialTransfer,
+};
 use fp_types::{actions::xhub::NonConfidentialOutput, crypto::Address};
-use ledger::data_model::ASSET_TYPE_FRA;
+use ledger::{converter::check_confidential_outputs, data_model::ASSET_TYPE_FRA};
 use primitive_types::U256;
 use ruc::*;
 use tracing::debug;
@@ -48,6 +50,43 @@ impl<C: Config> App<C> {
         Ok(ActionResult::default())
     }
 
+    pub fn transfer_to_confidential_utxo(
+        ctx: &Context,
+        sender: Address,
+        call: ConfidentialTransfer,
+    ) -> Result<ActionResult> {
+        let transfer_amount = check_confidential_outputs(
+            &call
+                .outputs
+                .iter()
+                .map(|o| (&o.record, &o.amount_proof))
+                .collect::<Vec<_>>(),
+            ctx.header.height,
+        )?;
+
+        debug!(target: "xhub", "transfer to confidential UTXO {} FRA", transfer_amount);
+
+        ensure!(
+            call.input_value == transfer_amount,
+            "Input value mismatch utxo output"
+        );
+
+        let amount = C::DecimalsMapping::from_native_token(U256::from(transfer_amount))
+            .ok_or_else(|| eg!("the transfer to UTXO amount is too large"))?;
+
+        let sa = C::AccountAsset::account_of(ctx, &sender, None)
+            .c(d!("account does not exist"))?;
+        if sa.balance < amount {
+            return Err(eg!("insufficient balance"));
+        }
+
+        if !amount.is_zero() {
+            C::AccountAsset::burn(ctx, &sender, amount)?;
+            Self::add_confidential_mint(ctx, call.outputs)?;
+        }
+        Ok(ActionResult::default())
+    }
+
     pub(crate) fn add_mint(
         ctx: &Context,
         mut outputs: Vec<NonConfidentialOutput>,
@@ -65,4 +104,23 @@ impl<C: Config> App<C> {
     pub fn consume_mint(ctx: &Context) -> Option<Vec<NonConfidentialOutput>> {
         PendingUTXOs::take(ctx.db.write().borrow_mut())
     }
+
+    pub(crate) fn add_confidential_mint(
+        ctx: &Context,
+        mut outputs: Vec<ConfidentialOutput>,
+    ) -> Result<()> {
+        let ops = if let Some(mut ori_outputs) =
+            PendingConfidentialUTXOs::get(ctx.db.read().borrow())
+        {
+            ori_outputs.append(&mut outputs);
+            ori_outputs
+        } else {
+            outputs
+        };
+        PendingConfidentialUTXOs::put(ctx.db.write().borrow_mut(), &ops)
+    }
+
+    pub fn consume_confidential_mint(ctx: &Context) -> Option<Vec<ConfidentialOutput>> {
+        PendingConfidentialUTXOs::take(ctx.db.write().borrow_mut())
+    }
 }
@@ -24,13 +24,15 @@ pub trait Config {
 }
 
 mod storage {
-    use fp_types::actions::xhub::NonConfidentialOutput;
+    use fp_types::actions::xhub::{ConfidentialOutput, NonConfidentialOutput};
 
     use fp_storage::*;
 
     // The following data is stored in non-state rocksdb
     // account balance transfer to utxo waiting to be mint.
     generate_storage!(XHub, PendingUTXOs => Value<Vec<NonConfidentialOutput>>);
+    // account balance transfer to confidential utxo waiting to be mint.
+    generate_storage!(XHub, PendingConfidentialUTXOs => Value<Vec<ConfidentialOutput>>);
 }
 
 #[derive(Clone)]
@@ -65,6 +67,13 @@ impl<C: Config> Executable for App<C> {
                     Err(eg!("invalid transaction origin"))
                 }
             }
+            Action::ConfidentialTransfer(action) => {
+                if let Some(sender) = origin {
+                    Self::transfer_to_confidential_utxo(ctx, sender, action)
+                } else {
+                    Err(eg!("invalid transaction origin"))
+                }
+            }
         }
     }
 }
@@ -10,14 +10,15 @@ pub trait AppModuleBasic {
     /// Returns default genesis state as raw bytes for the module.
     fn default_genesis(&self) -> Vec<u8>;
 
-    /// Performs genesis initialization for the module. It returns no validator updates.
-    fn init_genesis(&self);
+    /// Performs genesis initialization for the module from the bytes
+    /// returned by `export_genesis`. It returns no validator updates.
+    fn init_genesis(&self, ctx: &mut Context, genesis: &[u8]) -> Result<()>;
 
     /// Performs genesis state validation for the module.
     fn validate_genesis(&self) -> Result<()>;
 
     /// Returns the exported genesis state as raw bytes for the module.
-    fn export_genesis(&self);
+    fn export_genesis(&self, ctx: &Context) -> Result<Vec<u8>>;
 }
 
 /// AppModule is the standard form for an application module
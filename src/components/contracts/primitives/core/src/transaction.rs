@@ -196,6 +196,7 @@ pub struct ActionResult {
     /// 2 - EVM ExitReason::Error
     /// 3 - EVM ExitReason::Revert
     /// 4 - EVM ExitReason::Fatal
+    /// 5 - The gas limit exceeds the gas left in the block
     /// 0xff - context state maybe messed up
     pub code: u32,
     /// Data is any data returned from message or handler execution.
@@ -46,6 +46,10 @@ pub struct TransactionStatus {
     pub contract_address: Option<H160>,
     pub logs: Vec<Log>,
     pub logs_bloom: Bloom,
+    /// Executed for an action of the EVM module (a deposit, a withdrawal),
+    /// it has no secp256k1 signature and is served with a zero one.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub native: bool,
 }
 
 pub trait Runner {
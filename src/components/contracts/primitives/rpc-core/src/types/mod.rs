@@ -42,8 +42,8 @@ pub use self::block_number::BlockNumber;
 pub use self::bytes::Bytes;
 pub use self::call_request::CallRequest;
 pub use self::filter::{
-    Filter, FilterAddress, FilterChanges, FilterPool, FilterPoolItem, FilterType,
-    FilteredParams, Topic, VariadicValue,
+    BloomFilter, Filter, FilterAddress, FilterChanges, FilterPool, FilterPoolItem,
+    FilterType, FilteredParams, Topic, VariadicValue,
 };
 pub use self::index::Index;
 pub use self::log::Log;
@@ -120,6 +120,9 @@ fn storage_double_map_test() {
     let kvs = Data::iterate_prefix(state.read().borrow(), &2);
     assert_eq!(kvs, vec![(3, 30), (4, 40)]);
 
+    let kvs = Data::iterate(state.read().borrow());
+    assert_eq!(kvs, vec![(1, 3, 20), (2, 3, 30), (2, 4, 40)]);
+
     Data::remove_prefix(state.write().borrow_mut(), &2);
     let kvs = Data::iterate_prefix(state.read().borrow(), &2);
     assert_eq!(kvs, vec![]);
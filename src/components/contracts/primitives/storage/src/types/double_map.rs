@@ -138,4 +138,32 @@ where
         }
         res
     }
+
+    /// Iter over all values of the storage, along with both keys.
+    pub fn iterate<D: MerkleDB>(state: &State<D>) -> Vec<(Key1, Key2, Value)> {
+        let prefix_key: Vec<u8> =
+            [Self::module_prefix(), Self::storage_prefix()].concat();
+        let prefix = Prefix::new(prefix_key.as_ref());
+
+        let kv_map = Instance::iter_cur(state, prefix);
+
+        let mut res = Vec::new();
+        for (k, v) in kv_map {
+            let key_str = String::from_utf8_lossy(k.as_slice()).to_string();
+            let key_list: Vec<_> = key_str.split(DB_SEPARATOR).collect();
+
+            // the last two items are `key1` and `key2`
+            let key1 = key_list
+                .len()
+                .checked_sub(2)
+                .and_then(|i| Key1::from_str(key_list[i]).ok());
+            let key2 = Self::parse_key_for(key_list);
+            let raw_value = serde_json::from_slice::<Value>(v.as_slice()).ok();
+
+            if let (Some(k1), Ok(k2), Some(v)) = (key1, key2, raw_value) {
+                res.push((k1, k2, v))
+            }
+        }
+        res
+    }
 }
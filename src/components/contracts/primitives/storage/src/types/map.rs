@@ -170,4 +170,24 @@ where
         }
         res
     }
+
+    /// Iter over all serialized values of the storage.
+    pub fn iterate_bytes<D: MerkleDB>(state: &State<D>) -> Vec<(Key, Vec<u8>)> {
+        let prefix_key: Vec<u8> =
+            [Self::module_prefix(), Self::storage_prefix()].concat();
+        let prefix = Prefix::new(prefix_key.as_ref());
+
+        let kv_map = Instance::iter_cur(state, prefix);
+
+        let mut res = Vec::new();
+        for (k, v) in kv_map {
+            let key_str = String::from_utf8_lossy(k.as_slice()).to_string();
+            let key_list: Vec<_> = key_str.split(DB_SEPARATOR).collect();
+
+            if let Ok(k) = Self::parse_key_for(key_list) {
+                res.push((k, v))
+            }
+        }
+        res
+    }
 }
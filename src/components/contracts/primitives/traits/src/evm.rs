@@ -1,6 +1,7 @@
 use core::{convert::From, ops::Div};
-use fp_core::context::Context;
-use fp_types::crypto::Address;
+use fp_core::{context::Context, transaction::ActionResult};
+use fp_evm::CallOrCreateInfo;
+use fp_types::{actions::evm::Action, crypto::Address};
 use primitive_types::{H160, H256, U256};
 use ruc::Result;
 
@@ -75,3 +76,25 @@ pub trait OnChargeEVMTransaction {
         already_withdrawn: U256,
     ) -> Result<()>;
 }
+
+/// Record the EVM actions executed out of ethereum transactions,
+/// so that their receipts and logs show up in the ethereum blocks.
+pub trait ActionRecorder {
+    /// The gas limit of the current block.
+    fn block_gas_limit(ctx: &Context) -> U256;
+
+    /// Reject the actions whose gas limit exceeds the gas left in the block,
+    /// they are recorded with a failed receipt. `nonce` is the one used by the execution.
+    fn check_block_gas(
+        ctx: &Context,
+        action: &Action,
+        nonce: U256,
+    ) -> Result<Option<ActionResult>>;
+
+    /// The nonce and the gas price of `action` are the ones used by the execution.
+    fn record_action(
+        ctx: &Context,
+        action: Action,
+        info: CallOrCreateInfo,
+    ) -> Result<ActionResult>;
+}
@@ -1,10 +1,12 @@
+use globutils::amount_proof::AmountProof;
 use serde::{Deserialize, Serialize};
 use zei::xfr::sig::XfrPublicKey;
-use zei::xfr::structs::AssetType;
+use zei::xfr::structs::{AssetType, BlindAssetRecord, OwnerMemo};
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Action {
     NonConfidentialTransfer(NonConfidentialTransfer),
+    ConfidentialTransfer(ConfidentialTransfer),
 }
 
 /// Findora evm account balance transfer to NonConfidential utxo.
@@ -21,3 +23,19 @@ pub struct NonConfidentialOutput {
     pub amount: u64,
     pub target: XfrPublicKey,
 }
+
+/// Findora evm account balance transfer to utxo with confidential amounts.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConfidentialTransfer {
+    pub input_value: u64,
+    pub outputs: Vec<ConfidentialOutput>,
+}
+
+/// Evm account balance convert to confidential utxo,
+/// the record is built by the sender with the key of the receiver.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConfidentialOutput {
+    pub record: BlindAssetRecord,
+    pub owner_memo: Option<OwnerMemo>,
+    pub amount_proof: AmountProof,
+}
@@ -422,6 +422,15 @@ pub fn secp256k1_ecdsa_recover(sig: &[u8; 65], msg: &[u8; 32]) -> ruc::Result<[u
     Ok(res)
 }
 
+/// The EVM address of an account signing with a Findora (Xfr) key,
+/// the last 20 bytes of the keccak-256 hash of the public key.
+///
+/// The EVM actions signed by the key run as this address,
+/// and `fn contract-deposit` converts the UTXOs of a Findora account to it.
+pub fn xfr_evm_address(who: &Address32) -> H160 {
+    H160::from_slice(&keccak_256(who.as_ref())[12..])
+}
+
 /// Alias to 512-bit hash when used in the context of a transaction signature on the chain.
 pub type Signature = MultiSignature;
 
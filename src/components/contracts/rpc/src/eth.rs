@@ -1,3 +1,4 @@
+use crate::log_index::{candidate_blocks, too_many_logs};
 use crate::utils::{
     build_method_not_found, convert_error_to_rpc_error, convert_join_error_to_rpc_error,
 };
@@ -31,7 +32,12 @@ use jsonrpc_core::{futures::future, BoxFuture, Result};
 use lazy_static::lazy_static;
 use parking_lot::RwLock;
 use sha3::{Digest, Keccak256};
-use std::{collections::BTreeMap, convert::Into, ops::Range, sync::Arc};
+use std::{
+    collections::BTreeMap,
+    convert::Into,
+    ops::Range,
+    sync::{mpsc, Arc},
+};
 use tendermint::abci::Code;
 use tendermint_rpc::{Client, HttpClient};
 use tokio::runtime::{Handle, Runtime};
@@ -224,109 +230,79 @@ impl EthApi for EthApiImpl {
         })
     }
 
-    fn send_transaction(&self, _request: TransactionRequest) -> Result<H256> {
-        /*         debug!(target: "eth_rpc", "send_transaction, request:{:?}", request); */
-        /*  */
-        /* let from = match request.from { */
-        /*     Some(from) => from, */
-        /*     None => { */
-        /*         let accounts = match self._accounts() { */
-        /*             Ok(accounts) => accounts, */
-        /*             Err(e) => return Box::pin(future::err(e)), */
-        /*         }; */
-        /*  */
-        /*         match accounts.get(0) { */
-        /*             Some(account) => *account, */
-        /*             None => { */
-        /*                 return Box::pin(future::err(internal_err( */
-        /*                     "no signer available", */
-        /*                 ))); */
-        /*             } */
-        /*         } */
-        /*     } */
-        /* }; */
-        /*  */
-        /* let nonce = match request.nonce { */
-        /*     Some(nonce) => nonce, */
-        /*     None => match self.transaction_count(from, None) { */
-        /*         Ok(nonce) => nonce, */
-        /*         Err(e) => return Box::pin(future::err(e)), */
-        /*     }, */
-        /* }; */
-        /*  */
-        /* let chain_id = match self.chain_id() { */
-        /*     Ok(chain_id) => chain_id, */
-        /*     Err(e) => return Box::pin(future::err(e)), */
-        /* }; */
-        /*  */
-        /* let message = EthereumTransactionMessage { */
-        /*     nonce, */
-        /*     gas_price: request.gas_price.unwrap_or_else( */
-        /*         <BaseApp as module_evm::Config>::FeeCalculator::min_gas_price, */
-        /*     ), */
-        /*     gas_limit: request.gas.unwrap_or_else(U256::max_value), */
-        /*     value: request.value.unwrap_or_else(U256::zero), */
-        /*     input: request.data.map(|s| s.into_vec()).unwrap_or_default(), */
-        /*     action: match request.to { */
-        /*         Some(to) => ethereum::TransactionAction::Call(to), */
-        /*         None => ethereum::TransactionAction::Create, */
-        /*     }, */
-        /*     chain_id: chain_id.map(|s| s.as_u64()), */
-        /* }; */
-        /*  */
-        /* let mut transaction = None; */
-        /* for signer in &self.signers { */
-        /*     if signer.address() == from { */
-        /*         match sign_transaction_message(message, &H256::from(signer.seed())) */
-        /*             .map_err(internal_err) */
-        /*         { */
-        /*             Ok(tx) => transaction = Some(tx), */
-        /*             Err(e) => return Box::pin(future::err(e)), */
-        /*         } */
-        /*         break; */
-        /*     } */
-        /* } */
-        /*  */
-        /* let transaction = match transaction { */
-        /*     Some(transaction) => transaction, */
-        /*     None => { */
-        /*         return Box::pin(future::err(internal_err("no signer available"))); */
-        /*     } */
-        /* }; */
-        /* let transaction_hash = */
-        /*     H256::from_slice(Keccak256::digest(&rlp::encode(&transaction)).as_slice()); */
-        /* let function = */
-        /*     actions::Action::Ethereum(actions::ethereum::Action::Transact(transaction)); */
-        /* let txn = serde_json::to_vec( */
-        /*     &UncheckedTransaction::<SignedExtra>::new_unsigned(function), */
-        /* ) */
-        /* .map_err(internal_err); */
-        /* if let Err(e) = txn { */
-        /*     return Box::pin(future::err(e)); */
-        /* } */
-        /*  */
-        /* // check_tx and broadcast */
-        /* let client = self.tm_client.clone(); */
-        /* let txn_with_tag = EvmRawTxWrapper::wrap(&txn.unwrap()); */
-        /* let (tx, rx) = mpsc::channel(); */
-        /* RT.spawn(async move { */
-        /*     let resp = client.broadcast_tx_sync(txn_with_tag.into()).await; */
-        /*     tx.send(resp).unwrap(); */
-        /* }); */
-        /*  */
-        /* // fetch response */
-        /* if let Ok(resp) = rx.recv().unwrap() { */
-        /*     if resp.code != Code::Ok { */
-        /*         return Box::pin(future::err(internal_err(resp.log))); */
-        /*     } */
-        /* } else { */
-        /*     return Box::pin(future::err(internal_err(String::from( */
-        /*         "send_transaction: broadcast_tx_sync failed", */
-        /*     )))); */
-        /* } */
-        /*  */
-        /* Box::pin(future::ok(transaction_hash)) */
-        Err(build_method_not_found())
+    // Only the impersonated accounts of a dev chain can send transactions
+    // without signing them, the chain rejects the transactions of other accounts.
+    // The other builds do not sign on the server side.
+    fn send_transaction(&self, request: TransactionRequest) -> Result<H256> {
+        debug!(target: "eth_rpc", "send_transaction, request:{:?}", request);
+
+        if !cfg!(feature = "debug_env") {
+            return Err(build_method_not_found());
+        }
+
+        let from = request
+            .from
+            .ok_or_else(|| internal_err("the sender is missing"))?;
+
+        let nonce = match request.nonce {
+            Some(nonce) => nonce,
+            None => {
+                let account_id = EthereumAddressMapping::convert_to_account_id(from);
+                self.account_base_app
+                    .read()
+                    .account_of(&account_id, None)
+                    .unwrap_or_default()
+                    .nonce
+            }
+        };
+
+        let chain_id = <BaseApp as module_evm::Config>::ChainId::get();
+        let signature = module_ethereum::impersonated_signature(from, chain_id)
+            .ok_or_else(|| internal_err("invalid sender"))?;
+
+        let transaction = EthereumTransaction {
+            nonce,
+            gas_price: request.gas_price.unwrap_or_else(
+                <BaseApp as module_evm::Config>::FeeCalculator::min_gas_price,
+            ),
+            gas_limit: request.gas.unwrap_or_else(|| U256::from(21000)),
+            action: match request.to {
+                Some(to) => ethereum::TransactionAction::Call(to),
+                None => ethereum::TransactionAction::Create,
+            },
+            value: request.value.unwrap_or_default(),
+            input: request.data.map(|d| d.into_vec()).unwrap_or_default(),
+            signature,
+        };
+
+        let transaction_hash =
+            H256::from_slice(Keccak256::digest(&rlp::encode(&transaction)).as_slice());
+        let function =
+            actions::Action::Ethereum(actions::ethereum::Action::Transact(transaction));
+        let txn = serde_json::to_vec(
+            &UncheckedTransaction::<SignedExtra>::new_unsigned(function),
+        )
+        .map_err(internal_err)?;
+
+        // check_tx and broadcast
+        let client = self.tm_client.clone();
+        let txn_with_tag = EvmRawTxWrapper::wrap(&txn);
+        let (tx, rx) = mpsc::channel();
+        RT.spawn(async move {
+            let resp = client.broadcast_tx_sync(txn_with_tag.into()).await;
+            let _ = tx.send(resp);
+        });
+
+        match rx.recv() {
+            Ok(Ok(resp)) => {
+                if resp.code != Code::Ok {
+                    return Err(convert_error_to_rpc_error(resp));
+                }
+                Ok(transaction_hash)
+            }
+            Ok(Err(e)) => Err(convert_error_to_rpc_error(e)),
+            Err(e) => Err(internal_err(e)),
+        }
     }
 
     fn call(
@@ -1410,12 +1386,16 @@ fn transaction_build(
     block: Option<EthereumBlock>,
     status: Option<TransactionStatus>,
 ) -> Transaction {
-    let pubkey = match public_key(&transaction) {
-        Ok(p) => Some(p),
-        Err(_e) => None,
+    // the actions of the EVM module have no secp256k1 signature,
+    // they are served with a zero signature and without the raw transaction
+    let native = status.as_ref().map_or(false, |status| status.native);
+    let pubkey = if native {
+        None
+    } else {
+        public_key(&transaction).ok()
     };
 
-    Transaction {
+    let mut tx = Transaction {
         hash: H256::from_slice(Keccak256::digest(&rlp::encode(&transaction)).as_slice()),
         nonce: transaction.nonce,
         block_hash: block.as_ref().map(|block| {
@@ -1457,7 +1437,15 @@ fn transaction_build(
         v: U256::from(transaction.signature.v()),
         r: U256::from(transaction.signature.r().as_bytes()),
         s: U256::from(transaction.signature.s().as_bytes()),
+    };
+    if native {
+        tx.raw = Bytes(vec![]);
+        tx.standard_v = U256::zero();
+        tx.v = U256::zero();
+        tx.r = U256::zero();
+        tx.s = U256::zero();
     }
+    tx
 }
 
 pub fn public_key(transaction: &EthereumTransaction) -> ruc::Result<[u8; 64]> {
@@ -1481,7 +1469,10 @@ fn filter_range_logs(
     from: U256,
     to: U256,
 ) -> Result<()> {
-    let mut current = to;
+    if from > to {
+        return Ok(());
+    }
+    let (from, to) = (from.as_u64(), to.as_u64());
 
     let topics_input = if filter.topics.is_some() {
         let filtered_params = FilteredParams::new(Some(filter.clone()));
@@ -1492,8 +1483,8 @@ fn filter_range_logs(
     let address_bloom_filter = FilteredParams::addresses_bloom_filter(&filter.address);
     let topics_bloom_filter = FilteredParams::topics_bloom_filter(&topics_input);
 
-    while current >= from {
-        let id = BlockId::Number(current);
+    for current in candidate_blocks(app.clone(), filter, from, to) {
+        let id = BlockId::Number(current.into());
         let block = app.read().current_block(Some(id.clone()));
 
         if let Some(block) = block {
@@ -1504,28 +1495,16 @@ fn filter_range_logs(
                 block.header.logs_bloom,
                 &topics_bloom_filter,
             ) {
-                let mut logs: Vec<Log> = Vec::new();
                 let statuses = app.read().current_transaction_statuses(Some(id));
                 if let Some(statuses) = statuses {
-                    filter_block_logs(&mut logs, filter, block, statuses);
-                }
-
-                // insert logs at the beginning of ret
-                if !logs.is_empty() {
-                    logs.append(ret);
-                    ret.append(&mut logs);
+                    filter_block_logs(ret, filter, block, statuses);
                 }
             }
         }
         // Check for restrictions
         if ret.len() as u32 > max_past_logs {
             warn!(target: "eth_rpc", "max_past_logs reached at block {:?}", current);
-            break;
-        }
-        if current == U256::zero() {
-            break;
-        } else {
-            current = current.saturating_sub(U256::one());
+            return Err(too_many_logs(max_past_logs, from, current));
         }
     }
     Ok(())
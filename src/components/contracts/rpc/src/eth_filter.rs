@@ -1,3 +1,4 @@
+use crate::log_index::{candidate_blocks, too_many_logs};
 use crate::{filter_block_logs, internal_err};
 use baseapp::BaseApp;
 use ethereum::BlockV0 as EthereumBlock;
@@ -124,8 +125,8 @@ impl EthFilterApiImpl {
             FilteredParams::addresses_bloom_filter(&filter.address);
         let topics_bloom_filter = FilteredParams::topics_bloom_filter(&topics_input);
 
-        let mut current = to;
-        while current >= from {
+        for current in candidate_blocks(self.account_base_app.clone(), filter, from, to)
+        {
             let block = self
                 .block_data_cache
                 .current_block(&self.account_base_app, current.into());
@@ -146,16 +147,11 @@ impl EthFilterApiImpl {
                         filter_block_logs(ret, filter, block, statuses);
                     }
                 }
-            } else {
-                // stop when past the 1st ethereum block
-                break;
             }
 
             // Check for restrictions
             if ret.len() as u32 > max_past_logs {
-                return Err(internal_err(format!(
-                    "query returned more than {max_past_logs} results",
-                )));
+                return Err(too_many_logs(max_past_logs, from, current));
             }
             if begin_request.elapsed() > max_duration {
                 return Err(internal_err(format!(
@@ -163,7 +159,6 @@ impl EthFilterApiImpl {
                     max_duration.as_secs()
                 )));
             }
-            current = current.saturating_sub(1);
         }
         Ok(())
     }
@@ -4,6 +4,7 @@
 mod eth;
 mod eth_filter;
 mod eth_pubsub;
+mod log_index;
 mod net;
 mod utils;
 mod web3;
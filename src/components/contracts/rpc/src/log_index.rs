@@ -0,0 +1,146 @@
+//! Candidate blocks of a log filter, found by the log index of the ethereum module.
+
+use crate::internal_err;
+use baseapp::BaseApp;
+use ethereum_types::Bloom;
+use fp_rpc_core::types::{BloomFilter, Filter, FilteredParams};
+use jsonrpc_core::Error;
+use module_ethereum::BLOOM_SECTION_SIZE;
+use parking_lot::RwLock;
+use std::sync::Arc;
+
+const SECTION_BYTES: usize = (BLOOM_SECTION_SIZE / 8) as usize;
+
+/// Blocks in `[from, to]` which may contain the logs of `filter`, in ascending order.
+///
+/// All the blocks before the log index are candidates, check their logs bloom then.
+pub fn candidate_blocks(
+    app: Arc<RwLock<BaseApp>>,
+    filter: &Filter,
+    from: u64,
+    to: u64,
+) -> impl Iterator<Item = u64> {
+    let end = to.saturating_add(1);
+    let from = from.min(end);
+    let tail = app
+        .read()
+        .log_index_tail()
+        .map_or(end, |t| t.low_u64())
+        .clamp(from, end);
+
+    let address_filter = FilteredParams::addresses_bloom_filter(&filter.address);
+    let topics_input = if filter.topics.is_some() {
+        let filtered_params = FilteredParams::new(Some(filter.clone()));
+        Some(filtered_params.flat_topics)
+    } else {
+        None
+    };
+    let topics_filter = FilteredParams::topics_bloom_filter(&topics_input);
+
+    #[allow(clippy::reversed_empty_ranges)]
+    let sections = if tail < end {
+        tail / BLOOM_SECTION_SIZE..=to / BLOOM_SECTION_SIZE
+    } else {
+        1..=0
+    };
+    let indexed = sections
+        .flat_map(move |section| {
+            let bits =
+                section_matches(&app.read(), section, &address_filter, &topics_filter);
+            let start = section * BLOOM_SECTION_SIZE;
+            (0..BLOOM_SECTION_SIZE)
+                .filter(move |i| bits[(i / 8) as usize] & (0x80 >> (i % 8)) != 0)
+                .map(move |i| start + i)
+        })
+        .filter(move |n| tail <= *n && *n <= to);
+
+    (from..tail).chain(indexed)
+}
+
+/// The error of a query returning too many logs,
+/// with the range of blocks fitting in the limit, so the client can query by pages.
+pub fn too_many_logs(max_past_logs: u32, from: u64, at: u64) -> Error {
+    if at > from {
+        internal_err(format!(
+            "query returned more than {max_past_logs} results, try with the block range [{from:#x}, {:#x}]",
+            at - 1
+        ))
+    } else {
+        internal_err(format!("query returned more than {max_past_logs} results"))
+    }
+}
+
+// The same logic as `FilteredParams::address_in_bloom` and `topics_in_bloom`,
+// applied on the bitsets of the blocks of a section.
+fn section_matches(
+    app: &BaseApp,
+    section: u64,
+    address_filter: &[Option<Bloom>],
+    topics_filter: &[BloomFilter],
+) -> Vec<u8> {
+    let contains = |bloom: &Option<Bloom>| match bloom {
+        Some(bloom) => app.bloom_section(section, bloom),
+        // Wildcards are true.
+        None => vec![0xff; SECTION_BYTES],
+    };
+
+    let addresses = if address_filter.is_empty() {
+        vec![0xff; SECTION_BYTES]
+    } else {
+        address_filter
+            .iter()
+            .map(contains)
+            .fold(vec![0; SECTION_BYTES], or)
+    };
+    if addresses.iter().all(|b| *b == 0) {
+        return addresses;
+    }
+
+    let topics = if topics_filter.is_empty() {
+        vec![0xff; SECTION_BYTES]
+    } else {
+        topics_filter
+            .iter()
+            .map(|subset| {
+                if subset.is_empty() {
+                    vec![0; SECTION_BYTES]
+                } else {
+                    subset
+                        .iter()
+                        .map(contains)
+                        .fold(vec![0xff; SECTION_BYTES], and)
+                }
+            })
+            .fold(vec![0; SECTION_BYTES], or)
+    };
+
+    and(addresses, topics)
+}
+
+fn and(mut a: Vec<u8>, b: Vec<u8>) -> Vec<u8> {
+    a.iter_mut().zip(b).for_each(|(x, y)| *x &= y);
+    a
+}
+
+fn or(mut a: Vec<u8>, b: Vec<u8>) -> Vec<u8> {
+    a.iter_mut().zip(b).for_each(|(x, y)| *x |= y);
+    a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn too_many_logs_suggests_a_range() {
+        let err = too_many_logs(10000, 0x10, 0x20);
+        assert_eq!(
+            err.message,
+            "query returned more than 10000 results, try with the block range [0x10, 0x1f]"
+        );
+
+        // the logs of the first block alone are too many
+        let err = too_many_logs(10000, 0x10, 0x10);
+        assert_eq!(err.message, "query returned more than 10000 results");
+    }
+}
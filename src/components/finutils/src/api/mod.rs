@@ -4,9 +4,11 @@
 
 use {
     ledger::staking::{
-        self, StakerMemo, TendermintAddr, MAX_POWER_PERCENT_PER_VALIDATOR,
+        self, ops::vesting::VestingSchedule, BlockHeight, StakerMemo, TendermintAddr,
+        MAX_POWER_PERCENT_PER_VALIDATOR,
     },
     serde::{Deserialize, Serialize},
+    zei::xfr::sig::XfrPublicKey,
 };
 
 /// A list of basic validator information of current height
@@ -77,6 +79,18 @@ pub struct ValidatorDetail {
     pub voting_power_rank: usize,
     /// commission rate of this staker
     pub commission_rate: [u64; 2],
+    /// the ceiling of commission rate declared at staking time
+    #[serde(default)]
+    pub max_commission_rate: [u64; 2],
+    /// the max change of commission rate within one commission epoch
+    #[serde(default)]
+    pub commission_max_change: [u64; 2],
+    /// the lowest height at which the commission rate can be changed again
+    #[serde(default)]
+    pub commission_next_change_height: u64,
+    /// history of commission rate, (height, rate)
+    #[serde(default)]
+    pub commission_hist: Vec<(u64, [u64; 2])>,
     /// self-staking FRA amount
     pub self_staking: u64,
     /// rewards received
@@ -185,6 +199,69 @@ impl DelegationInfo {
     }
 }
 
+/// Vesting information of a beneficiary,
+/// all amounts are summed up from the unfinished vestings.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct VestingInfo {
+    /// total locked amount
+    pub total: u64,
+    /// the amount vested at current height
+    pub vested: u64,
+    /// the amount that has not been vested
+    pub unvested: u64,
+    /// the amount that has been released
+    pub released: u64,
+    /// the amount delegated by the beneficiary
+    pub delegated: u64,
+    /// the amount that can be released now
+    pub releasable: u64,
+    /// the released amount that is waiting to be paid
+    pub pending_payment: u64,
+    /// current block height of findora network
+    pub current_height: u64,
+    /// details of each vesting
+    pub entries: Vec<VestingEntry>,
+}
+
+/// Detail of a vesting.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct VestingEntry {
+    /// base64-encoded public key of the creator
+    pub creator: String,
+    /// total locked amount
+    pub amount: u64,
+    /// the amount vested at current height
+    pub vested: u64,
+    /// the amount that has been released
+    pub released: u64,
+    /// the amount delegated by the beneficiary
+    pub delegated: u64,
+    /// the block height at which the vesting was created
+    pub start_height: u64,
+    #[allow(missing_docs)]
+    pub schedule: VestingSchedule,
+}
+
+/// The validators of a height, used by an indexer to look them up.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct StakingValidators {
+    /// current block height of findora network
+    pub height: BlockHeight,
+    /// the current validators
+    pub validators: Vec<ValidatorStart>,
+}
+
+/// The ids of a validator and when it starts.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ValidatorStart {
+    /// tendermint node address
+    pub addr: TendermintAddr,
+    #[allow(missing_docs)]
+    pub id: XfrPublicKey,
+    /// when this node becomes validator
+    pub start_height: BlockHeight,
+}
+
 #[allow(missing_docs)]
 pub trait NetworkRoute {
     fn route(&self) -> String;
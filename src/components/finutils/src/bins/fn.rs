@@ -27,7 +27,7 @@
 
 use {
     clap::{crate_authors, load_yaml, App},
-    finutils::common::{self, evm::*},
+    finutils::common::{self, batch, evm::*, offline, trace},
     fp_utils::ecdsa::SecpPair,
     globutils::wallet,
     ledger::{
@@ -53,6 +53,24 @@ fn run() -> Result<()> {
         .author(crate_authors!())
         .get_matches();
 
+    // the key on a hardware wallet
+    let device = match matches.value_of("signer") {
+        Some(s)
+            if matches!(
+                matches.subcommand_name(),
+                Some("transfer" | "transfer-batch")
+            ) =>
+        {
+            Some(common::get_device_signer(s).c(d!())?)
+        }
+        Some(_) => {
+            return Err(eg!(
+                "`--signer` is only supported by `transfer` and `transfer-batch`"
+            ));
+        }
+        None => None,
+    };
+
     if matches.is_present("version") {
         println!("{}", env!("VERGEN_SHA"));
     } else if matches.is_present("genkey") {
@@ -91,13 +109,31 @@ fn run() -> Result<()> {
         let validator = m.value_of("validator");
         let show_info = m.is_present("info");
 
-        if amount.is_some() && validator.is_some() {
-            common::delegate(
-                seckey.as_deref(),
-                amount.unwrap().parse::<u64>().c(d!())?,
-                validator.unwrap(),
-            )
-            .c(d!())?;
+        if let (Some(file), Some(am), Some(v)) =
+            (m.value_of("build-only"), amount, validator)
+        {
+            if m.is_present("from-vesting") {
+                return Err(eg!("`--from-vesting` can not be built offline"));
+            }
+            let signer = offline::get_signer(m.value_of("signer"), seckey.as_deref())?;
+            offline::build_delegate(signer, am.parse::<u64>().c(d!())?, v, file)
+                .c(d!())?;
+        } else if amount.is_some() && validator.is_some() {
+            if m.is_present("from-vesting") {
+                common::delegate_vesting(
+                    seckey.as_deref(),
+                    amount.unwrap().parse::<u64>().c(d!())?,
+                    validator.unwrap(),
+                )
+                .c(d!())?;
+            } else {
+                common::delegate(
+                    seckey.as_deref(),
+                    amount.unwrap().parse::<u64>().c(d!())?,
+                    validator.unwrap(),
+                )
+                .c(d!())?;
+            }
         } else if show_info {
             common::show_delegations(seckey.as_deref()).c(d!())?;
         } else {
@@ -125,7 +161,15 @@ fn run() -> Result<()> {
         };
         common::undelegate(seckey.as_deref(), param).c(d!())?;
     } else if let Some(m) = matches.subcommand_matches("asset") {
-        if m.is_present("create") {
+        if let Some(sm) = m.subcommand_matches("trace") {
+            trace::trace_asset(
+                sm.value_of("code").c(d!())?,
+                sm.value_of("tracer-key").c(d!())?,
+                sm.value_of("format").c(d!())?,
+                sm.value_of("output"),
+            )
+            .c(d!())?;
+        } else if m.is_present("create") {
             let seckey = match m.value_of("seckey") {
                 Some(path) => {
                     Some(fs::read_to_string(path).c(d!("Failed to read seckey file"))?)
@@ -192,7 +236,7 @@ fn run() -> Result<()> {
             common::issue_asset(seckey.as_deref(), code.unwrap(), amount, hidden)
                 .c(d!())?;
         } else {
-            let help = "fn asset [--create | --issue | --show]";
+            let help = "fn asset [--create | --issue | --show | trace]";
             println!("{help}");
         }
     } else if let Some(m) = matches.subcommand_matches("staker-update") {
@@ -231,12 +275,19 @@ fn run() -> Result<()> {
             println!(
                 "Tips: to update the information of your node, please specify commission-rate or memo"
             );
+        } else if let Some(file) = m.value_of("build-only") {
+            let signer = offline::get_signer(m.value_of("signer"), None)?;
+            offline::build_staker_update(signer, cr, vm, file).c(d!())?;
         } else {
             common::staker_update(cr, vm).c(d!())?;
         }
     } else if let Some(m) = matches.subcommand_matches("stake") {
         let am = m.value_of("amount");
-        if m.is_present("append") {
+        if m.is_present("append") && m.is_present("build-only") {
+            return Err(eg!(
+                "`--append` can not be built offline, use `fn delegate`"
+            ));
+        } else if m.is_present("append") {
             let staker = match m.value_of("staker-priv-key") {
                 Some(path) => {
                     Some(fs::read_to_string(path).c(d!("Failed to read seckey file"))?)
@@ -251,6 +302,7 @@ fn run() -> Result<()> {
             }
         } else {
             let cr = m.value_of("commission-rate");
+            let max_cr = m.value_of("max-commission-rate");
             let vm = m.value_of("validator-memo");
             let force = m.is_present("force");
             if am.is_none() || cr.is_none() {
@@ -258,8 +310,12 @@ fn run() -> Result<()> {
                 println!(
                     "Tips: if you want to raise the power of your node, please use `fn stake --append [OPTIONS]`"
                 );
+            } else if let Some(file) = m.value_of("build-only") {
+                let signer = offline::get_signer(m.value_of("signer"), None)?;
+                offline::build_stake(signer, am.unwrap(), cr.unwrap(), max_cr, vm, file)
+                    .c(d!())?;
             } else {
-                common::stake(am.unwrap(), cr.unwrap(), vm, force).c(d!())?;
+                common::stake(am.unwrap(), cr.unwrap(), max_cr, vm, force).c(d!())?;
             }
         }
     } else if let Some(m) = matches.subcommand_matches("unstake") {
@@ -280,7 +336,50 @@ fn run() -> Result<()> {
             }
             None => None,
         };
-        common::claim(am, seckey.as_deref()).c(d!())?;
+        if let Some(file) = m.value_of("build-only") {
+            let signer = offline::get_signer(m.value_of("signer"), seckey.as_deref())?;
+            offline::build_claim(signer, am, file).c(d!())?;
+        } else {
+            common::claim(am, seckey.as_deref()).c(d!())?;
+        }
+    } else if let Some(m) = matches.subcommand_matches("vesting") {
+        let seckey = match m.value_of("seckey") {
+            Some(path) => {
+                Some(fs::read_to_string(path).c(d!("Failed to read seckey file"))?)
+            }
+            None => None,
+        };
+        let am = m.value_of("amount");
+        if m.is_present("create") {
+            let schedule = m.value_of("schedule");
+            if am.is_none() || schedule.is_none() {
+                println!("{}", m.usage());
+                return Ok(());
+            }
+            let beneficiary = m
+                .value_of("to-pubkey")
+                .c(d!())
+                .and_then(|pk| wallet::public_key_from_base64(pk).c(d!()))
+                .or_else(|_| {
+                    m.value_of("to-wallet-address").c(d!()).and_then(|addr| {
+                        wallet::public_key_from_bech32(addr)
+                            .c(d!("invalid wallet address"))
+                    })
+                })?;
+            common::create_vesting(
+                seckey.as_deref(),
+                beneficiary,
+                am.unwrap(),
+                schedule.unwrap(),
+            )
+            .c(d!())?;
+        } else if m.is_present("release") {
+            common::release_vested(am, seckey.as_deref()).c(d!())?;
+        } else if m.is_present("show") {
+            common::show_vesting(seckey.as_deref()).c(d!())?;
+        } else {
+            println!("{}", m.usage());
+        }
     } else if let Some(m) = matches.subcommand_matches("show") {
         let basic = m.is_present("basic");
         common::show(basic).c(d!())?;
@@ -311,24 +410,62 @@ fn run() -> Result<()> {
                 })
             })?;
         let am = m.value_of("amount");
+        if f.is_some() && device.is_some() {
+            return Err(eg!("`--from-seckey` can not be used with `--signer`"));
+        }
 
         if am.is_none() {
             println!("{}", m.usage());
-        } else {
+        } else if let Some(file) = m.value_of("build-only") {
             let token_code = if asset.to_uppercase() != "FRA" {
                 Some(AssetTypeCode::new_from_base64(asset).c(d!())?)
             } else {
                 None
             };
-            common::transfer_asset(
-                f.as_deref(),
-                t,
+            let am = am
+                .unwrap()
+                .parse::<u64>()
+                .c(d!("'amount' must be an integer"))?;
+            let signer = match device.as_ref() {
+                Some(d) => d.public_key().c(d!())?,
+                None => offline::get_signer(m.value_of("signer"), f.as_deref())?,
+            };
+            offline::build_transfer(
+                signer,
+                vec![(t, am)],
                 token_code,
-                am.unwrap(),
                 m.is_present("confidential-amount"),
                 m.is_present("confidential-type"),
+                file,
             )
             .c(d!())?;
+        } else {
+            let token_code = if asset.to_uppercase() != "FRA" {
+                Some(AssetTypeCode::new_from_base64(asset).c(d!())?)
+            } else {
+                None
+            };
+            if let Some(d) = device.as_ref() {
+                common::transfer_asset_batch_with_signer(
+                    d.as_ref(),
+                    &[t],
+                    token_code,
+                    am.unwrap(),
+                    m.is_present("confidential-amount"),
+                    m.is_present("confidential-type"),
+                )
+                .c(d!())?;
+            } else {
+                common::transfer_asset(
+                    f.as_deref(),
+                    t,
+                    token_code,
+                    am.unwrap(),
+                    m.is_present("confidential-amount"),
+                    m.is_present("confidential-type"),
+                )
+                .c(d!())?;
+            }
         }
     } else if let Some(m) = matches.subcommand_matches("transfer-batch") {
         let f = match m.value_of("from-seckey") {
@@ -360,9 +497,22 @@ fn run() -> Result<()> {
                 })
             })?;
         let am = m.value_of("amount");
+        if f.is_some() && device.is_some() {
+            return Err(eg!("`--from-seckey` can not be used with `--signer`"));
+        }
 
         if am.is_none() || t.is_empty() {
             println!("{}", m.usage());
+        } else if let Some(d) = device.as_ref() {
+            common::transfer_asset_batch_with_signer(
+                d.as_ref(),
+                &t,
+                None,
+                am.unwrap(),
+                m.is_present("confidential-amount"),
+                m.is_present("confidential-type"),
+            )
+            .c(d!())?;
         } else {
             common::transfer_asset_batch(
                 f.as_deref(),
@@ -374,6 +524,124 @@ fn run() -> Result<()> {
             )
             .c(d!())?;
         }
+    } else if let Some(m) = matches.subcommand_matches("batch") {
+        let f = match m.value_of("from-seckey") {
+            Some(path) => {
+                Some(fs::read_to_string(path).c(d!("Failed to read seckey file"))?)
+            }
+            None => None,
+        };
+        let file = m.value_of("file").c(d!())?;
+        batch::batch(file, f.as_deref(), m.is_present("yes")).c(d!())?;
+    } else if let Some(m) = matches.subcommand_matches("sign") {
+        let seckey = match m.value_of("seckey") {
+            Some(path) => {
+                Some(fs::read_to_string(path).c(d!("Failed to read seckey file"))?)
+            }
+            None => None,
+        };
+        offline::sign(
+            m.value_of("file").c(d!())?,
+            seckey.as_deref(),
+            m.value_of("validator-key"),
+            m.is_present("yes"),
+        )
+        .c(d!())?;
+    } else if let Some(m) = matches.subcommand_matches("broadcast") {
+        offline::broadcast(m.value_of("file").c(d!())?).c(d!())?;
+    } else if let Some(m) = matches.subcommand_matches("multisig") {
+        use finutils::common::multisig;
+
+        if let Some(sm) = m.subcommand_matches("address") {
+            let threshold = sm
+                .value_of("threshold")
+                .c(d!())?
+                .parse::<u64>()
+                .c(d!("'threshold' must be an integer"))?;
+            let signers = sm.values_of("signer").c(d!())?.collect::<Vec<_>>();
+            multisig::gen_address(
+                threshold,
+                multisig::parse_signers(&signers).c(d!())?,
+                sm.value_of("rules-file"),
+            )
+            .c(d!())?;
+        } else if let Some(sm) = m.subcommand_matches("create") {
+            let asset = sm.value_of("asset").unwrap_or("FRA");
+            let token_code = if asset.to_uppercase() != "FRA" {
+                Some(AssetTypeCode::new_from_base64(asset).c(d!())?)
+            } else {
+                None
+            };
+            let t = sm
+                .value_of("to-pubkey")
+                .c(d!())
+                .and_then(|pk| wallet::public_key_from_base64(pk).c(d!()))
+                .or_else(|_| {
+                    sm.value_of("to-wallet-address").c(d!()).and_then(|addr| {
+                        wallet::public_key_from_bech32(addr)
+                            .c(d!("invalid wallet address"))
+                    })
+                })?;
+            multisig::create_tx(
+                sm.value_of("rules-file").c(d!())?,
+                t,
+                token_code,
+                sm.value_of("amount").c(d!())?,
+                sm.value_of("tx-file").c(d!())?,
+            )
+            .c(d!())?;
+        } else if let Some(sm) = m.subcommand_matches("sign") {
+            let seckey = match sm.value_of("seckey") {
+                Some(path) => {
+                    Some(fs::read_to_string(path).c(d!("Failed to read seckey file"))?)
+                }
+                None => None,
+            };
+            multisig::sign_tx(seckey.as_deref(), sm.value_of("tx-file").c(d!())?)
+                .c(d!())?;
+        } else if let Some(sm) = m.subcommand_matches("combine") {
+            let files = sm.values_of("tx-file").c(d!())?.collect::<Vec<_>>();
+            multisig::combine_tx(&files, sm.value_of("output").c(d!())?).c(d!())?;
+        } else if let Some(sm) = m.subcommand_matches("send") {
+            multisig::send_tx(sm.value_of("tx-file").c(d!())?).c(d!())?;
+        } else {
+            println!("{}", m.usage());
+        }
+    } else if let Some(m) = matches.subcommand_matches("cosig") {
+        use finutils::common::cosig;
+
+        if let Some(sm) = m.subcommand_matches("propose") {
+            let lifetime = sm
+                .value_of("lifetime")
+                .map(|l| l.parse::<u64>().c(d!("invalid lifetime")))
+                .transpose()?;
+            cosig::propose(
+                sm.value_of("proposal").c(d!())?,
+                sm.value_of("tx-file").c(d!())?,
+                lifetime,
+            )
+            .c(d!())?;
+        } else if let Some(sm) = m.subcommand_matches("sign") {
+            let seckey = match sm.value_of("seckey") {
+                Some(path) => {
+                    Some(fs::read_to_string(path).c(d!("Failed to read seckey file"))?)
+                }
+                None => None,
+            };
+            cosig::sign(seckey.as_deref(), sm.value_of("tx-file").c(d!())?).c(d!())?;
+        } else if let Some(sm) = m.subcommand_matches("status") {
+            cosig::status(sm.value_of("tx-file").c(d!())?).c(d!())?;
+        } else if let Some(sm) = m.subcommand_matches("submit") {
+            let seckey = match sm.value_of("seckey") {
+                Some(path) => {
+                    Some(fs::read_to_string(path).c(d!("Failed to read seckey file"))?)
+                }
+                None => None,
+            };
+            cosig::submit(seckey.as_deref(), sm.value_of("tx-file").c(d!())?).c(d!())?;
+        } else {
+            println!("{}", m.usage());
+        }
     } else if matches.is_present("gen-eth-key") {
         let (pair, phrase, _) = SecpPair::generate_with_phrase(None);
         let kp = hex::encode(pair.seed());
@@ -390,13 +658,34 @@ fn run() -> Result<()> {
     } else if let Some(m) = matches.subcommand_matches("contract-deposit") {
         let amount = m.value_of("amount").c(d!())?;
         let address = m.value_of("addr");
-        transfer_to_account(amount.parse::<u64>().c(d!())?, address).c(d!())?
+        if let Some(file) = m.value_of("build-only") {
+            let signer = offline::get_signer(m.value_of("signer"), None)?;
+            offline::build_convert(
+                signer,
+                amount.parse::<u64>().c(d!())?,
+                address,
+                file,
+            )
+            .c(d!())?;
+        } else {
+            transfer_to_account(
+                amount.parse::<u64>().c(d!())?,
+                address,
+                m.is_present("confidential"),
+            )
+            .c(d!())?
+        }
     } else if let Some(m) = matches.subcommand_matches("contract-withdraw") {
         let amount = m.value_of("amount").c(d!())?;
         let address = m.value_of("addr");
         let eth_key = m.value_of("eth-key");
-        transfer_from_account(amount.parse::<u64>().c(d!())?, address, eth_key)
-            .c(d!())?
+        transfer_from_account(
+            amount.parse::<u64>().c(d!())?,
+            address,
+            eth_key,
+            m.is_present("confidential"),
+        )
+        .c(d!())?
     } else if let Some(m) = matches.subcommand_matches("replace_staker") {
         let target = m
             .value_of("target")
@@ -443,6 +732,8 @@ fn run() -> Result<()> {
                 let mut force_create = false;
                 let mut evm_chain_id = 2152;
                 let mut checkpoint_file = None;
+                let mut fork_from = None;
+                let mut impersonate = vec![];
 
                 if let Some(name) = sm.value_of("env_name") {
                     envcfg.name = name.into();
@@ -489,6 +780,16 @@ fn run() -> Result<()> {
                 if let Some(flags) = sm.value_of("tendermint_extra_flags") {
                     tendermint_extra_flags = Some(flags.to_owned());
                 }
+                if let Some(file) = sm.value_of("fork_from") {
+                    fork_from = Some(file.to_owned());
+                }
+                if let Some(addrs) = sm.value_of("impersonate") {
+                    impersonate = addrs
+                        .split(',')
+                        .map(|a| a.trim().to_owned())
+                        .filter(|a| !a.is_empty())
+                        .collect();
+                }
                 if sm.is_present("force") {
                     force_create = true;
                 }
@@ -503,6 +804,8 @@ fn run() -> Result<()> {
                     force_create,
                     evm_chain_id,
                     checkpoint_file,
+                    fork_from,
+                    impersonate,
                 }
             } else if let Some(sm) = m.subcommand_matches("destroy") {
                 if let Some(name) = sm.value_of("env_name") {
@@ -551,6 +854,28 @@ fn run() -> Result<()> {
                 dev::Ops::Init
             } else if m.subcommand_matches("init-all").is_some() {
                 dev::Ops::InitAll
+            } else if let Some(sm) = m.subcommand_matches("chaos") {
+                if let Some(name) = sm.value_of("env_name") {
+                    envcfg.name = name.into();
+                }
+                let kind = sm
+                    .value_of("scenario")
+                    .c(d!())?
+                    .parse::<dev::ChaosKind>()
+                    .c(d!())?;
+                let validator_idx = sm
+                    .value_of("validator_idx")
+                    .map(|i| i.parse::<u8>().c(d!()))
+                    .transpose()?;
+                let n_blocks = sm
+                    .value_of("n_blocks")
+                    .map(|n| n.parse::<u64>().c(d!()))
+                    .transpose()?;
+                dev::Ops::Chaos(dev::ChaosCfg {
+                    kind,
+                    validator_idx,
+                    n_blocks,
+                })
             } else {
                 if let Some(name) = m.value_of("env_name") {
                     envcfg.name = name.into();
@@ -0,0 +1,557 @@
+//!
+//! Batched transactions
+//!
+//! Several operations listed in a JSON or YAML file are sent in one transaction,
+//! so they succeed or fail together, and the fee is paid only once.
+//!
+//! ```yaml
+//! operations:
+//!   - type: transfer
+//!     asset: FRA                  # or the code of a custom asset, default to FRA
+//!     to:
+//!       - { address: fra1..., amount: 1000000 }
+//!   - type: issue
+//!     asset: <code of the asset>
+//!     amount: 1000000
+//!   - type: claim
+//!   - type: delegate
+//!     amount: 100000000
+//!     validator: 5C97EE9B91D90B332813078957E3A96B304791B4
+//!     from_seckey: /path/to/seckey  # default to the signer of the batch
+//! ```
+//!
+//! NOTE: the outputs of an operation can not be spent by a later one of the same batch.
+//!
+
+use {
+    super::{offline::fra, restore_keypair_from_str_with_default, utils},
+    crate::txn_builder::TransactionBuilder,
+    globutils::wallet,
+    ledger::{
+        data_model::{
+            AssetTypeCode, Operation, Transaction, TxoRef, TxoSID, Utxo,
+            BLACK_HOLE_PUBKEY_STAKING, TX_FEE_MIN,
+        },
+        staking::TendermintAddr,
+    },
+    ruc::*,
+    serde::Deserialize,
+    std::{
+        collections::HashMap,
+        fmt, fs,
+        io::{self, Write},
+    },
+    zei::{
+        setup::PublicParams,
+        xfr::{
+            asset_record::AssetRecordType,
+            sig::{XfrKeyPair, XfrPublicKey},
+            structs::OwnerMemo,
+        },
+    },
+};
+
+type Utxos = HashMap<TxoSID, (Utxo, Option<OwnerMemo>)>;
+
+/// Operations to be sent in one transaction.
+#[derive(Deserialize)]
+pub struct Batch {
+    #[allow(missing_docs)]
+    pub operations: Vec<BatchOp>,
+}
+
+/// An operation of a batch,
+/// `from_seckey` is the file of the secret key signing it, default to the signer of the batch.
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+#[allow(missing_docs)]
+pub enum BatchOp {
+    Transfer {
+        #[serde(default)]
+        from_seckey: Option<String>,
+        #[serde(default)]
+        asset: Option<String>,
+        to: Vec<Receiver>,
+        #[serde(default)]
+        confidential_amount: bool,
+        #[serde(default)]
+        confidential_type: bool,
+    },
+    Issue {
+        #[serde(default)]
+        from_seckey: Option<String>,
+        asset: String,
+        amount: u64,
+        #[serde(default)]
+        confidential: bool,
+    },
+    Delegate {
+        #[serde(default)]
+        from_seckey: Option<String>,
+        amount: u64,
+        validator: TendermintAddr,
+    },
+    Claim {
+        #[serde(default)]
+        from_seckey: Option<String>,
+        #[serde(default)]
+        amount: Option<u64>,
+    },
+}
+
+/// A receiver of a transfer.
+#[derive(Deserialize)]
+pub struct Receiver {
+    /// wallet address or base64-formated public key
+    pub address: String,
+    #[allow(missing_docs)]
+    pub amount: u64,
+}
+
+impl BatchOp {
+    fn from_seckey(&self) -> Option<&str> {
+        match self {
+            BatchOp::Transfer { from_seckey, .. }
+            | BatchOp::Issue { from_seckey, .. }
+            | BatchOp::Delegate { from_seckey, .. }
+            | BatchOp::Claim { from_seckey, .. } => from_seckey.as_deref(),
+        }
+    }
+}
+
+impl fmt::Display for BatchOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BatchOp::Transfer {
+                asset,
+                to,
+                confidential_amount,
+                confidential_type,
+                ..
+            } => {
+                writeln!(f, "Transfer {}:", asset.as_deref().unwrap_or("FRA"))?;
+                for r in to.iter() {
+                    writeln!(f, "    {} -> {}", r.amount, r.address)?;
+                }
+                write!(
+                    f,
+                    "    confidential amount: {confidential_amount}, confidential type: {confidential_type}"
+                )
+            }
+            BatchOp::Issue {
+                asset,
+                amount,
+                confidential,
+                ..
+            } => write!(
+                f,
+                "Issue {amount} of {asset}, confidential amount: {confidential}"
+            ),
+            BatchOp::Delegate {
+                amount, validator, ..
+            } => write!(f, "Delegate {} to {}", fra(*amount), validator),
+            BatchOp::Claim { amount, .. } => match amount {
+                Some(am) => write!(f, "Claim {} of rewards", fra(*am)),
+                None => write!(f, "Claim all rewards"),
+            },
+        }
+    }
+}
+
+/// Build the operations of `file` into one transaction,
+/// show the summary and the simulated effects of it, then send it.
+///
+/// The fee is paid by the keypair of `sk_str`, or the configured one if it is `None`.
+pub fn batch(file: &str, sk_str: Option<&str>, yes: bool) -> Result<()> {
+    let batch = read(file).c(d!())?;
+    if batch.operations.is_empty() {
+        return Err(eg!("no operation in the batch"));
+    }
+
+    let payer = restore_keypair_from_str_with_default(sk_str).c(d!())?;
+    let payer_pk = payer.get_pk();
+    let builder = utils::new_tx_builder().c(d!())?;
+    let seq_id = builder.get_seq_id();
+    let tx = build_tx(builder, &batch, payer, &mut HashMap::new()).c(d!())?;
+
+    println!(
+        "Fee payer: {}\nSeq id: {}\nFee: {}",
+        wallet::public_key_to_bech32(&payer_pk),
+        seq_id,
+        fra(TX_FEE_MIN)
+    );
+    for op in batch.operations.iter() {
+        println!("{op}");
+    }
+
+    let sim = utils::simulate_tx(&tx).c(d!())?;
+    if let Some(r) = sim.rejection {
+        return Err(eg!(format!(
+            "the transaction is rejected at {:?}: {}",
+            r.stage, r.reason
+        )));
+    }
+    println!(
+        "Simulated: {} utxos spent, {} utxos created, {} paid",
+        sim.spent_txo_sids.len(),
+        sim.new_txo_sids.len(),
+        fra(sim.fee)
+    );
+    for d in sim.staking_deltas.iter() {
+        println!(
+            "    {}: bonded {} -> {}, rewards {} -> {}",
+            wallet::public_key_to_bech32(&d.pubkey),
+            fra(d.bonded_before),
+            fra(d.bonded_after),
+            fra(d.rewards_before),
+            fra(d.rewards_after)
+        );
+    }
+
+    if !yes && !confirm().c(d!())? {
+        println!("Canceled");
+        return Ok(());
+    }
+
+    utils::send_tx(&tx).c(d!())?;
+    println!("{}", tx.handle());
+
+    Ok(())
+}
+
+// Build all operations of `batch` and the fee into one transaction,
+// which is signed by the payer and the owners of `from_seckey`s.
+fn build_tx(
+    mut builder: TransactionBuilder,
+    batch: &Batch,
+    payer: XfrKeyPair,
+    utxos: &mut HashMap<XfrPublicKey, Utxos>,
+) -> Result<Transaction> {
+    let mut signers = vec![payer.get_pk()];
+    let mut kps = HashMap::new();
+    kps.insert(payer.get_pk(), payer);
+
+    for op in batch.operations.iter() {
+        let pk = if let Some(path) = op.from_seckey() {
+            let kp = fs::read_to_string(path)
+                .c(d!("Failed to read seckey file"))
+                .and_then(|sk| restore_keypair_from_str_with_default(Some(&sk)))?;
+            let pk = kp.get_pk();
+            if !kps.contains_key(&pk) {
+                signers.push(pk);
+                kps.insert(pk, kp);
+            }
+            pk
+        } else {
+            signers[0]
+        };
+        add_op(&mut builder, op, &kps[&pk], utxos).c(d!())?;
+    }
+
+    // the fee of the whole transaction
+    let fee_op = gen_transfer_op(&kps[&signers[0]], utxos, vec![], None, true, None)
+        .c(d!("insufficient balance to pay the fee"))?;
+    builder.add_operation(fee_op);
+
+    let mut tx = builder.take_transaction();
+    for pk in signers.iter() {
+        tx.sign_to_map(&kps[pk]);
+    }
+
+    Ok(tx)
+}
+
+fn add_op(
+    builder: &mut TransactionBuilder,
+    op: &BatchOp,
+    kp: &XfrKeyPair,
+    utxos: &mut HashMap<XfrPublicKey, Utxos>,
+) -> Result<()> {
+    match op {
+        BatchOp::Transfer {
+            asset,
+            to,
+            confidential_amount,
+            confidential_type,
+            ..
+        } => {
+            let token_code = asset
+                .as_deref()
+                .filter(|a| a.to_uppercase() != "FRA")
+                .map(|a| AssetTypeCode::new_from_base64(a).c(d!("invalid asset code")))
+                .transpose()?;
+            let targets = to
+                .iter()
+                .map(|r| {
+                    wallet::public_key_from_bech32(&r.address)
+                        .or_else(|_| wallet::public_key_from_base64(&r.address))
+                        .c(d!("invalid address"))
+                        .map(|pk| (pk, r.amount))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let op = gen_transfer_op(
+                kp,
+                utxos,
+                targets.iter().map(|(pk, am)| (pk, *am)).collect(),
+                token_code,
+                false,
+                AssetRecordType::from_flags(*confidential_amount, *confidential_type)
+                    .into(),
+            )
+            .c(d!())?;
+            builder.add_operation(op);
+        }
+        BatchOp::Issue {
+            asset,
+            amount,
+            confidential,
+            ..
+        } => {
+            let code =
+                AssetTypeCode::new_from_base64(asset).c(d!("invalid asset code"))?;
+            let seq_id = builder.get_seq_id();
+            builder
+                .add_basic_issue_asset(
+                    kp,
+                    &code,
+                    seq_id,
+                    *amount,
+                    AssetRecordType::from_flags(*confidential, false),
+                    &PublicParams::default(),
+                )
+                .c(d!())?;
+        }
+        BatchOp::Delegate {
+            amount, validator, ..
+        } => {
+            let principal_op = gen_transfer_op(
+                kp,
+                utxos,
+                vec![(&BLACK_HOLE_PUBKEY_STAKING, *amount)],
+                None,
+                false,
+                Some(AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType),
+            )
+            .c(d!())?;
+            builder
+                .add_operation(principal_op)
+                .add_operation_delegation(kp, *amount, validator.clone());
+        }
+        BatchOp::Claim { amount, .. } => {
+            builder.add_operation_claim(kp, *amount);
+        }
+    }
+
+    Ok(())
+}
+
+// The inputs of the operation are removed from `utxos`,
+// so they will not be spent again by later operations.
+fn gen_transfer_op(
+    kp: &XfrKeyPair,
+    utxos: &mut HashMap<XfrPublicKey, Utxos>,
+    targets: Vec<(&XfrPublicKey, u64)>,
+    token_code: Option<AssetTypeCode>,
+    auto_fee: bool,
+    balance_type: Option<AssetRecordType>,
+) -> Result<Operation> {
+    let owned = if let Some(u) = utxos.get_mut(&kp.get_pk()) {
+        u
+    } else {
+        let u = utils::get_owned_utxos(&kp.get_pk()).c(d!())?;
+        utxos.entry(kp.get_pk()).or_insert(u)
+    };
+
+    let (confidential_am, confidential_ty) = match balance_type {
+        Some(AssetRecordType::ConfidentialAmount_NonConfidentialAssetType) => {
+            (true, false)
+        }
+        Some(AssetRecordType::NonConfidentialAmount_ConfidentialAssetType) => {
+            (false, true)
+        }
+        Some(AssetRecordType::ConfidentialAmount_ConfidentialAssetType) => (true, true),
+        _ => (false, false),
+    };
+    let op = utils::gen_transfer_op_with_utxos(
+        owned.clone(),
+        kp,
+        targets,
+        token_code,
+        auto_fee,
+        confidential_am,
+        confidential_ty,
+        balance_type,
+    )
+    .c(d!())?;
+
+    if let Operation::TransferAsset(t) = &op {
+        for i in t.body.inputs.iter() {
+            if let TxoRef::Absolute(sid) = i {
+                owned.remove(sid);
+            }
+        }
+    }
+
+    Ok(op)
+}
+
+fn read(file: &str) -> Result<Batch> {
+    let data = fs::read_to_string(file).c(d!())?;
+    if file.ends_with(".yaml") || file.ends_with(".yml") {
+        serde_yaml::from_str(&data).c(d!("invalid batch file"))
+    } else {
+        serde_json::from_str(&data).c(d!("invalid batch file"))
+    }
+}
+
+fn confirm() -> Result<bool> {
+    print!("Send this transaction? [y/N] ");
+    io::stdout().flush().c(d!())?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer).c(d!())?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+#[cfg(test)]
+#[allow(missing_docs)]
+mod test {
+    use {
+        super::*,
+        ledger::data_model::{TxOutput, ASSET_TYPE_FRA, BLACK_HOLE_PUBKEY},
+        rand_chacha::ChaChaRng,
+        rand_core::SeedableRng,
+        std::env,
+        zei::xfr::{
+            asset_record::build_blind_asset_record, structs::AssetRecordTemplate,
+        },
+    };
+
+    fn gen_utxos(prng: &mut ChaChaRng, pk: XfrPublicKey, sids: &[u64]) -> Utxos {
+        sids.iter()
+            .map(|sid| (TxoSID(*sid), gen_utxo(prng, pk)))
+            .collect()
+    }
+
+    fn gen_utxo(prng: &mut ChaChaRng, pk: XfrPublicKey) -> (Utxo, Option<OwnerMemo>) {
+        let template = AssetRecordTemplate::with_no_asset_tracing(
+            1000 * TX_FEE_MIN,
+            ASSET_TYPE_FRA,
+            AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType,
+            pk,
+        );
+        let (record, _, memo) = build_blind_asset_record(
+            prng,
+            &PublicParams::default().pc_gens,
+            &template,
+            vec![],
+        );
+        let utxo = Utxo(TxOutput {
+            id: None,
+            record,
+            lien: None,
+        });
+        (utxo, memo)
+    }
+
+    #[test]
+    fn batch_build_tx() {
+        let mut prng = ChaChaRng::from_entropy();
+        let payer = XfrKeyPair::generate(&mut prng);
+        let delegator = XfrKeyPair::generate(&mut prng);
+        let receiver = XfrKeyPair::generate(&mut prng).get_pk();
+        let code = AssetTypeCode::gen_random();
+
+        let tmp = |name: &str| {
+            env::temp_dir()
+                .join(format!("batch_{}_{}", rand::random::<u64>(), name))
+                .to_string_lossy()
+                .into_owned()
+        };
+        let seckey = tmp("seckey");
+        let sk = pnk!(serde_json::to_string(&delegator.get_sk()));
+        pnk!(fs::write(&seckey, sk.trim_matches('"')));
+
+        let file = tmp("ops.yaml");
+        pnk!(fs::write(
+            &file,
+            format!(
+                r#"
+operations:
+  - type: transfer
+    to:
+      - {{ address: {}, amount: {} }}
+  - type: issue
+    asset: {}
+    amount: 1000
+  - type: delegate
+    amount: {}
+    validator: 5C97EE9B91D90B332813078957E3A96B304791B4
+    from_seckey: {}
+  - type: claim
+"#,
+                wallet::public_key_to_bech32(&receiver),
+                3 * TX_FEE_MIN,
+                code.to_base64(),
+                10 * TX_FEE_MIN,
+                seckey,
+            ),
+        ));
+
+        let batch = pnk!(read(&file));
+        let _ = fs::remove_file(&file);
+        assert_eq!(4, batch.operations.len());
+
+        let mut utxos = HashMap::new();
+        utxos.insert(
+            payer.get_pk(),
+            gen_utxos(&mut prng, payer.get_pk(), &[1, 2]),
+        );
+        utxos.insert(
+            delegator.get_pk(),
+            gen_utxos(&mut prng, delegator.get_pk(), &[3]),
+        );
+
+        let payer_pk = payer.get_pk();
+        let tx = pnk!(build_tx(
+            TransactionBuilder::from_seq_id(100),
+            &batch,
+            payer,
+            &mut utxos
+        ));
+        let _ = fs::remove_file(&seckey);
+
+        let ops = &tx.body.operations;
+        assert!(matches!(ops[0], Operation::TransferAsset(_)));
+        assert!(matches!(ops[1], Operation::IssueAsset(_)));
+        assert!(matches!(ops[2], Operation::TransferAsset(_)));
+        assert!(matches!(ops[3], Operation::Delegation(_)));
+        assert!(matches!(ops[4], Operation::Claim(_)));
+
+        // the fee is paid only once, by the payer
+        let fee_ops = ops
+            .iter()
+            .filter_map(|op| match op {
+                Operation::TransferAsset(x)
+                    if x.body
+                        .outputs
+                        .iter()
+                        .any(|o| *BLACK_HOLE_PUBKEY == o.record.public_key) =>
+                {
+                    Some(x)
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(1, fee_ops.len());
+        assert_eq!(payer_pk, fee_ops[0].body.transfer.inputs[0].public_key);
+        assert!(tx.check_fee());
+
+        // signed by both the payer and the delegator
+        pnk!(tx.check_has_signature_from_map(&payer_pk));
+        pnk!(tx.check_has_signature_from_map(&delegator.get_pk()));
+        pnk!(tx.check_tx());
+
+        // the inputs have all been spent
+        assert!(utxos.values().all(|u| u.is_empty()));
+    }
+}
@@ -0,0 +1,410 @@
+//!
+//! Co-signing of `CoSigOp` operations
+//!
+//! `Governance`, `FraDistribution` and `UpdateValidator` operations
+//! need the signatures of validators holding enough power,
+//! see `CoSigRule` for the threshold.
+//!
+//! `fn cosig propose` writes the unsigned operation to a transaction file,
+//! which is passed around the validators to append their signatures
+//! by `fn cosig sign`, this can be done offline.
+//! `fn cosig status` checks the signed power against the current validators,
+//! and `fn cosig submit` sends the transaction once the threshold is reached.
+//!
+//! The signatures cover a `CoSigProposal` instead of the `NoReplayToken`,
+//! which is only accepted for `TRANSACTION_WINDOW_WIDTH` blocks: it has a random id
+//! and an expiry height checked by the ledger, so the signatures can be collected
+//! over up to `COSIG_PROPOSAL_LIFETIME_MAX` blocks. The transaction is rebuilt
+//! with a fresh `NoReplayToken` on submission. An expired proposal can
+//! not be signed again, a new one has to be proposed.
+//!
+
+use {
+    super::{get_serv_addr, restore_keypair_from_str_with_default, utils},
+    crate::txn_builder::TransactionBuilder,
+    globutils::wallet,
+    ledger::{
+        data_model::{Operation, Transaction},
+        staking::{
+            cosig::CoSigProposal, ops::governance::ByzantineKind, BlockHeight,
+            Validator, ValidatorData, COSIG_PROPOSAL_LIFETIME_MAX,
+        },
+    },
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    std::{collections::BTreeMap, fs},
+    zei::xfr::sig::{XfrKeyPair, XfrPublicKey},
+};
+
+/// The content of a proposal file, e.g.
+/// `{"governance": {"byzantine_id": "<base64 pubkey>", "kind": "OffLine"}}`.
+#[allow(missing_docs)]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Proposal {
+    /// Punish a byzantine validator
+    Governance {
+        byzantine_id: XfrPublicKey,
+        kind: ByzantineKind,
+        #[serde(default)]
+        custom_percent: Option<[u64; 2]>,
+    },
+    /// Pay FRAs to some addresses
+    FraDistribution {
+        alloc_table: BTreeMap<XfrPublicKey, u64>,
+    },
+    /// Set the validators at a height
+    UpdateValidator {
+        height: BlockHeight,
+        validators: Vec<Validator>,
+    },
+}
+
+/// Write an unsigned co-signature operation to `tx_file`,
+/// it is accepted for `lifetime` blocks, `COSIG_PROPOSAL_LIFETIME_MAX` by default.
+pub fn propose(
+    proposal_file: &str,
+    tx_file: &str,
+    lifetime: Option<BlockHeight>,
+) -> Result<()> {
+    let proposal = fs::read(proposal_file)
+        .c(d!())
+        .and_then(|b| serde_json::from_slice::<Proposal>(&b).c(d!()))?;
+
+    let lifetime = lifetime.unwrap_or(*COSIG_PROPOSAL_LIFETIME_MAX);
+    if lifetime > *COSIG_PROPOSAL_LIFETIME_MAX {
+        return Err(eg!(format!(
+            "the lifetime can not exceed {} blocks",
+            *COSIG_PROPOSAL_LIFETIME_MAX
+        )));
+    }
+    let expiry = current_height().c(d!())? + lifetime;
+
+    let mut builder = utils::new_tx_builder().c(d!())?;
+    add_proposal(&mut builder, proposal).c(d!())?;
+
+    let mut tx = builder.take_transaction();
+    set_proposal(&mut tx, CoSigProposal::new(expiry)).c(d!())?;
+    write_tx(tx_file, &tx).c(d!())?;
+    println!(
+        "The proposal is written to {tx_file}, it must be submitted by block {expiry}"
+    );
+
+    Ok(())
+}
+
+/// Append the signature of a validator to the transaction file,
+/// no network access is needed.
+pub fn sign(sk_str: Option<&str>, tx_file: &str) -> Result<()> {
+    let kp = restore_keypair_from_str_with_default(sk_str)?;
+    let mut tx = read_tx(tx_file).c(d!())?;
+
+    sign_tx(&mut tx, &kp).c(d!())?;
+
+    write_tx(tx_file, &tx).c(d!())?;
+    println!(
+        "Signed by {}, {} signatures in total",
+        wallet::public_key_to_bech32(kp.get_pk_ref()),
+        signers(&tx).len()
+    );
+
+    Ok(())
+}
+
+/// Show the signers and their power,
+/// and check them against the current validators.
+pub fn status(tx_file: &str) -> Result<()> {
+    let tx = read_tx(tx_file).c(d!())?;
+    let vd = utils::get_validator_data().c(d!())?;
+    let height = current_height().c(d!())?;
+    show_progress(&tx, &vd).c(d!())?;
+
+    let left = check_expiry(&tx, height).c(d!("a new proposal is needed"))?;
+    println!("The proposal expires in {left} blocks");
+
+    check(&tx, &vd).c(d!())?;
+    println!("Ready to submit");
+
+    Ok(())
+}
+
+/// Send the transaction if the signers have reached the threshold,
+/// the fee is paid by the submitter.
+pub fn submit(sk_str: Option<&str>, tx_file: &str) -> Result<()> {
+    let tx = read_tx(tx_file).c(d!())?;
+    let vd = utils::get_validator_data().c(d!())?;
+    let height = current_height().c(d!())?;
+    check_expiry(&tx, height).c(d!("a new proposal is needed"))?;
+    check(&tx, &vd).c(d!("signatures are not enough"))?;
+
+    // the signatures only cover the co-signed data and the proposal,
+    // so the replay token is attached now and the fee can be added
+    let mut tx = rebuild(&tx, utils::get_seq_id().c(d!())?);
+    let kp = restore_keypair_from_str_with_default(sk_str)?;
+    let op = utils::gen_fee_op(&kp).c(d!())?;
+    tx.add_operation(op);
+    tx.sign_to_map(&kp);
+
+    utils::send_tx(&tx).c(d!())?;
+    println!("{}", tx.handle());
+
+    Ok(())
+}
+
+fn add_proposal(builder: &mut TransactionBuilder, proposal: Proposal) -> Result<()> {
+    match proposal {
+        Proposal::Governance {
+            byzantine_id,
+            kind,
+            custom_percent,
+        } => builder
+            .add_operation_governance(&[], byzantine_id, kind, custom_percent)
+            .c(d!())?,
+        Proposal::FraDistribution { alloc_table } => builder
+            .add_operation_fra_distribution(&[], alloc_table)
+            .c(d!())?,
+        Proposal::UpdateValidator { height, validators } => builder
+            .add_operation_update_validator(&[], height, validators)
+            .c(d!())?,
+    };
+    Ok(())
+}
+
+fn set_proposal(tx: &mut Transaction, proposal: CoSigProposal) -> Result<()> {
+    let op = tx
+        .body
+        .operations
+        .iter_mut()
+        .find(|op| is_cosig_op(op))
+        .c(d!("no co-signature operation"))?;
+    match op {
+        Operation::Governance(i) => i.set_proposal(proposal),
+        Operation::FraDistribution(i) => i.set_proposal(proposal),
+        Operation::UpdateValidator(i) => i.set_proposal(proposal),
+        _ => return Err(eg!("not a co-signature operation")),
+    }
+    Ok(())
+}
+
+/// The number of blocks left before the proposal expires at `height`.
+fn check_expiry(tx: &Transaction, height: BlockHeight) -> Result<BlockHeight> {
+    let proposal = tx
+        .body
+        .operations
+        .iter()
+        .find_map(|op| match op {
+            Operation::Governance(i) => i.get_proposal(),
+            Operation::FraDistribution(i) => i.get_proposal(),
+            Operation::UpdateValidator(i) => i.get_proposal(),
+            _ => None,
+        })
+        .c(d!("no co-signature proposal"))?;
+    proposal.expiry.checked_sub(height).ok_or_else(|| {
+        eg!(format!(
+            "the proposal has expired: it was accepted until block {}, the chain is at block {height}",
+            proposal.expiry
+        ))
+    })
+}
+
+/// A new transaction holding the co-signature operations of `tx`,
+/// with the `NoReplayToken` of `seq_id`.
+fn rebuild(tx: &Transaction, seq_id: u64) -> Transaction {
+    let mut new_tx = TransactionBuilder::from_seq_id(seq_id).take_transaction();
+    tx.body
+        .operations
+        .iter()
+        .filter(|op| is_cosig_op(op))
+        .for_each(|op| new_tx.add_operation(op.clone()));
+    new_tx
+}
+
+fn current_height() -> Result<BlockHeight> {
+    match utils::get_block_height(get_serv_addr().c(d!())?) {
+        0 => Err(eg!("fail to get the block height")),
+        h => Ok(h),
+    }
+}
+
+fn sign_tx(tx: &mut Transaction, kp: &XfrKeyPair) -> Result<()> {
+    let op = tx
+        .body
+        .operations
+        .iter_mut()
+        .find(|op| is_cosig_op(op))
+        .c(d!("no co-signature operation"))?;
+    sign_op(op, kp).c(d!())
+}
+
+#[inline(always)]
+fn is_cosig_op(op: &Operation) -> bool {
+    matches!(
+        op,
+        Operation::Governance(_)
+            | Operation::FraDistribution(_)
+            | Operation::UpdateValidator(_)
+    )
+}
+
+fn sign_op(op: &mut Operation, kp: &XfrKeyPair) -> Result<()> {
+    match op {
+        Operation::Governance(i) => i.sign(kp).c(d!()),
+        Operation::FraDistribution(i) => i.sign(kp).c(d!()),
+        Operation::UpdateValidator(i) => i.sign(kp).c(d!()),
+        _ => Err(eg!("not a co-signature operation")),
+    }
+}
+
+fn signers(tx: &Transaction) -> Vec<XfrPublicKey> {
+    tx.body
+        .operations
+        .iter()
+        .flat_map(|op| match op {
+            Operation::Governance(i) => i.get_signers(),
+            Operation::FraDistribution(i) => i.get_signers(),
+            Operation::UpdateValidator(i) => i.get_signers(),
+            _ => vec![],
+        })
+        .collect()
+}
+
+fn check(tx: &Transaction, vd: &ValidatorData) -> Result<()> {
+    for op in tx.body.operations.iter() {
+        match op {
+            Operation::Governance(i) => i.check_cosigs(vd).c(d!())?,
+            Operation::FraDistribution(i) => i.check_cosigs(vd).c(d!())?,
+            Operation::UpdateValidator(i) => i.check_cosigs(vd).c(d!())?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn show_progress(tx: &Transaction, vd: &ValidatorData) -> Result<()> {
+    let [signed, total] = tx
+        .body
+        .operations
+        .iter()
+        .find_map(|op| match op {
+            Operation::Governance(i) => Some(i.get_weights(vd)),
+            Operation::FraDistribution(i) => Some(i.get_weights(vd)),
+            Operation::UpdateValidator(i) => Some(i.get_weights(vd)),
+            _ => None,
+        })
+        .c(d!("no co-signature operation"))?;
+
+    for pk in signers(tx) {
+        let power = vd
+            .body
+            .get(&pk)
+            .map(|v| v.td_power.to_string())
+            .unwrap_or_else(|| "not a validator".to_owned());
+        println!("{}: {}", wallet::public_key_to_bech32(&pk), power);
+    }
+
+    let threshold = vd.get_cosig_rule().threshold;
+    println!(
+        "Signed power: {}/{}, required: {}/{}",
+        signed, total, threshold[0], threshold[1]
+    );
+
+    Ok(())
+}
+
+fn read_tx(path: &str) -> Result<Transaction> {
+    fs::read(path)
+        .c(d!())
+        .and_then(|b| serde_json::from_slice(&b).c(d!()))
+}
+
+fn write_tx(path: &str, tx: &Transaction) -> Result<()> {
+    serde_json::to_vec_pretty(tx)
+        .c(d!())
+        .and_then(|b| fs::write(path, b).c(d!()))
+}
+
+#[cfg(test)]
+#[allow(missing_docs)]
+mod test {
+    use {
+        super::*,
+        ledger::{
+            staking::{cosig::CoSigRule, ValidatorKind},
+            store::TRANSACTION_WINDOW_WIDTH,
+        },
+        rand_chacha::ChaChaRng,
+        rand_core::SeedableRng,
+        std::env,
+    };
+
+    #[test]
+    fn cosig_offline_flow() {
+        let mut prng = ChaChaRng::from_entropy();
+        let kps = (0..4)
+            .map(|_| XfrKeyPair::generate(&mut prng))
+            .collect::<Vec<_>>();
+        let vs = kps
+            .iter()
+            .map(|kp| {
+                Validator::new(
+                    vec![],
+                    100,
+                    kp.get_pk(),
+                    [1, 5],
+                    Default::default(),
+                    ValidatorKind::Initiator,
+                )
+            })
+            .collect::<Result<Vec<_>>>();
+        let mut vd = pnk!(ValidatorData::new(1, pnk!(vs)));
+        *vd.get_cosig_rule_mut() = pnk!(CoSigRule::new([3, 4]));
+
+        let proposal = format!(
+            r#"{{"governance": {{"byzantine_id": {}, "kind": "OffLine"}}}}"#,
+            pnk!(serde_json::to_string(kps[3].get_pk_ref()))
+        );
+        let proposal = pnk!(serde_json::from_str::<Proposal>(&proposal));
+
+        let seq_id = 10;
+        let mut builder = TransactionBuilder::from_seq_id(seq_id);
+        pnk!(add_proposal(&mut builder, proposal));
+        let mut tx = builder.take_transaction();
+        let expiry = seq_id + 1000;
+        pnk!(set_proposal(&mut tx, CoSigProposal::new(expiry)));
+
+        let tx_file = env::temp_dir()
+            .join(format!("cosig_offline_flow_{}.json", rand::random::<u64>()))
+            .to_string_lossy()
+            .into_owned();
+        pnk!(write_tx(&tx_file, &tx));
+
+        // every validator signs the file in turn
+        for kp in kps.iter().take(3) {
+            let mut tx = pnk!(read_tx(&tx_file));
+            assert!(check(&tx, &vd).is_err());
+            pnk!(sign_tx(&mut tx, kp));
+            pnk!(write_tx(&tx_file, &tx));
+        }
+
+        let tx = pnk!(read_tx(&tx_file));
+        let _ = fs::remove_file(&tx_file);
+        assert_eq!(3, signers(&tx).len());
+        assert!(check(&tx, &vd).is_ok());
+
+        // the proposal is accepted until its expiry height,
+        // beyond the replay window of the transaction
+        assert_eq!(1000, pnk!(check_expiry(&tx, seq_id)));
+        assert_eq!(0, pnk!(check_expiry(&tx, expiry)));
+        assert!(check_expiry(&tx, expiry + 1).is_err());
+        assert!(utils::check_seq_id_window(&tx, expiry).is_err());
+
+        // the replay token is attached on submission,
+        // which keeps the signatures valid
+        let seq_id = seq_id + TRANSACTION_WINDOW_WIDTH * 2;
+        let tx = rebuild(&tx, seq_id);
+        assert_eq!(seq_id, tx.body.no_replay_token.get_seq_id());
+        assert_eq!(3, signers(&tx).len());
+        assert!(check(&tx, &vd).is_ok());
+        pnk!(utils::check_seq_id_window(&tx, seq_id));
+    }
+}
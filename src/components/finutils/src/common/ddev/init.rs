@@ -44,21 +44,7 @@ struct TmPubKey {
 }
 
 pub(super) fn init(env: &mut Env) -> Result<()> {
-    let (addr, ports) = env.get_addrports_any_node();
-    let port = ports[IDX_TM_RPC];
-    let page_size = env.custom_data.initial_validator_num;
-    let tmrpc_endpoint = format!("http://{addr}:{port}/validators?per_page={page_size}");
-
-    let tm_validators = attohttpc::get(&tmrpc_endpoint)
-        .send()
-        .c(d!(tmrpc_endpoint))?
-        .error_for_status()
-        .c(d!())?
-        .bytes()
-        .c(d!())
-        .and_then(|b| serde_json::from_slice::<TmValidators>(&b).c(d!()))?;
-
-    tm_validators.result.validators.into_iter().for_each(|v| {
+    get_tm_validators(env).c(d!())?.into_iter().for_each(|v| {
         let xfr_key = common::gen_key();
         let iv = InitialValidator {
             tendermint_addr: v.address,
@@ -143,6 +129,23 @@ pub(super) fn init(env: &mut Env) -> Result<()> {
     Ok(())
 }
 
+fn get_tm_validators(env: &Env) -> Result<Vec<TmValidator>> {
+    let (addr, ports) = env.get_addrports_any_node();
+    let port = ports[IDX_TM_RPC];
+    let page_size = env.custom_data.initial_validator_num;
+    let tmrpc_endpoint = format!("http://{addr}:{port}/validators?per_page={page_size}");
+
+    attohttpc::get(&tmrpc_endpoint)
+        .send()
+        .c(d!(tmrpc_endpoint))?
+        .error_for_status()
+        .c(d!())?
+        .bytes()
+        .c(d!())
+        .and_then(|b| serde_json::from_slice::<TmValidators>(&b).c(d!()))
+        .map(|vs| vs.result.validators)
+}
+
 fn setup_initial_validators(env: &Env) -> Result<()> {
     let mut builder = new_tx_builder(env).c(d!())?;
 
@@ -228,7 +231,7 @@ fn gen_8668_endpoint(env: &Env) -> String {
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub(crate) struct InitialValidator {
-    tendermint_addr: String,
+    pub(crate) tendermint_addr: String,
     tendermint_pubkey: String,
 
     xfr_keypair: XfrKeyPair,
@@ -243,6 +246,8 @@ impl From<&InitialValidator> for StakingValidator {
             td_addr: td_addr_to_bytes(&v.tendermint_addr).unwrap(),
             td_power: 400_0000 * FRA,
             commission_rate: [1, 100],
+            max_commission_rate: None,
+            commission_changed_height: 0,
             id: v.xfr_keypair.get_pk(),
             memo: Default::default(),
             kind: ValidatorKind::Initiator,
@@ -0,0 +1,494 @@
+//!
+//! Fault injections of `fn dev chaos`.
+//!
+//! Every scenario hurts one of the initial validators for some blocks,
+//! then checks the punishment of the staking module through the query API
+//! of another node:
+//!
+//! - `pause`, freeze the processes of the validator, it should be punished as `OFF_LINE`
+//! - `double-sign`, run a twin of the validator with the same key
+//!     until the evidence of its conflicting votes is committed,
+//!     it should be punished as `DUPLICATE_VOTE`
+//! - `isolate`, restart the validator without any peer on a remapped p2p port,
+//!     it should be punished as `OFF_LINE`
+//!
+
+use super::{Env, OptsGenerator, Ports};
+use crate::{api::ValidatorDetail, common::utils::parse_td_validator_keys};
+use chaindev::tm_dev::{Node, NodeOptsGenerator};
+use ledger::staking::ops::governance::{get_rule, ByzantineKind};
+use nix::{
+    sys::signal::{kill, Signal},
+    unistd::Pid,
+};
+use ruc::*;
+use serde::{Deserialize, Serialize};
+use std::{fs, net::TcpListener, process::Command, str::FromStr};
+
+// default duration of a fault
+const DEFAULT_BLOCKS: u64 = 10;
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum ChaosKind {
+    Pause,
+    DoubleSign,
+    Isolate,
+}
+
+impl FromStr for ChaosKind {
+    type Err = Box<dyn RucError>;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "pause" => Ok(ChaosKind::Pause),
+            "double-sign" => Ok(ChaosKind::DoubleSign),
+            "isolate" => Ok(ChaosKind::Isolate),
+            _ => Err(eg!("unknown scenario: {}", s)),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct ChaosCfg {
+    pub kind: ChaosKind,
+
+    // index in the initial validators,
+    // default value: the last one
+    pub validator_idx: Option<u8>,
+
+    // how many blocks the fault lasts,
+    // default value: 10
+    pub n_blocks: Option<u64>,
+}
+
+pub(super) fn run(env: &Env, cfg: ChaosCfg) -> Result<()> {
+    let vs = &env.custom_data.initial_validators;
+    if vs.is_empty() {
+        return Err(eg!("Not initialized, run `fn dev init` first!"));
+    }
+    let idx = cfg
+        .validator_idx
+        .map(|i| i as usize)
+        .unwrap_or(vs.len() - 1);
+    let td_addr = vs
+        .get(idx)
+        .map(|v| v.tendermint_addr.clone())
+        .c(d!("validator index out of range"))?;
+
+    let target = find_node(env, &td_addr).c(d!())?;
+    let observer = env
+        .nodes
+        .values()
+        .find(|n| n.home != target.home)
+        .c(d!("no node left to observe the chain"))?;
+    let n_blocks = cfg.n_blocks.unwrap_or(DEFAULT_BLOCKS);
+
+    let before = validator_detail(env, observer, &td_addr).c(d!())?;
+    println!(
+        "[ {} ] >>> {:?} {} for {} blocks ...",
+        &env.name, cfg.kind, &td_addr, n_blocks
+    );
+
+    match cfg.kind {
+        ChaosKind::Pause => pause(env, target, observer, n_blocks).c(d!())?,
+        ChaosKind::DoubleSign => double_sign(env, target, observer, n_blocks).c(d!())?,
+        ChaosKind::Isolate => isolate(env, target, observer, n_blocks).c(d!())?,
+    }
+
+    // the punishments are applied in the next blocks
+    wait_blocks(env, observer, 2).c(d!())?;
+    let after = validator_detail(env, observer, &td_addr).c(d!())?;
+    println!(
+        "[ {} ] >>> voting power: {} => {}, self staking: {} => {}",
+        &env.name,
+        before.voting_power,
+        after.voting_power,
+        before.self_staking,
+        after.self_staking
+    );
+
+    let expected = match cfg.kind {
+        ChaosKind::DoubleSign => {
+            let [num, den] = get_rule(&ByzantineKind::DuplicateVote)
+                .c(d!())?
+                .gen_penalty_percent();
+            before.voting_power - before.voting_power * num / den
+        }
+        // punished by a tiny rate in every block
+        ChaosKind::Pause | ChaosKind::Isolate => before.voting_power - 1,
+    };
+    if after.voting_power > expected {
+        return Err(eg!(
+            "{} is not punished, expected voting power <= {}, got {}",
+            td_addr,
+            expected,
+            after.voting_power
+        ));
+    }
+
+    println!("[ {} ] >>> Punished as expected !", &env.name);
+    Ok(())
+}
+
+// Stop the processes of `target` with SIGSTOP, resume them after `n_blocks`.
+fn pause(
+    env: &Env,
+    target: &Node<Ports>,
+    observer: &Node<Ports>,
+    n_blocks: u64,
+) -> Result<()> {
+    signal_node(&target.home, Signal::SIGSTOP).c(d!())?;
+    let ret = wait_blocks(env, observer, n_blocks).c(d!());
+    signal_node(&target.home, Signal::SIGCONT).c(d!())?;
+    ret
+}
+
+// Start a twin of `target` from a copy of its home,
+// the twin signs with the same validator key,
+// so the other validators receive conflicting votes from it.
+//
+// The twin and `target` only sign different blocks by chance,
+// eg. when both of them propose a block at the same height,
+// so the twin keeps running until the evidence is committed.
+fn double_sign(
+    env: &Env,
+    target: &Node<Ports>,
+    observer: &Node<Ports>,
+    n_blocks: u64,
+) -> Result<()> {
+    let td_addr =
+        fs::read_to_string(format!("{}/config/priv_validator_key.json", &target.home))
+            .c(d!())
+            .and_then(|key| parse_td_validator_keys(&key).c(d!()))?
+            .address;
+    let start = block_height(env, observer).c(d!())?;
+
+    let mut twin = target.clone();
+    twin.home = format!("{}.twin", &target.home);
+    twin.ports = free_ports().c(d!())?;
+
+    // copy a consistent snapshot of the data
+    signal_node(&target.home, Signal::SIGSTOP).c(d!())?;
+    let ret = cmd(&format!(
+        "rm -rf {0} && cp -r {1} {0}",
+        &twin.home, &target.home
+    ))
+    .c(d!());
+    signal_node(&target.home, Signal::SIGCONT).c(d!())?;
+    ret?;
+
+    // the p2p identity must differ from the one of `target`
+    fs::remove_file(format!("{}/config/node_key.json", &twin.home)).c(d!())?;
+    remap_ports(&twin, false).c(d!())?;
+
+    start_node(env, &twin).c(d!())?;
+    let ret = wait_blocks(env, observer, n_blocks)
+        .c(d!())
+        .and_then(|_| wait_evidence(env, observer, &td_addr, start, n_blocks).c(d!()));
+    signal_node(&twin.home, Signal::SIGKILL).c(d!())?;
+    fs::remove_dir_all(&twin.home).c(d!())?;
+    ret
+}
+
+// Restart `target` on a new p2p port without any peer,
+// restore its config and restart it again after `n_blocks`.
+fn isolate(
+    env: &Env,
+    target: &Node<Ports>,
+    observer: &Node<Ports>,
+    n_blocks: u64,
+) -> Result<()> {
+    let cfg_path = format!("{}/config/config.toml", &target.home);
+    let cfg = fs::read_to_string(&cfg_path).c(d!())?;
+
+    let mut isolated = target.clone();
+    isolated.ports.tm_p2p = free_ports().c(d!())?.tm_p2p;
+
+    restart_node(env, target, || remap_ports(&isolated, true).c(d!())).c(d!())?;
+    let ret = wait_blocks(env, observer, n_blocks).c(d!());
+    restart_node(env, target, || fs::write(&cfg_path, &cfg).c(d!())).c(d!())?;
+    ret
+}
+
+// Find the node signing with the key of `td_addr`.
+fn find_node<'a>(env: &'a Env, td_addr: &str) -> Result<&'a Node<Ports>> {
+    env.nodes
+        .values()
+        .find(|n| {
+            fs::read_to_string(format!("{}/config/priv_validator_key.json", &n.home))
+                .c(d!())
+                .and_then(|key| parse_td_validator_keys(&key).c(d!()))
+                .map(|key| key.address == td_addr)
+                .unwrap_or(false)
+        })
+        .c(d!("node not found"))
+}
+
+// Rewrite the listening addresses in the tendermint config of `n`,
+// also drop all the peers if `isolated`.
+fn remap_ports(n: &Node<Ports>, isolated: bool) -> Result<()> {
+    let path = format!("{}/config/config.toml", &n.home);
+    let cfg = fs::read_to_string(&path).c(d!())?;
+
+    let mut section = "";
+    let cfg = cfg
+        .lines()
+        .map(|line| {
+            let key = line.split('=').next().unwrap_or_default().trim();
+            if line.starts_with('[') {
+                section = line.trim();
+            }
+            match (section, key) {
+                ("", "proxy_app") => {
+                    format!("proxy_app = \"tcp://127.0.0.1:{}\"", n.ports.app_abci)
+                }
+                ("[rpc]", "laddr") => {
+                    format!("laddr = \"tcp://0.0.0.0:{}\"", n.ports.tm_rpc)
+                }
+                ("[p2p]", "laddr") => {
+                    format!("laddr = \"tcp://0.0.0.0:{}\"", n.ports.tm_p2p)
+                }
+                ("[p2p]", "persistent_peers" | "seeds") if isolated => {
+                    format!("{key} = \"\"")
+                }
+                ("[p2p]", "pex") if isolated => "pex = false".to_owned(),
+                _ => line.to_owned(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    fs::write(&path, cfg).c(d!())
+}
+
+fn restart_node(
+    env: &Env,
+    n: &Node<Ports>,
+    update_cfg: impl FnOnce() -> Result<()>,
+) -> Result<()> {
+    signal_node(&n.home, Signal::SIGKILL).c(d!())?;
+    update_cfg().c(d!())?;
+    start_node(env, n).c(d!())
+}
+
+// Start the processes of `n` in the same way as `fn dev start`.
+fn start_node(env: &Env, n: &Node<Ports>) -> Result<()> {
+    let (app_vars, app_opts) = OptsGenerator.app_opts(n, env);
+    let (tm_vars, tm_opts) = OptsGenerator.tendermint_opts(n, env);
+    cmd(&format!(
+        "\
+        cd {0} || exit 1; \
+        {1} nohup {2} {3} >>{0}/app.log 2>&1 & \
+        {4} nohup {5} {6} >>{0}/tendermint.log 2>&1 & \
+        ",
+        &n.home,
+        app_vars,
+        &env.app_bin_path,
+        app_opts,
+        tm_vars,
+        &env.tendermint_bin_path,
+        tm_opts,
+    ))
+    .c(d!())
+}
+
+// Send `sig` to the processes of the node in `home`.
+fn signal_node(home: &str, sig: Signal) -> Result<()> {
+    let pids = node_pids(home).c(d!())?;
+    if pids.is_empty() {
+        return Err(eg!("no process found in {}", home));
+    }
+    for pid in pids {
+        kill(Pid::from_raw(pid), sig).c(d!())?;
+    }
+    Ok(())
+}
+
+// The processes started with `--home <home>` or `--ledger-dir <home>/__findora__`.
+fn node_pids(home: &str) -> Result<Vec<i32>> {
+    let tm = format!(" --home {home} ");
+    let app = format!(" --ledger-dir {home}/__findora__ ");
+
+    let mut pids = vec![];
+    for entry in fs::read_dir("/proc").c(d!())? {
+        let entry = entry.c(d!())?;
+        let pid = match entry.file_name().to_string_lossy().parse::<i32>() {
+            Ok(pid) => pid,
+            Err(_) => continue,
+        };
+        if let Ok(cmdline) = fs::read(entry.path().join("cmdline")) {
+            let mut cmdline = String::from_utf8_lossy(&cmdline).replace('\0', " ");
+            cmdline.insert(0, ' ');
+            cmdline.push(' ');
+            if cmdline.contains(&tm) || cmdline.contains(&app) {
+                pids.push(pid);
+            }
+        }
+    }
+    Ok(pids)
+}
+
+// Ports picked by the OS, free when this function returns.
+fn free_ports() -> Result<Ports> {
+    let listeners = (0..7)
+        .map(|_| TcpListener::bind("127.0.0.1:0").c(d!()))
+        .collect::<Result<Vec<_>>>()?;
+    let ports = listeners
+        .iter()
+        .map(|l| l.local_addr().map(|a| a.port()).c(d!()))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Ports {
+        web3_http: ports[0],
+        web3_ws: ports[1],
+        app_8668: ports[2],
+        app_8669: ports[3],
+        tm_p2p: ports[4],
+        tm_rpc: ports[5],
+        app_abci: ports[6],
+    })
+}
+
+fn wait_blocks(env: &Env, observer: &Node<Ports>, n: u64) -> Result<()> {
+    let target = block_height(env, observer).c(d!())? + n;
+    let mut itv = f32::from(env.block_itv_secs) as u64 * 1000;
+    alt!(itv < 500, itv = 500);
+
+    let mut stuck = 0;
+    let mut last = 0;
+    loop {
+        let h = block_height(env, observer).c(d!())?;
+        if h >= target {
+            return Ok(());
+        }
+        if h == last {
+            stuck += 1;
+            if stuck > 20 {
+                return Err(eg!("the chain stopped at height {}", h));
+            }
+        } else {
+            stuck = 0;
+            last = h;
+        }
+        sleep_ms!(itv);
+    }
+}
+
+// Scan the blocks from height `from` for the evidence of a duplicate vote
+// of `td_addr`, fail if none is committed in `n_blocks` more blocks.
+fn wait_evidence(
+    env: &Env,
+    observer: &Node<Ports>,
+    td_addr: &str,
+    from: u64,
+    n_blocks: u64,
+) -> Result<()> {
+    let deadline = block_height(env, observer).c(d!())? + n_blocks;
+    let mut h = from + 1;
+    loop {
+        while h <= block_height(env, observer).c(d!())? {
+            if block_evidence(env, observer, h).c(d!())?.iter().any(|e| {
+                e["type"] == "tendermint/DuplicateVoteEvidence"
+                    && e["value"]["vote_a"]["validator_address"]
+                        .as_str()
+                        .map_or(false, |a| a.eq_ignore_ascii_case(td_addr))
+            }) {
+                println!(
+                    "[ {} ] >>> Duplicate votes of {} committed at height {}",
+                    &env.name, td_addr, h
+                );
+                return Ok(());
+            }
+            h += 1;
+        }
+        if h > deadline {
+            return Err(eg!(
+                "no evidence of double signing by {} from height {} to {}",
+                td_addr,
+                from + 1,
+                deadline
+            ));
+        }
+        wait_blocks(env, observer, 1).c(d!())?;
+    }
+}
+
+fn block_evidence(
+    env: &Env,
+    n: &Node<Ports>,
+    height: u64,
+) -> Result<Vec<serde_json::Value>> {
+    let url = format!(
+        "http://{}:{}/block?height={}",
+        &env.host_ip, n.ports.tm_rpc, height
+    );
+    attohttpc::get(&url)
+        .send()
+        .c(d!(url))?
+        .error_for_status()
+        .c(d!())?
+        .bytes()
+        .c(d!())
+        .and_then(|b| serde_json::from_slice::<serde_json::Value>(&b).c(d!()))
+        .map(|v| {
+            v["result"]["block"]["evidence"]["evidence"]
+                .as_array()
+                .cloned()
+                .unwrap_or_default()
+        })
+}
+
+#[derive(Deserialize)]
+struct TmStatus {
+    result: TmStatusRet,
+}
+
+#[derive(Deserialize)]
+struct TmStatusRet {
+    sync_info: TmSyncInfo,
+}
+
+#[derive(Deserialize)]
+struct TmSyncInfo {
+    latest_block_height: String,
+}
+
+fn block_height(env: &Env, n: &Node<Ports>) -> Result<u64> {
+    let url = format!("http://{}:{}/status", &env.host_ip, n.ports.tm_rpc);
+    attohttpc::get(&url)
+        .send()
+        .c(d!(url))?
+        .error_for_status()
+        .c(d!())?
+        .bytes()
+        .c(d!())
+        .and_then(|b| serde_json::from_slice::<TmStatus>(&b).c(d!()))
+        .and_then(|s| s.result.sync_info.latest_block_height.parse().c(d!()))
+}
+
+fn validator_detail(
+    env: &Env,
+    n: &Node<Ports>,
+    td_addr: &str,
+) -> Result<ValidatorDetail> {
+    let url = format!(
+        "http://{}:{}/validator_detail/{}",
+        &env.host_ip, n.ports.app_8668, td_addr
+    );
+    attohttpc::get(&url)
+        .send()
+        .c(d!(url))?
+        .error_for_status()
+        .c(d!())?
+        .bytes()
+        .c(d!())
+        .and_then(|b| serde_json::from_slice::<ValidatorDetail>(&b).c(d!()))
+}
+
+fn cmd(cmd: &str) -> Result<()> {
+    let out = Command::new("sh").arg("-c").arg(cmd).output().c(d!())?;
+    if out.status.success() {
+        Ok(())
+    } else {
+        Err(eg!(String::from_utf8_lossy(&out.stderr)))
+    }
+}
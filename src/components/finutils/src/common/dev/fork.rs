@@ -0,0 +1,96 @@
+//!
+//! Fork a local env from the state exported by `findorad export-genesis`,
+//! the way `anvil --fork-url` does.
+//!
+//! The validators of the exported state are replaced with the local ones,
+//! their xfr keys are generated here and paired with the tendermint keys
+//! of the local nodes when the chain starts, see `fn dev init`.
+//!
+
+use crate::common;
+use ruc::*;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fs;
+use zei::xfr::sig::XfrKeyPair;
+
+/// The xfr keys of a local validator of a forked env.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub(super) struct ForkKey {
+    pub(super) xfr_keypair: XfrKeyPair,
+    pub(super) xfr_mnemonic: String,
+    pub(super) xfr_wallet_addr: String,
+}
+
+/// Generate the `app_state` of the genesis file from an exported one,
+/// along with the xfr keys of `validator_num` local validators.
+///
+/// `impersonate` are the EVM addresses which can send transactions
+/// by `eth_sendTransaction` without signing them,
+/// the node must be a `debug_env` build to accept them.
+pub(super) fn gen_app_state(
+    genesis_file: &str,
+    validator_num: u8,
+    impersonate: &[String],
+) -> Result<(Value, Vec<ForkKey>)> {
+    let mut genesis = fs::read(genesis_file)
+        .c(d!(genesis_file))
+        .and_then(|g| serde_json::from_slice::<Value>(&g).c(d!()))?;
+
+    // a tendermint genesis file or the `app_state` of it
+    let mut app_state = match genesis.get_mut("app_state") {
+        Some(s) => s.take(),
+        None => genesis,
+    };
+    let s = app_state
+        .as_object_mut()
+        .filter(|s| s.contains_key("ledger"))
+        .c(d!("not an exported state"))?;
+
+    let keys = (0..validator_num)
+        .map(|_| {
+            let (xfr_wallet_addr, xfr_mnemonic, _, xfr_keypair) = common::gen_key();
+            ForkKey {
+                xfr_keypair,
+                xfr_mnemonic,
+                xfr_wallet_addr,
+            }
+        })
+        .collect::<Vec<_>>();
+    let ids = keys
+        .iter()
+        .map(|k| serde_json::to_value(k.xfr_keypair.get_pk()).c(d!()))
+        .collect::<Result<Vec<_>>>()?;
+    s.insert("fork".to_owned(), json!({ "validator_ids": ids }));
+
+    if !impersonate.is_empty() {
+        let mut addrs = impersonate
+            .iter()
+            .map(|a| parse_evm_addr(a))
+            .collect::<Result<Vec<_>>>()?;
+
+        let modules = s
+            .entry("modules")
+            .or_insert_with(|| json!({}))
+            .as_object_mut()
+            .c(d!("invalid modules"))?;
+        let ethereum = modules.entry("ethereum").or_insert(Value::Null);
+        if let Some(Value::Array(old)) = ethereum.get("impersonated") {
+            addrs.extend(old.iter().filter_map(|a| a.as_str()).map(String::from));
+        }
+        addrs.sort();
+        addrs.dedup();
+        *ethereum = json!({ "impersonated": addrs });
+    }
+
+    Ok((app_state, keys))
+}
+
+// `0x` + 40 hex chars, in lower case
+fn parse_evm_addr(addr: &str) -> Result<String> {
+    let a = addr.strip_prefix("0x").unwrap_or(addr).to_lowercase();
+    if 40 != a.len() || hex::decode(&a).is_err() {
+        return Err(eg!(format!("invalid EVM address: {addr}")));
+    }
+    Ok(format!("0x{a}"))
+}
@@ -1 +1,33 @@
 include!("../ddev/init.rs");
+
+// the validators of a forked ENV are set in the genesis file,
+// pair them with the Xfr keypairs by the same order as `abcid` does
+pub(super) fn init_fork(env: &mut Env) -> Result<()> {
+    let mut tm_validators = get_tm_validators(env).c(d!())?;
+    tm_validators.sort_by_key(|v| v.address.to_uppercase());
+
+    let keys = &env.custom_data.fork_keys;
+    if keys.len() != tm_validators.len() {
+        return Err(eg!(format!(
+            "{} Xfr keypairs for {} validators",
+            keys.len(),
+            tm_validators.len()
+        )));
+    }
+
+    let ivs = tm_validators
+        .into_iter()
+        .zip(keys.iter())
+        .map(|(v, k)| InitialValidator {
+            tendermint_addr: v.address,
+            tendermint_pubkey: v.pub_key.value,
+            xfr_keypair: k.xfr_keypair.clone(),
+            xfr_mnemonic: k.xfr_mnemonic.clone(),
+            xfr_wallet_addr: k.xfr_wallet_addr.clone(),
+        })
+        .collect();
+    env.custom_data.initial_validators = ivs;
+
+    println!("[ {} ] >>> Init work done !", &env.name);
+    Ok(())
+}
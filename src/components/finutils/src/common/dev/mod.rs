@@ -7,15 +7,21 @@
 #![deny(warnings)]
 #![allow(missing_docs)]
 
+mod chaos;
+mod fork;
 mod init;
 
+pub use chaos::{ChaosCfg, ChaosKind};
+
 use super::ddev::Ports;
 use chaindev::tm_dev::{
     self, CustomOps, EnvMeta, EnvName, EnvOpts, Node, NodeOptsGenerator, Op,
 };
+use fork::ForkKey;
 use init::{BankAccount, InitialValidator};
 use rucv3::*;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::{fmt::Write, thread};
 
 #[derive(Debug, Default)]
@@ -28,8 +34,10 @@ pub struct EnvCfg {
     pub ops: Ops,
 }
 
-impl From<EnvCfg> for tm_dev::EnvCfg<(), CustomData, Ports, InitOps> {
-    fn from(cfg: EnvCfg) -> Self {
+impl TryFrom<EnvCfg> for tm_dev::EnvCfg<Value, CustomData, Ports, InitOps> {
+    type Error = Box<dyn RucError>;
+
+    fn try_from(cfg: EnvCfg) -> Result<Self> {
         let op = match cfg.ops {
             Ops::Create {
                 block_itv_secs,
@@ -42,7 +50,15 @@ impl From<EnvCfg> for tm_dev::EnvCfg<(), CustomData, Ports, InitOps> {
                 force_create,
                 evm_chain_id,
                 checkpoint_file,
+                fork_from,
+                impersonate,
             } => {
+                let (app_state, fork_keys) = if let Some(file) = fork_from.as_deref() {
+                    fork::gen_app_state(file, initial_validator_num, &impersonate)
+                        .map_err(|e| eg!(e))?
+                } else {
+                    (Value::Null, vec![])
+                };
                 let opts = EnvOpts {
                     host_ip: host_ip.unwrap_or_else(|| "127.0.0.1".to_owned()),
                     block_itv_secs: block_itv_secs.into(),
@@ -53,13 +69,15 @@ impl From<EnvCfg> for tm_dev::EnvCfg<(), CustomData, Ports, InitOps> {
                         .unwrap_or_else(|| "tendermint".to_owned()),
                     tendermint_extra_opts: tendermint_extra_flags.unwrap_or_default(),
                     force_create,
-                    app_state: (),
+                    app_state,
                     custom_data: CustomData {
                         evm_chain_id,
                         checkpoint_file,
                         bank_account: BankAccount::default(),
                         initial_validator_num,
                         initial_validators: Vec::new(),
+                        fork_from,
+                        fork_keys,
                     },
                 };
                 Op::Create(opts)
@@ -74,17 +92,21 @@ impl From<EnvCfg> for tm_dev::EnvCfg<(), CustomData, Ports, InitOps> {
             Ops::PopNode => Op::PopNode,
             Ops::Init => Op::Custom(InitOps::Init),
             Ops::InitAll => Op::Custom(InitOps::InitAll),
+            Ops::Chaos(cfg) => Op::Custom(InitOps::Chaos(cfg)),
             Ops::Show => Op::Show,
             Ops::ShowAll => Op::ShowAll,
             Ops::List => Op::List,
         };
-        Self { name: cfg.name, op }
+        Ok(Self { name: cfg.name, op })
     }
 }
 
 impl EnvCfg {
     pub fn exec(self) -> Result<()> {
-        tm_dev::EnvCfg::from(self).exec(OptsGenerator).c(d!())
+        tm_dev::EnvCfg::try_from(self)
+            .c(d!())?
+            .exec(OptsGenerator)
+            .c(d!())
     }
 }
 
@@ -121,6 +143,14 @@ pub enum Ops {
         // only used in `Ops::Create`
         // used in `Ops::Create`
         checkpoint_file: Option<String>,
+
+        // the genesis file exported by `findorad export-genesis`,
+        // start from the state in it instead of an empty one
+        fork_from: Option<String>,
+
+        // EVM addresses that can send transactions without signing them,
+        // only used along with `fork_from`
+        impersonate: Vec<String>,
     },
     Destroy,
     DestroyAll,
@@ -132,6 +162,7 @@ pub enum Ops {
     PopNode,
     Init,
     InitAll,
+    Chaos(ChaosCfg),
     Show,
     ShowAll,
     List,
@@ -204,6 +235,7 @@ impl NodeOptsGenerator<Node<Ports>, EnvMeta<CustomData, Node<Ports>>> for OptsGe
 enum InitOps {
     Init,
     InitAll,
+    Chaos(ChaosCfg),
 }
 
 type Env = EnvMeta<CustomData, Node<Ports>>;
@@ -216,6 +248,10 @@ impl CustomOps for InitOps {
                 .and_then(|env| env.c(d!()))
                 .and_then(|env| init(env).c(d!())),
             InitOps::InitAll => init_all().c(d!()),
+            InitOps::Chaos(cfg) => Env::load_env_by_name::<OptsGenerator>(env_name)
+                .c(d!())
+                .and_then(|env| env.c(d!()))
+                .and_then(|env| chaos::run(&env.meta, *cfg).map_err(|e| eg!(e))),
         }
     }
 }
@@ -223,6 +259,9 @@ impl CustomOps for InitOps {
 // 1. get validator list by ':26657/validators'
 // 2. generate coresponding Xfr keypairs by `common::gen_key()`
 // 3. send out the initial staking transaction
+//
+// a forked ENV has got its validators and FRAs from the exported state,
+// so only the Xfr keypairs generated in `Ops::Create` are recorded
 fn init(mut env: tm_dev::Env<CustomData, Ports, OptsGenerator>) -> Result<()> {
     if !env.meta.custom_data.initial_validators.is_empty() {
         eprintln!(
@@ -231,8 +270,12 @@ fn init(mut env: tm_dev::Env<CustomData, Ports, OptsGenerator>) -> Result<()> {
         );
         return Ok(());
     }
-    init::init(&mut env.meta)
-        .map_err(|e| eg!(e))
+    let ret = if env.meta.custom_data.fork_keys.is_empty() {
+        init::init(&mut env.meta)
+    } else {
+        init::init_fork(&mut env.meta)
+    };
+    ret.map_err(|e| eg!(e))
         .and_then(|_| env.write_cfg().c(d!("fail to update meta info")))
 }
 
@@ -265,4 +308,13 @@ struct CustomData {
 
     #[serde(rename = "initial_pos_settings")]
     initial_validators: Vec<InitialValidator>,
+
+    // the genesis file this ENV is forked from
+    #[serde(default)]
+    fork_from: Option<String>,
+
+    // Xfr keypairs of the validators of a forked ENV
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    fork_keys: Vec<ForkKey>,
 }
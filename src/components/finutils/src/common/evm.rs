@@ -5,11 +5,14 @@
 use super::get_keypair;
 use super::get_serv_addr;
 use super::utils;
+use crate::txn_builder::TransferOperationBuilder;
+use curve25519_dalek::scalar::Scalar;
 use fp_core::account::SmartAccount;
 use fp_types::{
     actions::{
         xhub::{
-            Action as AccountAction, NonConfidentialOutput, NonConfidentialTransfer,
+            Action as AccountAction, ConfidentialOutput, ConfidentialTransfer,
+            NonConfidentialOutput, NonConfidentialTransfer,
         },
         Action,
     },
@@ -20,38 +23,79 @@ use fp_types::{
 };
 use fp_utils::ecdsa::SecpPair;
 use fp_utils::tx::EvmRawTxWrapper;
-use ledger::data_model::ASSET_TYPE_FRA;
-use ledger::data_model::BLACK_HOLE_PUBKEY_STAKING;
+use globutils::amount_proof::AmountProof;
+use ledger::data_model::{
+    Operation, TransferType, TxoRef, ASSET_TYPE_FRA, BLACK_HOLE_PUBKEY_STAKING,
+};
+use rand_chacha::ChaChaRng;
+use rand_core::SeedableRng;
 use ruc::*;
-use std::str::FromStr;
+use std::{cmp::min, str::FromStr};
 use tendermint::block::Height;
 use tendermint_rpc::endpoint::abci_query::AbciQuery;
 use tendermint_rpc::{Client, HttpClient};
 use tokio::runtime::Runtime;
-use zei::xfr::{asset_record::AssetRecordType, sig::XfrKeyPair};
+use zei::{
+    setup::PublicParams,
+    xfr::{
+        asset_record::{
+            build_open_asset_record, open_blind_asset_record, AssetRecordType,
+        },
+        sig::{XfrKeyPair, XfrPublicKey},
+        structs::AssetRecordTemplate,
+    },
+};
+
+/// The account of an ethereum address or of a Findora (Xfr) key, default to `owner`.
+///
+/// The UTXOs converted to a Findora key are credited to the account of the key itself,
+/// which is the one its withdrawals (`transfer_from_account`) spend.
+/// The EVM actions signed by the key run as `xfr_evm_address` of it instead,
+/// fund that address explicitly to pay for them.
+pub fn evm_account(address: Option<&str>, owner: &XfrPublicKey) -> Result<MultiSigner> {
+    match address {
+        Some(s) => MultiSigner::from_str(s).c(d!()),
+        None => Ok(MultiSigner::Xfr(*owner)),
+    }
+}
 
 /// transfer utxo assets to account(ed25519 or ecdsa address) balance.
-pub fn transfer_to_account(amount: u64, address: Option<&str>) -> Result<()> {
+///
+/// If `confidential`, confidential UTXOs are spent too,
+/// and the converted amount is hidden in the transfer and proved aside.
+pub fn transfer_to_account(
+    amount: u64,
+    address: Option<&str>,
+    confidential: bool,
+) -> Result<()> {
     let mut builder = utils::new_tx_builder().c(d!())?;
 
     let kp = get_keypair().c(d!())?;
-    let transfer_op = utils::gen_transfer_op(
-        &kp,
-        vec![(&BLACK_HOLE_PUBKEY_STAKING, amount)],
-        None,
-        false,
-        false,
-        Some(AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType),
-    )
-    .c(d!())?;
-    let target_address = match address {
-        Some(s) => MultiSigner::from_str(s).c(d!())?,
-        None => MultiSigner::Xfr(kp.get_pk()),
+    let (transfer_op, amount_proofs) = if confidential {
+        let (op, proof) = gen_confidential_convert_op(&kp, amount).c(d!())?;
+        (op, vec![proof])
+    } else {
+        let op = utils::gen_transfer_op(
+            &kp,
+            vec![(&BLACK_HOLE_PUBKEY_STAKING, amount)],
+            None,
+            false,
+            false,
+            Some(AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType),
+        )
+        .c(d!())?;
+        (op, vec![])
     };
+    let target_address = evm_account(address, &kp.get_pk()).c(d!())?;
 
     builder
         .add_operation(transfer_op)
-        .add_operation_convert_account(&kp, target_address, amount)
+        .add_operation_convert_account_with_proofs(
+            &kp,
+            target_address,
+            amount,
+            amount_proofs,
+        )
         .c(d!())?
         .sign(&kp);
 
@@ -61,6 +105,63 @@ pub fn transfer_to_account(amount: u64, address: Option<&str>) -> Result<()> {
     utils::send_tx(&tx).c(d!())
 }
 
+// The output to the black hole has a confidential amount,
+// its blinds are kept to prove the amount.
+fn gen_confidential_convert_op(
+    kp: &XfrKeyPair,
+    amount: u64,
+) -> Result<(Operation, AmountProof)> {
+    let mut prng = ChaChaRng::from_entropy();
+    let mut trans_builder = TransferOperationBuilder::new();
+
+    let mut am = amount;
+    for (sid, (utxo, owner_memo)) in utils::get_owned_utxos(&kp.get_pk()).c(d!())? {
+        let oar = open_blind_asset_record(&utxo.0.record, &owner_memo, kp).c(d!())?;
+        if oar.asset_type != ASSET_TYPE_FRA {
+            continue;
+        }
+        let i_am = min(oar.amount, am);
+        am -= i_am;
+        trans_builder
+            .add_input(TxoRef::Absolute(sid), oar, None, None, i_am)
+            .c(d!())?;
+        if 0 == am {
+            break;
+        }
+    }
+    if 0 != am {
+        return Err(eg!("insufficient balance"));
+    }
+
+    let mut blinds = ((Scalar::zero(), Scalar::zero()), Scalar::zero());
+    trans_builder
+        .add_output_and_store_blinds(
+            &AssetRecordTemplate::with_no_asset_tracing(
+                amount,
+                ASSET_TYPE_FRA,
+                AssetRecordType::ConfidentialAmount_NonConfidentialAssetType,
+                *BLACK_HOLE_PUBKEY_STAKING,
+            ),
+            None,
+            &mut prng,
+            &mut blinds,
+        )
+        .c(d!())?;
+    let op = trans_builder
+        .balance(Some(
+            AssetRecordType::ConfidentialAmount_NonConfidentialAssetType,
+        ))
+        .c(d!())?
+        .create(TransferType::Standard)
+        .c(d!())?
+        .sign(kp)
+        .c(d!())?
+        .transaction()
+        .c(d!())?;
+
+    Ok((op, AmountProof::prove(&mut prng, amount, blinds.0)))
+}
+
 #[allow(missing_docs)]
 pub enum Keypair {
     Ed25519(XfrKeyPair),
@@ -69,6 +170,13 @@ pub enum Keypair {
 
 #[allow(missing_docs)]
 impl Keypair {
+    pub fn address(&self) -> Address {
+        match self {
+            Keypair::Ecdsa(kp) => Address::from(kp.address()),
+            Keypair::Ed25519(kp) => Address::from(kp.get_pk()),
+        }
+    }
+
     pub fn sign(&self, data: &[u8]) -> MultiSignature {
         match self {
             Keypair::Ecdsa(kp) => MultiSignature::from(kp.sign(data)),
@@ -80,10 +188,14 @@ impl Keypair {
 }
 
 /// transfer to uxto assets from account(ed25519 or ecdsa address) balance.
+///
+/// If `confidential`, the UTXO is created with a confidential amount
+/// for the receiver, only the account balance reveals the amount.
 pub fn transfer_from_account(
     amount: u64,
     address: Option<&str>,
     eth_phrase: Option<&str>,
+    confidential: bool,
 ) -> Result<()> {
     let fra_kp = get_keypair()?;
 
@@ -98,19 +210,10 @@ pub fn transfer_from_account(
         None => fra_kp.get_pk(),
     };
 
-    let output = NonConfidentialOutput {
-        target,
-        amount,
-        asset: ASSET_TYPE_FRA,
-    };
-
-    let (signer, kp) = if let Some(key_path) = eth_phrase {
-        let kp = SecpPair::from_phrase(key_path, None)?.0;
-        let signer = Address::from(kp.address());
-        (signer, Keypair::Ecdsa(kp))
+    let kp = if let Some(key_path) = eth_phrase {
+        Keypair::Ecdsa(SecpPair::from_phrase(key_path, None)?.0)
     } else {
-        let signer = Address::from(fra_kp.get_pk());
-        (signer, Keypair::Ed25519(fra_kp))
+        Keypair::Ed25519(fra_kp)
     };
 
     let tm_client = tendermint_rpc::HttpClient::new(
@@ -121,35 +224,76 @@ pub fn transfer_from_account(
     let query_ret = one_shot_abci_query(
         &tm_client,
         "module/account/nonce",
-        serde_json::to_vec(&signer).unwrap(),
+        serde_json::to_vec(&kp.address()).unwrap(),
         None,
         false,
     )?;
 
     let nonce = serde_json::from_slice::<U256>(query_ret.value.as_slice())
         .c(d!("invalid nonce"))?;
+    let txn_with_tag =
+        gen_transfer_from_account_tx(&kp, nonce, target, amount, confidential);
+
+    Runtime::new()
+        .unwrap()
+        .block_on(tm_client.broadcast_tx_sync(txn_with_tag.into()))
+        .c(d!())?;
 
-    let account_call = AccountAction::NonConfidentialTransfer(NonConfidentialTransfer {
-        input_value: amount,
-        outputs: vec![output],
-    });
+    Ok(())
+}
+
+/// The raw XHub transaction withdrawing `amount` from the account of `kp`
+/// to a new UTXO of `target`, `nonce` is the current nonce of the account.
+pub fn gen_transfer_from_account_tx(
+    kp: &Keypair,
+    nonce: U256,
+    target: XfrPublicKey,
+    amount: u64,
+    confidential: bool,
+) -> Vec<u8> {
+    let account_call = if confidential {
+        let mut prng = ChaChaRng::from_entropy();
+        let template = AssetRecordTemplate::with_no_asset_tracing(
+            amount,
+            ASSET_TYPE_FRA,
+            AssetRecordType::ConfidentialAmount_NonConfidentialAssetType,
+            target,
+        );
+        let (oar, _, owner_memo) = build_open_asset_record(
+            &mut prng,
+            &PublicParams::default().pc_gens,
+            &template,
+            vec![],
+        );
+        let blinds = (oar.amount_blinds.0 .0, oar.amount_blinds.1 .0);
+        AccountAction::ConfidentialTransfer(ConfidentialTransfer {
+            input_value: amount,
+            outputs: vec![ConfidentialOutput {
+                record: oar.blind_asset_record,
+                owner_memo,
+                amount_proof: AmountProof::prove(&mut prng, amount, blinds),
+            }],
+        })
+    } else {
+        AccountAction::NonConfidentialTransfer(NonConfidentialTransfer {
+            input_value: amount,
+            outputs: vec![NonConfidentialOutput {
+                target,
+                amount,
+                asset: ASSET_TYPE_FRA,
+            }],
+        })
+    };
     let action = Action::XHub(account_call);
     let extra = (CheckNonce::new(nonce), CheckFee::new(None));
     let msg = serde_json::to_vec(&(action.clone(), extra.clone())).unwrap();
 
     let signature = kp.sign(msg.as_slice());
 
-    let tx = UncheckedTransaction::new_signed(action, signer, signature, extra);
+    let tx = UncheckedTransaction::new_signed(action, kp.address(), signature, extra);
     let txn = serde_json::to_vec(&tx).unwrap();
 
-    let txn_with_tag = EvmRawTxWrapper::wrap(&txn);
-
-    Runtime::new()
-        .unwrap()
-        .block_on(tm_client.broadcast_tx_sync(txn_with_tag.into()))
-        .c(d!())?;
-
-    Ok(())
+    EvmRawTxWrapper::wrap(&txn)
 }
 
 fn one_shot_abci_query(
@@ -184,11 +328,7 @@ fn one_shot_abci_query(
 pub fn contract_account_info(address: Option<&str>) -> Result<(Address, SmartAccount)> {
     let fra_kp = get_keypair()?;
 
-    let address = match address {
-        Some(s) => MultiSigner::from_str(s).c(d!())?,
-        None => MultiSigner::Xfr(fra_kp.get_pk()),
-    };
-    let account: Address = address.into();
+    let account: Address = evm_account(address, &fra_kp.get_pk()).c(d!())?.into();
 
     let tm_client = tendermint_rpc::HttpClient::new(
         format!("{}:26657", get_serv_addr().c(d!())?).as_str(),
@@ -12,12 +12,20 @@ pub mod dev;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod ddev;
 
+pub mod batch;
+pub mod cosig;
 pub mod evm;
+pub mod multisig;
+pub mod offline;
+pub mod trace;
 pub mod utils;
 
 use {
-    crate::api::DelegationInfo,
-    globutils::wallet,
+    crate::api::{DelegationInfo, ValidatorDetail, VestingInfo},
+    globutils::{
+        signer::{ApduSigner, Signer, TcpTransport},
+        wallet::{self, BipPath},
+    },
     lazy_static::lazy_static,
     ledger::{
         data_model::{
@@ -25,9 +33,9 @@ use {
             BLACK_HOLE_PUBKEY_STAKING,
         },
         staking::{
-            check_delegation_amount, td_addr_to_bytes, td_pubkey_to_td_addr,
-            td_pubkey_to_td_addr_bytes, PartialUnDelegation, StakerMemo,
-            TendermintAddrRef,
+            check_delegation_amount, convert_rate, ops::vesting::VestingSchedule,
+            td_addr_to_bytes, td_pubkey_to_td_addr, td_pubkey_to_td_addr_bytes,
+            PartialUnDelegation, StakerMemo, TendermintAddrRef,
         },
     },
     ruc::*,
@@ -63,17 +71,7 @@ lazy_static! {
 
 /// Updating the information of a staker includes commission_rate and staker_memo
 pub fn staker_update(cr: Option<&str>, memo: Option<StakerMemo>) -> Result<()> {
-    let addr = get_td_pubkey().map(|i| td_pubkey_to_td_addr(&i)).c(d!())?;
-    let vd = get_validator_detail(&addr).c(d!())?;
-
-    let cr = cr
-        .map_or(Ok(vd.commission_rate), |s| {
-            s.parse::<f64>()
-                .c(d!("commission rate must be a float number"))
-                .and_then(convert_commission_rate)
-        })
-        .c(d!())?;
-    let memo = memo.unwrap_or(vd.memo);
+    let (cr, memo) = staker_update_args(cr, memo).c(d!())?;
 
     let td_pubkey = get_td_pubkey().c(d!())?;
 
@@ -95,20 +93,37 @@ pub fn staker_update(cr: Option<&str>, memo: Option<StakerMemo>) -> Result<()> {
     utils::send_tx(&tx).c(d!())
 }
 
+// Fill the commission rate and the memo with the current ones of the validator
+fn staker_update_args(
+    cr: Option<&str>,
+    memo: Option<StakerMemo>,
+) -> Result<([u64; 2], StakerMemo)> {
+    let addr = get_td_pubkey().map(|i| td_pubkey_to_td_addr(&i)).c(d!())?;
+    let vd = get_validator_detail(&addr).c(d!())?;
+
+    let cr = cr
+        .map_or(Ok(vd.commission_rate), |s| {
+            s.parse::<f64>()
+                .c(d!("commission rate must be a float number"))
+                .and_then(|cr| convert_rate(cr).c(d!()))
+        })
+        .c(d!())?;
+    check_commission_change(&vd, cr).c(d!())?;
+
+    Ok((cr, memo.unwrap_or(vd.memo)))
+}
+
 /// Perform a staking operation to add current tendermint node to validator list
 /// The cli tool user will be alert if the block height of local node is too small
 pub fn stake(
     amount: &str,
     commission_rate: &str,
+    max_commission_rate: Option<&str>,
     memo: Option<&str>,
     force: bool,
 ) -> Result<()> {
-    let am = amount.parse::<u64>().c(d!("'amount' must be an integer"))?;
-    check_delegation_amount(am, false).c(d!())?;
-    let cr = commission_rate
-        .parse::<f64>()
-        .c(d!("commission rate must be a float number"))
-        .and_then(|cr| convert_commission_rate(cr).c(d!()))?;
+    let (am, cr, max_cr) =
+        stake_args(amount, commission_rate, max_commission_rate).c(d!())?;
     let td_pubkey = get_td_pubkey().c(d!())?;
 
     let kp = get_keypair().c(d!())?;
@@ -141,7 +156,15 @@ pub fn stake(
 
     let mut builder = utils::new_tx_builder().c(d!())?;
     builder
-        .add_operation_staking(&kp, am, &vkp, td_pubkey, cr, memo.map(|m| m.to_owned()))
+        .add_operation_staking(
+            &kp,
+            am,
+            &vkp,
+            td_pubkey,
+            cr,
+            max_cr,
+            memo.map(|m| m.to_owned()),
+        )
         .c(d!())?;
     utils::gen_transfer_op(
         &kp,
@@ -160,6 +183,29 @@ pub fn stake(
     utils::send_tx(&tx).c(d!())
 }
 
+fn stake_args(
+    amount: &str,
+    commission_rate: &str,
+    max_commission_rate: Option<&str>,
+) -> Result<(u64, [u64; 2], Option<[u64; 2]>)> {
+    let am = amount.parse::<u64>().c(d!("'amount' must be an integer"))?;
+    check_delegation_amount(am, false).c(d!())?;
+    let cr = commission_rate
+        .parse::<f64>()
+        .c(d!("commission rate must be a float number"))
+        .and_then(|cr| convert_rate(cr).c(d!()))?;
+    let max_cr = max_commission_rate
+        .map(|max_cr| {
+            max_cr
+                .parse::<f64>()
+                .c(d!("max commission rate must be a float number"))
+                .and_then(|max_cr| convert_rate(max_cr).c(d!()))
+        })
+        .transpose()?;
+
+    Ok((am, cr, max_cr))
+}
+
 /// Append more FRA token to the specified tendermint node
 pub fn stake_append(
     amount: &str,
@@ -481,6 +527,27 @@ pub fn transfer_asset_batch_x(
     .c(d!())
 }
 
+/// Like `transfer_asset_batch`, but the owner key is held by `signer`, e.g. a hardware wallet.
+pub fn transfer_asset_batch_with_signer(
+    signer: &dyn Signer,
+    target_addr: &[XfrPublicKey],
+    token_code: Option<AssetTypeCode>,
+    am: &str,
+    confidential_am: bool,
+    confidential_ty: bool,
+) -> Result<()> {
+    let am = am.parse::<u64>().c(d!("'amount' must be an integer"))?;
+
+    utils::transfer_batch_with_signer(
+        signer,
+        target_addr.iter().map(|addr| (addr, am)).collect(),
+        token_code,
+        confidential_am,
+        confidential_ty,
+    )
+    .c(d!())
+}
+
 /// Mainly for official usage,
 /// and can be also used in test scenes.
 pub fn set_initial_validators() -> Result<()> {
@@ -512,6 +579,35 @@ pub fn get_keypair() -> Result<XfrKeyPair> {
     }
 }
 
+/// Connect to the signer of `--signer`, `ledger` for a Ledger device plugged by USB,
+/// `tcp:<addr>` for a speculos-style emulator, the key is derived at
+/// "m/44'/917'/0'/0/0", the same path as the one of the mnemonic.
+pub fn get_device_signer(signer: &str) -> Result<Box<dyn Signer>> {
+    let path = BipPath::new(917, 0, 0, 0);
+    if "ledger" == signer {
+        return ledger_signer(path).c(d!());
+    }
+    let addr = signer
+        .strip_prefix("tcp:")
+        .c(d!("invalid signer, expected `ledger` or `tcp:<addr>`"))?;
+    ApduSigner::new(TcpTransport::connect(addr).c(d!())?, path)
+        .c(d!())
+        .map(|s| Box::new(s) as Box<dyn Signer>)
+}
+
+#[cfg(feature = "hid")]
+fn ledger_signer(path: BipPath) -> Result<Box<dyn Signer>> {
+    use globutils::signer::HidTransport;
+    ApduSigner::new(HidTransport::open().c(d!())?, path)
+        .c(d!())
+        .map(|s| Box::new(s) as Box<dyn Signer>)
+}
+
+#[cfg(not(feature = "hid"))]
+fn ledger_signer(_path: BipPath) -> Result<Box<dyn Signer>> {
+    Err(eg!("`fn` is built without the `hid` feature"))
+}
+
 fn get_td_pubkey() -> Result<Vec<u8>> {
     if let Some(key_path) = TD_KEY.as_ref() {
         fs::read_to_string(key_path)
@@ -539,15 +635,42 @@ fn get_td_privkey() -> Result<PrivateKey> {
     }
 }
 
-#[allow(missing_docs)]
-pub fn convert_commission_rate(cr: f64) -> Result<[u64; 2]> {
-    if 1.0 < cr {
-        return Err(eg!("commission rate can exceed 100%"));
+// Check the commission change against the limits reported by the server,
+// so that a doomed transaction will not be sent.
+fn check_commission_change(vd: &ValidatorDetail, cr: [u64; 2]) -> Result<()> {
+    let to_f64 = |r: [u64; 2]| r[0] as f64 / r[1] as f64;
+
+    // servers of older versions do not report these limits
+    if 0 == vd.max_commission_rate[1] || to_f64(cr) == to_f64(vd.commission_rate) {
+        return Ok(());
     }
-    if 0.0 > cr {
-        return Err(eg!("commission rate must be a positive float number"));
+
+    if to_f64(cr) > to_f64(vd.max_commission_rate) {
+        return Err(eg!(format!(
+            "commission rate can not exceed {}",
+            to_f64(vd.max_commission_rate)
+        )));
     }
-    Ok([(cr * 10000.0) as u64, 10000])
+
+    if 0 < vd.commission_max_change[1]
+        && (to_f64(cr) - to_f64(vd.commission_rate)).abs()
+            > to_f64(vd.commission_max_change)
+    {
+        return Err(eg!(format!(
+            "commission rate can change at most {} at a time, current: {}",
+            to_f64(vd.commission_max_change),
+            to_f64(vd.commission_rate)
+        )));
+    }
+
+    if vd.cur_height < vd.commission_next_change_height {
+        return Err(eg!(format!(
+            "commission rate can not be changed before height {}, current height: {}",
+            vd.commission_next_change_height, vd.cur_height
+        )));
+    }
+
+    Ok(())
 }
 
 #[allow(missing_docs)]
@@ -645,6 +768,129 @@ pub fn show_delegations(sk_str: Option<&str>) -> Result<()> {
     Ok(())
 }
 
+/// Lock FRAs for a beneficiary under a release schedule, the schedule is one of:
+/// - `cliff:<height>`
+/// - `linear:<start height>:<end height>`
+/// - `periodic:<start height>:<period>:<periods>`
+pub fn create_vesting(
+    sk_str: Option<&str>,
+    beneficiary: XfrPublicKey,
+    amount: &str,
+    schedule: &str,
+) -> Result<()> {
+    let kp = restore_keypair_from_str_with_default(sk_str)?;
+    let amount = amount.parse::<u64>().c(d!("'amount' must be an integer"))?;
+    let schedule = parse_vesting_schedule(schedule).c(d!())?;
+    schedule
+        .check(get_block_height(get_serv_addr().c(d!())?))
+        .c(d!())?;
+
+    let mut builder = utils::new_tx_builder().c(d!())?;
+
+    utils::gen_transfer_op(
+        &kp,
+        vec![(&BLACK_HOLE_PUBKEY_STAKING, amount)],
+        None,
+        false,
+        false,
+        Some(AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType),
+    )
+    .c(d!())
+    .map(|principal_op| {
+        builder.add_operation(principal_op);
+        builder.add_operation_create_vesting(&kp, beneficiary, amount, schedule);
+    })?;
+
+    let mut tx = builder.take_transaction();
+    tx.sign_to_map(&kp);
+
+    utils::send_tx(&tx).c(d!())
+}
+
+/// Release vested FRAs, `None` means all releasable FRAs.
+pub fn release_vested(am: Option<&str>, sk_str: Option<&str>) -> Result<()> {
+    let am = if let Some(i) = am {
+        Some(i.parse::<u64>().c(d!("'amount' must be an integer"))?)
+    } else {
+        None
+    };
+
+    let kp = restore_keypair_from_str_with_default(sk_str)?;
+
+    let mut builder = utils::new_tx_builder().c(d!())?;
+
+    utils::gen_fee_op(&kp).c(d!()).map(|op| {
+        builder.add_operation(op);
+        builder.add_operation_release_vested(&kp, am);
+    })?;
+
+    let mut tx = builder.take_transaction();
+    tx.sign_to_map(&kp);
+
+    utils::send_tx(&tx).c(d!())
+}
+
+/// Delegate FRAs that are still locked in vestings.
+pub fn delegate_vesting(
+    sk_str: Option<&str>,
+    amount: u64,
+    validator: &str,
+) -> Result<()> {
+    let kp = restore_keypair_from_str_with_default(sk_str)?;
+
+    let mut builder = utils::new_tx_builder().c(d!())?;
+
+    utils::gen_fee_op(&kp).c(d!()).map(|op| {
+        builder.add_operation(op);
+        builder.add_operation_delegation_from_vesting(&kp, amount, validator.to_owned());
+    })?;
+
+    let mut tx = builder.take_transaction();
+    tx.sign_to_map(&kp);
+
+    utils::send_tx(&tx).c(d!())
+}
+
+/// Display vesting information of a findora account
+pub fn show_vesting(sk_str: Option<&str>) -> Result<()> {
+    let pk = restore_keypair_from_str_with_default(sk_str)?.get_pk();
+
+    println!(
+        "{}",
+        serde_json::to_string_pretty::<VestingInfo>(
+            &utils::get_vesting_info(&pk).c(d!())?
+        )
+        .c(d!())?
+    );
+
+    Ok(())
+}
+
+fn parse_vesting_schedule(s: &str) -> Result<VestingSchedule> {
+    let fields = s.split(':').collect::<Vec<_>>();
+    let num = |i: usize| -> Result<u64> {
+        fields
+            .get(i)
+            .c(d!("missing field"))?
+            .parse::<u64>()
+            .c(d!("must be an integer"))
+    };
+
+    match (fields[0], fields.len()) {
+        ("cliff", 2) => Ok(VestingSchedule::Cliff { height: num(1)? }),
+        ("linear", 3) => Ok(VestingSchedule::Linear {
+            start: num(1)?,
+            end: num(2)?,
+        }),
+        ("periodic", 4) => Ok(VestingSchedule::Periodic {
+            start: num(1)?,
+            period: num(2)?,
+            periods: num(3)?,
+        }),
+        _ => Err(eg!(format!("invalid vesting schedule: {s}"))),
+    }
+}
+
 fn gen_undelegate_tx(
     owner_kp: &XfrKeyPair,
     param: Option<(u64, &str)>,
@@ -0,0 +1,180 @@
+//!
+//! Weighted multisig addresses
+//!
+//! A multisig address is derived from its `SignatureRules`,
+//! funds sent to it can only be spent by a transaction
+//! whose signers reach the threshold of the rules.
+//!
+//! Co-signers sign the transfer body in the same transaction file independently,
+//! then the signatures are combined and the transaction is sent.
+//!
+
+use {
+    super::{restore_keypair_from_str_with_default, utils},
+    globutils::wallet,
+    ledger::data_model::{
+        AssetTypeCode, Operation, SignatureRules, Transaction, MULTISIG_SIGNERS_MAX,
+    },
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    std::fs,
+    zei::xfr::sig::XfrPublicKey,
+};
+
+/// Signers and their weights, used to display a multisig address.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MultisigAddress {
+    /// fra prefixed address
+    pub address: String,
+    /// base64-formated public key of the address
+    pub pubkey: String,
+    #[allow(missing_docs)]
+    pub rules: SignatureRules,
+}
+
+impl From<SignatureRules> for MultisigAddress {
+    fn from(rules: SignatureRules) -> Self {
+        let pk = rules.to_address();
+        MultisigAddress {
+            address: wallet::public_key_to_bech32(&pk),
+            pubkey: wallet::public_key_to_base64(&pk),
+            rules,
+        }
+    }
+}
+
+/// Parse the signers in the `<pubkey>:<weight>` format,
+/// the pubkey is either base64-formated or fra prefixed.
+pub fn parse_signers(signers: &[&str]) -> Result<Vec<(XfrPublicKey, u64)>> {
+    signers
+        .iter()
+        .map(|s| {
+            let (pk, weight) = s.rsplit_once(':').c(d!(format!(
+                "invalid signer: {s}, expected <pubkey>:<weight>"
+            )))?;
+            let pk = wallet::public_key_from_base64(pk)
+                .or_else(|_| wallet::public_key_from_bech32(pk))
+                .c(d!(format!("invalid public key: {pk}")))?;
+            let weight = weight.parse::<u64>().c(d!("'weight' must be an integer"))?;
+            Ok((pk, weight))
+        })
+        .collect()
+}
+
+/// Print the address of the rules, and save the rules to `rules_file` if it is given.
+pub fn gen_address(
+    threshold: u64,
+    weights: Vec<(XfrPublicKey, u64)>,
+    rules_file: Option<&str>,
+) -> Result<()> {
+    let rules = SignatureRules { threshold, weights };
+    if !rules.is_valid() {
+        return Err(eg!(format!(
+            "invalid rules: 1~{MULTISIG_SIGNERS_MAX} distinct signers with non-zero weights are required, and their total weight must reach the threshold"
+        )));
+    }
+
+    let addr = serde_json::to_string_pretty(&MultisigAddress::from(rules)).c(d!())?;
+    if let Some(f) = rules_file {
+        fs::write(f, &addr).c(d!())?;
+    }
+    println!("{addr}");
+
+    Ok(())
+}
+
+/// Build an unsigned transaction which transfers assets out of a multisig address.
+pub fn create_tx(
+    rules_file: &str,
+    target_addr: XfrPublicKey,
+    token_code: Option<AssetTypeCode>,
+    am: &str,
+    tx_file: &str,
+) -> Result<()> {
+    let rules = read_rules(rules_file).c(d!())?;
+    let am = am.parse::<u64>().c(d!("'amount' must be an integer"))?;
+
+    let mut builder = utils::new_tx_builder().c(d!())?;
+    utils::gen_multisig_transfer_op(&rules, vec![(&target_addr, am)], token_code)
+        .c(d!())
+        .map(|op| {
+            builder.add_operation(op);
+        })?;
+
+    write_tx(tx_file, &builder.take_transaction()).c(d!())
+}
+
+/// Add the signature of a co-signer to the transaction file.
+pub fn sign_tx(sk_str: Option<&str>, tx_file: &str) -> Result<()> {
+    let kp = restore_keypair_from_str_with_default(sk_str)?;
+    let mut tx = read_tx(tx_file).c(d!())?;
+
+    tx.sign_multisig(&kp).c(d!())?;
+    write_tx(tx_file, &tx).c(d!())?;
+    show_progress(&tx);
+
+    Ok(())
+}
+
+/// Merge the signatures that are collected separately into `output`.
+pub fn combine_tx(tx_files: &[&str], output: &str) -> Result<()> {
+    let mut txs = tx_files.iter().map(|f| read_tx(f).c(d!()));
+    let mut tx = txs.next().c(d!("no transaction file"))??;
+    for i in txs {
+        tx.merge_multisig_signatures(&i?).c(d!())?;
+    }
+
+    write_tx(output, &tx).c(d!())?;
+    show_progress(&tx);
+
+    Ok(())
+}
+
+/// Send the transaction if its signers have reached the threshold.
+pub fn send_tx(tx_file: &str) -> Result<()> {
+    let tx = read_tx(tx_file).c(d!())?;
+    tx.check_tx().c(d!("signatures are not enough"))?;
+    utils::send_tx(&tx).c(d!())
+}
+
+fn show_progress(tx: &Transaction) {
+    let transfers = tx.body.operations.iter().filter_map(|op| match op {
+        Operation::TransferAsset(t) => Some(t),
+        _ => None,
+    });
+    for (t, rules) in
+        transfers.flat_map(|t| t.multisig_rules.iter().map(move |r| (t, r)))
+    {
+        let weight = rules
+            .weights
+            .iter()
+            .filter(|(pk, _)| t.body_signatures.iter().any(|s| &s.address.key == pk))
+            .map(|(_, w)| w)
+            .sum::<u64>();
+        println!(
+            "{}: {}/{} signed",
+            wallet::public_key_to_bech32(&rules.to_address()),
+            weight,
+            rules.threshold
+        );
+    }
+}
+
+fn read_rules(path: &str) -> Result<SignatureRules> {
+    fs::read(path)
+        .c(d!())
+        .and_then(|b| serde_json::from_slice::<MultisigAddress>(&b).c(d!()))
+        .map(|addr| addr.rules)
+}
+
+fn read_tx(path: &str) -> Result<Transaction> {
+    fs::read(path)
+        .c(d!())
+        .and_then(|b| serde_json::from_slice(&b).c(d!()))
+}
+
+fn write_tx(path: &str, tx: &Transaction) -> Result<()> {
+    serde_json::to_vec_pretty(tx)
+        .c(d!())
+        .and_then(|b| fs::write(path, b).c(d!()))
+}
@@ -0,0 +1,624 @@
+//!
+//! Offline signing
+//!
+//! A transaction is prepared on an online machine with `--build-only`,
+//! which queries everything that needs the network: the sequence id and
+//! the utxos (with their owner memos) of the signer.
+//!
+//! The file is then carried to an air-gapped machine, where `fn sign`
+//! builds the operations and signs the transaction with the secret keys,
+//! and the signed file is sent back and submitted by `fn broadcast`.
+//!
+//! The sequence id is fixed when the transaction is built, and the ledger
+//! only accepts it for `TRANSACTION_WINDOW_WIDTH` (128) blocks, so the
+//! whole round trip must be done within that window. An expired transaction
+//! is rejected by `fn broadcast`, and has to be built and signed again.
+//!
+
+use {
+    super::{
+        evm::evm_account, get_td_privkey, get_td_pubkey,
+        restore_keypair_from_str_with_default, stake_args, staker_update_args, utils,
+    },
+    crate::txn_builder::TransactionBuilder,
+    fp_types::crypto::MultiSigner,
+    globutils::wallet,
+    ledger::{
+        data_model::{
+            AssetTypeCode, Transaction, TxoSID, Utxo, BLACK_HOLE_PUBKEY_STAKING,
+            FRA_DECIMALS, TX_FEE_MIN,
+        },
+        staking::{StakerMemo, TendermintAddr},
+        store::seq_id_deadline,
+    },
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    std::{
+        collections::HashMap,
+        fmt, fs,
+        io::{self, Write},
+        str::FromStr,
+    },
+    tendermint::PrivateKey,
+    zei::xfr::{
+        asset_record::AssetRecordType,
+        sig::{XfrKeyPair, XfrPublicKey},
+        structs::OwnerMemo,
+    },
+};
+
+/// An unsigned transaction and the context that is needed to sign it offline.
+#[derive(Serialize, Deserialize)]
+pub struct OfflineTx {
+    /// The public key of the signer
+    pub signer: XfrPublicKey,
+    /// The unsigned transaction, which fixes the sequence id and the nonce
+    pub builder: TransactionBuilder,
+    /// The utxos of the signer and their owner memos
+    pub utxos: HashMap<TxoSID, (Utxo, Option<OwnerMemo>)>,
+    #[allow(missing_docs)]
+    pub action: OfflineAction,
+    /// The transaction after being signed
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signed: Option<Transaction>,
+}
+
+/// What the transaction is going to do.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[allow(missing_docs)]
+pub enum OfflineAction {
+    Transfer {
+        targets: Vec<(XfrPublicKey, u64)>,
+        token_code: Option<AssetTypeCode>,
+        confidential_am: bool,
+        confidential_ty: bool,
+    },
+    Stake {
+        amount: u64,
+        td_pubkey: Vec<u8>,
+        commission_rate: [u64; 2],
+        max_commission_rate: Option<[u64; 2]>,
+        memo: Option<String>,
+    },
+    Delegate {
+        amount: u64,
+        validator: TendermintAddr,
+    },
+    Claim {
+        amount: Option<u64>,
+    },
+    StakerUpdate {
+        td_pubkey: Vec<u8>,
+        commission_rate: [u64; 2],
+        memo: StakerMemo,
+    },
+    Convert {
+        amount: u64,
+        receiver: Option<String>,
+    },
+}
+
+impl OfflineAction {
+    // Operations signed by a tendermint key
+    fn need_validator_key(&self) -> bool {
+        matches!(
+            self,
+            OfflineAction::Stake { .. } | OfflineAction::StakerUpdate { .. }
+        )
+    }
+}
+
+impl fmt::Display for OfflineAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rate = |cr: &[u64; 2]| cr[0] as f64 / cr[1] as f64;
+        match self {
+            OfflineAction::Transfer {
+                targets,
+                token_code,
+                confidential_am,
+                confidential_ty,
+            } => {
+                let asset = token_code.map_or("FRA".to_owned(), |c| c.to_base64());
+                writeln!(f, "Transfer {asset}:")?;
+                for (pk, am) in targets.iter() {
+                    writeln!(f, "    {} -> {}", am, wallet::public_key_to_bech32(pk))?;
+                }
+                write!(
+                    f,
+                    "    confidential amount: {confidential_am}, confidential type: {confidential_ty}"
+                )
+            }
+            OfflineAction::Stake {
+                amount,
+                td_pubkey,
+                commission_rate,
+                max_commission_rate,
+                memo,
+            } => {
+                writeln!(f, "Stake {}", fra(*amount))?;
+                writeln!(f, "    validator pubkey: {}", base64::encode(td_pubkey))?;
+                writeln!(f, "    commission rate: {}", rate(commission_rate))?;
+                if let Some(max_cr) = max_commission_rate {
+                    writeln!(f, "    max commission rate: {}", rate(max_cr))?;
+                }
+                write!(f, "    memo: {}", memo.as_deref().unwrap_or_default())
+            }
+            OfflineAction::Delegate { amount, validator } => {
+                write!(f, "Delegate {} to {}", fra(*amount), validator)
+            }
+            OfflineAction::Claim { amount } => match amount {
+                Some(am) => write!(f, "Claim {} of rewards", fra(*am)),
+                None => write!(f, "Claim all rewards"),
+            },
+            OfflineAction::StakerUpdate {
+                td_pubkey,
+                commission_rate,
+                memo,
+            } => {
+                writeln!(f, "Update validator {}", base64::encode(td_pubkey))?;
+                writeln!(f, "    commission rate: {}", rate(commission_rate))?;
+                write!(f, "    memo: {}", serde_json::to_string(memo).unwrap())
+            }
+            OfflineAction::Convert { amount, receiver } => {
+                write!(
+                    f,
+                    "Convert {} to the account {}",
+                    fra(*amount),
+                    receiver.as_deref().unwrap_or("of the signer")
+                )
+            }
+        }
+    }
+}
+
+pub(super) fn fra(am: u64) -> String {
+    let unit = 10u64.pow(FRA_DECIMALS as u32);
+    format!(
+        "{}.{:0width$} FRA",
+        am / unit,
+        am % unit,
+        width = FRA_DECIMALS as usize
+    )
+}
+
+/// The public key of the signer, the keypair of `sk_str` (or the default one) is used if `signer` is `None`.
+pub fn get_signer(signer: Option<&str>, sk_str: Option<&str>) -> Result<XfrPublicKey> {
+    if let Some(s) = signer {
+        wallet::public_key_from_base64(s)
+            .or_else(|_| wallet::public_key_from_bech32(s))
+            .c(d!("invalid signer"))
+    } else {
+        restore_keypair_from_str_with_default(sk_str)
+            .c(d!())
+            .map(|kp| kp.get_pk())
+    }
+}
+
+/// Write an unsigned transaction and its context to `file`.
+pub fn build(signer: XfrPublicKey, action: OfflineAction, file: &str) -> Result<()> {
+    let tx = OfflineTx {
+        signer,
+        builder: utils::new_tx_builder().c(d!())?,
+        utxos: utils::get_owned_utxos(&signer).c(d!())?,
+        action,
+        signed: None,
+    };
+    write(file, &tx).c(d!())?;
+    println!(
+        "{}\nThe unsigned transaction is written to {file}, it must be broadcast by block {}",
+        tx.action,
+        seq_id_deadline(tx.builder.get_seq_id())
+    );
+
+    Ok(())
+}
+
+#[allow(missing_docs)]
+pub fn build_transfer(
+    signer: XfrPublicKey,
+    targets: Vec<(XfrPublicKey, u64)>,
+    token_code: Option<AssetTypeCode>,
+    confidential_am: bool,
+    confidential_ty: bool,
+    file: &str,
+) -> Result<()> {
+    let action = OfflineAction::Transfer {
+        targets,
+        token_code,
+        confidential_am,
+        confidential_ty,
+    };
+    build(signer, action, file).c(d!())
+}
+
+/// The validator key of the local node is used.
+pub fn build_stake(
+    signer: XfrPublicKey,
+    amount: &str,
+    commission_rate: &str,
+    max_commission_rate: Option<&str>,
+    memo: Option<&str>,
+    file: &str,
+) -> Result<()> {
+    let (amount, commission_rate, max_commission_rate) =
+        stake_args(amount, commission_rate, max_commission_rate).c(d!())?;
+    if let Some(m) = memo {
+        serde_json::from_str::<StakerMemo>(m).c(d!("invalid memo"))?;
+    }
+    let action = OfflineAction::Stake {
+        amount,
+        td_pubkey: get_td_pubkey().c(d!())?,
+        commission_rate,
+        max_commission_rate,
+        memo: memo.map(|m| m.to_owned()),
+    };
+    build(signer, action, file).c(d!())
+}
+
+#[allow(missing_docs)]
+pub fn build_delegate(
+    signer: XfrPublicKey,
+    amount: u64,
+    validator: &str,
+    file: &str,
+) -> Result<()> {
+    let action = OfflineAction::Delegate {
+        amount,
+        validator: validator.to_owned(),
+    };
+    build(signer, action, file).c(d!())
+}
+
+#[allow(missing_docs)]
+pub fn build_claim(signer: XfrPublicKey, am: Option<&str>, file: &str) -> Result<()> {
+    let amount = am
+        .map(|i| i.parse::<u64>().c(d!("'amount' must be an integer")))
+        .transpose()?;
+    build(signer, OfflineAction::Claim { amount }, file).c(d!())
+}
+
+/// The validator key of the local node is used.
+pub fn build_staker_update(
+    signer: XfrPublicKey,
+    cr: Option<&str>,
+    memo: Option<StakerMemo>,
+    file: &str,
+) -> Result<()> {
+    let (commission_rate, memo) = staker_update_args(cr, memo).c(d!())?;
+    let action = OfflineAction::StakerUpdate {
+        td_pubkey: get_td_pubkey().c(d!())?,
+        commission_rate,
+        memo,
+    };
+    build(signer, action, file).c(d!())
+}
+
+#[allow(missing_docs)]
+pub fn build_convert(
+    signer: XfrPublicKey,
+    amount: u64,
+    address: Option<&str>,
+    file: &str,
+) -> Result<()> {
+    if let Some(addr) = address {
+        MultiSigner::from_str(addr).c(d!("invalid address"))?;
+    }
+    let action = OfflineAction::Convert {
+        amount,
+        receiver: address.map(|a| a.to_owned()),
+    };
+    build(signer, action, file).c(d!())
+}
+
+/// Show the summary of the transaction, then build and sign it.
+///
+/// `td_key` is the path of a tendermint validator key file,
+/// it is only needed by staking and updating the staker,
+/// and the configured one is used if it is `None`.
+pub fn sign(
+    file: &str,
+    sk_str: Option<&str>,
+    td_key: Option<&str>,
+    yes: bool,
+) -> Result<()> {
+    let mut tx = read(file).c(d!())?;
+    if tx.signed.is_some() {
+        return Err(eg!("the transaction has been signed"));
+    }
+
+    let kp = restore_keypair_from_str_with_default(sk_str)?;
+    if kp.get_pk() != tx.signer {
+        return Err(eg!("the secret key does not match the signer"));
+    }
+
+    println!(
+        "Signer: {}\nSeq id: {} (valid until block {})\nFee: {}\n{}",
+        wallet::public_key_to_bech32(&tx.signer),
+        tx.builder.get_seq_id(),
+        seq_id_deadline(tx.builder.get_seq_id()),
+        fra(TX_FEE_MIN),
+        tx.action
+    );
+    if !yes && !confirm().c(d!())? {
+        println!("Canceled");
+        return Ok(());
+    }
+
+    let vkp = if tx.action.need_validator_key() {
+        let vkp = if let Some(path) = td_key {
+            fs::read_to_string(path)
+                .c(d!("can not read key file from path"))
+                .and_then(|k| utils::parse_td_validator_keys(&k).c(d!()))
+                .map(|v_keys| v_keys.priv_key)?
+        } else {
+            get_td_privkey().c(d!())?
+        };
+        Some(vkp)
+    } else {
+        None
+    };
+
+    let signed = gen_tx(&tx, &kp, vkp.as_ref()).c(d!())?;
+    tx.signed = Some(signed);
+    write(file, &tx).c(d!())?;
+    println!("The signed transaction is written to {file}");
+
+    Ok(())
+}
+
+/// Submit a transaction signed by `fn sign`.
+pub fn broadcast(file: &str) -> Result<()> {
+    let tx = read(file)
+        .c(d!())?
+        .signed
+        .c(d!("the transaction has not been signed"))?;
+    tx.check_tx().c(d!())?;
+    let block_count = utils::get_seq_id().c(d!())?;
+    utils::check_seq_id_window(&tx, block_count)
+        .c(d!("the transaction must be built and signed again"))?;
+    utils::send_tx(&tx).c(d!())?;
+    println!("{}", tx.handle());
+
+    Ok(())
+}
+
+fn gen_tx(
+    tx: &OfflineTx,
+    kp: &XfrKeyPair,
+    vkp: Option<&PrivateKey>,
+) -> Result<Transaction> {
+    let mut builder = tx.builder.clone();
+    let utxos = tx.utxos.clone();
+
+    // the principal of staking, delegation and conversion
+    let gen_principal_op = |am: u64| {
+        utils::gen_transfer_op_with_utxos(
+            utxos.clone(),
+            kp,
+            vec![(&BLACK_HOLE_PUBKEY_STAKING, am)],
+            None,
+            true,
+            false,
+            false,
+            Some(AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType),
+        )
+    };
+    let gen_fee_op = || {
+        utils::gen_transfer_op_with_utxos(
+            utxos.clone(),
+            kp,
+            vec![],
+            None,
+            true,
+            false,
+            false,
+            None,
+        )
+    };
+
+    match &tx.action {
+        OfflineAction::Transfer {
+            targets,
+            token_code,
+            confidential_am,
+            confidential_ty,
+        } => {
+            let op = utils::gen_transfer_op_with_utxos(
+                utxos.clone(),
+                kp,
+                targets.iter().map(|(pk, am)| (pk, *am)).collect(),
+                *token_code,
+                true,
+                *confidential_am,
+                *confidential_ty,
+                None,
+            )
+            .c(d!())?;
+            builder.add_operation(op);
+        }
+        OfflineAction::Stake {
+            amount,
+            td_pubkey,
+            commission_rate,
+            max_commission_rate,
+            memo,
+        } => {
+            builder
+                .add_operation_staking(
+                    kp,
+                    *amount,
+                    vkp.c(d!())?,
+                    td_pubkey.clone(),
+                    *commission_rate,
+                    *max_commission_rate,
+                    memo.clone(),
+                )
+                .c(d!())?;
+            builder.add_operation(gen_principal_op(*amount).c(d!())?);
+        }
+        OfflineAction::Delegate { amount, validator } => {
+            builder.add_operation(gen_principal_op(*amount).c(d!())?);
+            builder.add_operation_delegation(kp, *amount, validator.clone());
+        }
+        OfflineAction::Claim { amount } => {
+            builder.add_operation(gen_fee_op().c(d!())?);
+            builder.add_operation_claim(kp, *amount);
+        }
+        OfflineAction::StakerUpdate {
+            td_pubkey,
+            commission_rate,
+            memo,
+        } => {
+            builder
+                .add_operation_update_staker(
+                    kp,
+                    vkp.c(d!())?,
+                    td_pubkey.clone(),
+                    *commission_rate,
+                    memo.clone(),
+                )
+                .c(d!())?;
+            builder.add_operation(gen_fee_op().c(d!())?);
+        }
+        OfflineAction::Convert { amount, receiver } => {
+            let receiver = evm_account(receiver.as_deref(), &kp.get_pk()).c(d!())?;
+            builder
+                .add_operation(gen_principal_op(*amount).c(d!())?)
+                .add_operation_convert_account(kp, receiver, *amount)
+                .c(d!())?
+                .sign(kp);
+        }
+    }
+
+    let mut tx = builder.take_transaction();
+    tx.sign_to_map(kp);
+
+    Ok(tx)
+}
+
+fn confirm() -> Result<bool> {
+    print!("Sign this transaction? [y/N] ");
+    io::stdout().flush().c(d!())?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer).c(d!())?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+fn read(file: &str) -> Result<OfflineTx> {
+    fs::read(file)
+        .c(d!())
+        .and_then(|b| serde_json::from_slice(&b).c(d!()))
+}
+
+fn write(file: &str, tx: &OfflineTx) -> Result<()> {
+    serde_json::to_vec_pretty(tx)
+        .c(d!())
+        .and_then(|b| fs::write(file, b).c(d!()))
+}
+
+#[cfg(test)]
+#[allow(missing_docs)]
+mod test {
+    use {
+        super::*,
+        ledger::{
+            data_model::{Operation, TxOutput, ASSET_TYPE_FRA},
+            store::TRANSACTION_WINDOW_WIDTH,
+        },
+        rand_chacha::ChaChaRng,
+        rand_core::SeedableRng,
+        std::env,
+        zei::{
+            setup::PublicParams,
+            xfr::{
+                asset_record::build_blind_asset_record, structs::AssetRecordTemplate,
+            },
+        },
+    };
+
+    #[test]
+    fn offline_transfer_round_trip() {
+        let mut prng = ChaChaRng::from_entropy();
+        let kp = XfrKeyPair::generate(&mut prng);
+        let receiver = XfrKeyPair::generate(&mut prng).get_pk();
+
+        let template = AssetRecordTemplate::with_no_asset_tracing(
+            10 * TX_FEE_MIN,
+            ASSET_TYPE_FRA,
+            AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType,
+            kp.get_pk(),
+        );
+        let (record, _, memo) = build_blind_asset_record(
+            &mut prng,
+            &PublicParams::default().pc_gens,
+            &template,
+            vec![],
+        );
+        let utxo = Utxo(TxOutput {
+            id: None,
+            record,
+            lien: None,
+        });
+
+        // built on the online machine
+        let seq_id = 100;
+        let tx = OfflineTx {
+            signer: kp.get_pk(),
+            builder: TransactionBuilder::from_seq_id(seq_id),
+            utxos: vec![(TxoSID(7), (utxo, memo))].into_iter().collect(),
+            action: OfflineAction::Transfer {
+                targets: vec![(receiver, 3 * TX_FEE_MIN)],
+                token_code: None,
+                confidential_am: false,
+                confidential_ty: false,
+            },
+            signed: None,
+        };
+        let file = env::temp_dir()
+            .join(format!("offline_round_trip_{}.json", rand::random::<u64>()))
+            .to_string_lossy()
+            .into_owned();
+        pnk!(write(&file, &tx));
+
+        // signed on the air-gapped machine
+        let mut tx = pnk!(read(&file));
+        assert!(tx.signed.is_none());
+        tx.signed = Some(pnk!(gen_tx(&tx, &kp, None)));
+        pnk!(write(&file, &tx));
+
+        // broadcast from the online machine
+        let signed = pnk!(read(&file)).signed.unwrap();
+        let _ = fs::remove_file(&file);
+        assert_eq!(seq_id, signed.body.no_replay_token.get_seq_id());
+        assert!(signed.check_fee());
+        pnk!(signed.check_tx());
+
+        let outputs = signed
+            .body
+            .operations
+            .iter()
+            .flat_map(|op| match op {
+                Operation::TransferAsset(op) => op.body.outputs.clone(),
+                _ => vec![],
+            })
+            .collect::<Vec<_>>();
+        let paid = outputs
+            .iter()
+            .filter(|o| o.record.public_key == receiver)
+            .map(|o| o.record.amount.get_amount().unwrap())
+            .sum::<u64>();
+        assert_eq!(3 * TX_FEE_MIN, paid);
+
+        // the transaction expires with its seq_id
+        pnk!(utils::check_seq_id_window(
+            &signed,
+            seq_id + TRANSACTION_WINDOW_WIDTH
+        ));
+        assert!(utils::check_seq_id_window(
+            &signed,
+            seq_id + TRANSACTION_WINDOW_WIDTH + 1
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,214 @@
+//!
+//! Audit the transfer history of a traceable asset
+//!
+//! All transfers of the asset are fetched from the query server,
+//! the tracing memos of their outputs are decrypted with the tracer key,
+//! then a report is generated for regulatory reporting.
+//!
+
+use {
+    super::utils,
+    globutils::wallet,
+    ledger::data_model::{AssetTypeCode, Operation, Transaction},
+    ruc::*,
+    serde::Serialize,
+    std::fs,
+    zei::xfr::{
+        lib::trace_assets,
+        sig::XfrPublicKey,
+        structs::{AssetTracerKeyPair, XfrBody},
+    },
+};
+
+/// A traced output of a transfer.
+#[derive(Serialize, Debug)]
+pub struct TraceRecord {
+    /// height of the block which contains the transfer
+    pub height: u64,
+    #[allow(missing_docs)]
+    pub txn_sid: usize,
+    /// tendermint hash of the transaction
+    pub txn_hash: String,
+    /// owners of the inputs
+    pub senders: Vec<String>,
+    /// owner of the output
+    pub receiver: String,
+    #[allow(missing_docs)]
+    pub amount: u64,
+}
+
+/// Trace all transfers of the asset `code`,
+/// the report is printed if `output` is not given.
+pub fn trace_asset(
+    code: &str,
+    tracer_key_file: &str,
+    format: &str,
+    output: Option<&str>,
+) -> Result<()> {
+    let code = AssetTypeCode::new_from_base64(code).c(d!("invalid asset code"))?;
+    let keypair = fs::read(tracer_key_file).c(d!()).and_then(|b| {
+        serde_json::from_slice::<AssetTracerKeyPair>(&b).c(d!("invalid tracer key"))
+    })?;
+
+    let mut records = vec![];
+    for sid in utils::get_related_xfrs(&code).c(d!())? {
+        let tx = utils::get_transaction_light(sid).c(d!())?.txn;
+        let traced = trace_transaction(&tx, &code, &keypair).c(d!())?;
+        if traced.is_empty() {
+            continue;
+        }
+
+        let txn_hash = utils::get_transaction_hash(sid).c(d!())?;
+        let height = utils::get_tx_height(&txn_hash).c(d!())?;
+        for (senders, receiver, amount) in traced {
+            records.push(TraceRecord {
+                height,
+                txn_sid: sid.0,
+                txn_hash: txn_hash.clone(),
+                senders: senders.iter().map(wallet::public_key_to_bech32).collect(),
+                receiver: wallet::public_key_to_bech32(&receiver),
+                amount,
+            });
+        }
+    }
+
+    let report = match format {
+        "json" => serde_json::to_string_pretty(&records).c(d!())?,
+        "csv" => to_csv(&records),
+        _ => return Err(eg!("'format' must be one of: csv, json")),
+    };
+    if let Some(f) = output {
+        fs::write(f, report).c(d!())
+    } else {
+        println!("{report}");
+        Ok(())
+    }
+}
+
+// (senders, receiver, amount) of the traced outputs of `code`,
+// inputs are skipped since they are traced as the outputs of earlier transfers.
+fn trace_transaction(
+    tx: &Transaction,
+    code: &AssetTypeCode,
+    keypair: &AssetTracerKeyPair,
+) -> Result<Vec<(Vec<XfrPublicKey>, XfrPublicKey, u64)>> {
+    let mut res = vec![];
+    for op in tx.body.operations.iter() {
+        let body = match op {
+            Operation::TransferAsset(t) => &t.body.transfer,
+            _ => continue,
+        };
+
+        let mut senders = vec![];
+        for i in body.inputs.iter() {
+            if !senders.contains(&i.public_key) {
+                senders.push(i.public_key);
+            }
+        }
+
+        // memos of the inputs are followed by the ones of the outputs
+        let outputs = XfrBody {
+            inputs: vec![],
+            asset_tracing_memos: body
+                .asset_tracing_memos
+                .get(body.inputs.len()..)
+                .map(|m| m.to_vec())
+                .unwrap_or_default(),
+            ..(**body).clone()
+        };
+        for (amount, asset_type, _, receiver) in
+            trace_assets(&outputs, keypair).c(d!())?
+        {
+            if asset_type == code.val {
+                res.push((senders.clone(), receiver, amount));
+            }
+        }
+    }
+
+    Ok(res)
+}
+
+fn to_csv(records: &[TraceRecord]) -> String {
+    let mut csv = "height,txn_sid,txn_hash,senders,receiver,amount\n".to_owned();
+    for r in records {
+        csv += &format!(
+            "{},{},{},{},{},{}\n",
+            r.height,
+            r.txn_sid,
+            r.txn_hash,
+            r.senders.join(";"),
+            r.receiver,
+            r.amount
+        );
+    }
+    csv
+}
+
+#[cfg(test)]
+#[allow(missing_docs)]
+mod test {
+    use {
+        super::*,
+        ledger::store::{
+            helpers::create_issue_and_transfer_txn_with_asset_tracing, LedgerState,
+        },
+        rand_chacha::ChaChaRng,
+        rand_core::SeedableRng,
+        zei::{
+            setup::PublicParams,
+            xfr::{sig::XfrKeyPair, structs::TracingPolicy},
+        },
+    };
+
+    #[test]
+    fn trace_transfer() {
+        let mut prng = ChaChaRng::from_entropy();
+        let mut ledger = LedgerState::tmp_ledger();
+        let tracer = AssetTracerKeyPair::generate(&mut prng);
+        let issuer = XfrKeyPair::generate(&mut prng);
+        let receiver = XfrKeyPair::generate(&mut prng).get_pk();
+        let code = AssetTypeCode::gen_random();
+
+        let (tx, _) = create_issue_and_transfer_txn_with_asset_tracing(
+            &mut ledger,
+            &PublicParams::default(),
+            &code,
+            100,
+            &issuer,
+            &receiver,
+            0,
+            TracingPolicy {
+                enc_keys: tracer.enc_key.clone(),
+                asset_tracing: true,
+                identity_tracing: None,
+            },
+        );
+
+        let traced = pnk!(trace_transaction(&tx, &code, &tracer));
+        assert_eq!(traced, vec![(vec![issuer.get_pk()], receiver, 100)]);
+
+        // the decoded asset type must match, outputs of other assets are skipped
+        let other = AssetTypeCode::gen_random();
+        assert!(pnk!(trace_transaction(&tx, &other, &tracer)).is_empty());
+
+        let records = traced
+            .into_iter()
+            .map(|(senders, receiver, amount)| TraceRecord {
+                height: 9,
+                txn_sid: 3,
+                txn_hash: "ABCD".to_owned(),
+                senders: senders.iter().map(wallet::public_key_to_bech32).collect(),
+                receiver: wallet::public_key_to_bech32(&receiver),
+                amount,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            to_csv(&records),
+            format!(
+                "height,txn_sid,txn_hash,senders,receiver,amount\n9,3,ABCD,{},{},100\n",
+                wallet::public_key_to_bech32(&issuer.get_pk()),
+                wallet::public_key_to_bech32(&receiver)
+            )
+        );
+    }
+}
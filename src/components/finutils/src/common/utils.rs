@@ -4,27 +4,31 @@
 
 use {
     crate::{
-        api::{DelegationInfo, ValidatorDetail},
+        api::{DelegationInfo, ValidatorDetail, VestingInfo},
         common::get_serv_addr,
         txn_builder::{TransactionBuilder, TransferOperationBuilder},
     },
-    globutils::{wallet, HashOf, SignatureOf},
+    globutils::{signer::Signer, wallet, HashOf, SignatureOf},
     ledger::{
         data_model::{
-            AssetType, AssetTypeCode, DefineAsset, Operation, StateCommitmentData,
-            Transaction, TransferType, TxoRef, TxoSID, Utxo, ASSET_TYPE_FRA,
-            BLACK_HOLE_PUBKEY, TX_FEE_MIN,
+            AssetType, AssetTypeCode, DefineAsset, FinalizedTransaction, Operation,
+            SignatureRules, StateCommitmentData, Transaction, TransferType, TxnSID,
+            TxoRef, TxoSID, Utxo, ASSET_TYPE_FRA, BLACK_HOLE_PUBKEY, TX_FEE_MIN,
         },
-        staking::{init::get_inital_validators, TendermintAddrRef, FRA_TOTAL_AMOUNT},
+        staking::{
+            init::get_inital_validators, TendermintAddrRef, ValidatorData,
+            FRA_TOTAL_AMOUNT,
+        },
+        store::{seq_id_deadline, simulation::TxnSimulation},
     },
     ruc::*,
     serde::{self, Deserialize, Serialize},
-    std::collections::HashMap,
+    std::collections::{HashMap, HashSet},
     tendermint::{PrivateKey, PublicKey},
     zei::xfr::{
         asset_record::{open_blind_asset_record, AssetRecordType},
         sig::{XfrKeyPair, XfrPublicKey},
-        structs::{AssetRecordTemplate, OwnerMemo},
+        structs::{AssetRecordTemplate, BlindAssetRecord, OpenAssetRecord, OwnerMemo},
     },
 };
 
@@ -52,6 +56,20 @@ pub fn send_tx(tx: &Transaction) -> Result<()> {
         .map(|_| ())
 }
 
+/// Dry-run a transaction on the node, nothing is committed
+pub fn simulate_tx(tx: &Transaction) -> Result<TxnSimulation> {
+    let url = format!("{}:8669/simulate_transaction", get_serv_addr().c(d!())?);
+    attohttpc::post(url)
+        .header(attohttpc::header::CONTENT_TYPE, "application/json")
+        .bytes(&serde_json::to_vec(tx).c(d!())?)
+        .send()
+        .c(d!("fail to simulate transaction"))?
+        .error_for_status()
+        .c(d!())?
+        .json::<TxnSimulation>()
+        .c(d!())
+}
+
 /// Fee is needless in a `UpdateValidator` operation
 #[inline(always)]
 pub fn set_initial_validators() -> Result<()> {
@@ -116,6 +134,37 @@ pub fn transfer_batch(
     send_tx(&tx).c(d!())
 }
 
+/// Like `transfer_batch`, but the owner key is held by a `Signer`, e.g. a hardware wallet,
+/// only the non-confidential utxos can be spent.
+pub fn transfer_batch_with_signer(
+    signer: &dyn Signer,
+    target_list: Vec<(&XfrPublicKey, u64)>,
+    token_code: Option<AssetTypeCode>,
+    confidential_am: bool,
+    confidential_ty: bool,
+) -> Result<()> {
+    let owner = signer.public_key().c(d!())?;
+    let mut builder = new_tx_builder().c(d!())?;
+    let op = gen_transfer_op_with_signer(
+        get_owned_utxos(&owner).c(d!())?,
+        signer,
+        None,
+        target_list,
+        token_code,
+        true,
+        confidential_am,
+        confidential_ty,
+        None,
+    )
+    .c(d!())?;
+    builder.add_operation(op);
+
+    let mut tx = builder.take_transaction();
+    tx.sign_to_map_with_signer(signer).c(d!())?;
+
+    send_tx(&tx).c(d!())
+}
+
 /// @target_list: use `Vec` but `HashMap` ?
 ///     there might be multi entries to one address
 #[inline(always)]
@@ -167,6 +216,63 @@ pub fn gen_transfer_op_x(
 pub fn gen_transfer_op_xx(
     rpc_endpoint: Option<&str>,
     owner_kp: &XfrKeyPair,
+    target_list: Vec<(&XfrPublicKey, u64)>,
+    token_code: Option<AssetTypeCode>,
+    auto_fee: bool,
+    confidential_am: bool,
+    confidential_ty: bool,
+    balance_type: Option<AssetRecordType>,
+) -> Result<Operation> {
+    let utxos = get_owned_utxos_x(rpc_endpoint, owner_kp.get_pk_ref()).c(d!())?;
+
+    gen_transfer_op_with_utxos(
+        utxos,
+        owner_kp,
+        target_list,
+        token_code,
+        auto_fee,
+        confidential_am,
+        confidential_ty,
+        balance_type,
+    )
+    .c(d!())
+}
+
+/// Same as `gen_transfer_op_xx`, but spends the given utxos instead of querying them,
+/// this is useful when the transaction is built offline.
+#[allow(clippy::too_many_arguments)]
+pub fn gen_transfer_op_with_utxos(
+    utxos: HashMap<TxoSID, (Utxo, Option<OwnerMemo>)>,
+    owner_kp: &XfrKeyPair,
+    target_list: Vec<(&XfrPublicKey, u64)>,
+    token_code: Option<AssetTypeCode>,
+    auto_fee: bool,
+    confidential_am: bool,
+    confidential_ty: bool,
+    balance_type: Option<AssetRecordType>,
+) -> Result<Operation> {
+    gen_transfer_op_with_signer(
+        utxos,
+        owner_kp,
+        Some(owner_kp),
+        target_list,
+        token_code,
+        auto_fee,
+        confidential_am,
+        confidential_ty,
+        balance_type,
+    )
+    .c(d!())
+}
+
+/// Same as `gen_transfer_op_with_utxos`, but the owner key is held by a `Signer`,
+/// e.g. a hardware wallet, the inputs are opened with `owner_kp` if it is given,
+/// otherwise only the non-confidential utxos can be spent.
+#[allow(clippy::too_many_arguments)]
+pub fn gen_transfer_op_with_signer<S: Signer + ?Sized>(
+    utxos: HashMap<TxoSID, (Utxo, Option<OwnerMemo>)>,
+    signer: &S,
+    owner_kp: Option<&XfrKeyPair>,
     mut target_list: Vec<(&XfrPublicKey, u64)>,
     token_code: Option<AssetTypeCode>,
     auto_fee: bool,
@@ -191,13 +297,16 @@ pub fn gen_transfer_op_xx(
         op_fee = 0;
     }
     let mut i_am;
-    let utxos = get_owned_utxos_x(rpc_endpoint, owner_kp.get_pk_ref())
-        .c(d!())?
-        .into_iter();
-
     for (sid, (utxo, owner_memo)) in utxos {
-        let oar =
-            open_blind_asset_record(&utxo.0.record, &owner_memo, owner_kp).c(d!())?;
+        let oar = match owner_kp {
+            Some(kp) => {
+                open_blind_asset_record(&utxo.0.record, &owner_memo, kp).c(d!())?
+            }
+            None => match open_non_confidential(&utxo.0.record) {
+                Some(oar) => oar,
+                None => continue,
+            },
+        };
 
         if oar.asset_type != asset_type && oar.asset_type != ASSET_TYPE_FRA {
             continue;
@@ -272,7 +381,96 @@ pub fn gen_transfer_op_xx(
         .c(d!())?
         .create(TransferType::Standard)
         .c(d!())?
-        .sign(owner_kp)
+        .sign_with_signer(signer)
+        .c(d!())?
+        .transaction()
+        .c(d!())
+}
+
+// A non-confidential record is opened without any key, its blinds are zero.
+fn open_non_confidential(record: &BlindAssetRecord) -> Option<OpenAssetRecord> {
+    Some(OpenAssetRecord {
+        blind_asset_record: record.clone(),
+        amount: record.amount.get_amount()?,
+        amount_blinds: Default::default(),
+        asset_type: record.asset_type.get_asset_type()?,
+        type_blind: Default::default(),
+    })
+}
+
+/// Transfer from a multisig address, the fee is paid by the address too,
+/// the returned operation is unsigned, signers sign the whole transaction.
+pub fn gen_multisig_transfer_op(
+    rules: &SignatureRules,
+    target_list: Vec<(&XfrPublicKey, u64)>,
+    token_code: Option<AssetTypeCode>,
+) -> Result<Operation> {
+    let asset_type = token_code.map(|code| code.val).unwrap_or(ASSET_TYPE_FRA);
+
+    let mut am: u64 = target_list.iter().map(|(_, am)| *am).sum();
+    let mut op_fee = TX_FEE_MIN;
+    if asset_type == ASSET_TYPE_FRA {
+        am += op_fee;
+        op_fee = 0;
+    }
+
+    let mut trans_builder = TransferOperationBuilder::new();
+
+    let mut i_am;
+    for (sid, (utxo, _)) in get_owned_utxos(&rules.to_address()).c(d!())? {
+        let record = &utxo.0.record;
+        let (i_type, i_amount) = match (
+            record.asset_type.get_asset_type(),
+            record.amount.get_amount(),
+        ) {
+            (Some(t), Some(n)) => (t, n),
+            _ => continue,
+        };
+
+        if i_type == asset_type && am != 0 {
+            alt!(i_amount < am, i_am = i_amount, i_am = am);
+            am -= i_am;
+        } else if i_type == ASSET_TYPE_FRA && op_fee != 0 {
+            alt!(i_amount < op_fee, i_am = i_amount, i_am = op_fee);
+            op_fee -= i_am;
+        } else {
+            continue;
+        }
+
+        trans_builder
+            .add_multisig_input(TxoRef::Absolute(sid), record, rules.clone(), i_am)
+            .c(d!())?;
+
+        alt!(0 == am && 0 == op_fee, break);
+    }
+
+    if 0 != am || 0 != op_fee {
+        return Err(eg!("insufficient balance"));
+    }
+
+    let nct = AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType;
+    let outputs = target_list
+        .into_iter()
+        .map(|(pk, n)| {
+            AssetRecordTemplate::with_no_asset_tracing(n, asset_type, nct, *pk)
+        })
+        .chain([AssetRecordTemplate::with_no_asset_tracing(
+            TX_FEE_MIN,
+            ASSET_TYPE_FRA,
+            nct,
+            *BLACK_HOLE_PUBKEY,
+        )]);
+
+    for output in outputs {
+        trans_builder
+            .add_output(&output, None, None, None)
+            .c(d!())?;
+    }
+
+    trans_builder
+        .balance(None)
+        .c(d!())?
+        .create(TransferType::Standard)
         .c(d!())?
         .transaction()
         .c(d!())
@@ -386,6 +584,32 @@ fn get_network_status(addr: &str) -> Result<TmStatus> {
         })
 }
 
+#[derive(Deserialize, Debug)]
+struct TmTxResp {
+    result: TmTxInfo,
+}
+
+// The part we need of a transaction queried from tendermint
+#[derive(Deserialize, Debug)]
+struct TmTxInfo {
+    height: String,
+}
+
+/// Retrieve the height of the block which contains the transaction
+pub fn get_tx_height(tx_hash: &str) -> Result<u64> {
+    let url = format!("{}:26657/tx?hash=0x{}", get_serv_addr().c(d!())?, tx_hash);
+
+    attohttpc::get(url)
+        .send()
+        .c(d!())?
+        .error_for_status()
+        .c(d!())?
+        .bytes()
+        .c(d!())
+        .and_then(|b| serde_json::from_slice::<TmTxResp>(&b).c(d!()))
+        .and_then(|r| r.result.height.parse::<u64>().c(d!()))
+}
+
 /// Retrieve current block height of the specified tendermint node address
 pub fn get_block_height(addr: &str) -> u64 {
     get_network_status(addr)
@@ -413,6 +637,61 @@ pub fn get_asset_type(code: &str) -> Result<AssetType> {
         .and_then(|b| serde_json::from_slice::<AssetType>(&b).c(d!()))
 }
 
+/// Retrieve the transfers of a custom asset, in ascending order
+pub fn get_related_xfrs(code: &AssetTypeCode) -> Result<Vec<TxnSID>> {
+    let url = format!(
+        "{}:8667/get_related_xfrs/{}",
+        get_serv_addr().c(d!())?,
+        code.to_base64()
+    );
+
+    let mut sids = attohttpc::get(url)
+        .send()
+        .c(d!())?
+        .error_for_status()
+        .c(d!())?
+        .bytes()
+        .c(d!())
+        .and_then(|b| serde_json::from_slice::<HashSet<TxnSID>>(&b).c(d!()))?
+        .into_iter()
+        .collect::<Vec<_>>();
+    sids.sort();
+
+    Ok(sids)
+}
+
+/// Retrieve a transaction without its proof data
+pub fn get_transaction_light(sid: TxnSID) -> Result<FinalizedTransaction> {
+    let url = format!("{}:8668/txn_sid_light/{}", get_serv_addr().c(d!())?, sid.0);
+
+    attohttpc::get(url)
+        .send()
+        .c(d!())?
+        .error_for_status()
+        .c(d!())?
+        .bytes()
+        .c(d!())
+        .and_then(|b| serde_json::from_slice::<FinalizedTransaction>(&b).c(d!()))
+}
+
+/// Retrieve the tendermint hash of a transaction
+pub fn get_transaction_hash(sid: TxnSID) -> Result<String> {
+    let url = format!(
+        "{}:8667/get_transaction_hash/{}",
+        get_serv_addr().c(d!())?,
+        sid.0
+    );
+
+    attohttpc::get(url)
+        .send()
+        .c(d!())?
+        .error_for_status()
+        .c(d!())?
+        .bytes()
+        .c(d!())
+        .and_then(|b| serde_json::from_slice::<String>(&b).c(d!()))
+}
+
 /// Retrieve a list of assets created by the specified findora account
 pub fn get_created_assets(addr: &XfrPublicKey) -> Result<Vec<DefineAsset>> {
     let url = format!(
@@ -457,7 +736,8 @@ pub fn get_asset_balance(kp: &XfrKeyPair, asset: Option<AssetTypeCode>) -> Resul
     Ok(balance)
 }
 
-fn get_owned_utxos(
+/// Retrieve the utxos owned by a findora account, with their owner memos
+pub fn get_owned_utxos(
     addr: &XfrPublicKey,
 ) -> Result<HashMap<TxoSID, (Utxo, Option<OwnerMemo>)>> {
     get_owned_utxos_x(None, addr).c(d!())
@@ -487,8 +767,10 @@ fn get_owned_utxos_x(
         })
 }
 
+/// The current block count of the ledger,
+/// new transactions use it as their seq_id
 #[inline(always)]
-fn get_seq_id() -> Result<u64> {
+pub fn get_seq_id() -> Result<u64> {
     type Resp = (
         HashOf<Option<StateCommitmentData>>,
         u64,
@@ -508,6 +790,20 @@ fn get_seq_id() -> Result<u64> {
         .map(|resp| resp.1)
 }
 
+/// Check that a transaction built earlier is still accepted by the ledger at `block_count`,
+/// return the number of blocks left before its seq_id falls out of the replay window.
+pub fn check_seq_id_window(tx: &Transaction, block_count: u64) -> Result<u64> {
+    let seq_id = tx.body.no_replay_token.get_seq_id();
+    let deadline = seq_id_deadline(seq_id);
+    if deadline < block_count {
+        Err(eg!(format!(
+            "the transaction has expired: it was built at block {seq_id} and is only accepted until block {deadline}, the ledger is at block {block_count}"
+        )))
+    } else {
+        Ok(deadline - block_count)
+    }
+}
+
 #[inline(always)]
 #[allow(missing_docs)]
 pub fn get_owner_memo_batch(ids: &[TxoSID]) -> Result<Vec<Option<OwnerMemo>>> {
@@ -550,6 +846,24 @@ pub fn get_delegation_info(pk: &XfrPublicKey) -> Result<DelegationInfo> {
         .and_then(|b| serde_json::from_slice::<DelegationInfo>(&b).c(d!()))
 }
 
+/// Vesting info of a beneficiary.
+pub fn get_vesting_info(pk: &XfrPublicKey) -> Result<VestingInfo> {
+    let url = format!(
+        "{}:8668/vesting_info/{}",
+        get_serv_addr().c(d!())?,
+        wallet::public_key_to_base64(pk)
+    );
+
+    attohttpc::get(url)
+        .send()
+        .c(d!())?
+        .error_for_status()
+        .c(d!())?
+        .bytes()
+        .c(d!())
+        .and_then(|b| serde_json::from_slice::<VestingInfo>(&b).c(d!()))
+}
+
 /// Get validator infomations.
 pub fn get_validator_detail(td_addr: TendermintAddrRef) -> Result<ValidatorDetail> {
     let url = format!(
@@ -568,6 +882,20 @@ pub fn get_validator_detail(td_addr: TendermintAddrRef) -> Result<ValidatorDetai
         .and_then(|b| serde_json::from_slice::<ValidatorDetail>(&b).c(d!()))
 }
 
+/// The current validators and the rule of co-signatures.
+pub fn get_validator_data() -> Result<ValidatorData> {
+    let url = format!("{}:8668/validator_data", get_serv_addr().c(d!())?);
+
+    attohttpc::get(url)
+        .send()
+        .c(d!())?
+        .error_for_status()
+        .c(d!())?
+        .bytes()
+        .c(d!())
+        .and_then(|b| serde_json::from_slice::<ValidatorData>(&b).c(d!()))
+}
+
 #[allow(missing_docs)]
 #[derive(Serialize, Deserialize)]
 pub struct ValidatorKey {
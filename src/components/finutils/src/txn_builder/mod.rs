@@ -6,16 +6,17 @@
 #![allow(clippy::needless_borrow)]
 
 use {
-    credentials::CredUserSecretKey,
+    credentials::{CredIssuerPublicKey, CredUserSecretKey},
     curve25519_dalek::scalar::Scalar,
     fp_types::crypto::MultiSigner,
-    globutils::SignatureOf,
+    globutils::{amount_proof::AmountProof, signer::Signer, SignatureOf},
     ledger::{
         converter::ConvertAccount,
         data_model::{
-            AssetRules, AssetTypeCode, ConfidentialMemo, DefineAsset, DefineAssetBody,
-            IndexedSignature, IssueAsset, IssueAssetBody, IssuerKeyPair,
-            IssuerPublicKey, Memo, NoReplayToken, Operation, Transaction,
+            AssetRules, AssetTypeCode, ConfidentialMemo, CredentialProof, DefineAsset,
+            DefineAssetBody, IndexedSignature, IssueAsset, IssueAssetBody,
+            IssuerKeyPair, IssuerPublicKey, Memo, NoReplayToken, Operation,
+            RegisterCredIssuer, RegisterCredIssuerBody, SignatureRules, Transaction,
             TransactionBody, TransferAsset, TransferAssetBody, TransferType, TxOutput,
             TxoRef, UpdateMemo, UpdateMemoBody, ASSET_TYPE_FRA, BLACK_HOLE_PUBKEY,
             TX_FEE_MIN,
@@ -31,6 +32,7 @@ use {
                 undelegation::UnDelegationOps,
                 update_staker::UpdateStakerOps,
                 update_validator::UpdateValidatorOps,
+                vesting::{CreateVestingOps, ReleaseVestedOps, VestingSchedule},
             },
             td_addr_to_string, BlockHeight, PartialUnDelegation, StakerMemo,
             TendermintAddr, Validator,
@@ -276,12 +278,20 @@ impl TransactionBuilder {
 
     /// Create a instance from seq_id
     pub fn from_seq_id(seq_id: u64) -> Self {
-        let mut prng = ChaChaRng::from_entropy();
-        let no_replay_token = NoReplayToken::new(&mut prng, seq_id);
+        Self::from_seq_id_with_rng(&mut ChaChaRng::from_entropy(), seq_id)
+    }
+
+    /// Create a instance from seq_id, the no-replay token is generated by `prng`,
+    /// use a seeded one to build reproducible transactions.
+    pub fn from_seq_id_with_rng<R: CryptoRng + RngCore>(
+        prng: &mut R,
+        seq_id: u64,
+    ) -> Self {
+        let txn = Transaction::from_seq_id_with_rng(prng, seq_id);
         TransactionBuilder {
-            txn: Transaction::from_seq_id(seq_id),
+            no_replay_token: txn.body.no_replay_token,
+            txn,
             outputs: 0,
-            no_replay_token,
         }
     }
 
@@ -469,6 +479,31 @@ impl TransactionBuilder {
         self
     }
 
+    /// Add a operation to registering the public key of a credential issuer,
+    /// an earlier registration of the same `keypair` will be replaced.
+    pub fn add_operation_register_cred_issuer(
+        &mut self,
+        keypair: &XfrKeyPair,
+        issuer_pub_key: CredIssuerPublicKey,
+    ) -> &mut Self {
+        let op = RegisterCredIssuer::new(
+            RegisterCredIssuerBody {
+                issuer_pub_key,
+                no_replay_token: self.txn.body.no_replay_token,
+            },
+            keypair,
+        );
+        self.txn.add_operation(Operation::RegisterCredIssuer(op));
+        self
+    }
+
+    /// Attach the credential proof of a recipient of credential-gated assets,
+    /// it must be done before the transaction is signed.
+    pub fn add_credential_proof(&mut self, proof: CredentialProof) -> &mut Self {
+        self.txn.body.credentials.push(proof);
+        self
+    }
+
     /// Add a operation to delegating findora account to a tendermint validator.
     /// The transfer operation to BLACK_HOLE_PUBKEY_STAKING should be sent along with.
     pub fn add_operation_delegation(
@@ -488,6 +523,22 @@ impl TransactionBuilder {
         self.add_operation(Operation::Delegation(op))
     }
 
+    /// Add a operation to delegate the FRAs locked in vestings
+    pub fn add_operation_delegation_from_vesting(
+        &mut self,
+        keypair: &XfrKeyPair,
+        amount: u64,
+        validator: TendermintAddr,
+    ) -> &mut Self {
+        let op = DelegationOps::new_from_vesting(
+            keypair,
+            amount,
+            validator,
+            self.txn.body.no_replay_token,
+        );
+        self.add_operation(Operation::Delegation(op))
+    }
+
     /// Add a operation to updating staker memo and commission_rate
     pub fn add_operation_update_staker(
         &mut self,
@@ -525,6 +576,7 @@ impl TransactionBuilder {
         vltor_key: &PrivateKey,
         td_pubkey: Vec<u8>,
         commission_rate: [u64; 2],
+        max_commission_rate: Option<[u64; 2]>,
         memo: Option<String>,
     ) -> Result<&mut Self> {
         let v_id = keypair.get_pk();
@@ -535,7 +587,11 @@ impl TransactionBuilder {
             Default::default()
         };
 
-        let v = Validator::new_staker(td_pubkey, v_id, commission_rate, memo).c(d!())?;
+        let mut v =
+            Validator::new_staker(td_pubkey, v_id, commission_rate, memo).c(d!())?;
+        if let Some(max_rate) = max_commission_rate {
+            v.set_max_commission_rate(max_rate).c(d!())?;
+        }
         let vaddr = td_addr_to_string(&v.td_addr);
 
         if !is_valid_tendermint_addr(&vaddr) {
@@ -576,6 +632,34 @@ impl TransactionBuilder {
         self.add_operation(Operation::Claim(op))
     }
 
+    /// Add a operation to lock FRAs for a beneficiary
+    pub fn add_operation_create_vesting(
+        &mut self,
+        keypair: &XfrKeyPair,
+        beneficiary: XfrPublicKey,
+        amount: u64,
+        schedule: VestingSchedule,
+    ) -> &mut Self {
+        let op = CreateVestingOps::new(
+            keypair,
+            beneficiary,
+            amount,
+            schedule,
+            self.txn.body.no_replay_token,
+        );
+        self.add_operation(Operation::CreateVesting(op))
+    }
+
+    /// Add a operation to release vested FRAs
+    pub fn add_operation_release_vested(
+        &mut self,
+        keypair: &XfrKeyPair,
+        am: Option<u64>,
+    ) -> &mut Self {
+        let op = ReleaseVestedOps::new(keypair, am, self.txn.body.no_replay_token);
+        self.add_operation(Operation::ReleaseVested(op))
+    }
+
     #[allow(missing_docs)]
     pub fn add_operation_fra_distribution(
         &mut self,
@@ -641,12 +725,25 @@ impl TransactionBuilder {
         kp: &XfrKeyPair,
         addr: MultiSigner,
         amount: u64,
+    ) -> Result<&mut Self> {
+        self.add_operation_convert_account_with_proofs(kp, addr, amount, vec![])
+    }
+
+    /// Same as `add_operation_convert_account`,
+    /// with the proofs of the confidential amounts sent to the black hole.
+    pub fn add_operation_convert_account_with_proofs(
+        &mut self,
+        kp: &XfrKeyPair,
+        addr: MultiSigner,
+        amount: u64,
+        amount_proofs: Vec<AmountProof>,
     ) -> Result<&mut Self> {
         self.add_operation(Operation::ConvertAccount(ConvertAccount {
             signer: kp.get_pk(),
             nonce: self.txn.body.no_replay_token,
             receiver: addr,
             value: amount,
+            amount_proofs,
         }));
         Ok(self)
     }
@@ -663,6 +760,15 @@ impl TransactionBuilder {
         self
     }
 
+    /// Like `sign`, but the key is held by a `Signer`, e.g. a hardware wallet
+    pub fn sign_with_signer<S: Signer + ?Sized>(
+        &mut self,
+        signer: &S,
+    ) -> Result<&mut Self> {
+        self.txn.sign_with_signer(signer).c(d!())?;
+        Ok(self)
+    }
+
     /// Check and append signature to transaction
     pub fn add_signature(
         &mut self,
@@ -680,6 +786,15 @@ impl TransactionBuilder {
         self
     }
 
+    /// Like `sign_to_map`, but the key is held by a `Signer`, e.g. a hardware wallet
+    pub fn sign_to_map_with_signer<S: Signer + ?Sized>(
+        &mut self,
+        signer: &S,
+    ) -> Result<&mut Self> {
+        self.txn.sign_to_map_with_signer(signer).c(d!())?;
+        Ok(self)
+    }
+
     #[allow(missing_docs)]
     pub fn serialize(&self) -> Vec<u8> {
         // Unwrap is safe beacuse the underlying transaction is guaranteed to be serializable.
@@ -782,6 +897,8 @@ pub struct TransferOperationBuilder {
     transfer: Option<TransferAsset>,
     transfer_type: TransferType,
     auto_refund: bool,
+    #[serde(default)]
+    multisig_rules: Vec<SignatureRules>,
 }
 
 impl TransferOperationBuilder {
@@ -808,6 +925,26 @@ impl TransferOperationBuilder {
         tracing_policies: Option<TracingPolicies>,
         identity_commitment: Option<ACCommitment>,
         amount: u64,
+    ) -> Result<&mut Self> {
+        self.add_input_with_rng(
+            &mut ChaChaRng::from_entropy(),
+            txo_sid,
+            open_ar,
+            tracing_policies,
+            identity_commitment,
+            amount,
+        )
+    }
+
+    /// Same as `add_input`, the randomness comes from `prng`.
+    pub fn add_input_with_rng<R: CryptoRng + RngCore>(
+        &mut self,
+        prng: &mut R,
+        txo_sid: TxoRef,
+        open_ar: OpenAssetRecord,
+        tracing_policies: Option<TracingPolicies>,
+        identity_commitment: Option<ACCommitment>,
+        amount: u64,
     ) -> Result<&mut Self> {
         if self.transfer.is_some() {
             return Err(eg!(
@@ -818,7 +955,7 @@ impl TransferOperationBuilder {
 
         let asset_record =
             AssetRecord::from_open_asset_record_with_asset_tracing_but_no_identity(
-                &mut ChaChaRng::from_entropy(),
+                prng,
                 open_ar,
                 policies.clone(),
             )
@@ -831,6 +968,45 @@ impl TransferOperationBuilder {
         Ok(self)
     }
 
+    /// Spend a non-confidential record owned by the multisig address of `rules`,
+    /// the transaction must be signed by enough signers of the rules.
+    pub fn add_multisig_input(
+        &mut self,
+        txo_sid: TxoRef,
+        record: &BlindAssetRecord,
+        rules: SignatureRules,
+        amount: u64,
+    ) -> Result<&mut Self> {
+        if rules.to_address() != record.public_key {
+            return Err(eg!("record is not owned by the multisig address"));
+        }
+        let (record_amount, asset_type) = match (
+            record.amount.get_amount(),
+            record.asset_type.get_asset_type(),
+        ) {
+            (Some(am), Some(at)) => (am, at),
+            _ => {
+                return Err(eg!(
+                    "multisig address can only own non-confidential records"
+                ));
+            }
+        };
+
+        // a non-confidential record is opened without any key, its blinds are zero
+        let oar = OpenAssetRecord {
+            blind_asset_record: record.clone(),
+            amount: record_amount,
+            amount_blinds: Default::default(),
+            asset_type,
+            type_blind: Default::default(),
+        };
+        self.add_input(txo_sid, oar, None, None, amount).c(d!())?;
+        if !self.multisig_rules.contains(&rules) {
+            self.multisig_rules.push(rules);
+        }
+        Ok(self)
+    }
+
     #[allow(missing_docs)]
     pub fn add_output(
         &mut self,
@@ -839,7 +1015,24 @@ impl TransferOperationBuilder {
         identity_commitment: Option<ACCommitment>,
         credential_record: Option<(&CredUserSecretKey, &Credential, &ACCommitmentKey)>,
     ) -> Result<&mut Self> {
-        let prng = &mut ChaChaRng::from_entropy();
+        self.add_output_with_rng(
+            &mut ChaChaRng::from_entropy(),
+            asset_record_template,
+            tracing_policies,
+            identity_commitment,
+            credential_record,
+        )
+    }
+
+    /// Same as `add_output`, the randomness comes from `prng`.
+    pub fn add_output_with_rng<R: CryptoRng + RngCore>(
+        &mut self,
+        prng: &mut R,
+        asset_record_template: &AssetRecordTemplate,
+        tracing_policies: Option<TracingPolicies>,
+        identity_commitment: Option<ACCommitment>,
+        credential_record: Option<(&CredUserSecretKey, &Credential, &ACCommitmentKey)>,
+    ) -> Result<&mut Self> {
         if self.transfer.is_some() {
             return Err(eg!(
                 ("Cannot mutate a transfer that has been signed".to_string())
@@ -956,7 +1149,15 @@ impl TransferOperationBuilder {
     /// Ensures that outputs and inputs are balanced by adding remainder outputs for leftover asset
     /// amounts
     pub fn balance(&mut self, rt: Option<AssetRecordType>) -> Result<&mut Self> {
-        let mut prng = ChaChaRng::from_entropy();
+        self.balance_with_rng(&mut ChaChaRng::from_entropy(), rt)
+    }
+
+    /// Same as `balance`, the randomness comes from `prng`.
+    pub fn balance_with_rng<R: CryptoRng + RngCore>(
+        &mut self,
+        prng: &mut R,
+        rt: Option<AssetRecordType>,
+    ) -> Result<&mut Self> {
         if self.transfer.is_some() {
             return Err(eg!(
                 ("Cannot mutate a transfer that has been signed".to_string())
@@ -994,7 +1195,7 @@ impl TransferOperationBuilder {
                         policies.clone(),
                     );
                     let ar = AssetRecord::from_template_no_identity_tracing(
-                        &mut prng,
+                        prng,
                         &ar_template,
                     )
                     .c(d!())?;
@@ -1029,13 +1230,21 @@ impl TransferOperationBuilder {
     /// Finalize the transaction and prepare for signing. Once called, the transaction cannot be
     /// modified.
     pub fn create(&mut self, transfer_type: TransferType) -> Result<&mut Self> {
+        self.create_with_rng(&mut ChaChaRng::from_entropy(), transfer_type)
+    }
+
+    /// Same as `create`, the randomness comes from `prng`.
+    pub fn create_with_rng<R: CryptoRng + RngCore>(
+        &mut self,
+        prng: &mut R,
+        transfer_type: TransferType,
+    ) -> Result<&mut Self> {
         if self.auto_refund {
-            self.balance(None).c(d!())?;
+            self.balance_with_rng(prng, None).c(d!())?;
         } else {
             self.check_balance().c(d!())?;
         }
 
-        let mut prng = ChaChaRng::from_entropy();
         let num_inputs = self.input_records.len();
         let num_outputs = self.output_records.len();
         let xfr_policies = XfrNotePolicies::new(
@@ -1045,7 +1254,7 @@ impl TransferOperationBuilder {
             vec![None; num_outputs],
         );
         let body = TransferAssetBody::new(
-            &mut prng,
+            prng,
             self.input_sids.clone(),
             &self.input_records,
             &self.output_records,
@@ -1054,7 +1263,11 @@ impl TransferOperationBuilder {
             transfer_type,
         )
         .c(d!())?;
-        self.transfer = Some(TransferAsset::new(body).c(d!())?);
+        let mut transfer = TransferAsset::new(body).c(d!())?;
+        for rules in self.multisig_rules.iter() {
+            transfer.attach_multisig_rules(rules.clone()).c(d!())?;
+        }
+        self.transfer = Some(transfer);
         Ok(self)
     }
 
@@ -1078,6 +1291,19 @@ impl TransferOperationBuilder {
         Ok(self)
     }
 
+    /// Like `sign`, but the key is held by a `Signer`, e.g. a hardware wallet.
+    pub fn sign_with_signer<S: Signer + ?Sized>(
+        &mut self,
+        signer: &S,
+    ) -> Result<&mut Self> {
+        self.transfer
+            .as_mut()
+            .c(d!(no_transfer_err!()))?
+            .sign_with_signer(signer)
+            .c(d!())?;
+        Ok(self)
+    }
+
     #[allow(missing_docs)]
     pub fn create_input_signature(
         &self,
@@ -1360,6 +1586,195 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_sign_with_signer() {
+        let mut prng = ChaChaRng::seed_from_u64(0);
+        let params = PublicParams::default();
+        let alice = XfrKeyPair::generate(&mut prng);
+        let bob = XfrKeyPair::generate(&mut prng);
+
+        let ar = AssetRecordTemplate::with_no_asset_tracing(
+            1000,
+            ASSET_TYPE_FRA,
+            NonConfidentialAmount_NonConfidentialAssetType,
+            alice.get_pk(),
+        );
+        let (ba, _, memo) =
+            build_blind_asset_record(&mut prng, &params.pc_gens, &ar, vec![]);
+        let oar = pnk!(open_blind_asset_record(&ba, &memo, &alice));
+        let output_template = AssetRecordTemplate::with_no_asset_tracing(
+            1000,
+            ASSET_TYPE_FRA,
+            NonConfidentialAmount_NonConfidentialAssetType,
+            bob.get_pk(),
+        );
+
+        let mut op = TransferOperationBuilder::new();
+        pnk!(op
+            .add_input_with_rng(&mut prng, TxoRef::Relative(0), oar, None, None, 1000)
+            .and_then(|op| op.add_output_with_rng(
+                &mut prng,
+                &output_template,
+                None,
+                None,
+                None
+            ))
+            .and_then(|op| op.create_with_rng(&mut prng, TransferType::Standard)));
+        let mut builder = TransactionBuilder::from_seq_id_with_rng(&mut prng, 1);
+
+        // the software signer gives the same signatures as the keypair
+        let signer: &dyn Signer = &alice;
+        let mut op_with_signer = op.clone();
+        pnk!(op.sign(&alice));
+        pnk!(op_with_signer.sign_with_signer(signer));
+        assert_eq!(pnk!(op.transaction()), pnk!(op_with_signer.transaction()));
+
+        builder.add_operation(pnk!(op.transaction()));
+        let mut builder_with_signer = builder.clone();
+        builder.sign(&alice).sign_to_map(&alice);
+        pnk!(builder_with_signer
+            .sign_with_signer(signer)
+            .and_then(|b| b.sign_to_map_with_signer(signer)));
+        assert_eq!(builder.serialize(), builder_with_signer.serialize());
+        pnk!(builder.get_transaction().check_tx());
+
+        let summary = builder.get_transaction().body.summary();
+        assert_eq!(
+            format!(
+                "transfer 1000 FRA to {}",
+                globutils::wallet::public_key_to_bech32(&bob.get_pk())
+            ),
+            summary
+        );
+    }
+
+    #[test]
+    fn test_multisig_input() {
+        let mut prng = ChaChaRng::seed_from_u64(0);
+        let params = PublicParams::default();
+        let signers = (0..3)
+            .map(|_| XfrKeyPair::generate(&mut prng))
+            .collect::<Vec<_>>();
+        let bob = XfrKeyPair::generate(&mut prng);
+        let rules = SignatureRules {
+            threshold: 2,
+            weights: signers.iter().map(|kp| (kp.get_pk(), 1)).collect(),
+        };
+
+        let record = |prng: &mut ChaChaRng, owner| {
+            let ar = AssetRecordTemplate::with_no_asset_tracing(
+                1000,
+                ASSET_TYPE_FRA,
+                NonConfidentialAmount_NonConfidentialAssetType,
+                owner,
+            );
+            build_blind_asset_record(prng, &params.pc_gens, &ar, vec![]).0
+        };
+        let ba = record(&mut prng, rules.to_address());
+        let output_template = AssetRecordTemplate::with_no_asset_tracing(
+            1000,
+            ASSET_TYPE_FRA,
+            NonConfidentialAmount_NonConfidentialAssetType,
+            bob.get_pk(),
+        );
+
+        // a record of another owner can not be spent by the rules
+        let mut op = TransferOperationBuilder::new();
+        let other = record(&mut prng, bob.get_pk());
+        assert!(op
+            .add_multisig_input(TxoRef::Relative(0), &other, rules.clone(), 1000)
+            .is_err());
+
+        pnk!(op
+            .add_multisig_input(TxoRef::Relative(0), &ba, rules.clone(), 1000)
+            .and_then(|op| op.add_output_with_rng(
+                &mut prng,
+                &output_template,
+                None,
+                None,
+                None
+            ))
+            .and_then(|op| op.create_with_rng(&mut prng, TransferType::Standard)));
+        let mut tx = Transaction::from_operation(pnk!(op.transaction()), 1);
+
+        // the transfer is valid once the threshold of signers is reached
+        pnk!(tx.sign_multisig(&signers[0]));
+        assert!(tx.check_tx().is_err());
+        pnk!(tx.sign_multisig(&signers[2]));
+        pnk!(tx.check_tx());
+        pnk!(TxnEffect::compute_effect(tx));
+    }
+
+    #[test]
+    fn test_reproducible_transaction() {
+        let mut prng = ChaChaRng::seed_from_u64(0);
+        let params = PublicParams::default();
+        let alice = XfrKeyPair::generate(&mut prng);
+        let bob = XfrKeyPair::generate(&mut prng);
+        let code = AssetTypeCode::gen_random_with_rng(&mut prng);
+
+        let ar = AssetRecordTemplate::with_no_asset_tracing(
+            1000,
+            code.val,
+            NonConfidentialAmount_NonConfidentialAssetType,
+            alice.get_pk(),
+        );
+        let (ba, _, memo) =
+            build_blind_asset_record(&mut prng, &params.pc_gens, &ar, vec![]);
+        let oar = pnk!(open_blind_asset_record(&ba, &memo, &alice));
+        let output_template = AssetRecordTemplate::with_no_asset_tracing(
+            100,
+            code.val,
+            NonConfidentialAmount_NonConfidentialAssetType,
+            bob.get_pk(),
+        );
+
+        let build = |seed: u64| {
+            let mut prng = ChaChaRng::seed_from_u64(seed);
+            let mut op = TransferOperationBuilder::new();
+            pnk!(op
+                .add_input_with_rng(
+                    &mut prng,
+                    TxoRef::Relative(0),
+                    oar.clone(),
+                    None,
+                    None,
+                    1000,
+                )
+                .and_then(|op| op.add_output_with_rng(
+                    &mut prng,
+                    &output_template,
+                    None,
+                    None,
+                    None
+                ))
+                .and_then(|op| op.create_with_rng(&mut prng, TransferType::Standard))
+                .and_then(|op| op.sign(&alice)));
+
+            let mut builder = TransactionBuilder::from_seq_id_with_rng(&mut prng, 1);
+            builder.add_operation(pnk!(op.transaction())).sign(&alice);
+            assert_eq!(
+                builder.no_replay_token,
+                builder.get_transaction().body.no_replay_token
+            );
+            builder.serialize()
+        };
+
+        // the same seed gives byte-identical transactions
+        assert_eq!(build(7), build(7));
+        assert_ne!(build(7), build(8));
+
+        // golden serialization, catches drifts of the rng or the wire format
+        let builder = TransactionBuilder::from_seq_id_with_rng(
+            &mut ChaChaRng::seed_from_u64(7),
+            1,
+        );
+        assert_eq!(
+            builder.serialize_str(),
+            r#"{"body":{"no_replay_token":[[5,249,82,183,39,74,69,25],1],"operations":[]}}"#
+        );
+    }
+
     #[test]
     fn test_check_fee_with_ledger() {
         let mut ledger = LedgerState::tmp_ledger();
@@ -1444,7 +1859,7 @@ mod tests {
             TX_FEE_MIN,
             TxoRef::Absolute(txo_sid[0]),
             utxo.utxo.0,
-            utxo.txn.txn.get_owner_memos_ref()[utxo.utxo_location.0].cloned(),
+            utxo.get_owner_memo(),
             bob_kp.get_sk().into_keypair(),
         );
         let mut tx3 = TransactionBuilder::from_seq_id(2);
@@ -1474,7 +1889,7 @@ mod tests {
             TX_FEE_MIN,
             TxoRef::Absolute(txo_sid[0]),
             utxo.utxo.0,
-            utxo.txn.txn.get_owner_memos_ref()[utxo.utxo_location.0].cloned(),
+            utxo.get_owner_memo(),
             bob_kp.get_sk().into_keypair(),
         );
         let mut tx4 = TransactionBuilder::from_seq_id(3);
@@ -0,0 +1,64 @@
+//!
+//! # Benchmarks
+//!
+//! Pre-generate a workload of funded keys and signed transactions,
+//! replay it against a node and report the throughput as JSON.
+//!
+//! The workload mixes:
+//! - UTXO transfers between the generated keys
+//! - delegations of the generated keys to the validators
+//! - ERC-20 transfers between the generated EVM accounts
+//!
+//! The keys are derived from the seed, so two runs with the same
+//! configuration replay the same workload.
+//!
+//! NOTE:
+//! the transactions must be replayed within 128 blocks after they are built,
+//! or the ledger rejects them as expired.
+//!
+
+mod node;
+mod report;
+mod workload;
+
+pub use {
+    node::{CallbackSamples, InProcess, Node, Remote, Samples},
+    report::{Percentiles, Report},
+    workload::{TxKind, Workload},
+};
+
+use ruc::*;
+
+/// What to generate and how to replay it.
+#[derive(Debug, Clone)]
+pub struct BenchCfg {
+    /// seed of the generated keys
+    pub seed: u64,
+    /// number of UTXO transfers
+    pub transfers: usize,
+    /// number of delegations
+    pub delegations: usize,
+    /// number of ERC-20 transfers
+    pub erc20_calls: usize,
+    /// transactions per second sent to a remote node, 0 for no limit
+    pub rate: u64,
+}
+
+impl Default for BenchCfg {
+    fn default() -> Self {
+        BenchCfg {
+            seed: 0,
+            transfers: 1000,
+            delegations: 100,
+            erc20_calls: 1000,
+            rate: 0,
+        }
+    }
+}
+
+/// Fund the keys, build the workload offline and replay it.
+pub fn run(cfg: &BenchCfg, node: &mut dyn Node) -> Result<Report> {
+    let workload = Workload::prepare(cfg, node).c(d!())?;
+    let samples = node.replay(&workload.txs(), cfg.rate).c(d!())?;
+    Ok(Report::new(cfg, node.name(), &workload, samples))
+}
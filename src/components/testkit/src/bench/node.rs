@@ -0,0 +1,501 @@
+//!
+//! The nodes a workload is replayed against.
+//!
+
+use {
+    crate::{
+        txs::{fra_genesis, test_validator, update_validators},
+        Block, TestKit,
+    },
+    finutils::txn_builder::TransactionBuilder,
+    fp_types::{crypto::Address, H160, U256},
+    globutils::wallet,
+    ledger::{
+        data_model::{TxoSID, Utxo},
+        staking::{
+            td_addr_to_string, TendermintAddr, Validator, STAKING_VALIDATOR_MIN_POWER,
+        },
+    },
+    rand_chacha::ChaChaRng,
+    rand_core::SeedableRng,
+    ruc::*,
+    serde_json::Value,
+    std::{
+        collections::HashMap,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc, Mutex,
+        },
+        thread,
+        time::{Duration, Instant},
+    },
+    zei::xfr::{
+        sig::{XfrKeyPair, XfrPublicKey},
+        structs::OwnerMemo,
+    },
+};
+
+/// Measurements of a replay.
+#[derive(Debug, Default)]
+pub struct Samples {
+    /// number of transactions sent
+    pub submitted: usize,
+    /// number of transactions rejected by `check_tx` or `deliver_tx`
+    pub failed: usize,
+    /// from the first transaction sent to the last one committed
+    pub duration: Duration,
+    /// from sending to committing, for each committed transaction
+    pub latencies: Vec<Duration>,
+    /// time spent in each ABCI callback,
+    /// only known when the app runs in-process
+    pub callbacks: Option<CallbackSamples>,
+}
+
+/// Time spent in each ABCI callback.
+#[derive(Debug, Default)]
+pub struct CallbackSamples {
+    /// one entry per block
+    pub begin_block: Vec<Duration>,
+    /// one entry per transaction
+    pub deliver_tx: Vec<Duration>,
+    /// one entry per block
+    pub end_block: Vec<Duration>,
+    /// one entry per block
+    pub commit: Vec<Duration>,
+}
+
+impl CallbackSamples {
+    fn add(&mut self, block: &Block) {
+        let t = &block.timings;
+        self.begin_block.push(t.begin_block);
+        self.deliver_tx.extend_from_slice(&t.deliver_tx);
+        self.end_block.push(t.end_block);
+        self.commit.push(t.commit);
+    }
+}
+
+/// A findora node to run a benchmark against.
+pub trait Node {
+    /// The name of the target in the report.
+    fn name(&self) -> &'static str;
+
+    /// The key funding the workload.
+    fn root(&self) -> &XfrKeyPair;
+
+    /// A transaction builder for the current block.
+    fn tx_builder(&self) -> Result<TransactionBuilder>;
+
+    #[allow(missing_docs)]
+    fn owned_utxos(
+        &self,
+        pk: &XfrPublicKey,
+    ) -> Result<HashMap<TxoSID, (Utxo, Option<OwnerMemo>)>>;
+
+    /// The validators to delegate to.
+    fn validators(&self) -> Result<Vec<TendermintAddr>>;
+
+    /// The nonce of an EVM account, ie. the one of its next transaction.
+    fn evm_nonce(&mut self, address: H160) -> Result<U256>;
+
+    /// Send the transactions and wait until they are all committed,
+    /// fail if any of them is rejected.
+    fn deliver(&mut self, txs: &[Vec<u8>]) -> Result<()>;
+
+    /// Send the transactions at `rate` per second and measure them,
+    /// a `rate` of 0 sends them as fast as possible.
+    fn replay(&mut self, txs: &[Vec<u8>], rate: u64) -> Result<Samples>;
+}
+
+/// The findora app driven in-process by a [`TestKit`](crate::TestKit).
+///
+/// There is no mempool, the transactions are packed
+/// into blocks of `block_txs` and each block is committed at once,
+/// so the latency of a transaction is the time to process its block.
+pub struct InProcess {
+    kit: TestKit,
+    root: XfrKeyPair,
+    validators: Vec<Validator>,
+    block_txs: usize,
+}
+
+impl InProcess {
+    /// Start a node with FRA issued to a key derived from `seed`,
+    /// and a few validators.
+    pub fn new(seed: u64, block_txs: usize) -> Result<Self> {
+        let mut kit = TestKit::new().c(d!())?;
+        let root = XfrKeyPair::generate(&mut ChaChaRng::seed_from_u64(!seed));
+        let validators = (0..4)
+            .map(|_| test_validator(STAKING_VALIDATOR_MIN_POWER))
+            .collect::<Vec<_>>();
+
+        let block = kit
+            .block()
+            .tx(&fra_genesis(&root))
+            .tx(&update_validators(validators.clone()).c(d!())?)
+            .commit();
+        check(&block).c(d!())?;
+        kit.set_voters(&validators);
+        // let the validators take effect
+        kit.block().commit();
+
+        Ok(InProcess {
+            kit,
+            root,
+            validators,
+            block_txs: block_txs.max(1),
+        })
+    }
+}
+
+impl Node for InProcess {
+    fn name(&self) -> &'static str {
+        "in-process"
+    }
+
+    fn root(&self) -> &XfrKeyPair {
+        &self.root
+    }
+
+    fn tx_builder(&self) -> Result<TransactionBuilder> {
+        let seq_id = self.kit.ledger().read().get_block_commit_count();
+        Ok(TransactionBuilder::from_seq_id(seq_id))
+    }
+
+    fn owned_utxos(
+        &self,
+        pk: &XfrPublicKey,
+    ) -> Result<HashMap<TxoSID, (Utxo, Option<OwnerMemo>)>> {
+        self.kit
+            .ledger()
+            .read()
+            .get_owned_utxos(pk)
+            .c(d!())
+            .map(|utxos| utxos.into_iter().collect())
+    }
+
+    fn validators(&self) -> Result<Vec<TendermintAddr>> {
+        Ok(self
+            .validators
+            .iter()
+            .map(|v| td_addr_to_string(&v.td_addr))
+            .collect())
+    }
+
+    fn evm_nonce(&mut self, address: H160) -> Result<U256> {
+        let data = serde_json::to_vec(&Address::from(address)).c(d!())?;
+        let resp = self.kit.query("module/account/nonce", data);
+        if 0 != resp.code {
+            return Err(eg!(resp.log));
+        }
+        serde_json::from_slice(&resp.value).c(d!())
+    }
+
+    fn deliver(&mut self, txs: &[Vec<u8>]) -> Result<()> {
+        for chunk in txs.chunks(self.block_txs) {
+            let block = chunk
+                .iter()
+                .fold(self.kit.block(), |b, tx| b.raw_tx(tx.clone()))
+                .commit();
+            check(&block).c(d!())?;
+        }
+        Ok(())
+    }
+
+    fn replay(&mut self, txs: &[Vec<u8>], _rate: u64) -> Result<Samples> {
+        let mut samples = Samples {
+            submitted: txs.len(),
+            ..Default::default()
+        };
+        let mut callbacks = CallbackSamples::default();
+
+        for chunk in txs.chunks(self.block_txs) {
+            let ts = Instant::now();
+            let block = chunk
+                .iter()
+                .fold(self.kit.block(), |b, tx| b.raw_tx(tx.clone()))
+                .commit();
+            let elapsed = ts.elapsed();
+
+            samples.duration += elapsed;
+            block.deliver.iter().for_each(|r| {
+                if 0 == r.code {
+                    samples.latencies.push(elapsed);
+                } else {
+                    samples.failed += 1;
+                }
+            });
+            callbacks.add(&block);
+        }
+
+        samples.callbacks = Some(callbacks);
+        Ok(samples)
+    }
+}
+
+fn check(block: &Block) -> Result<()> {
+    block
+        .deliver
+        .iter()
+        .find(|r| 0 != r.code)
+        .map_or(Ok(()), |r| {
+            Err(eg!(format!("block {}: {}", block.height, r.log)))
+        })
+}
+
+/// A running node, eg. one of a `fn dev` cluster,
+/// reached through its tendermint RPC and its query server.
+pub struct Remote {
+    tendermint_rpc: String,
+    query_rpc: String,
+    root: XfrKeyPair,
+    validators: Vec<TendermintAddr>,
+}
+
+impl Remote {
+    /// Wait at most this long for a sent transaction to be committed.
+    pub const COMMIT_TIMEOUT: Duration = Duration::from_secs(60);
+
+    /// `tendermint_rpc` is like "http://127.0.0.1:26657",
+    /// `query_rpc` is like "http://127.0.0.1:8668",
+    /// the validators are queried from tendermint if none is given.
+    pub fn new(
+        tendermint_rpc: &str,
+        query_rpc: &str,
+        root_sk: &str,
+        validators: Vec<TendermintAddr>,
+    ) -> Result<Self> {
+        let root = wallet::restore_keypair_from_seckey_base64(root_sk).c(d!())?;
+        Ok(Remote {
+            tendermint_rpc: tendermint_rpc.trim_end_matches('/').to_owned(),
+            query_rpc: query_rpc.trim_end_matches('/').to_owned(),
+            root,
+            validators,
+        })
+    }
+
+    // `broadcast_tx_sync` returns after `check_tx`,
+    // `broadcast_tx_commit` after the transaction is committed.
+    fn broadcast(&self, method: &str, tx: &[u8]) -> Result<Value> {
+        let url = format!(
+            "{}/{}?tx=0x{}",
+            &self.tendermint_rpc,
+            method,
+            hex::encode(tx)
+        );
+        let resp = get(url).c(d!())?;
+        if let Some(e) = resp.get("error") {
+            return Err(eg!(e.to_string()));
+        }
+        Ok(resp["result"].clone())
+    }
+
+    fn height(&self) -> Result<u64> {
+        let url = format!("{}/status", &self.tendermint_rpc);
+        get(url).c(d!()).and_then(|v| {
+            v["result"]["sync_info"]["latest_block_height"]
+                .as_str()
+                .and_then(|h| h.parse::<u64>().ok())
+                .c(d!())
+        })
+    }
+}
+
+impl Node for Remote {
+    fn name(&self) -> &'static str {
+        "remote"
+    }
+
+    fn root(&self) -> &XfrKeyPair {
+        &self.root
+    }
+
+    fn tx_builder(&self) -> Result<TransactionBuilder> {
+        let url = format!("{}/global_state", &self.query_rpc);
+        get(url)
+            .c(d!())
+            .and_then(|v| v[1].as_u64().c(d!("invalid global state")))
+            .map(TransactionBuilder::from_seq_id)
+    }
+
+    fn owned_utxos(
+        &self,
+        pk: &XfrPublicKey,
+    ) -> Result<HashMap<TxoSID, (Utxo, Option<OwnerMemo>)>> {
+        let url = format!(
+            "{}/owned_utxos/{}",
+            &self.query_rpc,
+            wallet::public_key_to_base64(pk)
+        );
+        get(url)
+            .c(d!())
+            .and_then(|v| serde_json::from_value(v).c(d!()))
+    }
+
+    fn validators(&self) -> Result<Vec<TendermintAddr>> {
+        if !self.validators.is_empty() {
+            return Ok(self.validators.clone());
+        }
+        let url = format!("{}/validators", &self.tendermint_rpc);
+        let resp = get(url).c(d!())?;
+        resp["result"]["validators"]
+            .as_array()
+            .c(d!("invalid validator list"))?
+            .iter()
+            .map(|v| {
+                v["address"]
+                    .as_str()
+                    .map(|s| s.to_owned())
+                    .c(d!("invalid validator address"))
+            })
+            .collect()
+    }
+
+    fn evm_nonce(&mut self, address: H160) -> Result<U256> {
+        let data = serde_json::to_vec(&Address::from(address)).c(d!())?;
+        let url = format!(
+            "{}/abci_query?path=%22module/account/nonce%22&data=0x{}",
+            &self.tendermint_rpc,
+            hex::encode(data)
+        );
+        let resp = get(url).c(d!())?;
+        if let Some(e) = resp.get("error") {
+            return Err(eg!(e.to_string()));
+        }
+        let resp = &resp["result"]["response"];
+        if 0 != resp["code"].as_u64().unwrap_or(0) {
+            return Err(eg!(resp["log"].to_string()));
+        }
+        resp["value"]
+            .as_str()
+            .c(d!("invalid nonce"))
+            .and_then(|v| base64::decode(v).c(d!()))
+            .and_then(|v| serde_json::from_slice(&v).c(d!()))
+    }
+
+    fn deliver(&mut self, txs: &[Vec<u8>]) -> Result<()> {
+        let (last, txs) = match txs.split_last() {
+            Some(x) => x,
+            None => return Ok(()),
+        };
+        for tx in txs {
+            let resp = self.broadcast("broadcast_tx_sync", tx).c(d!())?;
+            if 0 != resp["code"].as_u64().unwrap_or(0) {
+                return Err(eg!(resp["log"].to_string()));
+            }
+        }
+        let resp = self.broadcast("broadcast_tx_commit", last).c(d!())?;
+        for r in [&resp["check_tx"], &resp["deliver_tx"]] {
+            if 0 != r["code"].as_u64().unwrap_or(0) {
+                return Err(eg!(r["log"].to_string()));
+            }
+        }
+        Ok(())
+    }
+
+    fn replay(&mut self, txs: &[Vec<u8>], rate: u64) -> Result<Samples> {
+        let sent = Arc::new(Mutex::new(HashMap::<String, Instant>::new()));
+        let committed = Arc::new(Mutex::new(vec![]));
+        let done = Arc::new(AtomicBool::new(false));
+
+        // watch the new blocks while sending
+        let mut height = self.height().c(d!())? + 1;
+        let watcher = {
+            let tendermint_rpc = self.tendermint_rpc.clone();
+            let sent = Arc::clone(&sent);
+            let committed = Arc::clone(&committed);
+            let done = Arc::clone(&done);
+            thread::spawn(move || {
+                let mut last_seen = Instant::now();
+                loop {
+                    if let Some(txs) = block_txs(&tendermint_rpc, height) {
+                        let now = Instant::now();
+                        let mut sent = sent.lock().unwrap();
+                        let mut committed = committed.lock().unwrap();
+                        txs.iter().for_each(|tx| {
+                            if let Some(ts) = sent.remove(tx) {
+                                committed.push((ts, now));
+                            }
+                        });
+                        height += 1;
+                        last_seen = now;
+                    } else {
+                        let finished = done.load(Ordering::Acquire)
+                            && (sent.lock().unwrap().is_empty()
+                                || Remote::COMMIT_TIMEOUT < last_seen.elapsed());
+                        if finished {
+                            break;
+                        }
+                        thread::sleep(Duration::from_millis(100));
+                    }
+                }
+            })
+        };
+
+        let mut samples = Samples {
+            submitted: txs.len(),
+            ..Default::default()
+        };
+        let start = Instant::now();
+        for (i, tx) in txs.iter().enumerate() {
+            if 0 < rate {
+                let due = start + Duration::from_secs_f64(i as f64 / rate as f64);
+                if let Some(d) = due.checked_duration_since(Instant::now()) {
+                    thread::sleep(d);
+                }
+            }
+            let key = base64::encode(tx);
+            sent.lock().unwrap().insert(key.clone(), Instant::now());
+            let ok = self
+                .broadcast("broadcast_tx_sync", tx)
+                .map(|r| 0 == r["code"].as_u64().unwrap_or(0))
+                .unwrap_or(false);
+            if !ok {
+                sent.lock().unwrap().remove(&key);
+                samples.failed += 1;
+            }
+        }
+        done.store(true, Ordering::Release);
+        watcher.join().map_err(|_| eg!("block watcher panicked"))?;
+
+        let committed = committed.lock().unwrap();
+        samples.latencies = committed.iter().map(|(ts, te)| *te - *ts).collect();
+        samples.duration = committed
+            .iter()
+            .map(|(_, te)| *te - start)
+            .max()
+            .unwrap_or_default();
+        // sent but never committed
+        samples.failed += sent.lock().unwrap().len();
+
+        Ok(samples)
+    }
+}
+
+fn get(url: String) -> Result<Value> {
+    attohttpc::get(&url)
+        .send()
+        .c(d!(url))?
+        .error_for_status()
+        .c(d!())?
+        .bytes()
+        .c(d!())
+        .and_then(|b| serde_json::from_slice::<Value>(&b).c(d!()))
+}
+
+// The transactions of a block, in base64, `None` if not committed yet.
+fn block_txs(tendermint_rpc: &str, height: u64) -> Option<Vec<String>> {
+    let url = format!("{tendermint_rpc}/block?height={height}");
+    let resp = get(url).ok()?;
+    if resp.get("error").is_some() {
+        return None;
+    }
+    let txs = resp["result"]["block"]["data"]["txs"]
+        .as_array()
+        .map(|txs| {
+            txs.iter()
+                .filter_map(|tx| tx.as_str().map(|s| s.to_owned()))
+                .collect()
+        })
+        .unwrap_or_default();
+    Some(txs)
+}
@@ -0,0 +1,114 @@
+//!
+//! The JSON report of a benchmark.
+//!
+
+use {
+    super::{node::Samples, BenchCfg, Workload},
+    serde::Serialize,
+    std::{collections::BTreeMap, time::Duration},
+};
+
+/// Distribution of durations, in milliseconds.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct Percentiles {
+    #[allow(missing_docs)]
+    pub p50: f64,
+    #[allow(missing_docs)]
+    pub p90: f64,
+    #[allow(missing_docs)]
+    pub p99: f64,
+    #[allow(missing_docs)]
+    pub max: f64,
+    #[allow(missing_docs)]
+    pub mean: f64,
+}
+
+impl Percentiles {
+    #[allow(missing_docs)]
+    pub fn new(samples: &[Duration]) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+
+        let mut ms = samples
+            .iter()
+            .map(|d| d.as_secs_f64() * 1000.0)
+            .collect::<Vec<_>>();
+        ms.sort_by(|a, b| a.total_cmp(b));
+
+        let at = |p: usize| ms[(ms.len() * p / 100).min(ms.len() - 1)];
+        Percentiles {
+            p50: at(50),
+            p90: at(90),
+            p99: at(99),
+            max: ms[ms.len() - 1],
+            mean: ms.iter().sum::<f64>() / ms.len() as f64,
+        }
+    }
+}
+
+/// Results of a benchmark.
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    /// "in-process" or "remote"
+    pub target: String,
+    #[allow(missing_docs)]
+    pub seed: u64,
+    /// transactions per second sent, 0 for no limit
+    pub rate: u64,
+    /// number of transactions of each kind
+    pub workload: BTreeMap<&'static str, usize>,
+    #[allow(missing_docs)]
+    pub submitted: usize,
+    #[allow(missing_docs)]
+    pub committed: usize,
+    #[allow(missing_docs)]
+    pub failed: usize,
+    /// from the first transaction sent to the last one committed
+    pub duration_ms: f64,
+    /// committed transactions per second
+    pub tps: f64,
+    #[allow(missing_docs)]
+    pub commit_latency_ms: Percentiles,
+    /// only known when the app runs in-process
+    pub abci_callbacks_ms: Option<BTreeMap<&'static str, Percentiles>>,
+}
+
+impl Report {
+    #[allow(missing_docs)]
+    pub fn new(
+        cfg: &BenchCfg,
+        target: &str,
+        workload: &Workload,
+        samples: Samples,
+    ) -> Self {
+        let secs = samples.duration.as_secs_f64();
+        let committed = samples.latencies.len();
+        Report {
+            target: target.to_owned(),
+            seed: cfg.seed,
+            rate: cfg.rate,
+            workload: workload.counts(),
+            submitted: samples.submitted,
+            committed,
+            failed: samples.failed,
+            duration_ms: secs * 1000.0,
+            tps: if 0.0 < secs {
+                committed as f64 / secs
+            } else {
+                0.0
+            },
+            commit_latency_ms: Percentiles::new(&samples.latencies),
+            abci_callbacks_ms: samples.callbacks.map(|c| {
+                [
+                    ("begin_block", Percentiles::new(&c.begin_block)),
+                    ("deliver_tx", Percentiles::new(&c.deliver_tx)),
+                    ("end_block", Percentiles::new(&c.end_block)),
+                    ("commit", Percentiles::new(&c.commit)),
+                ]
+                .into_iter()
+                .collect()
+            }),
+        }
+    }
+}
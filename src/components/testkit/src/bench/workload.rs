@@ -0,0 +1,368 @@
+//!
+//! Deterministic workload generation.
+//!
+
+use {
+    super::{BenchCfg, Node},
+    crate::{evm_tx_bytes, tx_bytes, txs::EVM_GAS_LIMIT},
+    baseapp::{BaseApp, ChainId},
+    ethabi::Token,
+    ethereum::{TransactionAction, TransactionV0},
+    finutils::common::utils::gen_transfer_op_with_utxos,
+    fp_mocks::UnsignedTransaction,
+    fp_traits::evm::FeeCalculator,
+    fp_types::{crypto::MultiSigner, H160, H256, U256},
+    fp_utils::{ecdsa::SecpPair, hashing::keccak_256},
+    ledger::{
+        data_model::{Operation, BLACK_HOLE_PUBKEY_STAKING},
+        staking::FRA,
+    },
+    rand_chacha::ChaChaRng,
+    rand_core::{RngCore, SeedableRng},
+    rlp::RlpStream,
+    ruc::*,
+    std::collections::BTreeMap,
+    zei::xfr::{
+        asset_record::AssetRecordType,
+        sig::{XfrKeyPair, XfrPublicKey},
+    },
+};
+
+// the contract of `contracts/modules/evm/tests`
+const ERC20_CODE: &str =
+    include_str!("../../../contracts/modules/evm/tests/contracts/abi/ERC20.bin");
+
+// FRA sent to each generated key
+const XFR_FUND: u64 = 10 * FRA;
+const TRANSFER_AMOUNT: u64 = FRA;
+const DELEGATION_AMOUNT: u64 = FRA;
+
+// wei sent to each generated EVM account,
+// enough for the gas of a mint and a transfer
+const EVM_FUND: u64 = 10_u64.pow(16);
+const ERC20_GAS_LIMIT: u64 = 200_000;
+const ERC20_DEPLOY_GAS_LIMIT: u64 = 5_000_000;
+
+// outputs of a funding transaction
+const FUND_CHUNK: usize = 100;
+
+/// The kinds of the generated transactions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TxKind {
+    #[allow(missing_docs)]
+    Transfer,
+    #[allow(missing_docs)]
+    Delegation,
+    #[allow(missing_docs)]
+    Erc20,
+}
+
+impl TxKind {
+    /// The name used in the report.
+    pub fn name(self) -> &'static str {
+        match self {
+            TxKind::Transfer => "transfer",
+            TxKind::Delegation => "delegation",
+            TxKind::Erc20 => "erc20",
+        }
+    }
+}
+
+/// Signed transactions ready to be replayed.
+pub struct Workload {
+    txs: Vec<(TxKind, Vec<u8>)>,
+}
+
+impl Workload {
+    /// Fund the generated keys on `node`, then build the transactions.
+    pub fn prepare(cfg: &BenchCfg, node: &mut dyn Node) -> Result<Self> {
+        let mut rng = ChaChaRng::seed_from_u64(cfg.seed);
+        let xfr_keys = (0..cfg.transfers + cfg.delegations)
+            .map(|_| XfrKeyPair::generate(&mut rng))
+            .collect::<Vec<_>>();
+        let funder = EvmKey::generate(&mut rng);
+        let evm_keys = (0..cfg.erc20_calls)
+            .map(|_| EvmKey::generate(&mut rng))
+            .collect::<Vec<_>>();
+
+        fund_xfr_keys(node, &xfr_keys).c(d!())?;
+        let erc20 = if evm_keys.is_empty() {
+            H160::zero()
+        } else {
+            setup_erc20(node, &funder, &evm_keys).c(d!())?
+        };
+
+        let (transfer_keys, delegation_keys) = xfr_keys.split_at(cfg.transfers);
+
+        let transfers = transfer_keys
+            .iter()
+            .enumerate()
+            .map(|(i, kp)| {
+                let to = transfer_keys[(i + 1) % transfer_keys.len()].get_pk_ref();
+                transfer_tx(node, kp, vec![(to, TRANSFER_AMOUNT)]).c(d!())
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let validators = alt!(
+            delegation_keys.is_empty(),
+            vec![],
+            node.validators().c(d!())?
+        );
+        if !delegation_keys.is_empty() && validators.is_empty() {
+            return Err(eg!("no validator to delegate to"));
+        }
+        let delegations = delegation_keys
+            .iter()
+            .enumerate()
+            .map(|(i, kp)| {
+                delegation_tx(node, kp, &validators[i % validators.len()]).c(d!())
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let erc20_calls = evm_keys
+            .iter()
+            .enumerate()
+            .map(|(i, k)| {
+                let nonce = node.evm_nonce(k.address).c(d!())?;
+                let to = evm_keys[(i + 1) % evm_keys.len()].address;
+                let input = erc20_input("transfer(address,uint256)", to);
+                Ok(evm_tx_bytes(&k.sign(erc20, nonce, input)))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // interleave the kinds, as the users of a real network do
+        let mut queues = vec![
+            (TxKind::Transfer, transfers.into_iter()),
+            (TxKind::Delegation, delegations.into_iter()),
+            (TxKind::Erc20, erc20_calls.into_iter()),
+        ];
+        let mut txs = vec![];
+        loop {
+            let n = txs.len();
+            for (kind, q) in queues.iter_mut() {
+                if let Some(tx) = q.next() {
+                    txs.push((*kind, tx));
+                }
+            }
+            if n == txs.len() {
+                break;
+            }
+        }
+
+        Ok(Workload { txs })
+    }
+
+    /// The transactions, in the order of replay.
+    pub fn txs(&self) -> Vec<Vec<u8>> {
+        self.txs.iter().map(|(_, tx)| tx.clone()).collect()
+    }
+
+    /// Number of transactions of each kind.
+    pub fn counts(&self) -> BTreeMap<&'static str, usize> {
+        let mut counts = BTreeMap::new();
+        self.txs.iter().for_each(|(kind, _)| {
+            *counts.entry(kind.name()).or_insert(0) += 1;
+        });
+        counts
+    }
+}
+
+// Send `XFR_FUND` to each key, one block per `FUND_CHUNK` keys.
+fn fund_xfr_keys(node: &mut dyn Node, keys: &[XfrKeyPair]) -> Result<()> {
+    for chunk in keys.chunks(FUND_CHUNK) {
+        let targets = chunk.iter().map(|kp| (kp.get_pk_ref(), XFR_FUND)).collect();
+        let tx = transfer_tx(node, node.root(), targets).c(d!())?;
+        node.deliver(&[tx]).c(d!())?;
+    }
+    Ok(())
+}
+
+// Fund the EVM accounts through `funder`, deploy the ERC-20 contract
+// and mint some tokens to each account, return the address of the contract.
+fn setup_erc20(node: &mut dyn Node, funder: &EvmKey, keys: &[EvmKey]) -> Result<H160> {
+    let wei_per_fra = U256::from(10_u64.pow(12));
+    let gas = U256::from(ERC20_DEPLOY_GAS_LIMIT) * min_gas_price();
+    let needed = U256::from(EVM_FUND) * U256::from(keys.len()) + gas;
+    let amount = (needed / wei_per_fra).as_u64() + FRA;
+
+    let tx = convert_tx(node, node.root(), funder.address, amount).c(d!())?;
+    node.deliver(&[tx]).c(d!())?;
+
+    // the keys may have been used before, eg. on a remote node with the same seed
+    let nonce = node.evm_nonce(funder.address).c(d!())?;
+
+    let mut input = hex::decode(ERC20_CODE.trim()).c(d!())?;
+    input.extend(ethabi::encode(&[
+        Token::String("bench".to_owned()),
+        Token::String("BENCH".to_owned()),
+    ]));
+    let deploy = funder.sign_with(
+        TransactionAction::Create,
+        nonce,
+        U256::zero(),
+        ERC20_DEPLOY_GAS_LIMIT,
+        input,
+    );
+    let contract = create_address(funder.address, nonce);
+
+    let mut txs = vec![evm_tx_bytes(&deploy)];
+    txs.extend(keys.iter().enumerate().map(|(i, k)| {
+        evm_tx_bytes(&funder.sign_with(
+            TransactionAction::Call(k.address),
+            nonce + U256::from(i + 1),
+            U256::from(EVM_FUND),
+            EVM_GAS_LIMIT,
+            vec![],
+        ))
+    }));
+    node.deliver(&txs).c(d!())?;
+
+    let mints = keys
+        .iter()
+        .map(|k| {
+            let nonce = node.evm_nonce(k.address).c(d!())?;
+            let input = erc20_input("mint(address,uint256)", k.address);
+            Ok(evm_tx_bytes(&k.sign(contract, nonce, input)))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    node.deliver(&mints).c(d!())?;
+
+    Ok(contract)
+}
+
+fn transfer_tx(
+    node: &dyn Node,
+    kp: &XfrKeyPair,
+    targets: Vec<(&XfrPublicKey, u64)>,
+) -> Result<Vec<u8>> {
+    let op = transfer_op(node, kp, targets).c(d!())?;
+    let mut builder = node.tx_builder().c(d!())?;
+    builder.add_operation(op).sign(kp);
+    Ok(tx_bytes(&builder.take_transaction()))
+}
+
+// Same as `fn account --to-evm`.
+fn convert_tx(
+    node: &dyn Node,
+    kp: &XfrKeyPair,
+    to: H160,
+    amount: u64,
+) -> Result<Vec<u8>> {
+    let op =
+        transfer_op(node, kp, vec![(&BLACK_HOLE_PUBKEY_STAKING, amount)]).c(d!())?;
+    let mut builder = node.tx_builder().c(d!())?;
+    builder
+        .add_operation(op)
+        .add_operation_convert_account(kp, MultiSigner::Ethereum(to), amount)
+        .c(d!())?
+        .sign(kp);
+    let mut tx = builder.take_transaction();
+    tx.sign_to_map(kp);
+    Ok(tx_bytes(&tx))
+}
+
+// Same as `fn delegate`.
+fn delegation_tx(node: &dyn Node, kp: &XfrKeyPair, validator: &str) -> Result<Vec<u8>> {
+    let op = transfer_op(
+        node,
+        kp,
+        vec![(&BLACK_HOLE_PUBKEY_STAKING, DELEGATION_AMOUNT)],
+    )
+    .c(d!())?;
+    let mut builder = node.tx_builder().c(d!())?;
+    builder.add_operation(op).add_operation_delegation(
+        kp,
+        DELEGATION_AMOUNT,
+        validator.to_owned(),
+    );
+    let mut tx = builder.take_transaction();
+    tx.sign_to_map(kp);
+    Ok(tx_bytes(&tx))
+}
+
+fn transfer_op(
+    node: &dyn Node,
+    kp: &XfrKeyPair,
+    targets: Vec<(&XfrPublicKey, u64)>,
+) -> Result<Operation> {
+    let utxos = node.owned_utxos(kp.get_pk_ref()).c(d!())?;
+    gen_transfer_op_with_utxos(
+        utxos,
+        kp,
+        targets,
+        None,
+        true,
+        false,
+        false,
+        Some(AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType),
+    )
+    .c(d!())
+}
+
+// `transfer` and `mint` of the ERC-20 contract, always one token.
+fn erc20_input(signature: &str, to: H160) -> Vec<u8> {
+    let mut input = keccak_256(signature.as_bytes())[..4].to_vec();
+    input.extend(ethabi::encode(&[
+        Token::Address(to),
+        Token::Uint(U256::one()),
+    ]));
+    input
+}
+
+// The address of a contract created by `sender`,
+// the last 20 bytes of `keccak(rlp([sender, nonce]))`.
+fn create_address(sender: H160, nonce: U256) -> H160 {
+    let mut rlp = RlpStream::new_list(2);
+    rlp.append(&sender);
+    rlp.append(&nonce);
+    H160::from_slice(&keccak_256(&rlp.out())[12..])
+}
+
+fn min_gas_price() -> U256 {
+    <BaseApp as module_ethereum::Config>::FeeCalculator::min_gas_price()
+}
+
+struct EvmKey {
+    address: H160,
+    private_key: H256,
+}
+
+impl EvmKey {
+    fn generate(rng: &mut ChaChaRng) -> Self {
+        let mut seed = [0; 32];
+        rng.fill_bytes(&mut seed);
+        EvmKey {
+            address: SecpPair::from_seed(&seed).address(),
+            private_key: H256::from(seed),
+        }
+    }
+
+    fn sign(&self, contract: H160, nonce: U256, input: Vec<u8>) -> TransactionV0 {
+        self.sign_with(
+            TransactionAction::Call(contract),
+            nonce,
+            U256::zero(),
+            ERC20_GAS_LIMIT,
+            input,
+        )
+    }
+
+    fn sign_with(
+        &self,
+        action: TransactionAction,
+        nonce: U256,
+        value: U256,
+        gas_limit: u64,
+        input: Vec<u8>,
+    ) -> TransactionV0 {
+        UnsignedTransaction {
+            nonce,
+            gas_price: min_gas_price(),
+            gas_limit: U256::from(gas_limit),
+            action,
+            value,
+            input,
+        }
+        .sign(&self.private_key, ChainId::get())
+    }
+}
@@ -0,0 +1,98 @@
+//!
+//! # findora-bench
+//!
+//! Replay a generated workload and print a JSON report.
+//!
+//! ```shell
+//! # in-process, without tendermint
+//! findora-bench --transfers=10000 --delegations=1000 --erc20=10000 --block-txs=2000
+//!
+//! # against a node, eg. of a `fn dev` cluster
+//! findora-bench --tendermint-rpc=http://127.0.0.1:26657 \
+//!     --query-rpc=http://127.0.0.1:8668 --rate=500
+//! ```
+//!
+
+#![deny(warnings)]
+
+use {
+    clap::{crate_authors, App},
+    ruc::*,
+    std::fs,
+    testkit::bench::{self, BenchCfg, InProcess, Node, Remote},
+};
+
+// the bank account of `fn dev`
+const DEV_BANK_SECKEY: &str = "Ew9fMaryTL44ZXnEhcF7hQ-AB-fxgaC8vyCH-hCGtzg=";
+
+fn main() {
+    pnk!(run());
+}
+
+fn run() -> Result<()> {
+    let m = App::new("findora-bench")
+        .author(crate_authors!())
+        .about("Replay a generated workload and report the throughput as JSON")
+        .arg_from_usage("-s, --seed=[Seed] 'seed of the generated keys, default to 0'")
+        .arg_from_usage("--transfers=[N] 'number of UTXO transfers, default to 1000'")
+        .arg_from_usage("--delegations=[N] 'number of delegations, default to 100'")
+        .arg_from_usage("--erc20=[N] 'number of ERC-20 transfers, default to 1000'")
+        .arg_from_usage("-r, --rate=[TPS] 'transactions sent per second to a remote node, default to no limit'")
+        .arg_from_usage("--block-txs=[N] 'transactions per block when running in-process, default to 1000'")
+        .arg_from_usage("--tendermint-rpc=[URL] 'benchmark a running node instead of an in-process app'")
+        .arg_from_usage("--query-rpc=[URL] 'query server of the running node, default to http://127.0.0.1:8668'")
+        .arg_from_usage("--root-seckey=[SecKey] 'base64 secret key funding the workload, default to the bank of `fn dev`'")
+        .arg_from_usage("--validator=[TendermintAddr]... 'validators to delegate to, default to all'")
+        .arg_from_usage("-o, --output=[Path] 'write the report to this file instead of stdout'")
+        .get_matches();
+
+    let num = |name: &str| -> Result<Option<u64>> {
+        m.value_of(name)
+            .map(|n| n.parse::<u64>().c(d!(name)))
+            .transpose()
+    };
+
+    let default = BenchCfg::default();
+    let cfg = BenchCfg {
+        seed: num("seed").c(d!())?.unwrap_or(default.seed),
+        transfers: num("transfers")
+            .c(d!())?
+            .map_or(default.transfers, |n| n as usize),
+        delegations: num("delegations")
+            .c(d!())?
+            .map_or(default.delegations, |n| n as usize),
+        erc20_calls: num("erc20")
+            .c(d!())?
+            .map_or(default.erc20_calls, |n| n as usize),
+        rate: num("rate").c(d!())?.unwrap_or(default.rate),
+    };
+
+    let mut node: Box<dyn Node> = if let Some(url) = m.value_of("tendermint-rpc") {
+        let validators = m
+            .values_of("validator")
+            .map(|v| v.map(|s| s.to_owned()).collect())
+            .unwrap_or_default();
+        Box::new(
+            Remote::new(
+                url,
+                m.value_of("query-rpc").unwrap_or("http://127.0.0.1:8668"),
+                m.value_of("root-seckey").unwrap_or(DEV_BANK_SECKEY),
+                validators,
+            )
+            .c(d!())?,
+        )
+    } else {
+        let block_txs = num("block-txs").c(d!())?.unwrap_or(1000) as usize;
+        Box::new(InProcess::new(cfg.seed, block_txs).c(d!())?)
+    };
+
+    let report = bench::run(&cfg, node.as_mut()).c(d!())?;
+    let report = serde_json::to_string_pretty(&report).c(d!())?;
+
+    if let Some(path) = m.value_of("output") {
+        fs::write(path, report).c(d!())
+    } else {
+        println!("{report}");
+        Ok(())
+    }
+}
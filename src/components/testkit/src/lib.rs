@@ -0,0 +1,372 @@
+//!
+//! # In-process ABCI test harness
+//!
+//! Drives the findora ABCI application through full blocks,
+//! `begin_block` -> `deliver_tx`* -> `end_block` -> `commit`,
+//! with synthetic headers, validator votes and byzantine evidence,
+//! so that cross-module behaviors can be tested without tendermint.
+//!
+//! The ABCI callbacks and the storages keep some process-wide states,
+//! so only one `TestKit` can be started in a process,
+//! ie. one per test binary, a file under `tests/`.
+//!
+
+#![deny(warnings)]
+#![deny(missing_docs)]
+
+pub mod bench;
+pub mod txs;
+
+use {
+    abci::{
+        Application, Evidence, Header, LastCommitInfo, RequestBeginBlock,
+        RequestCheckTx, RequestCommit, RequestDeliverTx, RequestEndBlock,
+        RequestInitChain, RequestQuery, ResponseCheckTx, ResponseDeliverTx,
+        ResponseEndBlock, ResponseQuery, Validator as AbciValidator, VoteInfo,
+    },
+    abciapp::{
+        abci::ABCISubmissionServer, api::query_server::query_api::server::QueryServer,
+    },
+    ethereum::TransactionV0,
+    fp_types::{
+        actions::{ethereum::Action as EthereumAction, Action},
+        assemble::UncheckedTransaction,
+    },
+    fp_utils::tx::EvmRawTxWrapper,
+    ledger::{
+        data_model::Transaction,
+        staking::{td_addr_to_string, Validator},
+        store::LedgerState,
+    },
+    parking_lot::RwLock,
+    protobuf::{well_known_types::Timestamp, RepeatedField},
+    ruc::*,
+    std::{
+        collections::BTreeSet,
+        env,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        time::{Duration, Instant},
+    },
+};
+
+static STARTED: AtomicBool = AtomicBool::new(false);
+
+/// The chain id of the synthetic headers.
+pub const CHAIN_ID: &str = "findora-testkit";
+
+/// Seconds between two blocks.
+pub const BLOCK_INTERVAL: i64 = 16;
+
+/// A findora node driven in-process.
+pub struct TestKit {
+    app: ABCISubmissionServer,
+    height: i64,
+    time: i64,
+    validators: Vec<(Vec<u8>, i64)>,
+    app_hash: Vec<u8>,
+}
+
+impl TestKit {
+    /// Start a node with its data in a fresh temporary directory,
+    /// fails if a node has been started in this process.
+    pub fn new() -> Result<Self> {
+        if STARTED.swap(true, Ordering::SeqCst) {
+            return Err(eg!("only one TestKit can be started in a process"));
+        }
+
+        let dir = globutils::fresh_tmp_dir();
+        let dir = dir.to_str().c(d!())?;
+        env::set_var("LEDGER_DIR", dir);
+        env::set_var("BNC_DATA_DIR", format!("{dir}/__bnc__"));
+
+        let mut app =
+            ABCISubmissionServer::new(Some(dir), "http://127.0.0.1:26657".to_owned())
+                .c(d!())?;
+
+        let mut req = RequestInitChain::new();
+        req.set_chain_id(CHAIN_ID.to_owned());
+        app.init_chain(&req);
+
+        Ok(TestKit {
+            app,
+            height: 0,
+            time: 1_600_000_000,
+            validators: vec![],
+            app_hash: vec![],
+        })
+    }
+
+    /// The height of the last committed block.
+    pub fn height(&self) -> i64 {
+        self.height
+    }
+
+    /// The app hash returned by the last commit.
+    pub fn app_hash(&self) -> &[u8] {
+        &self.app_hash
+    }
+
+    /// The ABCI application.
+    pub fn app(&mut self) -> &mut ABCISubmissionServer {
+        &mut self.app
+    }
+
+    /// The committed ledger state.
+    pub fn ledger(&self) -> Arc<RwLock<LedgerState>> {
+        self.app.la.read().borrowable_ledger_state()
+    }
+
+    /// A query server on the committed ledger state, as served by the query API.
+    pub fn query_server(&self) -> QueryServer {
+        QueryServer::new(self.app.la.read().borrowable_ledger_state())
+    }
+
+    /// Query the EVM side, eg. "module/account/nonce".
+    pub fn query(&mut self, path: &str, data: Vec<u8>) -> ResponseQuery {
+        let mut req = RequestQuery::new();
+        req.set_path(path.to_owned());
+        req.set_data(data);
+        self.app.query(&req)
+    }
+
+    /// Set the validators voting for the next blocks,
+    /// the power is the one reported by tendermint.
+    pub fn set_voters(&mut self, validators: &[Validator]) {
+        self.validators = validators
+            .iter()
+            .map(|v| (v.td_addr.clone(), v.td_power as i64))
+            .collect();
+    }
+
+    /// Run `check_tx` on a findora transaction.
+    pub fn check_tx(&mut self, tx: &Transaction) -> ResponseCheckTx {
+        let mut req = RequestCheckTx::new();
+        req.set_tx(tx_bytes(tx));
+        self.app.check_tx(&req)
+    }
+
+    /// Run `check_tx` on an ethereum transaction.
+    pub fn check_evm_tx(&mut self, tx: &TransactionV0) -> ResponseCheckTx {
+        let mut req = RequestCheckTx::new();
+        req.set_tx(evm_tx_bytes(tx));
+        self.app.check_tx(&req)
+    }
+
+    /// Start the next block.
+    pub fn block(&mut self) -> BlockBuilder {
+        BlockBuilder {
+            kit: self,
+            txs: vec![],
+            absent: BTreeSet::new(),
+            evidences: vec![],
+            proposer: None,
+        }
+    }
+}
+
+/// The transactions and the consensus information of a block.
+pub struct BlockBuilder<'a> {
+    kit: &'a mut TestKit,
+    txs: Vec<Vec<u8>>,
+    absent: BTreeSet<Vec<u8>>,
+    evidences: Vec<(Vec<u8>, String)>,
+    proposer: Option<Vec<u8>>,
+}
+
+impl<'a> BlockBuilder<'a> {
+    /// Add a findora transaction.
+    pub fn tx(mut self, tx: &Transaction) -> Self {
+        self.txs.push(tx_bytes(tx));
+        self
+    }
+
+    /// Add an ethereum transaction.
+    pub fn evm_tx(mut self, tx: &TransactionV0) -> Self {
+        self.txs.push(evm_tx_bytes(tx));
+        self
+    }
+
+    /// Add raw transaction bytes, as tendermint hands them to the app.
+    pub fn raw_tx(mut self, tx: Vec<u8>) -> Self {
+        self.txs.push(tx);
+        self
+    }
+
+    /// The validator did not sign the last block.
+    pub fn absent(mut self, td_addr: &[u8]) -> Self {
+        self.absent.insert(td_addr.to_vec());
+        self
+    }
+
+    /// Report a byzantine validator,
+    /// `kind` is one of "DUPLICATE_VOTE", "LIGHT_CLIENT_ATTACK" and "UNKNOWN".
+    pub fn evidence(mut self, td_addr: &[u8], kind: &str) -> Self {
+        self.evidences.push((td_addr.to_vec(), kind.to_owned()));
+        self
+    }
+
+    /// The proposer of the block, the first voter by default.
+    pub fn proposer(mut self, td_addr: &[u8]) -> Self {
+        self.proposer = Some(td_addr.to_vec());
+        self
+    }
+
+    /// Run the block through the app and commit it.
+    pub fn commit(self) -> Block {
+        let kit = self.kit;
+        kit.height += 1;
+        kit.time += BLOCK_INTERVAL;
+
+        let mut time = Timestamp::new();
+        time.set_seconds(kit.time);
+        let mut header = Header::new();
+        header.set_chain_id(CHAIN_ID.to_owned());
+        header.set_height(kit.height);
+        header.set_time(time);
+        header.set_app_hash(kit.app_hash.clone());
+        header.set_proposer_address(
+            self.proposer
+                .or_else(|| kit.validators.first().map(|(addr, _)| addr.clone()))
+                .unwrap_or_default(),
+        );
+
+        let mut req = RequestBeginBlock::new();
+        req.set_hash(kit.height.to_be_bytes().to_vec());
+        req.set_header(header);
+        if kit.height > 1 {
+            let votes = kit
+                .validators
+                .iter()
+                .map(|(addr, power)| {
+                    let mut vote = VoteInfo::new();
+                    vote.set_validator(abci_validator(addr, *power));
+                    vote.set_signed_last_block(!self.absent.contains(addr));
+                    vote
+                })
+                .collect();
+            let mut lci = LastCommitInfo::new();
+            lci.set_votes(RepeatedField::from_vec(votes));
+            req.set_last_commit_info(lci);
+        }
+        let evidences = self
+            .evidences
+            .iter()
+            .map(|(addr, kind)| {
+                let power = kit
+                    .validators
+                    .iter()
+                    .find(|(a, _)| a == addr)
+                    .map_or(0, |(_, p)| *p);
+                let mut ev = Evidence::new();
+                ev.set_field_type(kind.clone());
+                ev.set_validator(abci_validator(addr, power));
+                ev.set_height(kit.height - 1);
+                ev
+            })
+            .collect();
+        req.set_byzantine_validators(RepeatedField::from_vec(evidences));
+
+        let mut timings = Timings::default();
+
+        let ts = Instant::now();
+        kit.app.begin_block(&req);
+        timings.begin_block = ts.elapsed();
+
+        let deliver = self
+            .txs
+            .into_iter()
+            .map(|tx| {
+                let mut req = RequestDeliverTx::new();
+                req.set_tx(tx);
+                let ts = Instant::now();
+                let resp = kit.app.deliver_tx(&req);
+                timings.deliver_tx.push(ts.elapsed());
+                resp
+            })
+            .collect();
+
+        let mut req = RequestEndBlock::new();
+        req.set_height(kit.height);
+        let ts = Instant::now();
+        let end_block = kit.app.end_block(&req);
+        timings.end_block = ts.elapsed();
+
+        let ts = Instant::now();
+        kit.app_hash = kit.app.commit(&RequestCommit::new()).data;
+        timings.commit = ts.elapsed();
+
+        Block {
+            height: kit.height,
+            app_hash: kit.app_hash.clone(),
+            deliver,
+            end_block,
+            timings,
+        }
+    }
+}
+
+/// The responses of a committed block.
+#[derive(Debug)]
+pub struct Block {
+    /// height of the block
+    pub height: i64,
+    /// the app hash after the block
+    pub app_hash: Vec<u8>,
+    /// responses of `deliver_tx`, in the order of the transactions
+    pub deliver: Vec<ResponseDeliverTx>,
+    /// response of `end_block`, with the validator updates
+    pub end_block: ResponseEndBlock,
+    /// time spent in the ABCI callbacks
+    pub timings: Timings,
+}
+
+/// Time spent in the ABCI callbacks of a block.
+#[derive(Debug, Default, Clone)]
+pub struct Timings {
+    #[allow(missing_docs)]
+    pub begin_block: Duration,
+    /// one entry per transaction
+    pub deliver_tx: Vec<Duration>,
+    #[allow(missing_docs)]
+    pub end_block: Duration,
+    #[allow(missing_docs)]
+    pub commit: Duration,
+}
+
+impl Block {
+    /// Panic if a transaction of the block failed.
+    pub fn assert_ok(&self) -> &Self {
+        self.deliver.iter().enumerate().for_each(|(i, r)| {
+            assert_eq!(0, r.code, "tx {} of block {}: {}", i, self.height, r.log);
+        });
+        self
+    }
+}
+
+/// The tendermint address of a validator, in the string format of the staking module.
+pub fn td_addr(v: &Validator) -> String {
+    td_addr_to_string(&v.td_addr)
+}
+
+fn abci_validator(addr: &[u8], power: i64) -> AbciValidator {
+    let mut v = AbciValidator::new();
+    v.set_address(addr.to_vec());
+    v.set_power(power);
+    v
+}
+
+/// The bytes of a findora transaction, as tendermint hands them to the app.
+pub fn tx_bytes(tx: &Transaction) -> Vec<u8> {
+    pnk!(serde_json::to_vec(tx))
+}
+
+/// The bytes of an ethereum transaction, as tendermint hands them to the app.
+pub fn evm_tx_bytes(tx: &TransactionV0) -> Vec<u8> {
+    let tx = UncheckedTransaction::<()>::new_unsigned(Action::Ethereum(
+        EthereumAction::Transact(tx.clone()),
+    ));
+    EvmRawTxWrapper::wrap(&pnk!(serde_json::to_vec(&tx)))
+}
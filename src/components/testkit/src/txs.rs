@@ -0,0 +1,179 @@
+//!
+//! # Transaction helpers
+//!
+//! Build the findora and ethereum transactions of the test blocks,
+//! with the same builders as the `fn` cli.
+//!
+
+use {
+    baseapp::{BaseApp, ChainId},
+    ethereum::{TransactionAction, TransactionV0},
+    finutils::{
+        common::{
+            evm::{gen_transfer_from_account_tx, Keypair},
+            utils::gen_transfer_op_with_utxos,
+        },
+        txn_builder::TransactionBuilder,
+    },
+    fp_mocks::{KeyPair, UnsignedTransaction},
+    fp_traits::evm::FeeCalculator,
+    fp_types::{crypto::MultiSigner, H160, U256},
+    ledger::{
+        data_model::{Transaction, BLACK_HOLE_PUBKEY_STAKING},
+        staking::{td_addr_to_string, Validator, ValidatorKind},
+        store::{utils::fra_gen_initial_tx, LedgerState},
+    },
+    rand_chacha::ChaChaRng,
+    rand_core::{RngCore, SeedableRng},
+    ruc::*,
+    zei::xfr::{
+        asset_record::AssetRecordType,
+        sig::{XfrKeyPair, XfrPublicKey},
+    },
+};
+
+/// Gas limit of the ethereum transactions built here.
+pub const EVM_GAS_LIMIT: u64 = 0x100000;
+
+/// A new random key pair.
+pub fn keypair() -> XfrKeyPair {
+    XfrKeyPair::generate(&mut ChaChaRng::from_entropy())
+}
+
+/// Define and issue FRA to `kp`.
+pub fn fra_genesis(kp: &XfrKeyPair) -> Transaction {
+    fra_gen_initial_tx(kp)
+}
+
+/// Transfer FRA from `kp` to `to`, the fee is paid aside.
+pub fn transfer(
+    ledger: &LedgerState,
+    kp: &XfrKeyPair,
+    to: &XfrPublicKey,
+    amount: u64,
+) -> Result<Transaction> {
+    let op = transfer_op(ledger, kp, vec![(to, amount)]).c(d!())?;
+    let mut builder = TransactionBuilder::from_seq_id(ledger.get_block_commit_count());
+    builder.add_operation(op).sign(kp);
+    Ok(builder.take_transaction())
+}
+
+/// Convert FRA of `kp` to the balance of an EVM account.
+pub fn transfer_to_evm(
+    ledger: &LedgerState,
+    kp: &XfrKeyPair,
+    to: MultiSigner,
+    amount: u64,
+) -> Result<Transaction> {
+    let op =
+        transfer_op(ledger, kp, vec![(&BLACK_HOLE_PUBKEY_STAKING, amount)]).c(d!())?;
+    let mut builder = TransactionBuilder::from_seq_id(ledger.get_block_commit_count());
+    builder
+        .add_operation(op)
+        .add_operation_convert_account(kp, to, amount)
+        .c(d!())?
+        .sign(kp);
+    let mut tx = builder.take_transaction();
+    tx.sign_to_map(kp);
+    Ok(tx)
+}
+
+/// Withdraw FRA from the account of `kp` to a new UTXO of it,
+/// `nonce` is the current nonce of the account.
+pub fn transfer_from_account(kp: &XfrKeyPair, nonce: U256, amount: u64) -> Vec<u8> {
+    gen_transfer_from_account_tx(
+        &Keypair::Ed25519(kp.clone()),
+        nonce,
+        kp.get_pk(),
+        amount,
+        false,
+    )
+}
+
+/// Set the initial validators of the chain.
+pub fn update_validators(vs: Vec<Validator>) -> Result<Transaction> {
+    let mut builder = TransactionBuilder::from_seq_id(0);
+    builder.add_operation_update_validator(&[], 1, vs).c(d!())?;
+    Ok(builder.take_transaction())
+}
+
+/// A validator with a random tendermint key.
+pub fn test_validator(power: u64) -> Validator {
+    validator_of(&keypair(), power)
+}
+
+/// A validator of `kp` with a random tendermint key.
+pub fn validator_of(kp: &XfrKeyPair, power: u64) -> Validator {
+    let mut td_pubkey = vec![0; 32];
+    ChaChaRng::from_entropy().fill_bytes(&mut td_pubkey);
+    pnk!(Validator::new(
+        td_pubkey,
+        power,
+        kp.get_pk(),
+        [1, 100],
+        Default::default(),
+        ValidatorKind::Initiator,
+    ))
+}
+
+/// Delegate FRA of `kp` to `validator`,
+/// a self-delegation if `validator` is the one of `kp`.
+pub fn delegate(
+    ledger: &LedgerState,
+    kp: &XfrKeyPair,
+    validator: &Validator,
+    amount: u64,
+) -> Result<Transaction> {
+    let op =
+        transfer_op(ledger, kp, vec![(&BLACK_HOLE_PUBKEY_STAKING, amount)]).c(d!())?;
+    let mut builder = TransactionBuilder::from_seq_id(ledger.get_block_commit_count());
+    builder.add_operation(op).add_operation_delegation(
+        kp,
+        amount,
+        td_addr_to_string(&validator.td_addr),
+    );
+    let mut tx = builder.take_transaction();
+    tx.sign_to_map(kp);
+    Ok(tx)
+}
+
+/// An ethereum transfer signed by `from`, at the minimal gas price.
+pub fn evm_transfer(
+    from: &KeyPair,
+    nonce: U256,
+    to: H160,
+    value: U256,
+) -> TransactionV0 {
+    UnsignedTransaction {
+        nonce,
+        gas_price: <BaseApp as module_ethereum::Config>::FeeCalculator::min_gas_price(),
+        gas_limit: U256::from(EVM_GAS_LIMIT),
+        action: TransactionAction::Call(to),
+        value,
+        input: vec![],
+    }
+    .sign(&from.private_key, ChainId::get())
+}
+
+fn transfer_op(
+    ledger: &LedgerState,
+    kp: &XfrKeyPair,
+    targets: Vec<(&XfrPublicKey, u64)>,
+) -> Result<ledger::data_model::Operation> {
+    let utxos = ledger
+        .get_owned_utxos(kp.get_pk_ref())
+        .c(d!())?
+        .into_iter()
+        .collect();
+    gen_transfer_op_with_utxos(
+        utxos,
+        kp,
+        targets,
+        None,
+        true,
+        false,
+        false,
+        Some(AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType),
+    )
+    .c(d!())
+}
@@ -0,0 +1,28 @@
+//! A small workload replayed in-process.
+
+use {
+    ruc::*,
+    testkit::bench::{self, BenchCfg, InProcess},
+};
+
+#[test]
+fn bench_in_process() {
+    let cfg = BenchCfg {
+        seed: 7,
+        transfers: 20,
+        delegations: 8,
+        erc20_calls: 20,
+        rate: 0,
+    };
+    let mut node = pnk!(InProcess::new(cfg.seed, 16));
+    let report = pnk!(bench::run(&cfg, &mut node));
+
+    assert_eq!(48, report.submitted);
+    assert_eq!(0, report.failed);
+    assert_eq!(48, report.committed);
+    assert_eq!(Some(&20), report.workload.get("erc20"));
+    assert!(0.0 < report.tps);
+
+    let callbacks = report.abci_callbacks_ms.unwrap();
+    assert_eq!(4, callbacks.len());
+}
@@ -0,0 +1,71 @@
+//! Full blocks driven through the ABCI application.
+//!
+//! Only one `TestKit` can be started in a test binary,
+//! so each test starting one has its own file.
+
+use {
+    abciapp::api::query_server::query_api::server::IndexedData,
+    fp_mocks::{ALICE_ECDSA, BOB_ECDSA},
+    fp_types::{crypto::MultiSigner, U256},
+    ruc::*,
+    testkit::{txs, TestKit},
+};
+
+const FRA: u64 = 1_000_000;
+
+#[test]
+fn utxo_and_evm_transfers() {
+    let mut kit = pnk!(TestKit::new());
+    let alice = txs::keypair();
+    let bob = txs::keypair();
+
+    kit.block()
+        .tx(&txs::fra_genesis(&alice))
+        .commit()
+        .assert_ok();
+    let genesis_hash = kit.app_hash().to_vec();
+
+    let tx = pnk!(txs::transfer(
+        &kit.ledger().read(),
+        &alice,
+        bob.get_pk_ref(),
+        100 * FRA
+    ));
+    assert_eq!(0, kit.check_tx(&tx).code);
+    let block = kit.block().tx(&tx).commit();
+    block.assert_ok();
+    assert_ne!(genesis_hash, block.app_hash);
+
+    let qs = kit.query_server();
+    assert_eq!(2, qs.get_commits());
+    assert_eq!(1, pnk!(qs.get_owned_utxos(bob.get_pk_ref())).len());
+
+    let tx = pnk!(txs::transfer_to_evm(
+        &kit.ledger().read(),
+        &alice,
+        MultiSigner::Ethereum(ALICE_ECDSA.address),
+        10 * FRA
+    ));
+    kit.block().tx(&tx).commit().assert_ok();
+
+    // 1 FRA, in the 18 decimals of the EVM
+    let tx = txs::evm_transfer(
+        &ALICE_ECDSA,
+        U256::zero(),
+        BOB_ECDSA.address,
+        U256::from(FRA) * U256::from(10u64.pow(12)),
+    );
+    assert_eq!(0, kit.check_evm_tx(&tx).code);
+    kit.block().evm_tx(&tx).commit().assert_ok();
+
+    let resp = kit.query(
+        "module/account/info",
+        pnk!(serde_json::to_vec(&BOB_ECDSA.account_id)),
+    );
+    assert_eq!(0, resp.code, "{}", resp.log);
+    let info = pnk!(serde_json::from_slice::<serde_json::Value>(&resp.value));
+    assert_eq!(
+        U256::from(FRA),
+        pnk!(serde_json::from_value::<U256>(info["balance"].clone()))
+    );
+}
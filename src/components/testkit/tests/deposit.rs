@@ -0,0 +1,73 @@
+//! FRA deposited to an EVM account and withdrawn back, as `fn contract-deposit`
+//! and `fn contract-withdraw` do.
+
+use {
+    abciapp::api::query_server::query_api::server::IndexedData,
+    finutils::common::evm::evm_account,
+    fp_types::{crypto::Address, U256},
+    ruc::*,
+    testkit::{txs, TestKit},
+};
+
+const FRA: u64 = 1_000_000;
+
+#[test]
+fn deposit_and_withdraw() {
+    let mut kit = pnk!(TestKit::new());
+    let alice = txs::keypair();
+
+    kit.block()
+        .tx(&txs::fra_genesis(&alice))
+        .commit()
+        .assert_ok();
+
+    // the default receiver of `fn contract-deposit`
+    let receiver = pnk!(evm_account(None, alice.get_pk_ref()));
+    let tx = pnk!(txs::transfer_to_evm(
+        &kit.ledger().read(),
+        &alice,
+        receiver,
+        10 * FRA
+    ));
+    kit.block().tx(&tx).commit().assert_ok();
+
+    // the account `fn contract-withdraw` spends with the same key
+    let account = pnk!(serde_json::to_vec(&Address::from(alice.get_pk())));
+    let balance = |kit: &mut TestKit| {
+        let resp = kit.query("module/account/info", account.clone());
+        assert_eq!(0, resp.code, "{}", resp.log);
+        let info = pnk!(serde_json::from_slice::<serde_json::Value>(&resp.value));
+        pnk!(serde_json::from_value::<U256>(info["balance"].clone()))
+    };
+    assert_eq!(
+        U256::from(10 * FRA) * U256::from(10u64.pow(12)),
+        balance(&mut kit)
+    );
+
+    let resp = kit.query("module/account/nonce", account.clone());
+    assert_eq!(0, resp.code, "{}", resp.log);
+    let nonce = pnk!(serde_json::from_slice::<U256>(&resp.value));
+
+    let utxos = pnk!(kit.query_server().get_owned_utxos(alice.get_pk_ref()));
+    kit.block()
+        .raw_tx(txs::transfer_from_account(&alice, nonce, 4 * FRA))
+        .commit()
+        .assert_ok();
+
+    // the withdrawn FRAs are minted to a new UTXO of the same key
+    assert!(balance(&mut kit) < U256::from(6 * FRA) * U256::from(10u64.pow(12)));
+    let ledger = kit.ledger();
+    let ledger = ledger.read();
+    let minted = pnk!(kit.query_server().get_owned_utxos(alice.get_pk_ref()))
+        .difference(&utxos)
+        .map(|sid| {
+            pnk!(ledger.get_utxo_light(*sid))
+                .utxo
+                .0
+                .record
+                .amount
+                .get_amount()
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(vec![Some(4 * FRA)], minted);
+}
@@ -0,0 +1,127 @@
+//! A standalone indexer builds the same histories as the `ApiCache` of the node.
+
+use {
+    abciapp::api::{
+        indexer::Indexer,
+        query_server::{query_api::server::IndexedData, STAKING_HIST_EVENTS},
+    },
+    ledger::{
+        data_model::{TxnSID, XfrAddress},
+        staking::STAKING_VALIDATOR_MIN_POWER,
+        store::api_cache::ApiCache,
+    },
+    ruc::*,
+    std::env,
+    testkit::{txs, TestKit},
+};
+
+const FRA: u64 = 1_000_000;
+
+// The history of every validator in a field of an `ApiCache`.
+macro_rules! hist {
+    ($cache: expr, $field: ident) => {
+        $cache
+            .$field
+            .iter()
+            .map(|(pk, h)| (pk, h.iter().collect::<Vec<_>>()))
+            .collect::<Vec<_>>()
+    };
+}
+
+#[test]
+fn indexer_and_api_cache() {
+    // as a node serving the query service with history indices
+    env::set_var("FINDORAD_KEEP_HIST", "1");
+    let mut kit = pnk!(TestKit::new());
+    let alice = txs::keypair();
+    let bob = txs::keypair();
+    let mut vs = (0..4)
+        .map(|_| txs::test_validator(STAKING_VALIDATOR_MIN_POWER))
+        .collect::<Vec<_>>();
+    vs[0] = txs::validator_of(&alice, STAKING_VALIDATOR_MIN_POWER);
+
+    kit.block()
+        .tx(&txs::fra_genesis(&alice))
+        .tx(&pnk!(txs::update_validators(vs.clone())))
+        .commit()
+        .assert_ok();
+    let tx = pnk!(txs::transfer(
+        &kit.ledger().read(),
+        &alice,
+        bob.get_pk_ref(),
+        200 * FRA
+    ));
+    kit.block().tx(&tx).commit().assert_ok();
+    kit.block().commit();
+    kit.block().commit();
+
+    kit.set_voters(&vs);
+    let tx = pnk!(txs::delegate(
+        &kit.ledger().read(),
+        &alice,
+        &vs[0],
+        100 * FRA
+    ));
+    kit.block().tx(&tx).commit().assert_ok();
+    let tx = pnk!(txs::delegate(&kit.ledger().read(), &bob, &vs[0], 50 * FRA));
+    kit.block().tx(&tx).commit().assert_ok();
+    kit.block().commit();
+
+    // feed the indexer with what the node serves
+    let ledger = kit.ledger();
+    let ledger = ledger.read();
+    let mut indexer = Indexer::new("http://127.0.0.1:8668");
+    for sid in 0..ledger.get_next_txn().0 {
+        let ftx = pnk!(ledger.get_transaction_light(TxnSID(sid)));
+        pnk!(indexer.index_txn(&ftx));
+    }
+    for h in 1..=kit.height() {
+        let events = pnk!(STAKING_HIST_EVENTS.read().get(&h));
+        pnk!(indexer.index_staking(h as u64, &events));
+    }
+    assert_eq!(kit.height() as u64, indexer.get_staking_height());
+
+    let node = pnk!(ledger.api_cache.as_ref());
+    let indexed = pnk!(indexer.api_cache());
+
+    for kp in [&alice, &bob] {
+        let addr = XfrAddress { key: kp.get_pk() };
+        let txns = |cache: &ApiCache| {
+            pnk!(cache.related_transactions.get(&addr))
+                .iter()
+                .map(|(sid, _)| sid)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(txns(node), txns(indexed));
+    }
+    assert_eq!(
+        node.utxos_to_map_index.iter().collect::<Vec<_>>(),
+        indexed.utxos_to_map_index.iter().collect::<Vec<_>>()
+    );
+
+    let rates =
+        |cache: &ApiCache| cache.staking_global_rate_hist.iter().collect::<Vec<_>>();
+    assert!(!rates(node).is_empty());
+    assert_eq!(rates(node), rates(indexed));
+
+    assert_eq!(
+        vec![(alice.get_pk(), vec![(kit.height() as u64 - 2, 100 * FRA)])],
+        hist!(node, staking_self_delegation_hist)
+    );
+    assert_eq!(
+        hist!(node, staking_self_delegation_hist),
+        hist!(indexed, staking_self_delegation_hist)
+    );
+    assert_eq!(
+        vec![(alice.get_pk(), vec![(kit.height() as u64 - 1, 50 * FRA)])],
+        hist!(node, staking_delegation_amount_hist)
+    );
+    assert_eq!(
+        hist!(node, staking_delegation_amount_hist),
+        hist!(indexed, staking_delegation_amount_hist)
+    );
+    assert_eq!(
+        hist!(node, staking_commission_hist),
+        hist!(indexed, staking_commission_hist)
+    );
+}
@@ -0,0 +1,84 @@
+//! Validator votes and byzantine evidence.
+
+use {
+    ledger::staking::{
+        ops::governance::{get_rule, ByzantineKind},
+        STAKING_VALIDATOR_MIN_POWER,
+    },
+    ruc::*,
+    testkit::{txs, TestKit},
+};
+
+const FRA: u64 = 1_000_000;
+
+#[test]
+fn validator_votes() {
+    let mut kit = pnk!(TestKit::new());
+    let byzantine = txs::keypair();
+    let mut vs = (0..4)
+        .map(|_| txs::test_validator(STAKING_VALIDATOR_MIN_POWER))
+        .collect::<Vec<_>>();
+    vs[2] = txs::validator_of(&byzantine, STAKING_VALIDATOR_MIN_POWER);
+
+    kit.block()
+        .tx(&txs::fra_genesis(&byzantine))
+        .tx(&pnk!(txs::update_validators(vs.clone())))
+        .commit()
+        .assert_ok();
+    kit.block().commit();
+    kit.block().commit();
+
+    // validators are reported to tendermint every 4 blocks
+    let block = kit.block().commit();
+    assert_eq!(4, block.end_block.validator_updates.len());
+
+    // a validator can only be punished after its self-delegation
+    let tx = pnk!(txs::delegate(
+        &kit.ledger().read(),
+        &byzantine,
+        &vs[2],
+        100 * FRA
+    ));
+    kit.set_voters(&vs);
+    kit.block().tx(&tx).commit().assert_ok();
+    let (power, amount) = {
+        let ledger = kit.ledger();
+        let ledger = ledger.read();
+        let staking = ledger.get_staking();
+        (
+            pnk!(staking.validator_get_power(byzantine.get_pk_ref())),
+            pnk!(staking.delegation_get(byzantine.get_pk_ref())).amount(),
+        )
+    };
+    assert_eq!(STAKING_VALIDATOR_MIN_POWER + 100 * FRA, power);
+    assert_eq!(100 * FRA, amount);
+
+    kit.block()
+        .absent(&vs[3].td_addr)
+        .evidence(&vs[2].td_addr, "DUPLICATE_VOTE")
+        .commit();
+
+    let ledger = kit.ledger();
+    let ledger = ledger.read();
+    let staking = ledger.get_staking();
+    let vd = staking.validator_get_current().unwrap();
+    vs.iter().take(3).for_each(|v| {
+        let v = &vd.body[&v.id];
+        assert!(v.signed_last_block);
+        assert_eq!(2, v.signed_cnt);
+    });
+    let v = &vd.body[&vs[3].id];
+    assert!(!v.signed_last_block);
+    assert_eq!(1, v.signed_cnt);
+
+    // the penalty of duplicate votes is taken from the power and the stake
+    let [n, d] = pnk!(get_rule(&ByzantineKind::DuplicateVote)).gen_penalty_percent();
+    assert_eq!(
+        power - power * n / d,
+        pnk!(staking.validator_get_power(byzantine.get_pk_ref()))
+    );
+    assert_eq!(
+        amount - amount * n / d,
+        pnk!(staking.delegation_get(byzantine.get_pk_ref())).amount()
+    );
+}
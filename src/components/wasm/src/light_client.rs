@@ -0,0 +1,402 @@
+//!
+//! # Light client
+//!
+//! Follows tendermint headers from a trusted one, so the proofs of the ledger
+//! can be checked against a state commitment decided by the validators,
+//! instead of the one returned by a query server.
+//!
+//! The voting power of validators is set by `Staking`,
+//! a header is accepted only if it is signed by enough of the power.
+//!
+//! The data is fetched by `Network`:
+//! - `signed_header` of `/commit?height=H` of the tendermint RPC
+//! - `validators` of `/validators?height=H` and `/validators?height=H+1` of the tendermint RPC
+//! - `/app_hash_parts/{H-1}` of the ledger service, the header of `H` carries the app hash of `H-1`
+//!
+
+use {
+    crate::wasm_data_model::{error_to_jsvalue, AuthenticatedAssetRecord},
+    globutils::HashOf,
+    ledger::data_model::{AppHashParts, AuthenticatedTransaction, StateCommitmentData},
+    ruc::*,
+    std::time::Duration,
+    tendermint::{
+        block::signed_header::SignedHeader, hash::Algorithm, node, validator, Hash, Time,
+    },
+    tendermint_light_client::{
+        components::verifier::{ProdVerifier, Verdict, Verifier},
+        light_client::Options,
+        types::{LightBlock, TrustThreshold},
+    },
+    wasm_bindgen::prelude::*,
+};
+
+/// The maximum clock drift between the client and the validators.
+const CLOCK_DRIFT: Duration = Duration::from_secs(10);
+
+#[wasm_bindgen]
+/// A light client following the chain from a trusted header.
+pub struct LightClient {
+    trusted: LightBlock,
+    options: Options,
+}
+
+#[wasm_bindgen]
+impl LightClient {
+    /// Starts from a trusted header, eg. the one shipped with the wallet.
+    /// @param {JsValue} signed_header - `signed_header` of `/commit` at the trusted height.
+    /// @param {JsValue} validators - Validators at the trusted height.
+    /// @param {JsValue} next_validators - Validators at the next height.
+    /// @param {string} header_hash - Trusted hash of the header, in upper hex.
+    /// @param {BigInt} trusting_period - Seconds during which the validators of a header are trusted,
+    /// it must be shorter than the unbonding period of `Staking`.
+    /// @throws Will throw an error if the header mismatches the hash or the validators.
+    pub fn new(
+        signed_header: &JsValue,
+        validators: &JsValue,
+        next_validators: &JsValue,
+        header_hash: String,
+        trusting_period: u64,
+    ) -> Result<LightClient, JsValue> {
+        let trusted = light_block(signed_header, validators, next_validators)
+            .map_err(error_to_jsvalue)?;
+        let hash = Hash::from_hex_upper(Algorithm::Sha256, &header_hash)
+            .c(d!())
+            .map_err(error_to_jsvalue)?;
+
+        Self::from_trusted(trusted, hash, Duration::from_secs(trusting_period))
+            .map_err(error_to_jsvalue)
+    }
+
+    /// Returns the height of the latest verified header.
+    pub fn verified_height(&self) -> u64 {
+        self.trusted.height().value()
+    }
+
+    /// Verifies a header after the latest verified one, it becomes the latest verified one.
+    /// @param {JsValue} signed_header - `signed_header` of `/commit` at the height.
+    /// @param {JsValue} validators - Validators at the height.
+    /// @param {JsValue} next_validators - Validators at the next height.
+    /// @param {string} now - Current time in RFC 3339, eg. `new Date().toISOString()`.
+    /// @throws Will throw an error if the header is invalid,
+    /// or if the validators changed too much to trust it, then verify an intermediate height first.
+    pub fn verify(
+        &mut self,
+        signed_header: &JsValue,
+        validators: &JsValue,
+        next_validators: &JsValue,
+        now: String,
+    ) -> Result<(), JsValue> {
+        let untrusted = light_block(signed_header, validators, next_validators)
+            .map_err(error_to_jsvalue)?;
+        let now = Time::parse_from_rfc3339(&now)
+            .c(d!())
+            .map_err(error_to_jsvalue)?;
+
+        self.verify_light_block(untrusted, now)
+            .map_err(error_to_jsvalue)
+    }
+
+    /// Returns the ledger state commitment proved by the latest verified header,
+    /// in the form accepted by `AuthenticatedAssetRecord.is_valid` and `verify_authenticated_txn`.
+    /// @param {JsValue} app_hash_parts - `/app_hash_parts/{H-1}`, `H` is the verified height.
+    /// @throws Will throw an error if the parts mismatch the app hash of the header.
+    pub fn verified_state_commitment(
+        &self,
+        app_hash_parts: &JsValue,
+    ) -> Result<String, JsValue> {
+        parts(app_hash_parts)
+            .and_then(|parts| self.state_commitment(&parts))
+            .and_then(|c| serde_json::to_string(&c).c(d!()))
+            .map_err(error_to_jsvalue)
+    }
+
+    /// Returns true if the asset record exists on the ledger and is unspent,
+    /// according to the latest verified header.
+    /// @param {JsValue} app_hash_parts - `/app_hash_parts/{H-1}`, `H` is the verified height.
+    /// @param {AuthenticatedAssetRecord} record - The asset record with its proofs.
+    /// @throws Will throw an error if the parts mismatch the app hash of the header.
+    pub fn verify_asset_record(
+        &self,
+        app_hash_parts: &JsValue,
+        record: &AuthenticatedAssetRecord,
+    ) -> Result<bool, JsValue> {
+        let state_commitment = parts(app_hash_parts)
+            .and_then(|parts| self.state_commitment(&parts))
+            .map_err(error_to_jsvalue)?;
+        Ok(record.authenticated_record.is_valid(state_commitment))
+    }
+
+    /// Returns true if the transaction is committed on the ledger,
+    /// according to the latest verified header.
+    /// @param {JsValue} app_hash_parts - `/app_hash_parts/{H-1}`, `H` is the verified height.
+    /// @param {string} authenticated_txn - String representing the transaction.
+    /// @throws Will throw an error if the parts mismatch the app hash of the header,
+    /// or if the transaction fails to deserialize.
+    pub fn verify_txn(
+        &self,
+        app_hash_parts: &JsValue,
+        authenticated_txn: String,
+    ) -> Result<bool, JsValue> {
+        let state_commitment = parts(app_hash_parts)
+            .and_then(|parts| self.state_commitment(&parts))
+            .map_err(error_to_jsvalue)?;
+        let authenticated_txn =
+            serde_json::from_str::<AuthenticatedTransaction>(&authenticated_txn)
+                .c(d!())
+                .map_err(error_to_jsvalue)?;
+        Ok(authenticated_txn.is_valid(state_commitment))
+    }
+}
+
+impl LightClient {
+    fn from_trusted(
+        trusted: LightBlock,
+        header_hash: Hash,
+        trusting_period: Duration,
+    ) -> Result<Self> {
+        let header = &trusted.signed_header.header;
+        if header.hash() != header_hash {
+            return Err(eg!("Header mismatches the trusted hash"));
+        }
+        if trusted.validators.hash() != header.validators_hash
+            || trusted.next_validators.hash() != header.next_validators_hash
+        {
+            return Err(eg!("Validators mismatch the trusted header"));
+        }
+
+        Ok(LightClient {
+            trusted,
+            options: Options {
+                trust_threshold: TrustThreshold::default(),
+                trusting_period,
+                clock_drift: CLOCK_DRIFT,
+            },
+        })
+    }
+
+    fn verify_light_block(&mut self, untrusted: LightBlock, now: Time) -> Result<()> {
+        if untrusted.height() <= self.trusted.height() {
+            return Err(eg!("Header is not after the verified one"));
+        }
+
+        match ProdVerifier::default().verify(
+            &untrusted,
+            &self.trusted,
+            &self.options,
+            now,
+        ) {
+            Verdict::Success => {
+                self.trusted = untrusted;
+                Ok(())
+            }
+            Verdict::NotEnoughTrust(tally) => Err(eg!(format!(
+                "Not enough trust ({}), verify an intermediate height first",
+                tally
+            ))),
+            Verdict::Invalid(e) => Err(eg!(format!("Invalid header: {}", e))),
+        }
+    }
+
+    fn state_commitment(
+        &self,
+        parts: &AppHashParts,
+    ) -> Result<HashOf<Option<StateCommitmentData>>> {
+        if parts.app_hash() != self.trusted.signed_header.header.app_hash.value() {
+            return Err(eg!("App hash mismatches the verified header"));
+        }
+        Ok(parts.ledger.clone())
+    }
+}
+
+fn parts(app_hash_parts: &JsValue) -> Result<AppHashParts> {
+    app_hash_parts.into_serde::<AppHashParts>().c(d!())
+}
+
+fn light_block(
+    signed_header: &JsValue,
+    validators: &JsValue,
+    next_validators: &JsValue,
+) -> Result<LightBlock> {
+    let signed_header = signed_header.into_serde::<SignedHeader>().c(d!())?;
+    let validators = validators
+        .into_serde::<Vec<validator::Info>>()
+        .c(d!())
+        .map(validator::Set::without_proposer)?;
+    let next_validators = next_validators
+        .into_serde::<Vec<validator::Info>>()
+        .c(d!())
+        .map(validator::Set::without_proposer)?;
+
+    // the provider is only used by the light client daemon
+    Ok(LightBlock::new(
+        signed_header,
+        validators,
+        next_validators,
+        node::Id::new([0; 20]),
+    ))
+}
+
+#[cfg(test)]
+#[allow(missing_docs)]
+mod test {
+    use {
+        super::*,
+        ledger::{
+            data_model::{AssetRules, AssetTypeCode, Memo},
+            store::{
+                helpers::{apply_transaction, create_definition_transaction},
+                LedgerState,
+            },
+        },
+        rand_chacha::ChaChaRng,
+        rand_core::SeedableRng,
+        tendermint::{block::CommitSig, hash::AppHash},
+        tendermint_testgen::{
+            light_block::LightBlock as TestgenLightBlock, Commit, Generator, Header,
+            Validator, Vote,
+        },
+        zei::xfr::sig::XfrKeyPair,
+    };
+
+    const TRUSTING_PERIOD: Duration = Duration::from_secs(3600);
+
+    fn validators() -> Vec<Validator> {
+        ["a", "b", "c"]
+            .iter()
+            .map(|id| Validator::new(id).voting_power(10))
+            .collect()
+    }
+
+    // a block at `height`, whose commit is signed by the first `signers` validators
+    fn light_block(height: u64, signers: usize) -> LightBlock {
+        let vals = validators();
+        let header = Header::new(&vals)
+            .next_validators(&vals)
+            .chain_id("findora-test")
+            .height(height)
+            .time(height);
+        let votes = vals
+            .iter()
+            .map(|v| Vote::new(v.clone(), header.clone()))
+            .collect();
+        let commit = Commit::new_with_votes(header.clone(), 1, votes);
+        let mut tm = pnk!(TestgenLightBlock::new(header, commit)
+            .validators(&vals)
+            .next_validators(&vals)
+            .generate());
+        tm.signed_header.commit.signatures[signers..]
+            .iter_mut()
+            .for_each(|sig| *sig = CommitSig::BlockIdFlagAbsent);
+
+        LightBlock::new(
+            tm.signed_header,
+            tm.validators,
+            tm.next_validators,
+            node::Id::new([0; 20]),
+        )
+    }
+
+    fn client(trusted: LightBlock) -> LightClient {
+        let hash = trusted.signed_header.header.hash();
+        pnk!(LightClient::from_trusted(trusted, hash, TRUSTING_PERIOD))
+    }
+
+    fn now() -> Time {
+        pnk!(Time::parse_from_rfc3339("1970-01-01T00:01:00Z"))
+    }
+
+    #[test]
+    fn light_client_trusted_header() {
+        let trusted = light_block(1, 3);
+        let hash = trusted.signed_header.header.hash();
+
+        let mut other = trusted.clone();
+        other.signed_header.header.data_hash = Some(Hash::Sha256([1; 32]));
+        assert!(LightClient::from_trusted(other, hash, TRUSTING_PERIOD).is_err());
+
+        let mut other = trusted.clone();
+        other.next_validators = validator::Set::without_proposer(
+            trusted.next_validators.validators()[1..].to_vec(),
+        );
+        assert!(LightClient::from_trusted(other, hash, TRUSTING_PERIOD).is_err());
+
+        assert_eq!(1, client(trusted).verified_height());
+    }
+
+    #[test]
+    fn light_client_verify_commit() {
+        let mut lc = client(light_block(1, 3));
+
+        // the threshold is more than 2/3 of the power, 2/3 is not enough
+        assert!(lc.verify_light_block(light_block(2, 2), now()).is_err());
+        assert_eq!(1, lc.verified_height());
+
+        pnk!(lc.verify_light_block(light_block(2, 3), now()));
+        assert_eq!(2, lc.verified_height());
+
+        // the headers must go forward
+        assert!(lc.verify_light_block(light_block(2, 3), now()).is_err());
+
+        // a header modified after it was signed
+        let mut tampered = light_block(3, 3);
+        tampered.signed_header.header.data_hash = Some(Hash::Sha256([1; 32]));
+        assert!(lc.verify_light_block(tampered, now()).is_err());
+        assert_eq!(2, lc.verified_height());
+
+        // a header out of the trusting period
+        let late = pnk!(Time::parse_from_rfc3339("1970-01-02T00:00:00Z"));
+        assert!(lc.verify_light_block(light_block(3, 3), late).is_err());
+        pnk!(lc.verify_light_block(light_block(3, 3), now()));
+    }
+
+    #[test]
+    fn light_client_state_commitment() {
+        let mut ledger = LedgerState::tmp_ledger();
+        let mut prng = ChaChaRng::from_entropy();
+        let kp = XfrKeyPair::generate(&mut prng);
+        let tx = pnk!(create_definition_transaction(
+            &AssetTypeCode::gen_random(),
+            &kp,
+            AssetRules::default(),
+            None,
+            ledger.get_block_commit_count(),
+        ));
+        let (txn_sid, _) = apply_transaction(&mut ledger, tx);
+        let authenticated_txn = pnk!(ledger.get_transaction(txn_sid));
+
+        let parts = AppHashParts {
+            ledger: ledger.get_state_commitment().0,
+            evm: vec![7; 32],
+        };
+
+        // the app hash is committed by the trusted header
+        let mut trusted = light_block(1, 3);
+        trusted.signed_header.header.app_hash = pnk!(AppHash::from_hex_upper(
+            &hex::encode_upper(parts.app_hash())
+        ));
+        let lc = client(trusted);
+
+        let state_commitment = pnk!(lc.state_commitment(&parts));
+        assert_eq!(parts.ledger, state_commitment);
+        assert!(authenticated_txn.is_valid(state_commitment.clone()));
+
+        // parts which do not hash to the app hash
+        let mut tampered = parts.clone();
+        tampered.evm = vec![8; 32];
+        assert!(lc.state_commitment(&tampered).is_err());
+        let mut tampered = parts;
+        tampered.evm = vec![];
+        assert!(lc.state_commitment(&tampered).is_err());
+
+        // a transaction which is not the one in the proof
+        let mut tampered_txn = authenticated_txn;
+        tampered_txn
+            .finalized_txn
+            .txn
+            .body
+            .memos
+            .push(Memo("tampered".to_owned()));
+        assert!(!tampered_txn.is_valid(state_commitment));
+    }
+}
@@ -12,6 +12,7 @@
 #![deny(missing_docs)]
 #![allow(clippy::needless_borrow)]
 
+mod light_client;
 mod wasm_data_model;
 
 use {
@@ -20,7 +21,8 @@ use {
         AttributeDefinition, ClientAssetRecord, Credential, CredentialCommitment,
         CredentialCommitmentData, CredentialCommitmentKey, CredentialIssuerKeyPair,
         CredentialPoK, CredentialRevealSig, CredentialSignature, CredentialUserKeyPair,
-        OwnerMemo, PublicParams, TracingPolicies, TxoRef,
+        OwnerMemo, PublicParams, SignatureRules, StakerMemo, TdValidatorKeys,
+        TracingPolicies, TxoRef,
     },
     credentials::{
         credential_commit, credential_issuer_key_gen, credential_open_commitment,
@@ -47,19 +49,20 @@ use {
     globutils::{wallet, HashOf},
     ledger::{
         data_model::{
-            gen_random_keypair, AssetTypeCode, AuthenticatedTransaction, Operation,
-            TransferType, TxOutput, ASSET_TYPE_FRA, BLACK_HOLE_PUBKEY,
-            BLACK_HOLE_PUBKEY_STAKING, TX_FEE_MIN,
+            gen_random_keypair, AssetTypeCode, AuthenticatedTransaction,
+            CredentialProof, Operation, Transaction, TransferType, TxOutput,
+            ASSET_TYPE_FRA, BLACK_HOLE_PUBKEY, BLACK_HOLE_PUBKEY_STAKING, TX_FEE_MIN,
         },
         staking::{
-            td_addr_to_bytes, PartialUnDelegation, TendermintAddr,
+            convert_rate, ops::governance::ByzantineKind, td_addr_to_bytes,
+            td_pubkey_to_td_addr_bytes, PartialUnDelegation, TendermintAddr,
             MAX_DELEGATION_AMOUNT, MIN_DELEGATION_AMOUNT,
         },
     },
     rand_chacha::ChaChaRng,
-    rand_core::SeedableRng,
+    rand_core::{RngCore, SeedableRng},
     ruc::{d, err::RucResult},
-    std::str::FromStr,
+    std::{collections::BTreeMap, str::FromStr},
     wasm_bindgen::prelude::*,
     zei::{
         serialization::ZeiFromToBytes,
@@ -299,6 +302,19 @@ impl TransactionBuilder {
         }
     }
 
+    /// Create a new transaction builder whose no-replay token is derived from `seed`,
+    /// the same seed gives the same transaction, only for tests and offline signing.
+    /// @param {BigInt} seq_id - Unique sequence ID to prevent replay attacks.
+    /// @param {BigInt} seed - Seed of the random generator.
+    pub fn new_with_seed(seq_id: u64, seed: u64) -> Self {
+        TransactionBuilder {
+            transaction_builder: PlatformTransactionBuilder::from_seq_id_with_rng(
+                &mut ChaChaRng::seed_from_u64(seed),
+                seq_id,
+            ),
+        }
+    }
+
     /// Wraps around TransactionBuilder to add an asset definition operation to a transaction builder instance.
     /// @example <caption> Error handling </caption>
     /// try {
@@ -425,6 +441,53 @@ impl TransactionBuilder {
         Ok(self)
     }
 
+    /// Adds an operation to the transaction builder that registers the public key of a
+    /// credential issuer under the address of `keypair`.
+    /// @param {XfrKeyPair} keypair - Key pair of the issuer address.
+    /// @param {CredIssuerPublicKey} issuer_pub_key - Public key of the credential issuer.
+    /// @see {@link module:Findora-Wasm~AssetRules#set_required_credentials|AssetRules.set_required_credentials} for more information about
+    /// credential-gated assets.
+    pub fn add_operation_register_cred_issuer(
+        mut self,
+        keypair: &XfrKeyPair,
+        issuer_pub_key: &CredIssuerPublicKey,
+    ) -> TransactionBuilder {
+        self.get_builder_mut()
+            .add_operation_register_cred_issuer(keypair, issuer_pub_key.clone());
+        self
+    }
+
+    /// Attaches a credential proof of a recipient of credential-gated assets.
+    /// Must be called before the transaction is signed.
+    /// @param {CredUserSecretKey} user_secret_key - Secret key of the credential user.
+    /// @param {Credential} credential - Credential of the recipient.
+    /// @param {XfrKeyPair} owner_keypair - Key pair of the ledger address of the recipient, which signs the proof.
+    /// @param {JsValue} reveal_fields - Array of names of the attributes to reveal (i.e. `["kyc"]`).
+    pub fn add_credential_proof(
+        mut self,
+        user_secret_key: &CredUserSecretKey,
+        credential: &Credential,
+        owner_keypair: &XfrKeyPair,
+        reveal_fields: JsValue,
+    ) -> Result<TransactionBuilder, JsValue> {
+        let mut prng = ChaChaRng::from_entropy();
+        let reveal_fields: Vec<String> = reveal_fields
+            .into_serde()
+            .c(d!())
+            .map_err(error_to_jsvalue)?;
+        let proof = CredentialProof::new(
+            &mut prng,
+            user_secret_key,
+            credential.get_cred_ref(),
+            owner_keypair,
+            &reveal_fields,
+        )
+        .c(d!())
+        .map_err(error_to_jsvalue)?;
+        self.get_builder_mut().add_credential_proof(proof);
+        Ok(self)
+    }
+
     #[allow(missing_docs)]
     pub fn add_operation_delegate(
         mut self,
@@ -489,6 +552,167 @@ impl TransactionBuilder {
         Ok(self)
     }
 
+    /// Adds an operation to stake FRA units with a validator node, the staker becomes a validator.
+    /// The staked FRA units must be transferred to `get_delegation_target_address()` in the same transaction.
+    /// @param {XfrKeyPair} keypair - Key pair of the staker.
+    /// @param {BigInt} amount - Amount of FRA units to stake.
+    /// @param {TdValidatorKeys} td_keys - Keys of the validator node.
+    /// @param {number} commission_rate - Commission rate, from 0 to 1.
+    /// @param {number|undefined} max_commission_rate - Commission rate which can never be exceeded, from 0 to 1.
+    /// @param {StakerMemo} memo - Self-description of the staker.
+    /// @throws Will throw an error if a rate is invalid or the validator key is invalid.
+    pub fn add_operation_staking(
+        mut self,
+        keypair: &XfrKeyPair,
+        amount: u64,
+        td_keys: &TdValidatorKeys,
+        commission_rate: f64,
+        max_commission_rate: Option<f64>,
+        memo: &StakerMemo,
+    ) -> Result<TransactionBuilder, JsValue> {
+        let cr = convert_rate(commission_rate).map_err(error_to_jsvalue)?;
+        let max_cr = max_commission_rate
+            .map(convert_rate)
+            .transpose()
+            .map_err(error_to_jsvalue)?;
+        let memo = serde_json::to_string(&memo.memo)
+            .c(d!())
+            .map_err(error_to_jsvalue)?;
+        self.get_builder_mut()
+            .add_operation_staking(
+                keypair,
+                amount,
+                &td_keys.priv_key,
+                td_keys.pub_key.to_vec(),
+                cr,
+                max_cr,
+                Some(memo),
+            )
+            .c(d!())
+            .map_err(error_to_jsvalue)?;
+        Ok(self)
+    }
+
+    /// Adds an operation to update the commission rate and the memo of a validator.
+    /// @param {XfrKeyPair} keypair - Key pair of the staker.
+    /// @param {TdValidatorKeys} td_keys - Keys of the validator node.
+    /// @param {number} commission_rate - Commission rate, from 0 to 1.
+    /// @param {StakerMemo} memo - Self-description of the staker.
+    /// @throws Will throw an error if the rate is invalid or the validator key is invalid.
+    pub fn add_operation_update_staker(
+        mut self,
+        keypair: &XfrKeyPair,
+        td_keys: &TdValidatorKeys,
+        commission_rate: f64,
+        memo: &StakerMemo,
+    ) -> Result<TransactionBuilder, JsValue> {
+        let cr = convert_rate(commission_rate).map_err(error_to_jsvalue)?;
+        self.get_builder_mut()
+            .add_operation_update_staker(
+                keypair,
+                &td_keys.priv_key,
+                td_keys.pub_key.to_vec(),
+                cr,
+                memo.memo.clone(),
+            )
+            .c(d!())
+            .map_err(error_to_jsvalue)?;
+        Ok(self)
+    }
+
+    /// Adds an operation to hand over a validator to another staker.
+    /// @param {XfrKeyPair} keypair - Key pair of the current staker.
+    /// @param {XfrPublicKey} new_public_key - Public key of the new staker.
+    /// @param {string|undefined} new_td_pubkey - Base64 encoded public key of a new validator node, if it is moved.
+    /// @throws Will throw an error if `new_td_pubkey` fails to decode.
+    pub fn add_operation_replace_staker(
+        mut self,
+        keypair: &XfrKeyPair,
+        new_public_key: &XfrPublicKey,
+        new_td_pubkey: Option<String>,
+    ) -> Result<TransactionBuilder, JsValue> {
+        let new_td_addr_pk = new_td_pubkey
+            .map(|pk| {
+                base64::decode(pk)
+                    .c(d!())
+                    .map(|pk| (td_pubkey_to_td_addr_bytes(&pk), pk))
+            })
+            .transpose()
+            .map_err(error_to_jsvalue)?;
+        self.get_builder_mut()
+            .add_operation_replace_staker(keypair, *new_public_key, new_td_addr_pk)
+            .c(d!())
+            .map_err(error_to_jsvalue)?;
+        Ok(self)
+    }
+
+    /// Adds an operation to punish a byzantine validator, it must be co-signed by the validators.
+    /// @param {JsValue} keypairs - Array of the key pairs of co-signers, encoded by `keypair_to_str`.
+    /// @param {XfrPublicKey} byzantine_id - Public key of the staker of the byzantine validator.
+    /// @param {string} kind - One of `DuplicateVote`, `LightClientAttack`, `OffLine` and `Unknown`.
+    /// @param {number|undefined} custom_rate - Rate of the punishment from 0 to 1, the rate of `kind` is used if not given.
+    /// @throws Will throw an error if a key pair fails to decode, or `kind` or the rate is invalid.
+    pub fn add_operation_governance(
+        mut self,
+        keypairs: JsValue,
+        byzantine_id: &XfrPublicKey,
+        kind: String,
+        custom_rate: Option<f64>,
+    ) -> Result<TransactionBuilder, JsValue> {
+        let kps = keypairs_from_jsvalue(keypairs)?;
+        let kind = match kind.as_str() {
+            "DuplicateVote" => ByzantineKind::DuplicateVote,
+            "LightClientAttack" => ByzantineKind::LightClientAttack,
+            "OffLine" => ByzantineKind::OffLine,
+            "Unknown" => ByzantineKind::Unknown,
+            _ => return Err(error_to_jsvalue("Invalid byzantine kind")),
+        };
+        let custom_amount = custom_rate
+            .map(convert_rate)
+            .transpose()
+            .map_err(error_to_jsvalue)?;
+        self.get_builder_mut()
+            .add_operation_governance(
+                &kps.iter().collect::<Vec<_>>(),
+                *byzantine_id,
+                kind,
+                custom_amount,
+            )
+            .c(d!())
+            .map_err(error_to_jsvalue)?;
+        Ok(self)
+    }
+
+    /// Adds an operation to distribute FRA units to addresses, it must be co-signed by the validators.
+    /// @param {JsValue} keypairs - Array of the key pairs of co-signers, encoded by `keypair_to_str`.
+    /// @param {JsValue} alloc_table - Object from base64 encoded public keys to amounts, i.e. `{"cHViX2tleQ==": 100}`.
+    /// @throws Will throw an error if a key pair or the table fails to decode.
+    pub fn add_operation_fra_distribution(
+        mut self,
+        keypairs: JsValue,
+        alloc_table: JsValue,
+    ) -> Result<TransactionBuilder, JsValue> {
+        let kps = keypairs_from_jsvalue(keypairs)?;
+        let alloc_table = alloc_table
+            .into_serde::<BTreeMap<String, u64>>()
+            .c(d!())
+            .and_then(|t| {
+                t.into_iter()
+                    .map(|(pk, am)| {
+                        wallet::public_key_from_base64(&pk)
+                            .c(d!())
+                            .map(|pk| (pk, am))
+                    })
+                    .collect::<ruc::Result<BTreeMap<_, _>>>()
+            })
+            .map_err(error_to_jsvalue)?;
+        self.get_builder_mut()
+            .add_operation_fra_distribution(&kps.iter().collect::<Vec<_>>(), alloc_table)
+            .c(d!())
+            .map_err(error_to_jsvalue)?;
+        Ok(self)
+    }
+
     /// Adds an operation to the transaction builder that support transfer utxo asset to ethereum address.
     /// @param {XfrKeyPair} keypair - Asset creator key pair.
     /// @param {String} ethereum_address - The address to receive Ethereum assets.
@@ -652,6 +876,8 @@ pub fn get_serialized_address(address: String) -> Result<String, JsValue> {
 /// Structure that enables clients to construct complex transfers.
 pub struct TransferOperationBuilder {
     op_builder: PlatformTransferOperationBuilder,
+    // only set by `new_with_seed`
+    prng: Option<ChaChaRng>,
 }
 
 impl TransferOperationBuilder {
@@ -660,6 +886,18 @@ impl TransferOperationBuilder {
         &self.op_builder
     }
 
+    // a generator derived from the seed of `new_with_seed`,
+    // or seeded from the entropy
+    fn get_prng(&mut self) -> ChaChaRng {
+        if let Some(prng) = self.prng.as_mut() {
+            let mut seed = [0u8; 32];
+            prng.fill_bytes(&mut seed);
+            ChaChaRng::from_seed(seed)
+        } else {
+            ChaChaRng::from_entropy()
+        }
+    }
+
     #[allow(missing_docs)]
     pub fn get_builder_mut(&mut self) -> &mut PlatformTransferOperationBuilder {
         &mut self.op_builder
@@ -686,8 +924,10 @@ impl TransferOperationBuilder {
         .map_err(|e| {
             JsValue::from_str(&format!("Could not open asset record: {}", e))
         })?;
+        let mut prng = self.get_prng();
         self.get_builder_mut()
-            .add_input(
+            .add_input_with_rng(
+                &mut prng,
                 *txo_ref.get_txo(),
                 oar,
                 tracing_policies.map(|policies| policies.get_policies_ref().clone()),
@@ -731,8 +971,10 @@ impl TransferOperationBuilder {
                 *recipient,
             )
         };
+        let mut prng = self.get_prng();
         self.get_builder_mut()
-            .add_output(
+            .add_output_with_rng(
+                &mut prng,
                 &template,
                 tracing_policies.map(|policies| policies.get_policies_ref().clone()),
                 None,
@@ -751,6 +993,16 @@ impl TransferOperationBuilder {
         Self::default()
     }
 
+    /// Create a new transfer operation builder whose randomness is derived from `seed`,
+    /// the same seed gives the same operation, only for tests and offline signing.
+    /// @param {BigInt} seed - Seed of the random generator.
+    pub fn new_with_seed(seed: u64) -> Self {
+        TransferOperationBuilder {
+            op_builder: Default::default(),
+            prng: Some(ChaChaRng::seed_from_u64(seed)),
+        }
+    }
+
     /// Wraps around TransferOperationBuilder to add an input to a transfer operation builder.
     /// @param {TxoRef} txo_ref - Absolute or relative utxo reference
     /// @param {string} asset_record - Serialized client asset record to serve as transfer input. This record must exist on the
@@ -807,6 +1059,32 @@ impl TransferOperationBuilder {
         self.add_input(txo_ref, asset_record, owner_memo, None, key, amount)
     }
 
+    /// Wraps around TransferOperationBuilder to spend a record owned by a multisig address.
+    /// @param {TxoRef} txo_ref - Absolute or relative utxo reference
+    /// @param {ClientAssetRecord} asset_record - Non-confidential record owned by the multisig address.
+    /// @param {SignatureRules} rules - Rules of the multisig address.
+    /// @param {BigInt} amount - Amount of input record to transfer
+    /// @see {@link module:Findora-Wasm~sign_multisig_transaction|sign_multisig_transaction} for signing the transaction.
+    /// @throws Will throw an error if the record is not owned by the multisig address.
+    pub fn add_multisig_input(
+        mut self,
+        txo_ref: TxoRef,
+        asset_record: &ClientAssetRecord,
+        rules: &SignatureRules,
+        amount: u64,
+    ) -> Result<TransferOperationBuilder, JsValue> {
+        self.get_builder_mut()
+            .add_multisig_input(
+                *txo_ref.get_txo(),
+                asset_record.get_bar_ref(),
+                rules.sig_rules.clone(),
+                amount,
+            )
+            .c(d!())
+            .map_err(error_to_jsvalue)?;
+        Ok(self)
+    }
+
     /// Wraps around TransferOperationBuilder to add an output to a transfer operation builder.
     ///
     /// @param {BigInt} amount - amount to transfer to the recipient.
@@ -859,8 +1137,9 @@ impl TransferOperationBuilder {
     /// This function will add change outputs for all unspent portions of input records.
     /// @throws Will throw an error if the transaction cannot be balanced.
     pub fn balance(mut self) -> Result<TransferOperationBuilder, JsValue> {
+        let mut prng = self.get_prng();
         self.get_builder_mut()
-            .balance(None)
+            .balance_with_rng(&mut prng, None)
             .c(d!())
             .map_err(|e| JsValue::from_str(&format!("Error balancing txn: {}", e)))?;
         Ok(self)
@@ -871,9 +1150,10 @@ impl TransferOperationBuilder {
     /// @throws Will throw an error if input and output amounts do not add up.
     /// @throws Will throw an error if not all record owners have signed the transaction.
     pub fn create(mut self) -> Result<TransferOperationBuilder, JsValue> {
+        let mut prng = self.get_prng();
         self.get_builder_mut()
             .auto_refund(false)
-            .create(TransferType::Standard)
+            .create_with_rng(&mut prng, TransferType::Standard)
             .c(d!())
             .map_err(error_to_jsvalue)?;
         Ok(self)
@@ -908,6 +1188,44 @@ impl TransferOperationBuilder {
     }
 }
 
+#[wasm_bindgen]
+/// Adds the signature of a multisig signer to the transfers of a serialized transaction,
+/// signers can sign their own copies and combine them later.
+/// @param {string} tx - Serialized transaction.
+/// @param {XfrKeyPair} kp - Key pair of the signer.
+/// @throws Will throw an error if the key pair is not a signer of the multisig addresses.
+pub fn sign_multisig_transaction(
+    tx: String,
+    kp: &XfrKeyPair,
+) -> Result<String, JsValue> {
+    let mut tx = serde_json::from_str::<Transaction>(&tx)
+        .c(d!())
+        .map_err(error_to_jsvalue)?;
+    tx.sign_multisig(kp).c(d!()).map_err(error_to_jsvalue)?;
+    serde_json::to_string(&tx).c(d!()).map_err(error_to_jsvalue)
+}
+
+#[wasm_bindgen]
+/// Combines the signatures of two copies of the same multisig transaction.
+/// @param {string} tx - Serialized transaction.
+/// @param {string} other - Another signed copy of `tx`.
+/// @throws Will throw an error if the transactions are different.
+pub fn combine_multisig_transaction(
+    tx: String,
+    other: String,
+) -> Result<String, JsValue> {
+    let mut tx = serde_json::from_str::<Transaction>(&tx)
+        .c(d!())
+        .map_err(error_to_jsvalue)?;
+    let other = serde_json::from_str::<Transaction>(&other)
+        .c(d!())
+        .map_err(error_to_jsvalue)?;
+    tx.merge_multisig_signatures(&other)
+        .c(d!())
+        .map_err(error_to_jsvalue)?;
+    serde_json::to_string(&tx).c(d!()).map_err(error_to_jsvalue)
+}
+
 ///////////// CRYPTO //////////////////////
 #[wasm_bindgen]
 /// Returns a JavaScript object containing decrypted owner record information,
@@ -1465,6 +1783,22 @@ pub fn fra_get_dest_pubkey() -> XfrPublicKey {
     *BLACK_HOLE_PUBKEY
 }
 
+fn keypairs_from_jsvalue(keypairs: JsValue) -> Result<Vec<XfrKeyPair>, JsValue> {
+    keypairs
+        .into_serde::<Vec<String>>()
+        .c(d!())
+        .and_then(|kps| {
+            kps.iter()
+                .map(|kp| {
+                    hex::decode(kp)
+                        .c(d!())
+                        .and_then(|b| XfrKeyPair::zei_from_bytes(&b).c(d!()))
+                })
+                .collect()
+        })
+        .map_err(error_to_jsvalue)
+}
+
 #[wasm_bindgen]
 /// The system address used to reveive delegation principals.
 pub fn get_delegation_target_address() -> String {
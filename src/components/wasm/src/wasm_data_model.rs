@@ -6,15 +6,20 @@ use {
         Credential as PlatformCredential,
     },
     globutils::{wallet, HashOf},
-    ledger::data_model::{
-        AssetRules as PlatformAssetRules, AssetType as PlatformAssetType,
-        AuthenticatedUtxo, SignatureRules as PlatformSignatureRules, TxOutput,
-        TxoRef as PlatformTxoRef, TxoSID,
+    ledger::{
+        data_model::{
+            AssetRules as PlatformAssetRules, AssetType as PlatformAssetType,
+            AuthenticatedUtxo, CredentialRules,
+            SignatureRules as PlatformSignatureRules, TxOutput,
+            TxoRef as PlatformTxoRef, TxoSID,
+        },
+        staking::{td_pubkey_to_td_addr, StakerMemo as PlatformStakerMemo},
     },
     rand_chacha::ChaChaRng,
     rand_core::SeedableRng,
     ruc::{d, err::RucResult},
     serde::{Deserialize, Serialize},
+    tendermint::{PrivateKey as TdPrivateKey, PublicKey as TdPublicKey},
     wasm_bindgen::prelude::*,
     zei::{
         setup::PublicParams as ZeiPublicParams,
@@ -570,6 +575,15 @@ impl SignatureRules {
         let sig_rules = PlatformSignatureRules { threshold, weights };
         Ok(SignatureRules { sig_rules })
     }
+
+    /// Returns the fra prefixed multisig address defined by the rules.
+    /// @throws Will throw an error if the rules are invalid.
+    pub fn address(&self) -> Result<String, JsValue> {
+        if !self.sig_rules.is_valid() {
+            return Err(JsValue::from_str("invalid signature rules"));
+        }
+        Ok(wallet::public_key_to_bech32(&self.sig_rules.to_address()))
+    }
 }
 
 #[wasm_bindgen]
@@ -703,6 +717,32 @@ impl AssetRules {
         self
     }
 
+    /// Credential rules. Every recipient of assets with credential rules, except the issuer,
+    /// must present a credential signed by the registered issuer which reveals the attributes.
+    /// @param {XfrPublicKey} issuer - Address under which the credential issuer is registered.
+    /// @param {JsValue} attributes - Array of names of the required attributes (i.e. `["kyc"]`).
+    /// @param {JsValue} values - Array of `[name, bytes]` pairs of the values the revealed attributes must hold,
+    /// may be empty (i.e. `[["kyc", [1]]]`).
+    /// @see {@link module:Findora-Wasm~TransactionBuilder#add_operation_register_cred_issuer|add_operation_register_cred_issuer} for more information about
+    /// how to register a credential issuer.
+    pub fn set_required_credentials(
+        mut self,
+        issuer: &XfrPublicKey,
+        attributes: JsValue,
+        values: JsValue,
+    ) -> Result<AssetRules, JsValue> {
+        let attributes: Vec<String> =
+            attributes.into_serde().c(d!()).map_err(error_to_jsvalue)?;
+        let values: Vec<(String, Vec<u8>)> =
+            values.into_serde().c(d!()).map_err(error_to_jsvalue)?;
+        self.rules.set_required_credentials(Some(CredentialRules {
+            issuer: *issuer,
+            attributes,
+            values,
+        }));
+        Ok(self)
+    }
+
     /// Set the decimal number of asset. Return error string if failed, otherwise return changed asset.
     /// #param {Number} decimals - The number of decimals used to set its user representation.
     /// Decimals should be 0 ~ 255.
@@ -715,6 +755,89 @@ impl AssetRules {
     }
 }
 
+#[wasm_bindgen]
+/// Keys of a tendermint validator node.
+/// @see {@link module:Findora-Wasm~TransactionBuilder#add_operation_staking|add_operation_staking} for information about how to
+/// stake with a validator node.
+pub struct TdValidatorKeys {
+    pub(crate) pub_key: TdPublicKey,
+    pub(crate) priv_key: TdPrivateKey,
+}
+
+#[wasm_bindgen]
+impl TdValidatorKeys {
+    /// Restores the keys from the content of `priv_validator_key.json` of a tendermint node.
+    /// @param {string} key_data - JSON-encoded validator keys.
+    /// @throws Will throw an error if `key_data` fails to deserialize.
+    pub fn from_json(key_data: &str) -> Result<TdValidatorKeys, JsValue> {
+        #[derive(Deserialize)]
+        struct ValidatorKey {
+            pub_key: TdPublicKey,
+            priv_key: TdPrivateKey,
+        }
+
+        serde_json::from_str::<ValidatorKey>(key_data)
+            .c(d!())
+            .map_err(error_to_jsvalue)
+            .map(|k| TdValidatorKeys {
+                pub_key: k.pub_key,
+                priv_key: k.priv_key,
+            })
+    }
+
+    /// Returns the tendermint address of the validator, in upper hex.
+    pub fn address(&self) -> String {
+        td_pubkey_to_td_addr(&self.pub_key.to_vec())
+    }
+
+    /// Returns the public key of the validator, in base64.
+    pub fn public_key(&self) -> String {
+        base64::encode(self.pub_key.to_vec())
+    }
+}
+
+#[wasm_bindgen]
+/// Self-description of a staker, displayed by explorers and wallets.
+pub struct StakerMemo {
+    pub(crate) memo: PlatformStakerMemo,
+}
+
+#[wasm_bindgen]
+impl StakerMemo {
+    /// Creates a staker memo.
+    /// @param {string} name - Name of the staker, like "FastNode".
+    /// @param {string} desc - Description of the staker.
+    /// @param {string} website - URL of the staker.
+    /// @param {string} logo - URL of the logo image of the staker.
+    pub fn new(name: String, desc: String, website: String, logo: String) -> Self {
+        StakerMemo {
+            memo: PlatformStakerMemo {
+                name,
+                desc,
+                website,
+                logo,
+            },
+        }
+    }
+
+    /// Builds a staker memo from a JSON-encoded one, eg. the `memo` of a validator detail.
+    /// @param {JsValue} val - JSON-encoded staker memo.
+    /// @throws Will throw an error if `val` fails to deserialize.
+    pub fn from_json(val: &JsValue) -> Result<StakerMemo, JsValue> {
+        val.into_serde()
+            .c(d!())
+            .map_err(error_to_jsvalue)
+            .map(|memo| StakerMemo { memo })
+    }
+
+    /// Returns the JSON-encoded staker memo.
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        JsValue::from_serde(&self.memo)
+            .c(d!())
+            .map_err(error_to_jsvalue)
+    }
+}
+
 #[inline(always)]
 pub(crate) fn error_to_jsvalue<T: Display>(e: T) -> JsValue {
     JsValue::from_str(&e.to_string())
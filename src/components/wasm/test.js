@@ -7,6 +7,7 @@ const SUBMISSION_PORT = "8669";
 
 // Create some keypairs
 let kp_alice = wasm.new_keypair();
+let kp_bob = wasm.new_keypair();
 
 let define = function() {
 let memo = "test asset";
@@ -39,4 +40,58 @@ axios.post(route + '/submit_transaction', JSON.parse(issuance_transaction))
     });
 }
 
+// Build staking operations, no node is needed
+let staking = function() {
+const assert = require('assert');
+const td_key_json = JSON.stringify({
+  address: "70A8EF4B41CA0820265090C99D57121EEEA81FD4",
+  pub_key: {
+    type: "tendermint/PubKeyEd25519",
+    value: "dLSQuGVntvbNGVaEXh1H8eR8l1QLcjPAlhgucx9Dq2k="
+  },
+  priv_key: {
+    type: "tendermint/PrivKeyEd25519",
+    value: "sbAbR/sBF+QPXXZF9caF2bTe8eqiE5KNgGf7pPqbcYJ0tJC4ZWe29s0ZVoReHUfx5HyXVAtyM8CWGC5zH0OraQ=="
+  }
+});
+let td_keys = wasm.TdValidatorKeys.from_json(td_key_json);
+assert.strictEqual(td_keys.address(), "70A8EF4B41CA0820265090C99D57121EEEA81FD4");
+assert.strictEqual(td_keys.public_key(), "dLSQuGVntvbNGVaEXh1H8eR8l1QLcjPAlhgucx9Dq2k=");
+
+let memo = wasm.StakerMemo.new("FastNode", "test node", "https://fast.node", "https://fast.node/logo.png");
+assert.strictEqual(wasm.StakerMemo.from_json(memo.to_json()).to_json().name, "FastNode");
+
+let ops = function(tx) {
+  return JSON.parse(tx).body.operations.map(op => Object.keys(op)[0]);
+};
+
+let tx = wasm.TransactionBuilder.new(0n)
+  .add_operation_staking(kp_alice, 10000000000000n, td_keys, 0.1, 0.2, memo)
+  .transaction();
+assert.deepStrictEqual(ops(tx), ["Delegation"]);
+
+tx = wasm.TransactionBuilder.new(0n)
+  .add_operation_update_staker(kp_alice, td_keys, 0.15, memo)
+  .add_operation_replace_staker(kp_alice, kp_bob.get_pk(), td_keys.public_key())
+  .transaction();
+assert.deepStrictEqual(ops(tx), ["UpdateStaker", "ReplaceStaker"]);
+
+let signers = [wasm.keypair_to_str(kp_alice), wasm.keypair_to_str(kp_bob)];
+let alloc_table = {};
+alloc_table[wasm.public_key_to_base64(kp_bob.get_pk())] = 100;
+tx = wasm.TransactionBuilder.new(0n)
+  .add_operation_governance(signers, kp_bob.get_pk(), "OffLine", undefined)
+  .add_operation_fra_distribution(signers, alloc_table)
+  .transaction();
+assert.deepStrictEqual(ops(tx), ["Governance", "FraDistribution"]);
+
+assert.throws(() => wasm.TransactionBuilder.new(0n)
+  .add_operation_staking(kp_alice, 10000000000000n, td_keys, 1.5, undefined, memo));
+assert.throws(() => wasm.TransactionBuilder.new(0n)
+  .add_operation_governance(signers, kp_bob.get_pk(), "Sleeping", undefined));
+console.log("Successfully built staking operations.");
+}
+
+staking();
+
 setInterval(define, 1000);
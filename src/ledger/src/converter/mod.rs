@@ -1,15 +1,17 @@
 //! Multi Signer operation for transaction.
 
 use crate::data_model::{
-    NoReplayToken, Operation, Transaction, ASSET_TYPE_FRA, BLACK_HOLE_PUBKEY_STAKING,
+    NoReplayToken, Operation, Transaction, TxOutput, ASSET_TYPE_FRA,
+    BLACK_HOLE_PUBKEY_STAKING,
 };
 use config::abci::global_cfg::CFG;
 use fp_types::crypto::MultiSigner;
+use globutils::amount_proof::AmountProof;
 use ruc::*;
 use serde::{Deserialize, Serialize};
 use zei::xfr::{
     sig::XfrPublicKey,
-    structs::{XfrAmount, XfrAssetType},
+    structs::{BlindAssetRecord, XfrAmount, XfrAssetType},
 };
 
 /// Use this operation to transfer.
@@ -26,6 +28,10 @@ pub struct ConvertAccount {
     /// convert UTXOs value
     #[serde(with = "serde_strz")]
     pub value: u64,
+    /// proofs of the confidential amounts sent to the black hole,
+    /// in the order of the outputs
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub amount_proofs: Vec<AmountProof>,
 }
 
 #[allow(missing_docs)]
@@ -64,6 +70,7 @@ pub fn check_convert_account(
     let signer;
     let target;
     let expected_value;
+    let amount_proofs;
 
     if let Some(Operation::ConvertAccount(ca)) = tx.body.operations.last() {
         if ca.nonce != tx.body.no_replay_token {
@@ -85,6 +92,7 @@ pub fn check_convert_account(
         signer = ca.signer;
         target = ca.receiver.clone();
         expected_value = ca.value;
+        amount_proofs = &ca.amount_proofs;
     } else {
         return Err(eg!(
             "TransferUTXOsToEVM error: invalid ConvertAccount operation"
@@ -97,25 +105,16 @@ pub fn check_convert_account(
             return Err(eg!("TransferUTXOsToEVM error: not found signer"));
         }
 
-        let mut convert_amount = 0_u64;
-        for o in &t.body.outputs {
-            if matches!(o.record.asset_type, XfrAssetType::Confidential(_))
-                || matches!(o.record.amount, XfrAmount::Confidential(_))
-            {
+        let convert_amount = if CFG.checkpoint.confidential_convert_height > height {
+            if !amount_proofs.is_empty() {
                 return Err(eg!(
-                        "TransferUTXOsToEVM error: only support non-confidential UTXOs transfer to an evm account"
-                    ));
-            }
-            if let XfrAssetType::NonConfidential(ty) = o.record.asset_type {
-                if o.record.public_key == *BLACK_HOLE_PUBKEY_STAKING
-                    && ty == ASSET_TYPE_FRA
-                {
-                    if let XfrAmount::NonConfidential(amount) = o.record.amount {
-                        convert_amount += amount;
-                    }
-                }
+                    "TransferUTXOsToEVM error: confidential amounts are not supported yet"
+                ));
             }
-        }
+            nonconfidential_convert_amount(&t.body.outputs).c(d!())?
+        } else {
+            confidential_convert_amount(&t.body.outputs, amount_proofs).c(d!())?
+        };
         if expected_value != convert_amount {
             return Err(eg!("TransferUTXOsToEVM error: invalid convert value"));
         }
@@ -127,3 +126,89 @@ pub fn check_convert_account(
 
     Ok((target, expected_value))
 }
+
+fn nonconfidential_convert_amount(outputs: &[TxOutput]) -> Result<u64> {
+    let mut convert_amount = 0_u64;
+    for o in outputs {
+        if matches!(o.record.asset_type, XfrAssetType::Confidential(_))
+            || matches!(o.record.amount, XfrAmount::Confidential(_))
+        {
+            return Err(eg!(
+                "TransferUTXOsToEVM error: only support non-confidential UTXOs transfer to an evm account"
+            ));
+        }
+        if let XfrAssetType::NonConfidential(ty) = o.record.asset_type {
+            if o.record.public_key == *BLACK_HOLE_PUBKEY_STAKING && ty == ASSET_TYPE_FRA
+            {
+                if let XfrAmount::NonConfidential(amount) = o.record.amount {
+                    convert_amount += amount;
+                }
+            }
+        }
+    }
+    Ok(convert_amount)
+}
+
+// Only the outputs to the black hole are converted,
+// the others, eg. the change, may be fully confidential.
+fn confidential_convert_amount(
+    outputs: &[TxOutput],
+    amount_proofs: &[AmountProof],
+) -> Result<u64> {
+    let mut proofs = amount_proofs.iter();
+    let mut convert_amount = 0_u64;
+    for o in outputs {
+        if o.record.public_key != *BLACK_HOLE_PUBKEY_STAKING {
+            continue;
+        }
+        match o.record.asset_type {
+            XfrAssetType::NonConfidential(ty) if ty == ASSET_TYPE_FRA => {}
+            XfrAssetType::NonConfidential(_) => continue,
+            XfrAssetType::Confidential(_) => {
+                return Err(eg!(
+                    "TransferUTXOsToEVM error: the asset type to convert must be non-confidential"
+                ));
+            }
+        }
+        let amount = match o.record.amount {
+            XfrAmount::NonConfidential(amount) => amount,
+            XfrAmount::Confidential(_) => {
+                let proof = proofs
+                    .next()
+                    .c(d!("TransferUTXOsToEVM error: missing amount proof"))?;
+                proof
+                    .verify(&o.record.amount)
+                    .c(d!("TransferUTXOsToEVM error: invalid amount proof"))?;
+                proof.amount
+            }
+        };
+        convert_amount = convert_amount
+            .checked_add(amount)
+            .c(d!("TransferUTXOsToEVM error: convert value overflow"))?;
+    }
+    if proofs.next().is_some() {
+        return Err(eg!("TransferUTXOsToEVM error: redundant amount proofs"));
+    }
+    Ok(convert_amount)
+}
+
+/// Check the outputs of an EVM to UTXOs transfer with confidential amounts,
+/// returns the total amount of them.
+pub fn check_confidential_outputs(
+    outputs: &[(&BlindAssetRecord, &AmountProof)],
+    height: i64,
+) -> Result<u64> {
+    if CFG.checkpoint.confidential_convert_height > height {
+        return Err(eg!("confidential amounts are not supported yet"));
+    }
+    let mut amount = 0_u64;
+    for (record, proof) in outputs {
+        if !matches!(record.asset_type, XfrAssetType::NonConfidential(ty) if ty == ASSET_TYPE_FRA)
+        {
+            return Err(eg!("only non-confidential FRA is supported"));
+        }
+        proof.verify(&record.amount).c(d!("invalid amount proof"))?;
+        amount = amount.checked_add(proof.amount).c(d!("amount overflow"))?;
+    }
+    Ok(amount)
+}
@@ -4,19 +4,25 @@ use {
     crate::{
         data_model::{
             AssetType, AssetTypeCode, DefineAsset, IssueAsset, IssuerPublicKey, Memo,
-            NoReplayToken, Operation, Transaction, TransferAsset, TransferType,
-            TxOutput, TxnTempSID, TxoRef, TxoSID, UpdateMemo,
+            NoReplayToken, Operation, RegisterCredIssuer, Transaction, TransferAsset,
+            TransferType, TxOutput, TxnTempSID, TxoRef, TxoSID, UpdateMemo,
         },
         staking::{
             self,
             ops::{
-                claim::ClaimOps, delegation::DelegationOps,
-                fra_distribution::FraDistributionOps, governance::GovernanceOps,
-                replace_staker::ReplaceStakerOps, undelegation::UnDelegationOps,
-                update_staker::UpdateStakerOps, update_validator::UpdateValidatorOps,
+                claim::ClaimOps,
+                delegation::DelegationOps,
+                fra_distribution::FraDistributionOps,
+                governance::GovernanceOps,
+                replace_staker::ReplaceStakerOps,
+                undelegation::UnDelegationOps,
+                update_staker::UpdateStakerOps,
+                update_validator::UpdateValidatorOps,
+                vesting::{CreateVestingOps, ReleaseVestedOps},
             },
         },
     },
+    credentials::CredIssuerPublicKey,
     globutils::HashOf,
     lazy_static::lazy_static,
     parking_lot::Mutex,
@@ -77,6 +83,8 @@ pub struct TxnEffect {
     pub asset_types_involved: HashSet<AssetTypeCode>,
     /// Memo updates
     pub memo_updates: Vec<(AssetTypeCode, XfrPublicKey, Memo)>,
+    /// Registrations of credential issuers
+    pub cred_issuers: HashMap<XfrPublicKey, CredIssuerPublicKey>,
 
     /// Staking operations
     pub delegations: Vec<DelegationOps>,
@@ -94,6 +102,10 @@ pub struct TxnEffect {
     pub update_stakers: Vec<UpdateStakerOps>,
     /// replace staker operations
     pub replace_stakers: Vec<ReplaceStakerOps>,
+    /// Vesting operations
+    pub create_vestings: Vec<CreateVestingOps>,
+    /// Vesting operations
+    pub release_vesteds: Vec<ReleaseVestedOps>,
 }
 
 impl TxnEffect {
@@ -140,7 +152,7 @@ impl TxnEffect {
                     });
                 }
                 Operation::TransferAsset(trn) => {
-                    te.add_transfer_asset(trn, &mut txo_count).c(d!())?;
+                    te.add_transfer_asset(&txn, trn, &mut txo_count).c(d!())?;
                 }
                 Operation::Claim(i) => {
                     check_nonce!(i);
@@ -198,9 +210,27 @@ impl TxnEffect {
                 Operation::ConvertAccount(i) => {
                     check_nonce!(i)
                 }
+                Operation::CreateVesting(i) => {
+                    check_nonce!(i);
+                    i.verify().c(d!())?;
+                    te.create_vestings.push(i.clone());
+                }
+                Operation::ReleaseVested(i) => {
+                    check_nonce!(i);
+                    i.verify().c(d!())?;
+                    te.release_vesteds.push(i.clone());
+                }
+                Operation::RegisterCredIssuer(i) => {
+                    te.add_register_cred_issuer(&txn, i).c(d!())?;
+                }
             }
         }
 
+        // The issuer keys of credential proofs are checked in check_txn_effects
+        for proof in txn.body.credentials.iter() {
+            proof.verify().c(d!())?;
+        }
+
         te.txn = txn;
         Ok(te)
     }
@@ -335,6 +365,7 @@ impl TxnEffect {
     //            to have lien assignments
     fn add_transfer_asset(
         &mut self,
+        txn: &Transaction,
         trn: &TransferAsset,
         txo_count: &mut usize,
     ) -> Result<()> {
@@ -408,10 +439,14 @@ impl TxnEffect {
                     input_keys.insert(sig.address.key.zei_to_bytes());
                 }
 
-                // (1b) all input record owners have signed
+                // (1b) all input record owners have signed,
+                // the owners of multisig addresses must reach the threshold
                 for record in trn.body.transfer.inputs.iter() {
                     if !input_keys.contains(&record.public_key.zei_to_bytes()) {
-                        return Err(eg!());
+                        let rules = trn
+                            .get_multisig_rules(&record.public_key)
+                            .c(d!("input owner has not signed"))?;
+                        trn.check_multisig(rules).c(d!())?;
                     }
                 }
 
@@ -535,6 +570,31 @@ impl TxnEffect {
 
         Ok(())
     }
+
+    // A credential issuer registration is valid iff:
+    // 1) The signature is valid.
+    // 2) The issuer is registered only once in the transaction.
+    fn add_register_cred_issuer(
+        &mut self,
+        txn: &Transaction,
+        reg: &RegisterCredIssuer,
+    ) -> Result<()> {
+        if txn.body.no_replay_token != reg.body.no_replay_token {
+            return Err(eg!("replay token not match"));
+        }
+        // 1)
+        reg.signature.verify(&reg.pubkey, &reg.body).c(d!())?;
+        // 2)
+        if self
+            .cred_issuers
+            .insert(reg.pubkey, reg.body.issuer_pub_key.clone())
+            .is_some()
+        {
+            return Err(eg!("dup entries"));
+        }
+
+        Ok(())
+    }
 }
 
 /// Check tx in the context of a block, partially.
@@ -564,6 +624,8 @@ pub struct BlockEffect {
     pub issuance_keys: HashMap<AssetTypeCode, IssuerPublicKey>,
     /// Memo updates
     pub memo_updates: HashMap<AssetTypeCode, Memo>,
+    /// Registrations of credential issuers
+    pub cred_issuers: HashMap<XfrPublicKey, CredIssuerPublicKey>,
     /// counter for consensus integration; will add to a running count when applied.
     pub pulse_count: u64,
     /// simulator for safety
@@ -614,6 +676,10 @@ impl BlockEffect {
             self.memo_updates.insert(code, memo);
         }
 
+        for (issuer, key) in txn_effect.cred_issuers {
+            self.cred_issuers.insert(issuer, key);
+        }
+
         Ok(temp_sid)
     }
 
@@ -657,6 +723,34 @@ impl BlockEffect {
             }
         }
 
+        // Each credential issuer can only be registered once per block
+        for issuer in txn_effect.cred_issuers.keys() {
+            if self.cred_issuers.contains_key(issuer) {
+                return Err(eg!());
+            }
+        }
+
+        // Multisig addresses are not available before the checkpoint
+        if CFG.checkpoint.multisig_address_height > self.staking_simulator.cur_height()
+            && txn_effect.txn.body.operations.iter().any(|op| {
+                matches!(op, Operation::TransferAsset(t) if !t.multisig_rules.is_empty())
+            })
+        {
+            return Err(eg!("multisig address is not enabled"));
+        }
+
+        // Credential-gated assets are not available before the checkpoint
+        if CFG.checkpoint.cred_gated_asset_height > self.staking_simulator.cur_height()
+            && (!txn_effect.cred_issuers.is_empty()
+                || !txn_effect.txn.body.credentials.is_empty()
+                || txn_effect
+                    .new_asset_codes
+                    .values()
+                    .any(|a| a.properties.asset_rules.required_credentials.is_some()))
+        {
+            return Err(eg!("credential-gated asset is not enabled"));
+        }
+
         // Check that no operations are duplicated as in a replay attack
         // Note that we need to check here as well as in LedgerStatus::check_txn_effect
         for txn in self.txns.iter() {
@@ -682,6 +776,11 @@ impl BlockEffect {
                 .c(d!())?;
         }
 
+        for i in txn_effect.create_vestings.iter() {
+            i.check_run(&mut self.staking_simulator, &txn_effect.txn)
+                .c(d!())?;
+        }
+
         for i in txn_effect.delegations.iter() {
             i.check_run(&mut self.staking_simulator, &txn_effect.txn)
                 .c(d!())?;
@@ -696,6 +795,10 @@ impl BlockEffect {
             i.check_run(&mut self.staking_simulator).c(d!())?;
         }
 
+        for i in txn_effect.release_vesteds.iter() {
+            i.check_run(&mut self.staking_simulator).c(d!())?;
+        }
+
         for i in txn_effect.update_validators.values() {
             i.check_run(&mut self.staking_simulator).c(d!())?;
         }
@@ -15,20 +15,33 @@ use {
     crate::converter::ConvertAccount,
     crate::staking::{
         ops::{
-            claim::ClaimOps, delegation::DelegationOps,
-            fra_distribution::FraDistributionOps, governance::GovernanceOps,
-            mint_fra::MintFraOps, replace_staker::ReplaceStakerOps,
-            undelegation::UnDelegationOps, update_staker::UpdateStakerOps,
+            claim::ClaimOps,
+            delegation::DelegationOps,
+            fra_distribution::FraDistributionOps,
+            governance::GovernanceOps,
+            mint_fra::MintFraOps,
+            replace_staker::ReplaceStakerOps,
+            undelegation::UnDelegationOps,
+            update_staker::UpdateStakerOps,
             update_validator::UpdateValidatorOps,
+            vesting::{CreateVestingOps, ReleaseVestedOps},
         },
         Staking,
     },
     __trash__::{Policy, PolicyGlobals, TxnPolicyData},
     bitmap::SparseMap,
-    cryptohash::{sha256::Digest as BitDigest, HashValue},
+    credentials::{
+        credential_commit, credential_open_commitment, credential_verify,
+        credential_verify_commitment, CredCommitment, CredIssuerPublicKey, CredPoK,
+        CredUserSecretKey, Credential,
+    },
+    cryptohash::{
+        sha256::{self, Digest as BitDigest},
+        HashValue,
+    },
     fbnc::NumKey,
-    globutils::wallet::public_key_to_base64,
-    globutils::{HashOf, ProofOf, Serialized, SignatureOf},
+    globutils::wallet::{public_key_to_base64, public_key_to_bech32},
+    globutils::{signer::Signer, HashOf, ProofOf, Serialized, SignatureOf},
     lazy_static::lazy_static,
     rand::Rng,
     rand_chacha::{rand_core, ChaChaRng},
@@ -81,7 +94,7 @@ pub struct Code {
 }
 
 #[inline(always)]
-fn is_default<T: Default + PartialEq>(x: &T) -> bool {
+pub(crate) fn is_default<T: Default + PartialEq>(x: &T) -> bool {
     x == &T::default()
 }
 
@@ -478,6 +491,11 @@ where
     }
 }
 
+/// The max number of signers of a multisig address.
+pub const MULTISIG_SIGNERS_MAX: usize = 64;
+
+const MULTISIG_ADDRESS_DOMAIN: &[u8] = b"Findora Multisig Address";
+
 /// Stores threshold and weights for a multisignature requirement.
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct SignatureRules {
@@ -509,6 +527,36 @@ impl SignatureRules {
         }
         Ok(())
     }
+
+    /// Returns true if the threshold can be reached, and no signer is duplicated.
+    pub fn is_valid(&self) -> bool {
+        let mut keys = HashSet::new();
+        let mut sum: u64 = 0;
+        0 < self.threshold
+            && !self.weights.is_empty()
+            && MULTISIG_SIGNERS_MAX >= self.weights.len()
+            && self.weights.iter().all(|(key, weight)| {
+                sum = sum.saturating_add(*weight);
+                0 < *weight && keys.insert(key.as_bytes())
+            })
+            && sum >= self.threshold
+    }
+
+    /// The multisig address defined by these rules.
+    ///
+    /// The address is hashed to a curve point, so its secret key is unknown to anyone,
+    /// outputs locked to it can only be spent by the signers of these rules.
+    pub fn to_address(&self) -> XfrPublicKey {
+        let rules = pnk!(bincode::serialize(self));
+        (0u32..)
+            .find_map(|i| {
+                let mut data = MULTISIG_ADDRESS_DOMAIN.to_vec();
+                data.extend_from_slice(&rules);
+                data.extend_from_slice(&i.to_be_bytes());
+                XfrPublicKey::zei_from_bytes(&sha256::hash(&data).0).ok()
+            })
+            .unwrap()
+    }
 }
 
 /// Simple asset rules
@@ -531,6 +579,11 @@ pub struct AssetRules {
     pub max_units: Option<u64>,
     /// Decimals: default to FRA_DECIMALS
     pub decimals: u8,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_default")]
+    /// Required credentials: Every recipient other than the issuer must present a
+    ///   credential proof that reveals these attributes.
+    pub required_credentials: Option<CredentialRules>,
 }
 impl Default for AssetRules {
     #[inline(always)]
@@ -542,6 +595,7 @@ impl Default for AssetRules {
             max_units: None,
             transfer_multisig_rules: None,
             decimals: FRA_DECIMALS,
+            required_credentials: None,
         }
     }
 }
@@ -585,6 +639,16 @@ impl AssetRules {
         self
     }
 
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn set_required_credentials(
+        &mut self,
+        required_credentials: Option<CredentialRules>,
+    ) -> &mut Self {
+        self.required_credentials = required_credentials;
+        self
+    }
+
     #[inline(always)]
     #[allow(missing_docs)]
     pub fn set_decimals(&mut self, decimals: u8) -> Result<&mut Self> {
@@ -668,14 +732,134 @@ pub struct CustomAssetPolicy {
     policy: Vec<u8>, // serialized policy, underlying form TBD.
 }
 
-#[allow(missing_docs)]
-#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
-pub struct CredentialProofKey([u8; 16]);
-
-#[allow(missing_docs)]
-#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+/// Credentials required to receive a credential-gated asset.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct CredentialRules {
+    /// The address under which the credential issuer is registered
+    pub issuer: XfrPublicKey,
+    /// Names of the attributes that must be revealed
+    pub attributes: Vec<String>,
+    /// Values that the revealed attributes must hold, eg. `("country", b"US")`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub values: Vec<(String, Vec<u8>)>,
+}
+
+/// A credential presented by the recipient of a credential-gated asset.
+///
+/// The commitment is bound to the address of the recipient and the proof is signed by it,
+/// so the proof can not be reused by any other address,
+/// nor made for an address whose owner does not hold the credential.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct CredentialProof {
-    pub key: CredentialProofKey,
+    /// The recipient who owns the credential
+    pub owner: XfrPublicKey,
+    /// Public key of the issuer who signed the credential
+    pub issuer_pub_key: CredIssuerPublicKey,
+    /// The revealed attributes
+    pub attributes: Vec<(String, Vec<u8>)>,
+    /// Re-randomized signature of the credential
+    pub commitment: CredCommitment,
+    /// Proof of knowledge of the commitment, signed on `owner`
+    pub commitment_pok: CredPoK,
+    /// Proof that the commitment reveals `attributes`
+    pub reveal_pok: CredPoK,
+    /// Signature of `owner` on the revealed attributes and the proofs
+    pub owner_signature: SignatureOf<CredentialReveal>,
+}
+
+/// The part of a `CredentialProof` signed by its owner.
+pub type CredentialReveal = (Vec<(String, Vec<u8>)>, CredCommitment, CredPoK);
+
+impl CredentialProof {
+    /// Commit to `credential` for the address of `owner_kp`,
+    /// and reveal the attributes named in `reveal_fields`.
+    pub fn new<R: CryptoRng + RngCore>(
+        prng: &mut R,
+        user_sk: &CredUserSecretKey,
+        credential: &Credential,
+        owner_kp: &XfrKeyPair,
+        reveal_fields: &[String],
+    ) -> Result<Self> {
+        let owner = owner_kp.get_pk();
+        let (commitment, commitment_pok, key) =
+            credential_commit(prng, user_sk, credential, owner.as_bytes()).c(d!())?;
+        let reveal_pok =
+            credential_open_commitment(prng, user_sk, credential, &key, reveal_fields)
+                .c(d!())?;
+        let attributes = credential
+            .attributes
+            .iter()
+            .filter(|(k, _)| reveal_fields.contains(k))
+            .cloned()
+            .collect();
+
+        let owner_signature = SignatureOf::new(
+            owner_kp,
+            &(attributes.clone(), commitment.clone(), reveal_pok.clone()),
+        );
+
+        Ok(CredentialProof {
+            owner,
+            issuer_pub_key: credential.issuer_pub_key.clone(),
+            attributes,
+            commitment,
+            commitment_pok,
+            reveal_pok,
+            owner_signature,
+        })
+    }
+
+    /// Verify the proofs, the issuer key should be checked against the registered one.
+    pub fn verify(&self) -> Result<()> {
+        credential_verify_commitment(
+            &self.issuer_pub_key,
+            &self.commitment,
+            &self.commitment_pok,
+            self.owner.as_bytes(),
+        )
+        .c(d!("invalid credential commitment"))?;
+
+        self.owner_signature
+            .verify(
+                &self.owner,
+                &(
+                    self.attributes.clone(),
+                    self.commitment.clone(),
+                    self.reveal_pok.clone(),
+                ),
+            )
+            .c(d!("credential proof is not signed by its owner"))?;
+
+        let attrs = self
+            .attributes
+            .iter()
+            .map(|(k, v)| (k.clone(), v.as_slice()))
+            .collect::<Vec<_>>();
+        credential_verify(
+            &self.issuer_pub_key,
+            &attrs,
+            &self.commitment,
+            &self.reveal_pok,
+        )
+        .c(d!("invalid credential reveal proof"))
+    }
+
+    /// Whether this proof satisfies `rules` for `owner`,
+    /// `issuer_pub_key` is the key registered by `rules.issuer`.
+    pub fn satisfies(
+        &self,
+        owner: &XfrPublicKey,
+        rules: &CredentialRules,
+        issuer_pub_key: &CredIssuerPublicKey,
+    ) -> bool {
+        &self.owner == owner
+            && &self.issuer_pub_key == issuer_pub_key
+            && rules
+                .attributes
+                .iter()
+                .all(|a| self.attributes.iter().any(|(k, _)| k == a))
+            && rules.values.iter().all(|a| self.attributes.contains(a))
+    }
 }
 
 #[derive(
@@ -935,6 +1119,55 @@ impl TransferAssetBody {
         }
     }
 
+    /// Computes a body signature with a `Signer`, e.g. a hardware wallet.
+    pub fn compute_body_signature_with_signer<S: Signer + ?Sized>(
+        &self,
+        signer: &S,
+        input_idx: Option<usize>,
+    ) -> Result<IndexedSignature<TransferAssetBody>> {
+        let signature = SignatureOf::new_with_signer(
+            signer,
+            &(self.clone(), input_idx),
+            &self.summary(),
+        )
+        .c(d!())?;
+        Ok(IndexedSignature {
+            signature,
+            address: XfrAddress {
+                key: signer.public_key().c(d!())?,
+            },
+            input_idx,
+        })
+    }
+
+    /// A readable summary of the outputs,
+    /// which is displayed by the hardware wallets before signing.
+    pub fn summary(&self) -> String {
+        self.outputs
+            .iter()
+            .map(|o| {
+                let amount = o
+                    .record
+                    .amount
+                    .get_amount()
+                    .map(|am| am.to_string())
+                    .unwrap_or_else(|| "<confidential amount>".to_owned());
+                let asset = match o.record.asset_type.get_asset_type() {
+                    Some(ty) if ty == ASSET_TYPE_FRA => "FRA".to_owned(),
+                    Some(ty) => AssetTypeCode { val: ty }.to_base64(),
+                    None => "<confidential asset>".to_owned(),
+                };
+                format!(
+                    "transfer {} {} to {}",
+                    amount,
+                    asset,
+                    public_key_to_bech32(&o.record.public_key)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
     /// Verifies a body signature
     #[inline(always)]
     pub fn verify_body_signature(
@@ -1051,6 +1284,10 @@ pub enum OutputSpentStatus {
 pub struct TransferAsset {
     pub body: TransferAssetBody,
     pub body_signatures: Vec<IndexedSignature<TransferAssetBody>>,
+    /// Rules of the multisig addresses that own some of the inputs
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_default")]
+    pub multisig_rules: Vec<SignatureRules>,
 }
 
 impl TransferAsset {
@@ -1060,6 +1297,7 @@ impl TransferAsset {
         Ok(TransferAsset {
             body: transfer_body,
             body_signatures: Vec::new(),
+            multisig_rules: Vec::new(),
         })
     }
 
@@ -1070,6 +1308,15 @@ impl TransferAsset {
         self.attach_signature(sig).unwrap()
     }
 
+    /// Sign with a `Signer`, e.g. a hardware wallet.
+    #[inline(always)]
+    pub fn sign_with_signer<S: Signer + ?Sized>(&mut self, signer: &S) -> Result<()> {
+        self.body
+            .compute_body_signature_with_signer(signer, None)
+            .c(d!())
+            .and_then(|sig| self.attach_signature(sig).c(d!()))
+    }
+
     #[inline(always)]
     #[allow(missing_docs)]
     pub fn attach_signature(
@@ -1103,6 +1350,48 @@ impl TransferAsset {
             .collect()
     }
 
+    /// Attach the rules of a multisig address which owns some of the inputs.
+    pub fn attach_multisig_rules(&mut self, rules: SignatureRules) -> Result<()> {
+        if !rules.is_valid() {
+            return Err(eg!("invalid signature rules"));
+        }
+        if !self.multisig_rules.contains(&rules) {
+            self.multisig_rules.push(rules);
+        }
+        Ok(())
+    }
+
+    /// Find the rules of a multisig address in this transfer.
+    #[inline(always)]
+    pub fn get_multisig_rules(&self, addr: &XfrPublicKey) -> Option<&SignatureRules> {
+        self.multisig_rules.iter().find(|r| &r.to_address() == addr)
+    }
+
+    /// Check if `pk` is a signer of a multisig address in this transfer.
+    #[inline(always)]
+    pub fn is_multisig_signer(&self, pk: &XfrPublicKey) -> bool {
+        self.multisig_rules
+            .iter()
+            .any(|r| r.weights.iter().any(|(k, _)| k == pk))
+    }
+
+    /// Check that the signers of the transfer body reach the threshold of a multisig address,
+    /// the signatures can be collected separately with `attach_signature`.
+    pub fn check_multisig(&self, rules: &SignatureRules) -> Result<()> {
+        if !rules.is_valid() {
+            return Err(eg!("invalid signature rules"));
+        }
+        let signers = self
+            .body_signatures
+            .iter()
+            .filter(|sig| sig.input_idx.is_none() && sig.verify(&self.body))
+            .map(|sig| sig.address.key.as_bytes().to_vec())
+            .collect::<HashSet<_>>();
+        rules
+            .check_signature_set(&signers)
+            .c(d!("multisig threshold is not reached"))
+    }
+
     #[inline(always)]
     #[allow(missing_docs)]
     pub fn get_owner_addresses(&self) -> Vec<XfrPublicKey> {
@@ -1217,6 +1506,38 @@ impl UpdateMemo {
     }
 }
 
+/// Operation data for registering the public key of a credential issuer
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct RegisterCredIssuer {
+    /// Inner data to register
+    pub body: RegisterCredIssuerBody,
+    /// The address under which the issuer is registered
+    pub pubkey: XfrPublicKey,
+    /// the signature
+    pub signature: SignatureOf<RegisterCredIssuerBody>,
+}
+
+impl RegisterCredIssuer {
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn new(body: RegisterCredIssuerBody, signing_key: &XfrKeyPair) -> Self {
+        let signature = SignatureOf::new(signing_key, &body);
+        RegisterCredIssuer {
+            body,
+            pubkey: *signing_key.get_pk_ref(),
+            signature,
+        }
+    }
+}
+
+#[allow(missing_docs)]
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct RegisterCredIssuerBody {
+    /// A registered key will be replaced by the new one
+    pub issuer_pub_key: CredIssuerPublicKey,
+    pub no_replay_token: NoReplayToken,
+}
+
 /// Operation list supported in findora network
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub enum Operation {
@@ -1248,6 +1569,12 @@ pub enum Operation {
     ConvertAccount(ConvertAccount),
     ///replace staker.
     ReplaceStaker(ReplaceStakerOps),
+    /// Lock FRA token under a release schedule
+    CreateVesting(CreateVestingOps),
+    /// Release vested FRA token to the beneficiary
+    ReleaseVested(ReleaseVestedOps),
+    /// Register the public key of a credential issuer
+    RegisterCredIssuer(RegisterCredIssuer),
 }
 
 fn set_no_replay_token(op: &mut Operation, no_replay_token: NoReplayToken) {
@@ -1275,6 +1602,9 @@ fn set_no_replay_token(op: &mut Operation, no_replay_token: NoReplayToken) {
         }
         Operation::UpdateMemo(i) => i.body.no_replay_token = no_replay_token,
         Operation::ConvertAccount(i) => i.set_nonce(no_replay_token),
+        Operation::CreateVesting(i) => i.set_nonce(no_replay_token),
+        Operation::ReleaseVested(i) => i.set_nonce(no_replay_token),
+        Operation::RegisterCredIssuer(i) => i.body.no_replay_token = no_replay_token,
         _ => {}
     }
 }
@@ -1302,6 +1632,36 @@ impl TransactionBody {
         result.no_replay_token = no_replay_token;
         result
     }
+
+    /// A readable summary of the operations,
+    /// which is displayed by the hardware wallets before signing.
+    pub fn summary(&self) -> String {
+        self.operations
+            .iter()
+            .map(|op| match op {
+                Operation::TransferAsset(x) => x.body.summary(),
+                Operation::IssueAsset(_) => "issue asset".to_owned(),
+                Operation::DefineAsset(_) => "define asset".to_owned(),
+                Operation::UpdateMemo(_) => "update memo".to_owned(),
+                Operation::UpdateStaker(_) => "update staker".to_owned(),
+                Operation::Delegation(_) => "delegate".to_owned(),
+                Operation::UnDelegation(_) => "undelegate".to_owned(),
+                Operation::Claim(_) => "claim".to_owned(),
+                Operation::UpdateValidator(_) => "update validators".to_owned(),
+                Operation::Governance(_) => "governance".to_owned(),
+                Operation::FraDistribution(_) => "FRA distribution".to_owned(),
+                Operation::MintFra(_) => "mint FRA".to_owned(),
+                Operation::ConvertAccount(_) => "convert to EVM account".to_owned(),
+                Operation::ReplaceStaker(_) => "replace staker".to_owned(),
+                Operation::CreateVesting(_) => "create vesting".to_owned(),
+                Operation::ReleaseVested(_) => "release vested".to_owned(),
+                Operation::RegisterCredIssuer(_) => {
+                    "register credential issuer".to_owned()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
 }
 
 #[allow(missing_docs)]
@@ -1331,22 +1691,44 @@ pub struct FinalizedTransaction {
 pub struct AuthenticatedUtxo {
     /// Utxo to authenticate
     pub utxo: Utxo,
-    /// Merkle proof that transaction containing the utxo exists on the ledger
-    pub authenticated_txn: AuthenticatedTransaction,
+    /// Merkle proof that transaction containing the utxo exists on the ledger,
+    /// `None` if the transaction has been pruned
+    pub authenticated_txn: Option<AuthenticatedTransaction>,
     /// Bitmap proof that the utxo is unspent
     pub authenticated_spent_status: AuthenticatedUtxoStatus,
     /// which output this utxo locations
     pub utxo_location: OutputPosition,
     /// utxo proof data
     pub state_commitment_data: StateCommitmentData,
+    /// Owner memo of the utxo, only set if the transaction has been pruned
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner_memo: Option<OwnerMemo>,
 }
 
+/// `txn` is `None` if the transaction has been pruned,
+/// the owner memo of the utxo is then kept in `owner_memo`.
 #[allow(missing_docs)]
 #[derive(Clone, Serialize, Deserialize)]
 pub struct UnAuthenticatedUtxo {
     pub utxo: Utxo,
-    pub txn: FinalizedTransaction,
+    pub txn: Option<FinalizedTransaction>,
     pub utxo_location: OutputPosition,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner_memo: Option<OwnerMemo>,
+}
+
+impl UnAuthenticatedUtxo {
+    /// The owner memo of the utxo, taken from its transaction if it is still kept
+    pub fn get_owner_memo(&self) -> Option<OwnerMemo> {
+        match self.txn.as_ref() {
+            Some(txn) => txn
+                .txn
+                .get_owner_memos_ref()
+                .get(self.utxo_location.0)
+                .and_then(|m| m.cloned()),
+            None => self.owner_memo.clone(),
+        }
+    }
 }
 
 impl AuthenticatedUtxo {
@@ -1356,6 +1738,8 @@ impl AuthenticatedUtxo {
     /// 3) The spent status proof is valid and denotes the utxo as unspent
     /// 4) The utxo appears in one of the outputs of the transaction (i.e. the output at
     ///    OutputPosition)
+    ///
+    /// A utxo whose transaction has been pruned can not be authenticated.
     pub fn is_valid(
         &self,
         state_commitment: HashOf<Option<StateCommitmentData>>,
@@ -1366,8 +1750,12 @@ impl AuthenticatedUtxo {
         }
 
         //2)
+        let authenticated_txn = match self.authenticated_txn.as_ref() {
+            Some(txn) => txn,
+            None => return false,
+        };
 
-        if !self.authenticated_txn.is_valid(state_commitment.clone()) {
+        if !authenticated_txn.is_valid(state_commitment.clone()) {
             return false;
         }
 
@@ -1379,11 +1767,7 @@ impl AuthenticatedUtxo {
         }
 
         //4)
-        let outputs = self
-            .authenticated_txn
-            .finalized_txn
-            .txn
-            .get_outputs_ref(false);
+        let outputs = authenticated_txn.finalized_txn.txn.get_outputs_ref(false);
         let output = outputs.get(self.utxo_location.0);
 
         if output.is_none() {
@@ -1697,8 +2081,17 @@ impl Transaction {
     /// Create a transaction from seq id
     #[inline(always)]
     pub fn from_seq_id(seq_id: u64) -> Self {
-        let mut prng = ChaChaRng::from_entropy();
-        let no_replay_token = NoReplayToken::new(&mut prng, seq_id);
+        Self::from_seq_id_with_rng(&mut ChaChaRng::from_entropy(), seq_id)
+    }
+
+    /// Create an empty transaction, the no-replay token is generated by `prng`,
+    /// so a seeded `prng` gives the same transaction.
+    #[inline(always)]
+    pub fn from_seq_id_with_rng<R: RngCore + CryptoRng>(
+        prng: &mut R,
+        seq_id: u64,
+    ) -> Self {
+        let no_replay_token = NoReplayToken::new(prng, seq_id);
         Transaction {
             body: TransactionBody::from_token(no_replay_token),
             signatures: Vec::new(),
@@ -1749,6 +2142,27 @@ impl Transaction {
             .insert(keypair.pub_key, SignatureOf::new(keypair, &self.body));
     }
 
+    /// Sign with a `Signer`, e.g. a hardware wallet,
+    /// which displays the summary of the transaction.
+    pub fn sign_with_signer<S: Signer + ?Sized>(&mut self, signer: &S) -> Result<()> {
+        let sig = SignatureOf::new_with_signer(signer, &self.body, &self.body.summary())
+            .c(d!())?;
+        self.signatures.push(sig);
+        Ok(())
+    }
+
+    /// Like `sign_with_signer`, but insert to `pubkey_sign_map`.
+    pub fn sign_to_map_with_signer<S: Signer + ?Sized>(
+        &mut self,
+        signer: &S,
+    ) -> Result<()> {
+        let pk = signer.public_key().c(d!())?;
+        let sig = SignatureOf::new_with_signer(signer, &self.body, &self.body.summary())
+            .c(d!())?;
+        self.pubkey_sign_map.insert(pk, sig);
+        Ok(())
+    }
+
     #[inline(always)]
     #[allow(missing_docs)]
     pub fn check_signature(
@@ -1833,6 +2247,67 @@ impl Transaction {
         }
     }
 
+    /// Sign the transfers out of the multisig addresses which `keypair` is a signer of,
+    /// the signers can sign their own copies, merged by `merge_multisig_signatures`.
+    pub fn sign_multisig(&mut self, keypair: &XfrKeyPair) -> Result<()> {
+        let mut is_signer = false;
+        for op in self.body.operations.iter_mut() {
+            if let Operation::TransferAsset(t) = op {
+                if t.is_multisig_signer(keypair.get_pk_ref()) {
+                    is_signer = true;
+                    if !t
+                        .body_signatures
+                        .iter()
+                        .any(|sig| &sig.address.key == keypair.get_pk_ref())
+                    {
+                        t.sign(keypair);
+                    }
+                }
+            }
+        }
+
+        if is_signer {
+            Ok(())
+        } else {
+            Err(eg!("not a signer of the multisig address"))
+        }
+    }
+
+    /// Merge the multisig signatures of another signed copy of this transaction.
+    pub fn merge_multisig_signatures(&mut self, other: &Transaction) -> Result<()> {
+        let unsigned = |body: &TransactionBody| {
+            let mut body = body.clone();
+            body.operations.iter_mut().for_each(|op| {
+                if let Operation::TransferAsset(t) = op {
+                    t.body_signatures.clear();
+                }
+            });
+            body
+        };
+        if unsigned(&self.body) != unsigned(&other.body) {
+            return Err(eg!("transactions are different"));
+        }
+
+        for (op, other_op) in self
+            .body
+            .operations
+            .iter_mut()
+            .zip(other.body.operations.iter())
+        {
+            if let (Operation::TransferAsset(t), Operation::TransferAsset(o)) =
+                (op, other_op)
+            {
+                for sig in o.body_signatures.iter() {
+                    if !t.body_signatures.contains(sig) {
+                        t.attach_signature(sig.clone()).c(d!())?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     /// NOTE: This method is used to verify the signature in the transaction,
     /// when the user constructs the transaction not only needs to sign each `operation`,
     /// but also needs to sign the whole transaction, otherwise it will not be passed here
@@ -1850,7 +2325,11 @@ impl Transaction {
             match operation {
                 Operation::TransferAsset(o) => {
                     for pk in o.get_owner_addresses().iter() {
-                        select_check(self, pk).c(d!())?;
+                        if let Some(rules) = o.get_multisig_rules(pk) {
+                            o.check_multisig(rules).c(d!())?;
+                        } else {
+                            select_check(self, pk).c(d!())?;
+                        }
                     }
                 }
                 Operation::IssueAsset(o) => {
@@ -1888,6 +2367,15 @@ impl Transaction {
                         }
                     }
                 }
+                Operation::CreateVesting(o) => {
+                    select_check(self, &o.pubkey).c(d!())?;
+                }
+                Operation::ReleaseVested(o) => {
+                    select_check(self, &o.pubkey).c(d!())?;
+                }
+                Operation::RegisterCredIssuer(o) => {
+                    select_check(self, &o.pubkey).c(d!())?;
+                }
             }
         }
 
@@ -1930,6 +2418,29 @@ impl StateCommitmentData {
     }
 }
 
+/// The parts of the app hash of a tendermint block,
+/// used by light clients to prove a ledger state commitment with a verified header.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AppHashParts {
+    /// The ledger state commitment
+    pub ledger: HashOf<Option<StateCommitmentData>>,
+    /// The hash of the EVM chain state, empty if it is not combined
+    pub evm: Vec<u8>,
+}
+
+impl AppHashParts {
+    /// The app hash committed to tendermint,
+    /// a non-empty EVM chain state hash is appended and hashed.
+    pub fn app_hash(&self) -> Vec<u8> {
+        let la_hash = self.ledger.0.as_ref();
+        if self.evm.is_empty() {
+            la_hash.to_vec()
+        } else {
+            sha256::hash(&[la_hash, &self.evm].concat()).0.to_vec()
+        }
+    }
+}
+
 /// Used in `Staking` logic to create consensus-tmp XfrPublicKey
 #[derive(Clone, Copy, Debug, Deserialize, Serialize, Eq, PartialEq, Default)]
 pub struct ConsensusRng(u32);
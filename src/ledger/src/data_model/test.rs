@@ -299,6 +299,7 @@ fn gen_fee_operation(
             transfer_type: TransferType::Standard,
         },
         body_signatures: Vec::new(),
+        multisig_rules: Vec::new(),
     })
 }
 
@@ -377,3 +378,89 @@ fn test_check_fee() {
     tx.add_operation(invalid_destination_not_black_hole);
     assert!(tx.check_fee());
 }
+
+#[test]
+fn test_multisig_address() {
+    let mut prng = ChaChaRng::from_entropy();
+    let kps = (0..3)
+        .map(|_| XfrKeyPair::generate(&mut prng))
+        .collect::<Vec<_>>();
+
+    let rules = SignatureRules {
+        threshold: 3,
+        weights: vec![
+            (kps[0].get_pk(), 2),
+            (kps[1].get_pk(), 1),
+            (kps[2].get_pk(), 1),
+        ],
+    };
+    assert!(rules.is_valid());
+    assert_eq!(rules.to_address(), rules.to_address());
+
+    let mut dup = rules.clone();
+    dup.weights.push((kps[0].get_pk(), 1));
+    assert!(!dup.is_valid());
+    assert!(!SignatureRules {
+        threshold: 5,
+        weights: rules.weights.clone(),
+    }
+    .is_valid());
+
+    let mut other = rules.clone();
+    other.threshold = 2;
+    assert_ne!(rules.to_address(), other.to_address());
+
+    let mut tx = gen_sample_tx();
+    if let Operation::TransferAsset(t) = &mut tx.body.operations[0] {
+        pnk!(t.attach_multisig_rules(rules.clone()));
+    }
+    let transfer = |tx: &Transaction| match &tx.body.operations[0] {
+        Operation::TransferAsset(t) => t.clone(),
+        _ => unreachable!(),
+    };
+
+    // the signers sign their own copies of the transaction
+    let mut copy = tx.clone();
+    pnk!(tx.sign_multisig(&kps[1]));
+    pnk!(copy.sign_multisig(&kps[2]));
+    assert!(transfer(&tx).check_multisig(&rules).is_err());
+    pnk!(tx.merge_multisig_signatures(&copy));
+    assert!(transfer(&tx).check_multisig(&rules).is_err());
+
+    // signatures of the transaction do not count
+    tx.sign_to_map(&kps[0]);
+    tx.sign(&kps[0]);
+    assert!(transfer(&tx).check_multisig(&rules).is_err());
+
+    pnk!(tx.sign_multisig(&kps[0]));
+    pnk!(transfer(&tx).check_multisig(&rules));
+    // one more signature is made by `gen_sample_tx`
+    assert_eq!(4, transfer(&tx).body_signatures.len());
+
+    // only the signers of the rules can sign
+    let other = XfrKeyPair::generate(&mut prng);
+    assert!(tx.sign_multisig(&other).is_err());
+
+    // the copies must be the same transaction
+    let mut different = gen_sample_tx();
+    different.body.memos.push(Memo("different".to_owned()));
+    assert!(tx.merge_multisig_signatures(&different).is_err());
+}
+
+#[test]
+fn test_app_hash_parts() {
+    let ledger = HashOf::new(&None::<StateCommitmentData>);
+    let mut parts = AppHashParts {
+        ledger: ledger.clone(),
+        evm: vec![],
+    };
+    assert_eq!(parts.app_hash(), ledger.0.as_ref().to_vec());
+
+    parts.evm = vec![1; 32];
+    let app_hash = parts.app_hash();
+    assert_eq!(app_hash.len(), 32);
+    assert_ne!(app_hash, ledger.0.as_ref().to_vec());
+
+    parts.evm = vec![2; 32];
+    assert_ne!(parts.app_hash(), app_hash);
+}
@@ -5,12 +5,13 @@
 //!
 
 use {
-    super::MAX_TOTAL_POWER,
+    super::{BlockHeight, COSIG_PROPOSAL_LIFETIME_MAX, MAX_TOTAL_POWER},
     crate::{
         data_model::NoReplayToken,
         staking::{Staking, ValidatorData},
     },
     cryptohash::sha256::{self, Digest},
+    globutils::signer::Signer,
     ruc::*,
     serde::{Deserialize, Serialize},
     std::{
@@ -30,6 +31,35 @@ where
     pub(crate) data: T,
     pub(crate) cosigs: BTreeMap<XfrPublicKey, CoSig>,
     nonce: NoReplayToken,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    proposal: Option<CoSigProposal>,
+}
+
+/// The validity of a co-signed proposal.
+///
+/// If present, the co-signatures cover it instead of the `NoReplayToken`,
+/// which is only accepted for `TRANSACTION_WINDOW_WIDTH` blocks,
+/// so the signatures can be collected offline over a longer time
+/// and the token is attached when the transaction is submitted.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Deserialize, Serialize)]
+pub struct CoSigProposal {
+    /// A random id, an executed proposal can not be submitted again.
+    pub id: u64,
+    /// The last block height at which the proposal is accepted,
+    /// at most `COSIG_PROPOSAL_LIFETIME_MAX` blocks after the current one.
+    pub expiry: BlockHeight,
+}
+
+impl CoSigProposal {
+    /// A new proposal with a random id, accepted until `expiry`.
+    #[inline(always)]
+    pub fn new(expiry: BlockHeight) -> Self {
+        CoSigProposal {
+            id: rand::random(),
+            expiry,
+        }
+    }
 }
 
 impl<T> CoSigOp<T>
@@ -43,25 +73,57 @@ where
             data: msg,
             nonce,
             cosigs: BTreeMap::new(),
+            proposal: None,
+        }
+    }
+
+    /// Make the signatures cover `proposal` instead of the nonce,
+    /// the existing signatures are dropped.
+    #[inline(always)]
+    pub fn set_proposal(&mut self, proposal: CoSigProposal) {
+        self.proposal = Some(proposal);
+        self.cosigs.clear();
+    }
+
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn get_proposal(&self) -> Option<CoSigProposal> {
+        self.proposal
+    }
+
+    /// The message covered by the signatures.
+    #[inline(always)]
+    fn msg(&self) -> Result<Vec<u8>> {
+        match self.proposal {
+            Some(proposal) => bincode::serialize(&(proposal, &self.data)).c(d!()),
+            None => bincode::serialize(&(self.nonce, &self.data)).c(d!()),
         }
     }
 
     /// Attach a new signature.
     #[inline(always)]
     pub fn sign(&mut self, kp: &XfrKeyPair) -> Result<()> {
-        bincode::serialize(&(self.nonce, &self.data))
-            .c(d!())
-            .map(|msg| {
-                let k = kp.get_pk();
-                let v = CoSig::new(kp.get_pk(), kp.sign(&msg));
-                self.cosigs.insert(k, v);
-            })
+        self.msg().c(d!()).map(|msg| {
+            let k = kp.get_pk();
+            let v = CoSig::new(kp.get_pk(), kp.sign(&msg));
+            self.cosigs.insert(k, v);
+        })
+    }
+
+    /// Attach a new signature made by a `Signer`, e.g. a hardware wallet,
+    /// which displays the data to be signed.
+    pub fn sign_with_signer<S: Signer + ?Sized>(&mut self, signer: &S) -> Result<()> {
+        let msg = self.msg().c(d!())?;
+        let k = signer.public_key().c(d!())?;
+        let sig = signer.sign_msg(&msg, &format!("{:?}", self.data)).c(d!())?;
+        self.cosigs.insert(k, CoSig::new(k, sig));
+        Ok(())
     }
 
     /// Attach some new signatures in a batch mode.
     #[inline(always)]
     pub fn batch_sign(&mut self, kps: &[&XfrKeyPair]) -> Result<()> {
-        let msg = bincode::serialize(&(self.nonce, &self.data)).c(d!())?;
+        let msg = self.msg().c(d!())?;
         kps.iter().for_each(|kp| {
             let k = kp.get_pk();
             let v = CoSig::new(kp.get_pk(), kp.sign(&msg));
@@ -80,7 +142,7 @@ where
             .c(d!())
             .and_then(|_| self.check_weight(vd).c(d!()))
             .and_then(|_| {
-                let msg = bincode::serialize(&(self.nonce, &self.data)).c(d!())?;
+                let msg = self.msg().c(d!())?;
                 if self
                     .cosigs
                     .values()
@@ -104,12 +166,7 @@ where
 
     #[inline(always)]
     fn check_weight(&self, vd: &ValidatorData) -> Result<()> {
-        let rule_weights = vd.body.values().map(|v| v.td_power as u128).sum::<u128>();
-        let actual_weights = self
-            .cosigs
-            .values()
-            .flat_map(|s| vd.body.get(&s.pk).map(|v| v.td_power as u128))
-            .sum::<u128>();
+        let [actual_weights, rule_weights] = self.get_weights(vd);
 
         let rule = [
             vd.cosig_rule.threshold[0] as u128,
@@ -125,14 +182,61 @@ where
         Ok(())
     }
 
-    /// Verify co-signatures based on current validators.
+    /// The power of the validators who have signed,
+    /// and the total power of all the validators.
+    pub fn get_weights(&self, vd: &ValidatorData) -> [u128; 2] {
+        let actual_weights = self
+            .cosigs
+            .values()
+            .flat_map(|s| vd.body.get(&s.pk).map(|v| v.td_power as u128))
+            .sum::<u128>();
+        let rule_weights = vd.body.values().map(|v| v.td_power as u128).sum::<u128>();
+        [actual_weights, rule_weights]
+    }
+
+    /// The public keys of the signers.
+    #[inline(always)]
+    pub fn get_signers(&self) -> Vec<XfrPublicKey> {
+        self.cosigs.keys().copied().collect()
+    }
+
+    /// Verify co-signatures based on current validators,
+    /// and the proposal, if any, against the current height.
     pub fn verify(&self, staking: &Staking) -> Result<()> {
+        self.check_proposal(staking).c(d!())?;
         staking
             .validator_get_current()
             .ok_or(eg!())
             .and_then(|vd| self.check_cosigs(vd).c(d!()))
     }
 
+    fn check_proposal(&self, staking: &Staking) -> Result<()> {
+        let proposal = match self.proposal {
+            Some(p) => p,
+            None => return Ok(()),
+        };
+
+        let h = staking.cur_height();
+        if proposal.expiry < h {
+            Err(eg!(CoSigErr::ProposalExpired))
+        } else if proposal.expiry > h.saturating_add(*COSIG_PROPOSAL_LIFETIME_MAX) {
+            Err(eg!(CoSigErr::ProposalTooLong))
+        } else if staking.cosig_proposal_is_used(proposal.id) {
+            Err(eg!(CoSigErr::ProposalUsed))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Record the proposal, if any, as executed,
+    /// called after the operation has been applied.
+    #[inline(always)]
+    pub(crate) fn consume(&self, staking: &mut Staking) {
+        if let Some(p) = self.proposal.as_ref() {
+            staking.cosig_proposal_set_used(p);
+        }
+    }
+
     /// Generate sha256 digest.
     #[inline(always)]
     pub fn hash(&self) -> Result<Digest> {
@@ -198,6 +302,9 @@ enum CoSigErr {
     KeyUnknown,
     SigInvalid,
     WeightInsufficient,
+    ProposalExpired,
+    ProposalTooLong,
+    ProposalUsed,
 }
 
 impl fmt::Display for CoSigErr {
@@ -206,6 +313,9 @@ impl fmt::Display for CoSigErr {
             CoSigErr::KeyUnknown => "found keys outside of the predefined rules",
             CoSigErr::WeightInsufficient => "total weight is lower than the threshold",
             CoSigErr::SigInvalid => "invalid signature",
+            CoSigErr::ProposalExpired => "the proposal has expired",
+            CoSigErr::ProposalTooLong => "the expiry of the proposal is too far",
+            CoSigErr::ProposalUsed => "the proposal has been executed",
         };
         write!(f, "{msg}")
     }
@@ -297,4 +407,108 @@ mod test {
         });
         assert!(data.check_cosigs(&vd).is_err());
     }
+
+    #[test]
+    fn staking_cosig_offline() {
+        let kps = gen_keypairs(4);
+        let vs = kps
+            .iter()
+            .map(|kp| {
+                Validator::new(
+                    vec![],
+                    100,
+                    kp.get_pk(),
+                    [1, 5],
+                    Default::default(),
+                    ValidatorKind::Initiator,
+                )
+            })
+            .collect::<Result<Vec<_>>>();
+        let mut vd = pnk!(ValidatorData::new(1, pnk!(vs)));
+        vd.cosig_rule = pnk!(CoSigRule::new([3, 4]));
+
+        // every signer appends its signature to the file in turn
+        let mut file = pnk!(serde_json::to_string(&CoSigOp::create(
+            Data::default(),
+            no_replay_token()
+        )));
+        for (i, kp) in kps.iter().take(3).enumerate() {
+            let mut op = pnk!(serde_json::from_str::<CoSigOp<Data>>(&file));
+            assert_eq!([100 * i as u128, 400], op.get_weights(&vd));
+            assert!(op.check_cosigs(&vd).is_err());
+
+            pnk!(op.sign(kp));
+            file = pnk!(serde_json::to_string(&op));
+        }
+
+        let op = pnk!(serde_json::from_str::<CoSigOp<Data>>(&file));
+        assert_eq!(3, op.get_signers().len());
+        assert!(op.check_cosigs(&vd).is_ok());
+    }
+
+    #[test]
+    fn staking_cosig_proposal() {
+        let kps = gen_keypairs(4);
+        let vs = kps
+            .iter()
+            .map(|kp| {
+                Validator::new(
+                    vec![],
+                    100,
+                    kp.get_pk(),
+                    [1, 5],
+                    Default::default(),
+                    ValidatorKind::Initiator,
+                )
+            })
+            .collect::<Result<Vec<_>>>();
+        let mut vd = pnk!(ValidatorData::new(1, pnk!(vs)));
+        vd.cosig_rule = pnk!(CoSigRule::new([3, 4]));
+
+        let mut staking = Staking::new();
+        staking.validator_set_at_height_force(1, vd);
+        staking.cur_height = 10;
+
+        let signers = kps.iter().take(3).collect::<Vec<_>>();
+        let proposal = |expiry| {
+            let mut op = CoSigOp::create(Data::default(), no_replay_token());
+            op.set_proposal(CoSigProposal::new(expiry));
+            pnk!(op.batch_sign(&signers));
+            op
+        };
+
+        let mut op = proposal(20);
+        pnk!(op.verify(&staking));
+
+        // the signatures do not cover the nonce, which is attached on submission
+        op.set_nonce(no_replay_token());
+        pnk!(op.verify(&staking));
+
+        // but they cover the proposal
+        let mut forged = op.clone();
+        forged.proposal.as_mut().unwrap().expiry = 30;
+        assert!(forged.verify(&staking).is_err());
+
+        // an executed proposal can not be submitted again
+        op.consume(&mut staking);
+        assert!(op.verify(&staking).is_err());
+        assert!(staking.cosig_proposal_is_used(op.get_proposal().unwrap().id));
+
+        // it is accepted until its expiry height
+        let op = proposal(20);
+        staking.cur_height = 20;
+        pnk!(op.verify(&staking));
+        staking.cur_height = 21;
+        assert!(op.verify(&staking).is_err());
+
+        // the expired proposals are dropped from the executed ones
+        proposal(30).consume(&mut staking);
+        assert_eq!(1, staking.cosig_proposals.len());
+
+        // the lifetime is bounded
+        let op = proposal(21 + *COSIG_PROPOSAL_LIFETIME_MAX + 1);
+        assert!(op.verify(&staking).is_err());
+        let op = proposal(21 + *COSIG_PROPOSAL_LIFETIME_MAX);
+        pnk!(op.verify(&staking));
+    }
 }
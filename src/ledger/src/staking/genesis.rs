@@ -0,0 +1,352 @@
+//!
+//! # Staking genesis
+//!
+//! Export the staking state of a running chain and import it into a new one,
+//! it is used by hard-fork upgrades and by local networks forked from mainnet.
+//!
+//! All heights are rebased on the export height, which becomes height `0`
+//! of the new chain, the validators in effect are set at height `1`.
+//!
+//! The distribution history of the CoinBase is not exported,
+//! it only guards the idempotence of the transactions of the old chain.
+//!
+//! A local network forked from mainnet can not sign blocks with the keys of
+//! the mainnet validators, so it replaces them with its own validators,
+//! the delegations are moved to the new validators along with them.
+//!
+
+use {
+    super::{
+        ops::vesting::VestingSchedule, td_pubkey_to_td_addr_bytes, Amount, BlockHeight,
+        CoinBase, DelegationInfo, Staking, Validator, ValidatorData, ValidatorKind,
+        VestingInfo, BLOCK_HEIGHT_MAX,
+    },
+    crate::data_model::ConsensusRng,
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    std::collections::BTreeMap,
+    zei::xfr::sig::XfrPublicKey,
+};
+
+/// The staking state in a genesis file.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct StakingGenesis {
+    // the validators in effect and the ones scheduled for later heights
+    validator_info: BTreeMap<BlockHeight, ValidatorData>,
+    delegation_info: DelegationInfo,
+    coinbase_balance: Amount,
+    coinbase_principal_balance: Amount,
+    coinbase_distribution_plan: BTreeMap<XfrPublicKey, Amount>,
+    vesting_info: VestingInfo,
+}
+
+impl StakingGenesis {
+    /// The validators in effect at the first block of the new chain.
+    pub fn get_validators(&self) -> Option<&ValidatorData> {
+        self.validator_info.get(&1)
+    }
+
+    /// Replace the validators with new ones, `(id, tendermint pubkey)`.
+    ///
+    /// The old validators, from the most powerful one, are merged into
+    /// the new ones in turn, along with their power and delegators;
+    /// the self-delegation of the first old validator merged into a new one
+    /// becomes the self-delegation of the new one.
+    ///
+    /// NOTE: the validators scheduled for later heights are dropped.
+    pub fn replace_validators(
+        &mut self,
+        new: Vec<(XfrPublicKey, Vec<u8>)>,
+    ) -> Result<()> {
+        if new.is_empty() {
+            return Err(eg!("no validators"));
+        }
+
+        let mut old = self
+            .get_validators()
+            .c(d!("no validators at height 1"))?
+            .body
+            .values()
+            .cloned()
+            .collect::<Vec<_>>();
+        old.sort_by(|a, b| b.td_power.cmp(&a.td_power).then(a.id.cmp(&b.id)));
+
+        let records = &self.delegation_info.global_delegation_records_map;
+        if new.iter().any(|(id, _)| records.contains_key(id)) {
+            return Err(eg!("the new validators have delegated before"));
+        }
+
+        // the ids of the old validators => the ids of the new ones
+        let id_map = old
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (v.id, new[i % new.len()].0))
+            .collect::<BTreeMap<_, _>>();
+        // the self-delegations to be renamed
+        let renamed = old
+            .iter()
+            .zip(new.iter())
+            .map(|(v, (id, _))| (v.id, *id))
+            .collect::<BTreeMap<_, _>>();
+        let rename = |pk: &XfrPublicKey| *renamed.get(pk).unwrap_or(pk);
+
+        let mut vs = new
+            .iter()
+            .enumerate()
+            .map(|(i, (id, td_pubkey))| {
+                let mut v = if let Some(v) = old.get(i) {
+                    let mut v = v.clone();
+                    v.delegators.clear();
+                    v.td_power = 0;
+                    v.signed_last_block = false;
+                    v.signed_cnt = 0;
+                    v
+                } else {
+                    Validator::new(
+                        vec![],
+                        0,
+                        *id,
+                        [1, 100],
+                        Default::default(),
+                        ValidatorKind::Initiator,
+                    )
+                    .c(d!())?
+                };
+                v.id = *id;
+                v.td_addr = td_pubkey_to_td_addr_bytes(td_pubkey);
+                v.td_pubkey = td_pubkey.clone();
+                Ok(v)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        for (i, v) in old.into_iter().enumerate() {
+            let nv = &mut vs[i % new.len()];
+            nv.td_power = nv.td_power.saturating_add(v.td_power);
+
+            let mut delegators = v.delegators;
+            // the self-delegations which are not renamed become normal ones
+            if !renamed.contains_key(&v.id) {
+                if let Some(am) =
+                    records.get(&v.id).and_then(|d| d.delegations.get(&v.id))
+                {
+                    delegators.insert(v.id, *am);
+                }
+            }
+            for (pk, am) in delegators.into_iter() {
+                let entry = nv.delegators.entry(rename(&pk)).or_insert(0);
+                *entry = entry.saturating_add(am);
+            }
+        }
+        for v in vs.iter_mut() {
+            v.delegators.sort_by(|_, v1, _, v2| v2.cmp(v1));
+        }
+
+        let records = &mut self.delegation_info.global_delegation_records_map;
+        for (old_id, new_id) in renamed.iter() {
+            if let Some(mut d) = records.remove(old_id) {
+                d.id = *new_id;
+                records.insert(*new_id, d);
+            }
+        }
+        for d in records.values_mut() {
+            let mut delegations = BTreeMap::new();
+            for (pk, am) in d.delegations.iter() {
+                let entry = delegations
+                    .entry(*id_map.get(pk).unwrap_or(pk))
+                    .or_insert(0);
+                *entry = entry.saturating_add(*am);
+            }
+            d.delegations = delegations;
+        }
+        for pks in self.delegation_info.end_height_map.values_mut() {
+            *pks = pks.iter().map(rename).collect();
+        }
+
+        self.validator_info = BTreeMap::new();
+        self.validator_info
+            .insert(1, ValidatorData::new(1, vs).c(d!())?);
+        Ok(())
+    }
+}
+
+impl Staking {
+    /// Export the staking state at the current height.
+    pub fn export_genesis(&self) -> Result<StakingGenesis> {
+        if !self.has_been_inited() {
+            return Err(eg!("staking has not been initialized"));
+        }
+
+        let h = self.cur_height;
+        let rebase =
+            |x: BlockHeight| alt!(BLOCK_HEIGHT_MAX == x, x, x.saturating_sub(h));
+
+        // the scheduled validators of the next height
+        // take the place of the current ones
+        let mut validator_info = BTreeMap::new();
+        let cur = self.validator_get_current().c(d!())?;
+        validator_info.insert(1, cur.clone());
+        for (k, vd) in self.validator_info.range(h.saturating_add(1)..) {
+            validator_info.insert(rebase(*k), vd.clone());
+        }
+        for (k, vd) in validator_info.iter_mut() {
+            vd.height = *k;
+            for v in vd.body.values_mut() {
+                v.commission_changed_height = rebase(v.commission_changed_height);
+            }
+        }
+
+        let mut delegation_info = self.delegation_info.clone();
+        for d in delegation_info.global_delegation_records_map.values_mut() {
+            d.start_height = rebase(d.start_height);
+            d.end_height = rebase(d.end_height);
+        }
+        delegation_info.end_height_map = BTreeMap::new();
+        for (k, pks) in self.delegation_info.end_height_map.iter() {
+            delegation_info
+                .end_height_map
+                .entry(rebase(*k))
+                .or_default()
+                .extend(pks.iter().copied());
+        }
+
+        let mut vesting_info = self.vesting_info.clone();
+        for v in vesting_info.entries.values_mut().flatten() {
+            v.start_height = rebase(v.start_height);
+            match &mut v.schedule {
+                VestingSchedule::Cliff { height } => {
+                    *height = rebase(*height);
+                }
+                VestingSchedule::Linear { start, end } => {
+                    *start = rebase(*start);
+                    *end = rebase(*end);
+                }
+                VestingSchedule::Periodic { start, .. } => {
+                    *start = rebase(*start);
+                }
+            }
+        }
+
+        Ok(StakingGenesis {
+            validator_info,
+            delegation_info,
+            coinbase_balance: self.coinbase.balance,
+            coinbase_principal_balance: self.coinbase.principal_balance,
+            coinbase_distribution_plan: self.coinbase.distribution_plan.clone(),
+            vesting_info,
+        })
+    }
+
+    /// Create the staking state of a new chain from an exported one.
+    pub fn from_genesis(genesis: StakingGenesis) -> Result<Self> {
+        if genesis.get_validators().is_none() {
+            return Err(eg!("no validators at height 1"));
+        }
+
+        let mut coinbase = CoinBase::gen();
+        coinbase.balance = genesis.coinbase_balance;
+        coinbase.principal_balance = genesis.coinbase_principal_balance;
+        coinbase.distribution_plan = genesis.coinbase_distribution_plan;
+
+        Ok(Staking {
+            validator_info: genesis.validator_info,
+            delegation_info: genesis.delegation_info,
+            cur_height: 0,
+            coinbase,
+            cr: ConsensusRng::default(),
+            vesting_info: genesis.vesting_info,
+            cosig_proposals: Default::default(),
+        })
+    }
+}
+
+#[cfg(test)]
+#[allow(missing_docs)]
+mod test {
+    use {
+        super::*,
+        crate::staking::{Delegation, DelegationState},
+        rand_chacha::ChaChaRng,
+        rand_core::SeedableRng,
+        zei::xfr::sig::XfrKeyPair,
+    };
+
+    fn delegation(id: XfrPublicKey, validator: XfrPublicKey, am: Amount) -> Delegation {
+        Delegation {
+            delegations: [(validator, am)].into_iter().collect(),
+            id,
+            receiver_pk: None,
+            tmp_delegators: BTreeMap::new(),
+            start_height: 0,
+            end_height: BLOCK_HEIGHT_MAX,
+            state: DelegationState::Bond,
+            rwd_amount: 0,
+            proposer_rwd_cnt: 0,
+            delegation_rwd_cnt: 0,
+        }
+    }
+
+    #[test]
+    fn staking_replace_validators() {
+        let mut prng = ChaChaRng::from_entropy();
+        let kps = (0..6)
+            .map(|_| XfrKeyPair::generate(&mut prng).get_pk())
+            .collect::<Vec<_>>();
+        let (old, new, delegator) = (&kps[..3], &kps[3..5], kps[5]);
+
+        let mut delegation_info = DelegationInfo::default();
+        let mut vs = vec![];
+        for (i, id) in old.iter().enumerate() {
+            let am = 300 - 100 * i as Amount;
+            let v = Validator::new(
+                vec![i as u8; 32],
+                am,
+                *id,
+                [1, 100],
+                Default::default(),
+                ValidatorKind::Initiator,
+            )
+            .unwrap();
+            vs.push(v);
+            delegation_info
+                .global_delegation_records_map
+                .insert(*id, delegation(*id, *id, am));
+        }
+        vs[2].td_power += 50;
+        vs[2].delegators.insert(delegator, 50);
+        delegation_info
+            .global_delegation_records_map
+            .insert(delegator, delegation(delegator, old[2], 50));
+
+        let mut genesis = StakingGenesis {
+            validator_info: [(1, ValidatorData::new(1, vs).unwrap())]
+                .into_iter()
+                .collect(),
+            delegation_info,
+            coinbase_balance: 0,
+            coinbase_principal_balance: 0,
+            coinbase_distribution_plan: BTreeMap::new(),
+            vesting_info: VestingInfo::default(),
+        };
+        genesis
+            .replace_validators(
+                new.iter()
+                    .enumerate()
+                    .map(|(i, id)| (*id, vec![10 + i as u8; 32]))
+                    .collect(),
+            )
+            .unwrap();
+
+        let body = &genesis.get_validators().unwrap().body;
+        assert_eq!(2, body.len());
+        assert_eq!(450, body[&new[0]].td_power);
+        assert_eq!(200, body[&new[1]].td_power);
+        assert_eq!(Some(&100), body[&new[0]].delegators.get(&old[2]));
+        assert_eq!(Some(&50), body[&new[0]].delegators.get(&delegator));
+
+        let records = &genesis.delegation_info.global_delegation_records_map;
+        assert!(!records.contains_key(&old[0]));
+        assert_eq!(Some(&300), records[&new[0]].delegations.get(&new[0]));
+        assert_eq!(new[1], records[&new[1]].id);
+        assert_eq!(Some(&100), records[&old[2]].delegations.get(&new[0]));
+        assert_eq!(Some(&50), records[&delegator].delegations.get(&new[0]));
+    }
+}
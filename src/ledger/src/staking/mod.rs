@@ -16,18 +16,20 @@
 use {num_bigint::BigUint, std::convert::TryFrom};
 
 pub mod cosig;
+pub mod genesis;
 pub mod init;
 pub mod ops;
 
 use {
     crate::{
         data_model::{
-            ConsensusRng, Operation, Transaction, TransferAsset, TxoRef, FRA_DECIMALS,
+            is_default, ConsensusRng, Operation, Transaction, TransferAsset, TxoRef,
+            FRA_DECIMALS,
         },
         SNAPSHOT_ENTRIES_DIR,
     },
     config::abci::global_cfg::CFG,
-    cosig::CoSigRule,
+    cosig::{CoSigProposal, CoSigRule},
     cryptohash::sha256::{self, Digest},
     fbnc::{new_mapx, Mapx},
     globutils::wallet,
@@ -36,6 +38,7 @@ use {
     ops::{
         fra_distribution::FraDistributionOps,
         mint_fra::{MintKind, MINT_AMOUNT_LIMIT},
+        vesting::{Vesting, VestingSchedule},
     },
     parking_lot::Mutex,
     rand::random,
@@ -65,6 +68,9 @@ type DAHCP = (Arc<Mutex<Sender<DAH>>>, Arc<Mutex<Receiver<DAH>>>);
 // pk, height, <struct DelegationRwdDetail>
 type DRH = (XfrPublicKey, BlockHeight, DelegationRwdDetail);
 type DRHCP = (Arc<Mutex<Sender<DRH>>>, Arc<Mutex<Receiver<DRH>>>);
+// pk, height, commission_rate
+type CRH = (XfrPublicKey, BlockHeight, [u64; 2]);
+type CRHCP = (Arc<Mutex<Sender<CRH>>>, Arc<Mutex<Receiver<CRH>>>);
 
 macro_rules! chan {
     () => {{
@@ -78,6 +84,12 @@ lazy_static! {
     /// full-nodes may need this feature, meaningless in other kinds of node.
     pub static ref KEEP_HIST: bool = env::var("FINDORAD_KEEP_HIST").is_ok();
 
+    /// the changes of the staking histories are sent to the channels below,
+    /// for the `ApiCache` of this node, or for the standalone indexers
+    /// if it is set without `KEEP_HIST`.
+    pub static ref KEEP_HIST_EVENTS: bool =
+        *KEEP_HIST || env::var("FINDORAD_KEEP_HIST_EVENTS").is_ok();
+
     /// Reserved accounts of EcoSystem.
     pub static ref FF_PK_LIST: Vec<XfrPublicKey> = FF_ADDR_LIST
         .iter()
@@ -96,6 +108,34 @@ lazy_static! {
     pub static ref CHAN_D_AMOUNT_HIST: DAHCP = chan!();
     #[allow(missing_docs)]
     pub static ref CHAN_D_RWD_HIST: DRHCP = chan!();
+    #[allow(missing_docs)]
+    pub static ref CHAN_COMMISSION_HIST: CRHCP = chan!();
+}
+
+/// The changes of the staking histories in a block,
+/// the rewards of delegations are not included.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct StakingHistEvents {
+    /// (height, return rate)
+    pub global_rate: Vec<GRH>,
+    /// (validator, height, self-delegation amount)
+    pub self_delegation: Vec<SDH>,
+    /// (validator, height, amount delegated by others)
+    pub delegation_amount: Vec<DAH>,
+    /// (validator, height, commission rate)
+    pub commission: Vec<CRH>,
+}
+
+impl StakingHistEvents {
+    /// Take the changes sent since the last call, eg. at the commit of a block.
+    pub fn take() -> Self {
+        StakingHistEvents {
+            global_rate: CHAN_GLOB_RATE_HIST.1.lock().try_iter().collect(),
+            self_delegation: CHAN_V_SELF_D_HIST.1.lock().try_iter().collect(),
+            delegation_amount: CHAN_D_AMOUNT_HIST.1.lock().try_iter().collect(),
+            commission: CHAN_COMMISSION_HIST.1.lock().try_iter().collect(),
+        }
+    }
 }
 
 // Reserved accounts of Findora Foundation.
@@ -148,6 +188,9 @@ pub const MAX_DELEGATION_AMOUNT: Amount = FRA_TOTAL_AMOUNT;
 /// The minimum investment to become a validator through staking.
 pub const STAKING_VALIDATOR_MIN_POWER: Power = 1_0000 * FRA;
 
+/// The max number of unfinished vestings of a beneficiary.
+pub const VESTING_ENTRIES_MAX: usize = 64;
+
 /// The highest height in the context of tendermint.
 pub const BLOCK_HEIGHT_MAX: u64 = i64::MAX as u64;
 
@@ -174,8 +217,24 @@ lazy_static! {
             .unwrap_or("15")
             .parse::<u64>().unwrap()
     };
+
+    /// The minimum interval between two changes of the commission rate
+    /// of a validator, about 1 day, this is also the length of a 'commission epoch'.
+    pub static ref COMMISSION_CHANGE_MIN_ITV: BlockHeight = 3600 * 24 / *BLOCK_INTERVAL;
+
+    /// The max lifetime of a co-signed proposal, about 7 days,
+    /// its expiry height can not be farther than this from the current height.
+    pub static ref COSIG_PROPOSAL_LIFETIME_MAX: BlockHeight = 3600 * 24 * 7 / *BLOCK_INTERVAL;
 }
 
+/// The max change of the commission rate of a validator within one
+/// commission epoch(`COMMISSION_CHANGE_MIN_ITV`), 1 percentage point.
+pub const COMMISSION_RATE_MAX_CHANGE: [u64; 2] = [1, 100];
+
+/// The default ceiling of the commission rate, 100%,
+/// used for validators which have not declared one.
+pub const COMMISSION_RATE_MAX_DEFAULT: [u64; 2] = [1, 1];
+
 /// The lock time after the delegation expires, about 21 days.
 //pub const UNBOND_BLOCK_CNT: u64 = 3600 * 24 * 21 / BLOCK_INTERVAL;
 
@@ -232,6 +291,15 @@ pub struct Staking {
     // FRA CoinBase.
     coinbase: CoinBase,
     cr: ConsensusRng,
+    // time-locked FRAs of each beneficiary.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_default")]
+    vesting_info: VestingInfo,
+    // the executed co-signed proposals which have not expired,
+    // `id` => `expiry height`.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_default")]
+    cosig_proposals: BTreeMap<u64, BlockHeight>,
 }
 
 impl Default for Staking {
@@ -264,6 +332,8 @@ impl Staking {
             cur_height: 0,
             coinbase: CoinBase::gen(),
             cr: ConsensusRng::default(),
+            vesting_info: VestingInfo::default(),
+            cosig_proposals: BTreeMap::new(),
         }
     }
 
@@ -273,10 +343,24 @@ impl Staking {
         self.cur_height
     }
 
+    /// Check if a co-signed proposal has been executed.
+    #[inline(always)]
+    pub fn cosig_proposal_is_used(&self, id: u64) -> bool {
+        self.cosig_proposals.contains_key(&id)
+    }
+
+    /// Record an executed co-signed proposal, so it can not be submitted again,
+    /// the expired ones are dropped, they are rejected by their height anyway.
+    pub fn cosig_proposal_set_used(&mut self, proposal: &CoSigProposal) {
+        let h = self.cur_height;
+        self.cosig_proposals.retain(|_, expiry| *expiry >= h);
+        self.cosig_proposals.insert(proposal.id, proposal.expiry);
+    }
+
     /// record block reward rate aka return_rate of current block height
     #[inline(always)]
     pub fn record_block_rewards_rate(&mut self, rate: [u128; 2]) {
-        if *KEEP_HIST {
+        if *KEEP_HIST_EVENTS {
             CHAN_GLOB_RATE_HIST
                 .0
                 .lock()
@@ -562,24 +646,47 @@ impl Staking {
             })
     }
 
-    #[inline(always)]
     /// update staker
+    ///
+    /// After `commission_rule_height`, a change of the commission rate must:
+    /// - not exceed the `max_commission_rate` declared at staking time
+    /// - not differ from the current rate by more than `COMMISSION_RATE_MAX_CHANGE`
+    /// - happen at least `COMMISSION_CHANGE_MIN_ITV` blocks after the previous change
     pub fn update_staker(&mut self, new: &Validator) -> Result<()> {
+        let h = self.cur_height;
         let vd = self.validator_get_current_mut().c(d!())?;
-        let res = vd.body.values_mut().any(|v| {
-            if v.id == new.id {
-                v.memo = new.memo.clone();
-                v.commission_rate = new.commission_rate;
-                return true;
-            }
-            false
-        });
+        let v = vd
+            .body
+            .values_mut()
+            .find(|v| v.id == new.id)
+            .c(d!("Cannot update staker"))?;
+
+        let rate_changed = commission_rate_to_ppb(v.commission_rate)
+            != commission_rate_to_ppb(new.commission_rate);
+        let rule_enabled = CFG.checkpoint.commission_rule_height < h;
+
+        if rate_changed && rule_enabled {
+            v.check_commission_change(h, new.commission_rate).c(d!())?;
+        }
 
-        if res {
-            Ok(())
-        } else {
-            Err(eg!("Cannot update staker"))
+        v.memo = new.memo.clone();
+        v.commission_rate = new.commission_rate;
+
+        if rate_changed {
+            // keep the state of historical blocks unchanged
+            if rule_enabled {
+                v.commission_changed_height = h;
+            }
+            if *KEEP_HIST_EVENTS {
+                CHAN_COMMISSION_HIST
+                    .0
+                    .lock()
+                    .send((v.id, h, v.commission_rate))
+                    .unwrap();
+            }
         }
+
+        Ok(())
     }
 
     ///replace_staker
@@ -787,7 +894,7 @@ impl Staking {
         *d.delegations.entry(validator).or_insert(0) += am;
 
         // record self-delegation amount for a validator
-        if owner == validator && *KEEP_HIST {
+        if owner == validator && *KEEP_HIST_EVENTS {
             CHAN_V_SELF_D_HIST
                 .0
                 .lock()
@@ -800,7 +907,7 @@ impl Staking {
             if owner != validator {
                 *v.delegators.entry(owner).or_insert(0) += am;
                 v.delegators.sort_by(|_, v1, _, v2| v2.cmp(&v1));
-                if *KEEP_HIST {
+                if *KEEP_HIST_EVENTS {
                     CHAN_D_AMOUNT_HIST
                         .0
                         .lock()
@@ -976,7 +1083,7 @@ impl Staking {
                     proposer_rwd_cnt: 0,
                 };
                 // record per-block-height self-delegation amount for a validator
-                if target_validator == *addr && *KEEP_HIST {
+                if target_validator == *addr && *KEEP_HIST_EVENTS {
                     CHAN_V_SELF_D_HIST
                         .0
                         .lock()
@@ -1258,7 +1365,7 @@ impl Staking {
                         if let Some(v) = self.validator_get_current_mut_one_by_id(&vid) {
                             v.delegators.remove(&addr);
                             v.delegators.sort_by(|_, v1, _, v2| v2.cmp(&v1));
-                            if *KEEP_HIST {
+                            if *KEEP_HIST_EVENTS {
                                 CHAN_D_AMOUNT_HIST
                                     .0
                                     .lock()
@@ -1274,6 +1381,8 @@ impl Staking {
                         // NOTE: set this operation after cleaning delegators!
                         ruc::info_omit!(self.validator_change_power(&vid, am, true));
                     });
+
+                    self.vesting_relock(&addr);
                 }
             });
 
@@ -1564,6 +1673,14 @@ impl Staking {
                             *am = 0;
                         }
                     }
+                    if let Some(am) =
+                        self.vesting_info.payment_plan.get_mut(&et.target_pk)
+                    {
+                        if MintKind::Vesting == et.kind && *am == et.amount {
+                            cbsub!(@et.amount);
+                            *am = 0;
+                        }
+                    }
                 }
             }
         }
@@ -1574,6 +1691,10 @@ impl Staking {
                 .into_iter()
                 .filter(|(_, am)| 0 < *am)
                 .collect();
+        self.vesting_info.payment_plan = mem::take(&mut self.vesting_info.payment_plan)
+            .into_iter()
+            .filter(|(_, am)| 0 < *am)
+            .collect();
 
         self.coinbase.balance = cbb;
         self.coinbase.principal_balance = cbb_principal;
@@ -1704,8 +1825,221 @@ impl Staking {
         Ok(())
     }
 
+    /// Lock FRAs for a beneficiary under a release schedule.
+    ///
+    /// **NOTE:** It is the caller's duty to ensure that
+    /// the FRAs have been paid to `BLACK_HOLE_PUBKEY_STAKING`.
+    pub fn vesting_create(
+        &mut self,
+        creator: XfrPublicKey,
+        beneficiary: XfrPublicKey,
+        am: Amount,
+        schedule: VestingSchedule,
+    ) -> Result<()> {
+        let h = self.cur_height;
+
+        if CFG.checkpoint.vesting_height > h {
+            return Err(eg!("vesting is not enabled"));
+        }
+        if 0 == am {
+            return Err(eg!("vesting amount can not be zero"));
+        }
+        schedule.check(h).c(d!())?;
+
+        let vs = self.vesting_info.entries.entry(beneficiary).or_default();
+        if VESTING_ENTRIES_MAX <= vs.len() {
+            return Err(eg!("too many vestings"));
+        }
+        vs.push(Vesting {
+            creator,
+            amount: am,
+            released: 0,
+            delegated: 0,
+            start_height: h,
+            schedule,
+        });
+
+        // locked FRAs are kept by the coinbase along with principals
+        self.coinbase.principal_balance += am;
+
+        Ok(())
+    }
+
+    /// Release vested FRAs to the beneficiary,
+    /// `None` means all releasable FRAs.
+    pub fn vesting_release(
+        &mut self,
+        pk: XfrPublicKey,
+        am: Option<Amount>,
+    ) -> Result<()> {
+        let h = self.cur_height;
+        let vs = self
+            .vesting_info
+            .entries
+            .get_mut(&pk)
+            .c(d!("vesting not found"))?;
+
+        let releasable = vs.iter().map(|v| v.releasable(h)).sum::<Amount>();
+        let am = if let Some(am) = am {
+            if am > releasable {
+                return Err(eg!("release amount exceed releasable FRAs"));
+            }
+            am
+        } else {
+            releasable
+        };
+        if 0 == am {
+            return Err(eg!("nothing to release"));
+        }
+
+        let mut left = am;
+        for v in vs.iter_mut() {
+            let n = min!(left, v.releasable(h));
+            v.released += n;
+            left -= n;
+        }
+
+        vs.retain(|v| !v.is_finished());
+        if vs.is_empty() {
+            self.vesting_info.entries.remove(&pk);
+        }
+
+        *self.vesting_info.payment_plan.entry(pk).or_insert(0) += am;
+
+        Ok(())
+    }
+
+    /// Delegate FRAs that are still locked in vestings,
+    /// they will be locked again after the delegation is freed.
+    pub fn vesting_delegate(
+        &mut self,
+        owner: XfrPublicKey,
+        validator: TendermintAddrRef,
+        am: Amount,
+    ) -> Result<()> {
+        if am > self.vesting_get_locked(&owner) {
+            return Err(eg!("delegation amount exceed locked FRAs"));
+        }
+
+        self.delegate(owner, validator, am).c(d!())?;
+
+        // locked FRAs have been added to the principal balance when vesting
+        self.coinbase.principal_balance -= am;
+
+        let mut left = am;
+        // the existence has been checked above
+        for v in self
+            .vesting_info
+            .entries
+            .get_mut(&owner)
+            .unwrap()
+            .iter_mut()
+        {
+            let n = min!(left, v.locked());
+            v.delegated += n;
+            left -= n;
+        }
+
+        Ok(())
+    }
+
+    // Move the principal of a freed delegation back into vestings,
+    // locked FRAs are returned before the ones paid by the delegator.
+    fn vesting_relock(&mut self, addr: &XfrPublicKey) {
+        let records = &mut self.delegation_info.global_delegation_records_map;
+
+        let (owner, is_tmp) = if let Some(d) = records.get(addr) {
+            (d.receiver_pk.unwrap_or(*addr), d.receiver_pk.is_some())
+        } else {
+            return;
+        };
+
+        let delegated = self
+            .vesting_info
+            .entries
+            .get(&owner)
+            .map(|vs| vs.iter().map(|v| v.delegated).sum::<Amount>())
+            .unwrap_or(0);
+        if 0 == delegated {
+            return;
+        }
+
+        // partial undelegations that are still in their unbond period
+        let pending = alt!(
+            is_tmp,
+            0,
+            records[addr]
+                .tmp_delegators
+                .iter()
+                .filter(|(k, _)| {
+                    records
+                        .get(k)
+                        .map(|d| DelegationState::Bond == d.state)
+                        .unwrap_or(false)
+                })
+                .map(|(_, am)| *am)
+                .sum::<Amount>()
+        );
+
+        // this unwrap is safe
+        let d = records.get_mut(addr).unwrap();
+        let mut back = min!(delegated, d.amount());
+
+        let mut left = back;
+        d.delegations.values_mut().for_each(|am| {
+            let n = min!(left, *am);
+            *am -= n;
+            left -= n;
+        });
+
+        // the rest of them has been punished by governance
+        let mut lost = alt!(is_tmp, 0, delegated.saturating_sub(back + pending));
+
+        // the existence has been checked above
+        for v in self
+            .vesting_info
+            .entries
+            .get_mut(&owner)
+            .unwrap()
+            .iter_mut()
+        {
+            let n = min!(back, v.delegated);
+            v.delegated -= n;
+            back -= n;
+
+            let n = min!(lost, v.delegated);
+            v.delegated -= n;
+            v.amount -= n;
+            lost -= n;
+        }
+    }
+
+    /// Query all vestings of a beneficiary.
+    #[inline(always)]
+    pub fn vesting_get(&self, pk: &XfrPublicKey) -> Option<&Vec<Vesting>> {
+        self.vesting_info.entries.get(pk)
+    }
+
+    /// The amount of FRAs that are still locked in the vestings of a beneficiary.
+    #[inline(always)]
+    pub fn vesting_get_locked(&self, pk: &XfrPublicKey) -> Amount {
+        self.vesting_get(pk)
+            .map(|vs| vs.iter().map(|v| v.locked()).sum())
+            .unwrap_or(0)
+    }
+
+    /// Released FRAs that are waiting to be paid by the coinbase.
+    #[inline(always)]
+    pub fn vesting_get_plan(&self) -> &BTreeMap<XfrPublicKey, Amount> {
+        &self.vesting_info.payment_plan
+    }
+
     /// new validators from public staking operations
-    pub fn validator_add_staker(&mut self, h: BlockHeight, v: Validator) -> Result<()> {
+    pub fn validator_add_staker(
+        &mut self,
+        h: BlockHeight,
+        mut v: Validator,
+    ) -> Result<()> {
         if let Some(vd) = self.validator_get_effective_at_height(h) {
             if vd.body.contains_key(&v.id)
                 || vd
@@ -1715,6 +2049,21 @@ impl Staking {
                 return Err(eg!("already exists"));
             }
 
+            // staking counts as the first change of the commission rate
+            if CFG.checkpoint.commission_rule_height < h {
+                v.commission_changed_height = h;
+            } else {
+                v.commission_changed_height = 0;
+                v.max_commission_rate = None;
+            }
+            if *KEEP_HIST_EVENTS {
+                CHAN_COMMISSION_HIST
+                    .0
+                    .lock()
+                    .send((v.id, h, v.commission_rate))
+                    .unwrap();
+            }
+
             let mut vd = vd.clone();
             vd.addr_td_to_app
                 .insert(td_addr_to_string(&v.td_addr), v.id);
@@ -1940,6 +2289,15 @@ pub struct Validator {
     /// % commision is the % of FRA incentives the validator will take out as a commission fee
     /// for helping FRA owners stake their tokens.
     pub commission_rate: [u64; 2],
+    /// The ceiling of `commission_rate` declared at staking time,
+    /// it can not be changed afterwards, `None` means 100%.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_default")]
+    pub max_commission_rate: Option<[u64; 2]>,
+    /// The height of the last change of `commission_rate`.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_default")]
+    pub commission_changed_height: BlockHeight,
     /// optional descriptive information
     pub memo: StakerMemo,
     /// Which kind of validator it is
@@ -1975,6 +2333,8 @@ impl Validator {
             td_power,
             id,
             commission_rate,
+            max_commission_rate: None,
+            commission_changed_height: 0,
             memo,
             kind,
             signed_last_block: false,
@@ -2013,15 +2373,91 @@ impl Validator {
         self.commission_rate
     }
 
+    /// Declare the ceiling of the commission rate,
+    /// only meaningful before staking.
+    pub fn set_max_commission_rate(&mut self, max_rate: [u64; 2]) -> Result<()> {
+        if 0 == max_rate[1] || max_rate[0] > max_rate[1] {
+            return Err(eg!("invalid max commission rate"));
+        }
+        if commission_rate_to_ppb(self.commission_rate)
+            > commission_rate_to_ppb(max_rate)
+        {
+            return Err(eg!("commission rate exceeds the max commission rate"));
+        }
+        self.max_commission_rate = Some(max_rate);
+        Ok(())
+    }
+
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn get_max_commission_rate(&self) -> [u64; 2] {
+        self.max_commission_rate
+            .unwrap_or(COMMISSION_RATE_MAX_DEFAULT)
+    }
+
+    /// The lowest height at which the commission rate can be changed again.
+    #[inline(always)]
+    pub fn commission_next_change_height(&self) -> BlockHeight {
+        self.commission_changed_height
+            .saturating_add(*COMMISSION_CHANGE_MIN_ITV)
+    }
+
+    /// Check whether the commission rate can be changed to `new_rate` at height `h`.
+    pub fn check_commission_change(
+        &self,
+        h: BlockHeight,
+        new_rate: [u64; 2],
+    ) -> Result<()> {
+        let old = commission_rate_to_ppb(self.commission_rate);
+        let new = commission_rate_to_ppb(new_rate);
+
+        if new > commission_rate_to_ppb(self.get_max_commission_rate()) {
+            return Err(eg!("commission rate exceeds the max commission rate"));
+        }
+
+        if old.max(new) - old.min(new)
+            > commission_rate_to_ppb(COMMISSION_RATE_MAX_CHANGE)
+        {
+            return Err(eg!(format!(
+                "commission rate can change at most {}/{} at a time",
+                COMMISSION_RATE_MAX_CHANGE[0], COMMISSION_RATE_MAX_CHANGE[1]
+            )));
+        }
+
+        if h < self.commission_next_change_height() {
+            return Err(eg!(format!(
+                "commission rate can not be changed before height {}",
+                self.commission_next_change_height()
+            )));
+        }
+
+        Ok(())
+    }
+
     #[inline(always)]
     #[allow(missing_docs)]
     pub fn staking_is_basic_valid(&self) -> bool {
         self.td_power == 0
             && self.td_addr == td_pubkey_to_td_addr_bytes(&self.td_pubkey)
             && self.commission_rate[0] < self.commission_rate[1]
+            && self.max_commission_rate.map_or(true, |max| {
+                0 < max[1]
+                    && max[0] <= max[1]
+                    && commission_rate_to_ppb(self.commission_rate)
+                        <= commission_rate_to_ppb(max)
+            })
     }
 }
 
+// Convert a commission rate to parts per billion,
+// so that rates with different denominators can be compared.
+#[inline(always)]
+fn commission_rate_to_ppb(rate: [u64; 2]) -> u128 {
+    (rate[0] as u128 * 1_000_000_000)
+        .checked_div(rate[1] as u128)
+        .unwrap_or(u128::MAX)
+}
+
 /// FRA delegation, include:
 /// - user delegation
 /// - validator's self-delegation
@@ -2384,6 +2820,14 @@ struct CoinBase {
     principal_balance: Amount,
 }
 
+// Time-locked FRAs, they are kept by the coinbase
+// and paid to the beneficiaries after being released.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+struct VestingInfo {
+    entries: BTreeMap<XfrPublicKey, Vec<Vesting>>,
+    payment_plan: BTreeMap<XfrPublicKey, Amount>,
+}
+
 impl Default for CoinBase {
     fn default() -> Self {
         Self::gen()
@@ -2458,6 +2902,16 @@ pub fn check_delegation_amount(am: Amount, is_append: bool) -> Result<()> {
     }
 }
 
+/// Convert a rate from 0 to 1, e.g. a commission rate,
+/// to the fraction used by staking operations.
+#[inline(always)]
+pub fn convert_rate(rate: f64) -> Result<[u64; 2]> {
+    if !(0.0..=1.0).contains(&rate) {
+        return Err(eg!(format!("Invalid rate: {}, must be from 0 to 1", rate)));
+    }
+    Ok([(rate * 10000.0) as u64, 10000])
+}
+
 #[inline(always)]
 #[allow(missing_docs)]
 pub fn is_valid_tendermint_addr(addr: TendermintAddrRef) -> bool {
@@ -2523,6 +2977,110 @@ mod test {
         });
     }
 
+    #[test]
+    fn staking_commission_change() {
+        let id = crate::data_model::gen_random_keypair().get_pk();
+        let mut v = pnk!(Validator::new_staker(
+            vec![0; 32],
+            id,
+            [5, 100],
+            StakerMemo::default()
+        ));
+
+        assert!(v.set_max_commission_rate([4, 100]).is_err());
+        pnk!(v.set_max_commission_rate([10, 100]));
+        assert!(v.staking_is_basic_valid());
+
+        v.commission_changed_height = 100;
+        let h = v.commission_next_change_height();
+        assert_eq!(h, 100 + *COMMISSION_CHANGE_MIN_ITV);
+
+        // too early
+        assert!(v.check_commission_change(h - 1, [6, 100]).is_err());
+        // too big a step
+        assert!(v.check_commission_change(h, [7, 100]).is_err());
+        assert!(v.check_commission_change(h, [3, 100]).is_err());
+        // beyond the ceiling
+        v.commission_rate = [10, 100];
+        assert!(v.check_commission_change(h, [11, 100]).is_err());
+
+        pnk!(v.check_commission_change(h, [9, 100]));
+        pnk!(v.check_commission_change(h, [1000, 10000]));
+
+        assert_eq!([1000, 10000], pnk!(convert_rate(0.1)));
+        assert_eq!([10000, 10000], pnk!(convert_rate(1.0)));
+        assert!(convert_rate(1.01).is_err());
+        assert!(convert_rate(-0.01).is_err());
+        assert!(convert_rate(f64::NAN).is_err());
+    }
+
+    #[test]
+    fn staking_vesting() {
+        let h0 = CFG.checkpoint.vesting_height + 1;
+        let cliff = h0 + 10;
+
+        let creator = crate::data_model::gen_random_keypair().get_pk();
+        let owner = crate::data_model::gen_random_keypair().get_pk();
+        let vid = crate::data_model::gen_random_keypair().get_pk();
+
+        let v = pnk!(Validator::new(
+            vec![7; 32],
+            1,
+            vid,
+            [1, 100],
+            StakerMemo::default(),
+            ValidatorKind::Initiator
+        ));
+        let td_addr = td_addr_to_string(&v.td_addr);
+
+        let mut staking = Staking::new();
+        staking.set_custom_block_height(h0);
+        staking.validator_set_at_height_force(h0, pnk!(ValidatorData::new(h0, vec![v])));
+        pnk!(staking.delegate(vid, &td_addr, 1));
+
+        let am = 1000;
+        let principal = staking.coinbase.principal_balance;
+        pnk!(staking.vesting_create(
+            creator,
+            owner,
+            am,
+            VestingSchedule::Cliff { height: cliff }
+        ));
+        assert_eq!(staking.coinbase.principal_balance, principal + am);
+        assert_eq!(staking.vesting_get_locked(&owner), am);
+
+        // nothing has been vested before the cliff
+        assert!(staking.vesting_release(owner, None).is_err());
+        assert!(staking.vesting_release(owner, Some(1)).is_err());
+
+        // delegate a part of the locked FRAs
+        assert!(staking.vesting_delegate(owner, &td_addr, am + 1).is_err());
+        pnk!(staking.vesting_delegate(owner, &td_addr, 400));
+        assert_eq!(staking.vesting_get_locked(&owner), 600);
+        assert_eq!(staking.vesting_get(&owner).unwrap()[0].delegated, 400);
+        assert_eq!(staking.delegation_get(&owner).unwrap().amount(), 400);
+        assert_eq!(staking.coinbase.principal_balance, principal + am);
+
+        // delegated FRAs can not be released after the cliff
+        staking.set_custom_block_height(cliff);
+        assert!(staking.vesting_release(owner, Some(601)).is_err());
+        pnk!(staking.vesting_release(owner, None));
+        assert_eq!(staking.vesting_get_locked(&owner), 0);
+        assert_eq!(staking.vesting_get_plan().get(&owner), Some(&600));
+
+        // they are locked again after the unbond period
+        pnk!(staking.undelegate(&owner, None));
+        staking.set_custom_block_height(cliff + CFG.checkpoint.unbond_block_cnt);
+        staking.delegation_process();
+        assert_eq!(staking.vesting_get_locked(&owner), 400);
+        assert_eq!(staking.vesting_get(&owner).unwrap()[0].delegated, 0);
+        assert_eq!(staking.delegation_get(&owner).unwrap().amount(), 0);
+
+        pnk!(staking.vesting_release(owner, None));
+        assert!(staking.vesting_get(&owner).is_none());
+        assert_eq!(staking.vesting_get_plan().get(&owner), Some(&am));
+    }
+
     fn gen_round_vote_percent(lower_bound: u64, upper_bound: u64) -> [u64; 2] {
         let itv = upper_bound - lower_bound;
         let lb = if 0 == itv {
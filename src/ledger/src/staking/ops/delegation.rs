@@ -7,7 +7,7 @@
 use {
     crate::{
         data_model::{
-            NoReplayToken, Operation, Transaction, ASSET_TYPE_FRA,
+            is_default, NoReplayToken, Operation, Transaction, ASSET_TYPE_FRA,
             BLACK_HOLE_PUBKEY_STAKING,
         },
         staking::{
@@ -52,9 +52,15 @@ impl DelegationOps {
             .c(d!())
             .and_then(|_| self.check_set_context(staking, tx).c(d!()))
             .and_then(|am| {
-                staking
-                    .delegate(self.pubkey, &self.body.validator, am)
-                    .c(d!())
+                if self.body.from_vesting {
+                    staking
+                        .vesting_delegate(self.pubkey, &self.body.validator, am)
+                        .c(d!())
+                } else {
+                    staking
+                        .delegate(self.pubkey, &self.body.validator, am)
+                        .c(d!())
+                }
             })
     }
 
@@ -86,6 +92,14 @@ impl DelegationOps {
         staking: &mut Staking,
         tx: &Transaction,
     ) -> Result<Amount> {
+        // FRAs locked in vestings have been paid to the coinbase
+        if self.body.from_vesting {
+            if self.body.new_validator.is_some() {
+                return Err(eg!("staking with locked FRAs is not allowed"));
+            }
+            return check_delegation_context_vesting(tx).c(d!());
+        }
+
         let am = check_delegation_context(tx).c(d!())?;
 
         // Self Staking - New Validator
@@ -136,6 +150,25 @@ impl DelegationOps {
         }
     }
 
+    /// Delegate FRAs that are locked in the vestings of the `keypair`.
+    #[inline(always)]
+    pub fn new_from_vesting(
+        keypair: &XfrKeyPair,
+        amount: Amount,
+        validator: TendermintAddr,
+        nonce: NoReplayToken,
+    ) -> Self {
+        let mut body = Box::new(Data::new(validator, None, amount, nonce));
+        body.from_vesting = true;
+        let signature = keypair.sign(&body.to_bytes());
+        DelegationOps {
+            body,
+            pubkey: keypair.get_pk(),
+            signature,
+            v_signature: None,
+        }
+    }
+
     #[inline(always)]
     #[allow(missing_docs)]
     pub fn set_nonce(&mut self, nonce: NoReplayToken) {
@@ -159,6 +192,10 @@ pub struct Data {
     /// amount of current delegation
     pub amount: Amount,
     nonce: NoReplayToken,
+    /// if set this field, then delegate the FRAs locked in vestings
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_default")]
+    pub from_vesting: bool,
 }
 
 impl Data {
@@ -174,6 +211,7 @@ impl Data {
             new_validator,
             amount,
             nonce,
+            from_vesting: false,
         }
     }
 
@@ -216,6 +254,43 @@ fn check_delegation_context(tx: &Transaction) -> Result<Amount> {
         .c(d!("delegation amount is not paid correctly"))
 }
 
+// No principal should be paid when delegating locked FRAs.
+fn check_delegation_context_vesting(tx: &Transaction) -> Result<Amount> {
+    let owner = tx
+        .body
+        .operations
+        .iter()
+        .flat_map(|op| {
+            if let Operation::Delegation(ref x) = op {
+                Some((x.pubkey, x.body.amount))
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+
+    // only one delegation operation is allowed per transaction
+    if 1 != owner.len() {
+        return Err(eg!());
+    }
+
+    let target_pk = *BLACK_HOLE_PUBKEY_STAKING;
+    if tx.body.operations.iter().any(|op| {
+        if let Operation::TransferAsset(ref x) = op {
+            x.body
+                .outputs
+                .iter()
+                .any(|o| target_pk == o.record.public_key)
+        } else {
+            false
+        }
+    }) {
+        return Err(eg!("principal is not needed when delegating locked FRAs"));
+    }
+
+    Ok(owner[0].1)
+}
+
 fn check_delegation_context_principal(
     tx: &Transaction,
     owner: (XfrPublicKey, Amount),
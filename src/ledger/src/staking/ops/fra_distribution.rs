@@ -42,6 +42,7 @@ impl FraDistributionOps {
                     .coinbase_config_fra_distribution(self.clone())
                     .c(d!())
             })
+            .map(|_| self.consume(staking))
     }
 
     #[inline(always)]
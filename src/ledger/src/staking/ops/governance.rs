@@ -59,6 +59,7 @@ impl GovernanceOps {
                     )
                     .c(d!())
             })
+            .map(|_| self.consume(staking))
     }
 
     #[inline(always)]
@@ -150,6 +151,12 @@ impl Rule {
     }
 }
 
+/// The punishment rule of a kind of byzantine behavior.
+#[inline(always)]
+pub fn get_rule(bz_kind: &ByzantineKind) -> Option<&'static Rule> {
+    RULES.get(bz_kind)
+}
+
 /// Penalize the FRAs by a specified address.
 ///
 /// Any validator who has unstaked itself should not be punished,
@@ -7,7 +7,7 @@
 use {
     crate::staking::BlockHeight,
     crate::{
-        data_model::TxOutput,
+        data_model::{TxOutput, ASSET_TYPE_FRA},
         staking::{Amount, FRA},
     },
     rand_chacha::ChaChaRng,
@@ -18,7 +18,7 @@ use {
         xfr::{
             asset_record::{build_blind_asset_record, AssetRecordType},
             sig::XfrPublicKey,
-            structs::{AssetRecordTemplate, AssetType, OwnerMemo},
+            structs::{AssetRecordTemplate, AssetType, BlindAssetRecord, OwnerMemo},
         },
     },
 };
@@ -49,7 +49,7 @@ impl MintFraOps {
     #[inline(always)]
     #[allow(missing_docs)]
     pub fn get_owner_memos_ref(&self) -> Vec<Option<&OwnerMemo>> {
-        vec![None; self.entries.len()]
+        self.entries.iter().map(|e| e.owner_memo.as_ref()).collect()
     }
 }
 
@@ -61,6 +61,8 @@ pub struct MintEntry {
     pub amount: Amount,
     pub utxo: TxOutput,
     pub asset_type: AssetType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner_memo: Option<OwnerMemo>,
 }
 
 impl MintEntry {
@@ -95,6 +97,30 @@ impl MintEntry {
             amount,
             utxo,
             asset_type,
+            owner_memo: None,
+        }
+    }
+
+    /// Mint a record built elsewhere, eg. a confidential one
+    /// built by an EVM account with the key of the receiver.
+    pub fn with_record(
+        kind: MintKind,
+        target_pk: XfrPublicKey,
+        amount: Amount,
+        record: BlindAssetRecord,
+        owner_memo: Option<OwnerMemo>,
+    ) -> Self {
+        MintEntry {
+            kind,
+            target_pk,
+            amount,
+            utxo: TxOutput {
+                id: None,
+                record,
+                lien: None,
+            },
+            asset_type: ASSET_TYPE_FRA,
+            owner_memo,
         }
     }
 }
@@ -104,5 +130,6 @@ impl MintEntry {
 pub enum MintKind {
     Claim,
     UnStake,
+    Vesting,
     Other,
 }
@@ -16,3 +16,4 @@ pub mod replace_staker;
 pub mod undelegation;
 pub mod update_staker;
 pub mod update_validator;
+pub mod vesting;
@@ -38,6 +38,7 @@ impl UpdateValidatorOps {
                     .validator_set_at_height(self.data.height, self.data.clone())
                     .c(d!())
             })
+            .map(|_| self.consume(staking))
     }
 
     /// Apply new settings to the target `Staking` instance,
@@ -47,7 +48,10 @@ impl UpdateValidatorOps {
         self.verify(staking)
             .c(d!())
             .and_then(|_| self.check_context().c(d!()))
-            .map(|_| staking.validator_set_at_height_force(self.data.height, self.data))
+            .map(|_| {
+                self.consume(staking);
+                staking.validator_set_at_height_force(self.data.height, self.data)
+            })
     }
 
     #[inline(always)]
@@ -0,0 +1,447 @@
+//!
+//! # Vesting
+//!
+//! Lock FRAs under a release schedule owned by a beneficiary,
+//! and release the vested part of them to the beneficiary later.
+//!
+//! The locked FRAs are paid to `BLACK_HOLE_PUBKEY_STAKING`
+//! in the same transaction, just like the principal of a delegation,
+//! and the released FRAs will be paid back by the CoinBase.
+//!
+
+use {
+    crate::{
+        data_model::{
+            NoReplayToken, Operation, Transaction, ASSET_TYPE_FRA,
+            BLACK_HOLE_PUBKEY_STAKING,
+        },
+        staking::{deny_relative_inputs, Amount, BlockHeight, Staking},
+    },
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    std::collections::HashSet,
+    zei::xfr::{
+        sig::{XfrKeyPair, XfrPublicKey, XfrSignature},
+        structs::{XfrAmount, XfrAssetType},
+    },
+};
+
+/// The max number of periods of a periodic schedule.
+pub const VESTING_PERIODS_MAX: u64 = 10_0000;
+
+/// How the locked FRAs become vested.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum VestingSchedule {
+    /// All FRAs become vested at `height`.
+    Cliff {
+        #[allow(missing_docs)]
+        height: BlockHeight,
+    },
+    /// FRAs become vested block by block, from `start` to `end`.
+    Linear {
+        #[allow(missing_docs)]
+        start: BlockHeight,
+        #[allow(missing_docs)]
+        end: BlockHeight,
+    },
+    /// `1 / periods` of FRAs become vested every `period` blocks after `start`.
+    Periodic {
+        #[allow(missing_docs)]
+        start: BlockHeight,
+        #[allow(missing_docs)]
+        period: BlockHeight,
+        #[allow(missing_docs)]
+        periods: u64,
+    },
+}
+
+impl VestingSchedule {
+    /// Check the schedule against the height at which it is created.
+    pub fn check(&self, cur_height: BlockHeight) -> Result<()> {
+        let valid = match *self {
+            VestingSchedule::Cliff { height } => cur_height < height,
+            VestingSchedule::Linear { start, end } => cur_height <= start && start < end,
+            VestingSchedule::Periodic {
+                start,
+                period,
+                periods,
+            } => {
+                cur_height <= start
+                    && 0 < period
+                    && 0 < periods
+                    && periods <= VESTING_PERIODS_MAX
+                    && period.checked_mul(periods).is_some()
+            }
+        };
+        alt!(valid, Ok(()), Err(eg!("invalid vesting schedule")))
+    }
+
+    /// The amount of vested FRAs at height `h`, out of `total`.
+    pub fn vested_amount(&self, total: Amount, h: BlockHeight) -> Amount {
+        let vested = match *self {
+            VestingSchedule::Cliff { height } => alt!(h < height, 0, total as u128),
+            VestingSchedule::Linear { start, end } => {
+                if h <= start {
+                    0
+                } else if h >= end {
+                    total as u128
+                } else {
+                    total as u128 * (h - start) as u128 / (end - start) as u128
+                }
+            }
+            VestingSchedule::Periodic {
+                start,
+                period,
+                periods,
+            } => {
+                let passed = min!(h.saturating_sub(start) / period, periods);
+                total as u128 * passed as u128 / periods as u128
+            }
+        };
+        vested as Amount
+    }
+}
+
+/// FRAs locked for a beneficiary.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Vesting {
+    /// who locked these FRAs
+    pub creator: XfrPublicKey,
+    /// total amount of locked FRAs
+    pub amount: Amount,
+    /// the amount that has been released to the beneficiary
+    pub released: Amount,
+    /// the amount that is delegated by the beneficiary
+    pub delegated: Amount,
+    /// when the vesting was created
+    pub start_height: BlockHeight,
+    #[allow(missing_docs)]
+    pub schedule: VestingSchedule,
+}
+
+impl Vesting {
+    /// The amount of vested FRAs at height `h`.
+    #[inline(always)]
+    pub fn vested(&self, h: BlockHeight) -> Amount {
+        self.schedule.vested_amount(self.amount, h)
+    }
+
+    /// The amount of FRAs that have not been vested at height `h`.
+    #[inline(always)]
+    pub fn unvested(&self, h: BlockHeight) -> Amount {
+        self.amount - self.vested(h)
+    }
+
+    /// The amount of FRAs that are still locked, aka not released and not delegated.
+    #[inline(always)]
+    pub fn locked(&self) -> Amount {
+        self.amount - self.released - self.delegated
+    }
+
+    /// The amount of FRAs that can be released at height `h`,
+    /// delegated FRAs must be undelegated before releasing.
+    #[inline(always)]
+    pub fn releasable(&self, h: BlockHeight) -> Amount {
+        min!(self.vested(h).saturating_sub(self.released), self.locked())
+    }
+
+    /// A vesting can be removed after all of its FRAs have been released.
+    #[inline(always)]
+    pub fn is_finished(&self) -> bool {
+        self.released == self.amount
+    }
+}
+
+/// Used as the inner object of a `CreateVesting Operation`.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct CreateVestingOps {
+    pub(crate) body: Box<CreateVestingData>,
+    pub(crate) pubkey: XfrPublicKey,
+    signature: XfrSignature,
+}
+
+impl CreateVestingOps {
+    /// Check the validity of an operation by running it in a staking simulator.
+    #[inline(always)]
+    pub fn check_run(
+        &self,
+        staking_simulator: &mut Staking,
+        tx: &Transaction,
+    ) -> Result<()> {
+        self.apply(staking_simulator, tx).c(d!())
+    }
+
+    /// Apply new vesting to the target `Staking` instance.
+    pub fn apply(&self, staking: &mut Staking, tx: &Transaction) -> Result<()> {
+        self.verify()
+            .c(d!())
+            .and_then(|_| check_vesting_context(tx).c(d!()))
+            .and_then(|_| {
+                staking
+                    .vesting_create(
+                        self.pubkey,
+                        self.body.beneficiary,
+                        self.body.amount,
+                        self.body.schedule.clone(),
+                    )
+                    .c(d!())
+            })
+    }
+
+    /// Verify signature.
+    #[inline(always)]
+    pub fn verify(&self) -> Result<()> {
+        self.pubkey
+            .verify(&self.body.to_bytes(), &self.signature)
+            .c(d!())
+    }
+
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn get_related_pubkeys(&self) -> Vec<XfrPublicKey> {
+        vec![self.pubkey, self.body.beneficiary]
+    }
+
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn new(
+        keypair: &XfrKeyPair,
+        beneficiary: XfrPublicKey,
+        amount: Amount,
+        schedule: VestingSchedule,
+        nonce: NoReplayToken,
+    ) -> Self {
+        let body = Box::new(CreateVestingData {
+            beneficiary,
+            amount,
+            schedule,
+            nonce,
+        });
+        let signature = keypair.sign(&body.to_bytes());
+        CreateVestingOps {
+            body,
+            pubkey: keypair.get_pk(),
+            signature,
+        }
+    }
+
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn set_nonce(&mut self, nonce: NoReplayToken) {
+        self.body.nonce = nonce;
+    }
+
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn get_nonce(&self) -> NoReplayToken {
+        self.body.nonce
+    }
+}
+
+/// The body of a `CreateVesting` operation.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct CreateVestingData {
+    /// who will receive the vested FRAs
+    pub beneficiary: XfrPublicKey,
+    /// how many FRAs to lock
+    pub amount: Amount,
+    #[allow(missing_docs)]
+    pub schedule: VestingSchedule,
+    nonce: NoReplayToken,
+}
+
+impl CreateVestingData {
+    #[inline(always)]
+    fn to_bytes(&self) -> Vec<u8> {
+        pnk!(bincode::serialize(self))
+    }
+}
+
+/// Used as the inner object of a `ReleaseVested Operation`.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ReleaseVestedOps {
+    pub(crate) body: ReleaseVestedData,
+    pub(crate) pubkey: XfrPublicKey,
+    signature: XfrSignature,
+}
+
+impl ReleaseVestedOps {
+    /// Check the validity of an operation by running it in a staking simulator.
+    #[inline(always)]
+    pub fn check_run(&self, staking_simulator: &mut Staking) -> Result<()> {
+        self.apply(staking_simulator).c(d!())
+    }
+
+    /// Release vested FRAs in the target `Staking` instance.
+    pub fn apply(&self, staking: &mut Staking) -> Result<()> {
+        self.verify().c(d!()).and_then(|_| {
+            staking
+                .vesting_release(self.pubkey, self.body.amount)
+                .c(d!())
+        })
+    }
+
+    /// Verify signature.
+    #[inline(always)]
+    pub fn verify(&self) -> Result<()> {
+        self.pubkey
+            .verify(&self.body.to_bytes(), &self.signature)
+            .c(d!())
+    }
+
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn get_related_pubkeys(&self) -> Vec<XfrPublicKey> {
+        vec![self.pubkey]
+    }
+
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn new(
+        keypair: &XfrKeyPair,
+        amount: Option<Amount>,
+        nonce: NoReplayToken,
+    ) -> Self {
+        let body = ReleaseVestedData { amount, nonce };
+        let signature = keypair.sign(&body.to_bytes());
+        ReleaseVestedOps {
+            body,
+            pubkey: keypair.get_pk(),
+            signature,
+        }
+    }
+
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn set_nonce(&mut self, nonce: NoReplayToken) {
+        self.body.nonce = nonce;
+    }
+
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn get_nonce(&self) -> NoReplayToken {
+        self.body.nonce
+    }
+}
+
+/// The body of a `ReleaseVested` operation.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ReleaseVestedData {
+    /// how much to release, `None` means all releasable FRAs
+    pub amount: Option<Amount>,
+    nonce: NoReplayToken,
+}
+
+impl ReleaseVestedData {
+    #[inline(always)]
+    fn to_bytes(&self) -> Vec<u8> {
+        pnk!(bincode::serialize(self))
+    }
+}
+
+// Make sure that the FRAs to be locked have been paid
+// by the creator to `BLACK_HOLE_PUBKEY_STAKING`.
+fn check_vesting_context(tx: &Transaction) -> Result<()> {
+    let mut creator = None;
+    for op in tx.body.operations.iter() {
+        match op {
+            Operation::CreateVesting(x) => {
+                if creator.is_some() {
+                    return Err(eg!("only one vesting is allowed per transaction"));
+                }
+                creator = Some((x.pubkey, x.body.amount));
+            }
+            // the principal of a delegation is paid in the same way
+            Operation::Delegation(_) => {
+                return Err(eg!("vesting can not be mixed with delegation"));
+            }
+            _ => {}
+        }
+    }
+    let (owner, amount) = creator.c(d!())?;
+
+    let target_pk = *BLACK_HOLE_PUBKEY_STAKING;
+    let mut paid: Amount = 0;
+
+    for op in tx.body.operations.iter() {
+        if let Operation::TransferAsset(ref x) = op {
+            deny_relative_inputs(x).c(d!())?;
+
+            let keys = x
+                .body
+                .transfer
+                .inputs
+                .iter()
+                .map(|i| i.public_key)
+                .collect::<HashSet<_>>();
+            if 1 != keys.len() || !keys.contains(&owner) {
+                continue;
+            }
+
+            for o in x.body.outputs.iter() {
+                if target_pk != o.record.public_key {
+                    continue;
+                }
+                match (o.record.asset_type, o.record.amount) {
+                    (
+                        XfrAssetType::NonConfidential(ty),
+                        XfrAmount::NonConfidential(am),
+                    ) if ty == ASSET_TYPE_FRA => {
+                        paid = paid.checked_add(am).c(d!("overflow"))?;
+                    }
+                    _ => {
+                        return Err(eg!(
+                            "Confidential TXO outputs is not allowed in vesting"
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    alt!(
+        0 < paid && paid == amount,
+        Ok(()),
+        Err(eg!("vesting amount is not paid correctly"))
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn vesting_schedule() {
+        let s = VestingSchedule::Cliff { height: 10 };
+        assert!(s.check(10).is_err());
+        pnk!(s.check(9));
+        assert_eq!(s.vested_amount(100, 9), 0);
+        assert_eq!(s.vested_amount(100, 10), 100);
+
+        let s = VestingSchedule::Linear { start: 10, end: 20 };
+        assert!(s.check(11).is_err());
+        assert!(VestingSchedule::Linear { start: 10, end: 10 }
+            .check(0)
+            .is_err());
+        assert_eq!(s.vested_amount(100, 10), 0);
+        assert_eq!(s.vested_amount(100, 15), 50);
+        assert_eq!(s.vested_amount(100, 25), 100);
+
+        let s = VestingSchedule::Periodic {
+            start: 10,
+            period: 5,
+            periods: 4,
+        };
+        assert!(VestingSchedule::Periodic {
+            start: 10,
+            period: 0,
+            periods: 4
+        }
+        .check(0)
+        .is_err());
+        assert_eq!(s.vested_amount(100, 14), 0);
+        assert_eq!(s.vested_amount(100, 15), 25);
+        assert_eq!(s.vested_amount(100, 29), 75);
+        assert_eq!(s.vested_amount(100, 1000), 100);
+    }
+}
@@ -10,7 +10,7 @@ use {
         },
         staking::{
             ops::mint_fra::MintEntry, Amount, BlockHeight, DelegationRwdDetail,
-            CHAN_D_AMOUNT_HIST, CHAN_GLOB_RATE_HIST, CHAN_V_SELF_D_HIST, KEEP_HIST,
+            StakingHistEvents, KEEP_HIST,
         },
         store::LedgerState,
     },
@@ -64,12 +64,16 @@ pub struct ApiCache {
     /// rewards history, used on some pulic nodes, such as fullnode
     pub staking_delegation_rwd_hist:
         Mapx<XfrPublicKey, Mapxnk<BlockHeight, DelegationRwdDetail>>,
+    /// - commission rate history
+    /// - only valid for a validator
+    pub staking_commission_hist: Mapx<XfrPublicKey, Mapxnk<BlockHeight, [u64; 2]>>,
     /// there are no transactions lost before last_sid
     pub last_sid: Mapx<String, u64>,
 }
 
 impl ApiCache {
-    pub(crate) fn new(prefix: &str) -> Self {
+    /// Create an empty cache, its data is stored under `prefix`
+    pub fn new(prefix: &str) -> Self {
         ApiCache {
             prefix: prefix.to_owned(),
             related_transactions: new_mapx!(format!(
@@ -106,6 +110,9 @@ impl ApiCache {
             staking_delegation_rwd_hist: new_mapx!(format!(
                 "api_cache/{prefix}staking_delegation_rwd_hist",
             )),
+            staking_commission_hist: new_mapx!(format!(
+                "api_cache/{prefix}staking_commission_hist",
+            )),
             last_sid: new_mapx!(format!("api_cache/{prefix}last_sid",)),
         }
     }
@@ -148,41 +155,157 @@ impl ApiCache {
         save_issuance!(token_issuances, token_code);
     }
 
+    /// Index a committed transaction,
+    /// `addresses` are the owners of the outputs at `txo_sids`.
+    pub fn cache_txn(
+        &mut self,
+        txn_sid: TxnSID,
+        curr_txn: &Transaction,
+        txo_sids: &[TxoSID],
+        addresses: &[XfrAddress],
+    ) {
+        let prefix = self.prefix.clone();
+        let owner_memos = curr_txn.get_owner_memos_ref();
+
+        let claim_hist_txns = &mut self.claim_hist_txns;
+        let coinbase_oper_hist = &mut self.coinbase_oper_hist;
+        let classify_op = |op: &Operation| {
+            match op {
+                Operation::Claim(i) => {
+                    let key = XfrAddress {
+                        key: i.get_claim_publickey(),
+                    };
+                    claim_hist_txns
+                        .entry(key)
+                        .or_insert_with(|| {
+                            new_mapxnk!(format!(
+                                "api_cache/{}claim_hist_txns/{}",
+                                prefix,
+                                key.to_base64()
+                            ))
+                        })
+                        .set_value(txn_sid, Default::default());
+                }
+                Operation::MintFra(i) => i.entries.iter().for_each(|me| {
+                    let key = XfrAddress {
+                        key: me.utxo.record.public_key,
+                    };
+                    #[allow(unused_mut)]
+                    let mut hist = coinbase_oper_hist.entry(key).or_insert_with(|| {
+                        new_mapxnk!(format!(
+                            "api_cache/{}coinbase_oper_hist/{}",
+                            prefix,
+                            key.to_base64()
+                        ))
+                    });
+                    hist.insert(i.height, me.clone());
+                }),
+                _ => { /* filter more operations before this line */ }
+            };
+        };
+
+        // Update related addresses
+        // Apply classify_op for each operation in curr_txn
+        let related_addresses = get_related_addresses(curr_txn, classify_op);
+        for address in &related_addresses {
+            self.related_transactions
+                .entry(*address)
+                .or_insert_with(|| {
+                    new_mapxnk!(format!(
+                        "api_cache/{}related_transactions/{}",
+                        prefix,
+                        address.to_base64()
+                    ))
+                })
+                .insert(txn_sid, Default::default());
+        }
+
+        // Update transferred nonconfidential assets
+        let transferred_assets = get_transferred_nonconfidential_assets(curr_txn);
+        for asset in &transferred_assets {
+            self.related_transfers
+                .entry(*asset)
+                .or_insert_with(|| {
+                    new_mapxnk!(format!(
+                        "api_cache/{}related_transfers/{}",
+                        &prefix,
+                        asset.to_base64()
+                    ))
+                })
+                .insert(txn_sid, Default::default());
+        }
+
+        // Add created asset
+        for op in &curr_txn.body.operations {
+            match op {
+                Operation::DefineAsset(define_asset) => {
+                    self.add_created_asset(define_asset);
+                }
+                Operation::IssueAsset(issue_asset) => {
+                    self.cache_issuance(issue_asset);
+                }
+                _ => {}
+            };
+        }
+
+        // Add new utxos (this handles both transfers and issuances)
+        let hash = curr_txn.hash_tm().hex().to_uppercase();
+        for (txo_sid, (address, owner_memo)) in txo_sids
+            .iter()
+            .zip(addresses.iter().zip(owner_memos.iter()))
+        {
+            self.utxos_to_map_index.insert(*txo_sid, *address);
+            self.txo_to_txnid.insert(*txo_sid, (txn_sid, hash.clone()));
+            if let Some(owner_memo) = owner_memo {
+                self.owner_memos.insert(*txo_sid, (*owner_memo).clone());
+            }
+        }
+        self.txn_sid_to_hash.insert(txn_sid, hash.clone());
+        self.txn_hash_to_sid.insert(hash, txn_sid);
+    }
+
     /// Cache history style data
     ///
     /// Note: This function's data will migrate to findora scanner.
-    pub fn cache_hist_data(&mut self) {
-        CHAN_GLOB_RATE_HIST.1.lock().try_iter().for_each(|(h, r)| {
-            self.staking_global_rate_hist.insert(h, r);
+    pub fn cache_hist_data(&mut self, events: &StakingHistEvents) {
+        let prefix = self.prefix.clone();
+
+        events.global_rate.iter().for_each(|(h, r)| {
+            self.staking_global_rate_hist.insert(*h, *r);
         });
 
-        CHAN_V_SELF_D_HIST
-            .1
-            .lock()
-            .try_iter()
-            .for_each(|(pk, h, r)| {
-                self.staking_self_delegation_hist
-                    .entry(pk)
-                    .or_insert(new_mapxnk!(format!(
-                        "staking_self_delegation_hist_subdata/{}",
-                        wallet::public_key_to_base64(&pk)
-                    )))
-                    .insert(h, r);
-            });
+        events.self_delegation.iter().for_each(|(pk, h, r)| {
+            self.staking_self_delegation_hist
+                .entry(*pk)
+                .or_insert(new_mapxnk!(format!(
+                    "{}staking_self_delegation_hist_subdata/{}",
+                    prefix,
+                    wallet::public_key_to_base64(pk)
+                )))
+                .insert(*h, *r);
+        });
 
-        CHAN_D_AMOUNT_HIST
-            .1
-            .lock()
-            .try_iter()
-            .for_each(|(pk, h, r)| {
-                self.staking_delegation_amount_hist
-                    .entry(pk)
-                    .or_insert(new_mapxnk!(format!(
-                        "staking_delegation_amount_hist_subdata/{}",
-                        wallet::public_key_to_base64(&pk)
-                    )))
-                    .insert(h, r);
-            });
+        events.delegation_amount.iter().for_each(|(pk, h, r)| {
+            self.staking_delegation_amount_hist
+                .entry(*pk)
+                .or_insert(new_mapxnk!(format!(
+                    "{}staking_delegation_amount_hist_subdata/{}",
+                    prefix,
+                    wallet::public_key_to_base64(pk)
+                )))
+                .insert(*h, *r);
+        });
+
+        events.commission.iter().for_each(|(pk, h, r)| {
+            self.staking_commission_hist
+                .entry(*pk)
+                .or_insert(new_mapxnk!(format!(
+                    "{}staking_commission_hist_subdata/{}",
+                    prefix,
+                    wallet::public_key_to_base64(pk)
+                )))
+                .insert(*h, *r);
+        });
 
         //         CHAN_D_RWD_HIST.1.lock().try_iter().for_each(|(pk, h, r)| {
         // #[allow(unused_mut)]
@@ -248,6 +371,8 @@ where
             Operation::Governance(i) => staking_gen!(i),
             Operation::FraDistribution(i) => staking_gen!(i),
             Operation::MintFra(i) => staking_gen!(i),
+            Operation::CreateVesting(i) => staking_gen!(i),
+            Operation::ReleaseVested(i) => staking_gen!(i),
 
             Operation::ConvertAccount(i) => {
                 related_addresses.insert(XfrAddress {
@@ -282,6 +407,9 @@ where
                     key: update_memo.pubkey,
                 });
             }
+            Operation::RegisterCredIssuer(reg) => {
+                related_addresses.insert(XfrAddress { key: reg.pubkey });
+            }
         }
     }
     related_addresses
@@ -378,12 +506,13 @@ pub fn check_lost_data(ledger: &mut LedgerState) -> Result<()> {
                 .owner_memos
                 .contains_key(&TxoSID(index))
             {
-                let utxo_opt = ledger.get_utxo(TxoSID(index));
-                if let Some(utxo) = utxo_opt {
+                // the transactions of pruned utxos are not available
+                let txn_opt = ledger
+                    .get_utxo(TxoSID(index))
+                    .and_then(|utxo| utxo.authenticated_txn);
+                if let Some(authenticated_txn) = txn_opt {
                     let ftx = ledger
-                        .get_transaction_light(
-                            utxo.authenticated_txn.finalized_txn.tx_id,
-                        )
+                        .get_transaction_light(authenticated_txn.finalized_txn.tx_id)
                         .unwrap();
                     let tx_hash = ftx.txn.hash_tm().hex().to_uppercase();
                     let owner_memos = ftx.txn.get_owner_memos_ref();
@@ -449,15 +578,19 @@ pub fn check_lost_data(ledger: &mut LedgerState) -> Result<()> {
     Ok(())
 }
 
-/// update the data of QueryServer when we create a new block in ABCI
-pub fn update_api_cache(ledger: &mut LedgerState) -> Result<()> {
+/// update the data of QueryServer when we create a new block in ABCI,
+/// `events` are the staking changes of the block
+pub fn update_api_cache(
+    ledger: &mut LedgerState,
+    events: &StakingHistEvents,
+) -> Result<()> {
     if !*KEEP_HIST {
         return Ok(());
     }
 
     check_lost_data(ledger)?;
 
-    ledger.api_cache.as_mut().unwrap().cache_hist_data();
+    ledger.api_cache.as_mut().unwrap().cache_hist_data(events);
 
     let block = if let Some(b) = ledger.blocks.last() {
         b
@@ -465,177 +598,30 @@ pub fn update_api_cache(ledger: &mut LedgerState) -> Result<()> {
         return Ok(());
     };
 
-    let prefix = ledger.api_cache.as_mut().unwrap().prefix.clone();
-
     // Update ownership status
     for (txn_sid, txo_sids) in block.txns.iter().map(|v| (v.tx_id, v.txo_ids.as_slice()))
     {
         let curr_txn = ledger.get_transaction_light(txn_sid).c(d!())?.txn;
-        // get the transaction, ownership addresses, and memos associated with each transaction
-        let (addresses, owner_memos) = {
-            let addresses: Vec<XfrAddress> = txo_sids
-                .iter()
-                .map(|sid| XfrAddress {
-                    key: ((ledger
-                        .get_utxo_light(*sid)
-                        .or_else(|| ledger.get_spent_utxo_light(*sid))
-                        .unwrap()
-                        .utxo)
-                        .0)
-                        .record
-                        .public_key,
-                })
-                .collect();
-
-            let owner_memos = curr_txn.get_owner_memos_ref();
-
-            (addresses, owner_memos)
-        };
-
-        let classify_op = |op: &Operation| {
-            match op {
-                Operation::Claim(i) => {
-                    let key = XfrAddress {
-                        key: i.get_claim_publickey(),
-                    };
-                    ledger
-                        .api_cache
-                        .as_mut()
-                        .unwrap()
-                        .claim_hist_txns
-                        .entry(key)
-                        .or_insert_with(|| {
-                            new_mapxnk!(format!(
-                                "api_cache/{}claim_hist_txns/{}",
-                                prefix,
-                                key.to_base64()
-                            ))
-                        })
-                        .set_value(txn_sid, Default::default());
-                }
-                Operation::MintFra(i) => i.entries.iter().for_each(|me| {
-                    let key = XfrAddress {
-                        key: me.utxo.record.public_key,
-                    };
-                    #[allow(unused_mut)]
-                    let mut hist = ledger
-                        .api_cache
-                        .as_mut()
-                        .unwrap()
-                        .coinbase_oper_hist
-                        .entry(key)
-                        .or_insert_with(|| {
-                            new_mapxnk!(format!(
-                                "api_cache/{}coinbase_oper_hist/{}",
-                                prefix,
-                                key.to_base64()
-                            ))
-                        });
-                    hist.insert(i.height, me.clone());
-                }),
-                _ => { /* filter more operations before this line */ }
-            };
-        };
-
-        // Update related addresses
-        // Apply classify_op for each operation in curr_txn
-        let related_addresses = get_related_addresses(&curr_txn, classify_op);
-        for address in &related_addresses {
-            ledger
-                .api_cache
-                .as_mut()
-                .unwrap()
-                .related_transactions
-                .entry(*address)
-                .or_insert_with(|| {
-                    new_mapxnk!(format!(
-                        "api_cache/{}related_transactions/{}",
-                        prefix,
-                        address.to_base64()
-                    ))
-                })
-                .insert(txn_sid, Default::default());
-        }
-
-        // Update transferred nonconfidential assets
-        let transferred_assets = get_transferred_nonconfidential_assets(&curr_txn);
-        for asset in &transferred_assets {
-            ledger
-                .api_cache
-                .as_mut()
-                .unwrap()
-                .related_transfers
-                .entry(*asset)
-                .or_insert_with(|| {
-                    new_mapxnk!(format!(
-                        "api_cache/{}related_transfers/{}",
-                        &prefix,
-                        asset.to_base64()
-                    ))
-                })
-                .insert(txn_sid, Default::default());
-        }
-
-        // Add created asset
-        for op in &curr_txn.body.operations {
-            match op {
-                Operation::DefineAsset(define_asset) => {
-                    ledger
-                        .api_cache
-                        .as_mut()
-                        .unwrap()
-                        .add_created_asset(&define_asset);
-                }
-                Operation::IssueAsset(issue_asset) => {
-                    ledger
-                        .api_cache
-                        .as_mut()
-                        .unwrap()
-                        .cache_issuance(&issue_asset);
-                }
-                _ => {}
-            };
-        }
-
-        // Add new utxos (this handles both transfers and issuances)
-        for (txo_sid, (address, owner_memo)) in txo_sids
+        // get the ownership addresses associated with each transaction
+        let addresses: Vec<XfrAddress> = txo_sids
             .iter()
-            .zip(addresses.iter().zip(owner_memos.iter()))
-        {
-            ledger
-                .api_cache
-                .as_mut()
-                .unwrap()
-                .utxos_to_map_index
-                .insert(*txo_sid, *address);
-            let hash = curr_txn.hash_tm().hex().to_uppercase();
-            ledger
-                .api_cache
-                .as_mut()
-                .unwrap()
-                .txo_to_txnid
-                .insert(*txo_sid, (txn_sid, hash.clone()));
-            ledger
-                .api_cache
-                .as_mut()
-                .unwrap()
-                .txn_sid_to_hash
-                .insert(txn_sid, hash.clone());
-            ledger
-                .api_cache
-                .as_mut()
-                .unwrap()
-                .txn_hash_to_sid
-                .insert(hash.clone(), txn_sid);
-            if let Some(owner_memo) = owner_memo {
-                ledger
-                    .api_cache
-                    .as_mut()
+            .map(|sid| XfrAddress {
+                key: ((ledger
+                    .get_utxo_light(*sid)
+                    .or_else(|| ledger.get_spent_utxo_light(*sid))
                     .unwrap()
-                    .owner_memos
-                    .insert(*txo_sid, (*owner_memo).clone());
-            }
-        }
+                    .utxo)
+                    .0)
+                    .record
+                    .public_key,
+            })
+            .collect();
+
+        ledger
+            .api_cache
+            .as_mut()
+            .unwrap()
+            .cache_txn(txn_sid, &curr_txn, txo_sids, &addresses);
     }
 
     Ok(())
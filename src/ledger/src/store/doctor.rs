@@ -0,0 +1,657 @@
+//!
+//! # Offline integrity checks of the ledger
+//!
+//! A crash in the middle of a commit may leave the merkle trees,
+//! the utxo bitmap, the fbnc data and the `LedgerStatus` snapshot
+//! on different heights, all of them are checked against each other here.
+//!
+//! The snapshot is the source of truth, other parts are repaired to match it,
+//! and every repaired file is backed up before being replaced.
+//!
+//! The transactions of a pruned ledger are gone,
+//! so the transaction merkle tree and the utxo bitmap can not be derived again.
+//!
+//! NOTE: the node must be stopped before checking.
+//!
+
+use {
+    super::LedgerStatus,
+    crate::data_model::{
+        FinalizedBlock, Operation, Transaction, TxnSID, TxoRef, TxoSID,
+    },
+    bitmap::BitMap,
+    cryptohash::HashValue,
+    fbnc::{new_mapxnk, new_vecx, Mapxnk, Vecx},
+    globutils::HashOf,
+    merkle_tree::AppendOnlyMerkle,
+    ruc::*,
+    std::{
+        collections::HashSet,
+        fs::{self, OpenOptions},
+        time::{SystemTime, UNIX_EPOCH},
+    },
+};
+
+const SNAPSHOT_FILE: &str = "ledger_status";
+const BLOCK_MERKLE: &str = "block_merkle";
+const TXN_MERKLE: &str = "txn_merkle";
+const UTXO_MAP: &str = "utxo_map";
+const TMP_DIR: &str = "doctor_tmp";
+const BACKUP_DIR: &str = "doctor_backup";
+
+/// The result of a check
+#[derive(Clone, Debug)]
+pub struct Check {
+    #[allow(missing_docs)]
+    pub name: &'static str,
+    /// `None` if the check passes
+    pub error: Option<String>,
+}
+
+/// The merkle trees of the ledger
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MerkleKind {
+    /// one leaf per block
+    Block,
+    /// one leaf per transaction
+    Txn,
+}
+
+impl MerkleKind {
+    fn file_name(self) -> &'static str {
+        match self {
+            MerkleKind::Block => BLOCK_MERKLE,
+            MerkleKind::Txn => TXN_MERKLE,
+        }
+    }
+}
+
+/// A repair which can be done offline
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Repair {
+    /// Drop the blocks which are not recorded in the snapshot
+    TruncateBlocks,
+    /// Rebuild the upper levels of a merkle tree from its leaves
+    RebuildMerkle(MerkleKind),
+    /// Derive a merkle tree from the blocks
+    DeriveMerkle(MerkleKind),
+    /// Derive the utxo bitmap from the blocks
+    DeriveUtxoMap,
+}
+
+/// The report of `diagnose`
+#[derive(Clone, Debug)]
+pub struct Diagnosis {
+    #[allow(missing_docs)]
+    pub checks: Vec<Check>,
+    /// safe repairs of the failed checks, in the order to be applied
+    pub repairs: Vec<Repair>,
+    /// tendermint height recorded in the snapshot
+    pub tendermint_height: u64,
+}
+
+impl Diagnosis {
+    /// All checks passed
+    pub fn is_healthy(&self) -> bool {
+        self.checks.iter().all(|c| c.error.is_none())
+    }
+}
+
+struct Stores {
+    status: LedgerStatus,
+    blocks: Vecx<FinalizedBlock>,
+    tx_to_block_location: Mapxnk<TxnSID, [usize; 2]>,
+}
+
+impl Stores {
+    // `BNC_DATA_DIR` must have been set as the node does
+    fn open(basedir: &str) -> Result<Stores> {
+        let status = fs::read_to_string(format!("{basedir}/{SNAPSHOT_FILE}"))
+            .c(d!("ledger status not found"))
+            .and_then(|s| serde_json::from_str::<LedgerStatus>(&s).c(d!()))?;
+
+        Ok(Stores {
+            status,
+            blocks: new_vecx!("blocks"),
+            tx_to_block_location: new_mapxnk!("tx_to_block_location"),
+        })
+    }
+
+    fn blocks(&self) -> impl Iterator<Item = FinalizedBlock> + '_ {
+        (0..self.blocks.len()).filter_map(move |i| self.blocks.get(i))
+    }
+
+    fn expected_merkle(&self, kind: MerkleKind) -> (u64, HashValue) {
+        let state = self.status.state_commitment_data.as_ref();
+        match kind {
+            MerkleKind::Block => (
+                self.status.block_commit_count,
+                state.map(|s| s.block_merkle).unwrap_or_default(),
+            ),
+            MerkleKind::Txn => (
+                self.status.next_txn.0 as u64,
+                state
+                    .map(|s| s.transaction_merkle_commitment)
+                    .unwrap_or_default(),
+            ),
+        }
+    }
+}
+
+/// Check the ledger stored in `basedir`, the last `recent` blocks are checked one by one.
+pub fn diagnose(basedir: &str, recent: usize) -> Result<Diagnosis> {
+    let stores = Stores::open(basedir).c(d!())?;
+    let status = &stores.status;
+    let mut res = Diagnosis {
+        checks: vec![],
+        repairs: vec![],
+        tendermint_height: status.td_commit_height,
+    };
+
+    macro_rules! check {
+        ($name: expr, $e: expr) => {
+            res.checks.push(Check {
+                name: $name,
+                error: $e.err().map(|e: Box<dyn RucError>| e.to_string()),
+            })
+        };
+    }
+
+    check!("snapshot", status.fast_invariant_check());
+
+    let cnt = status.block_commit_count as usize;
+    let blocks_res = if stores.blocks.len() < cnt {
+        Err(eg!(format!(
+            "{} blocks are lost, restore the node from a snapshot",
+            cnt - stores.blocks.len()
+        )))
+    } else if stores.blocks.len() > cnt || status.state_commitment_versions.len() > cnt {
+        res.repairs.push(Repair::TruncateBlocks);
+        Err(eg!(format!(
+            "{} blocks and {} state commitments are stored, but {} blocks are committed",
+            stores.blocks.len(),
+            status.state_commitment_versions.len(),
+            cnt
+        )))
+    } else {
+        check_last_block(&stores)
+    };
+    check!("blocks", blocks_res);
+
+    check!("recent_blocks", check_recent_blocks(&stores, recent));
+
+    for kind in [MerkleKind::Block, MerkleKind::Txn] {
+        let (disk_res, data_res) = check_merkle(basedir, &stores, kind);
+        if data_res.is_err() {
+            if MerkleKind::Block == kind || 0 == status.pruned_blocks {
+                res.repairs.push(Repair::DeriveMerkle(kind));
+            }
+        } else if disk_res.is_err() {
+            res.repairs.push(Repair::RebuildMerkle(kind));
+        }
+        check!(kind.file_name(), disk_res.and(data_res));
+    }
+
+    let utxo_map_res = check_utxo_map(basedir, &stores);
+    if utxo_map_res.is_err() && 0 == status.pruned_blocks {
+        res.repairs.push(Repair::DeriveUtxoMap);
+    }
+    check!(UTXO_MAP, utxo_map_res);
+
+    Ok(res)
+}
+
+/// Apply the repairs given by `diagnose`.
+pub fn repair(basedir: &str, repairs: &[Repair]) -> Result<()> {
+    let mut stores = Stores::open(basedir).c(d!())?;
+    let backup = format!(
+        "{}/{}/{}",
+        basedir,
+        BACKUP_DIR,
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .c(d!())?
+            .as_secs()
+    );
+
+    for r in repairs {
+        match *r {
+            Repair::TruncateBlocks => truncate_blocks(&mut stores).c(d!())?,
+            Repair::RebuildMerkle(kind) => {
+                let path = format!("{}/{}", basedir, kind.file_name());
+                let mut tree = AppendOnlyMerkle::rebuild(&path).c(d!())?;
+                tree.check_disk(true).c(d!())?;
+                // the original leaves are renamed with a `-base` suffix
+                let base = format!("{path}-base");
+                fs::create_dir_all(&backup).c(d!())?;
+                fs::rename(&base, format!("{}/{}-base", backup, kind.file_name()))
+                    .c(d!())?;
+            }
+            Repair::DeriveMerkle(kind) => derive_merkle(basedir, &stores, kind)
+                .c(d!())
+                .and_then(|_| replace_files(basedir, &backup, kind.file_name()))?,
+            Repair::DeriveUtxoMap => derive_utxo_map(basedir, &stores)
+                .c(d!())
+                .and_then(|_| replace_files(basedir, &backup, UTXO_MAP))?,
+        }
+    }
+
+    fbnc::flush_data();
+
+    Ok(())
+}
+
+fn check_last_block(stores: &Stores) -> Result<()> {
+    let status = &stores.status;
+    let last = if let Some(b) = stores.blocks.last() {
+        b
+    } else {
+        return Ok(());
+    };
+
+    if status.state_commitment_data.as_ref() != Some(&last.state) {
+        return Err(eg!("the state of the last block mismatches the snapshot"));
+    }
+    if last.state.txo_count != status.next_txo.0 {
+        return Err(eg!(format!(
+            "txo_count is {}, but the next txo is {}",
+            last.state.txo_count, status.next_txo.0
+        )));
+    }
+    let next_txn = last.txns.last().map(|tx| tx.tx_id.0 + 1).unwrap_or(0);
+    if next_txn != status.next_txn.0 {
+        return Err(eg!(format!(
+            "the next transaction is {} in blocks, but {} in the snapshot",
+            next_txn, status.next_txn.0
+        )));
+    }
+
+    Ok(())
+}
+
+// The state commitments of the recent blocks are recomputed and chained.
+fn check_recent_blocks(stores: &Stores, recent: usize) -> Result<()> {
+    let versions = &stores.status.state_commitment_versions;
+    let len = stores.blocks.len();
+    let mut prev = None;
+
+    for i in len.saturating_sub(recent + 1)..len {
+        let b = stores.blocks.get(i).c(d!())?;
+        let state = &b.state;
+
+        if let Some(prev) = prev.replace(state.clone()) {
+            if state.previous_state_commitment != HashOf::new(&Some(prev)) {
+                return Err(eg!(format!("block {i} is not chained to its parent")));
+            }
+        }
+        if i + recent < len {
+            continue;
+        }
+
+        if b.merkle_id != i as u64 {
+            return Err(eg!(format!("block {} has merkle id {}", i, b.merkle_id)));
+        }
+        if versions.get(i) != Some(state.compute_commitment()) {
+            return Err(eg!(format!("the state commitment of block {i} mismatches")));
+        }
+        if i < stores.status.pruned_blocks {
+            continue;
+        }
+        if txns_hash(&b, false) != state.txns_in_block_hash
+            && txns_hash(&b, true) != state.txns_in_block_hash
+        {
+            return Err(eg!(format!("the transactions of block {i} are modified")));
+        }
+        for (j, tx) in b.txns.iter().enumerate() {
+            if stores.tx_to_block_location.get(&tx.tx_id) != Some([i, j]) {
+                return Err(eg!(format!(
+                    "the location of transaction {} mismatches",
+                    tx.tx_id.0
+                )));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// (errors on disk, mismatches with the snapshot)
+fn check_merkle(
+    basedir: &str,
+    stores: &Stores,
+    kind: MerkleKind,
+) -> (Result<()>, Result<()>) {
+    let mut tree =
+        match AppendOnlyMerkle::open(&format!("{}/{}", basedir, kind.file_name())) {
+            Ok(t) => t,
+            Err(e) => return (Err(e), Err(eg!("unreadable"))),
+        };
+
+    let disk_res = tree
+        .check_disk(true)
+        .c(d!())
+        .and_then(|_| tree.check().c(d!()));
+
+    let (cnt, root) = stores.expected_merkle(kind);
+    let data_res = if tree.state() != cnt {
+        Err(eg!(format!(
+            "{} leaves, but {} are expected",
+            tree.state(),
+            cnt
+        )))
+    } else if tree.get_root_hash() != root {
+        Err(eg!("the root hash mismatches the snapshot"))
+    } else {
+        Ok(())
+    };
+
+    (disk_res, data_res)
+}
+
+fn check_utxo_map(basedir: &str, stores: &Stores) -> Result<()> {
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(format!("{basedir}/{UTXO_MAP}"))
+        .c(d!())?;
+    let mut map = BitMap::open(file).c(d!())?;
+
+    if !map.validate(true) {
+        return Err(eg!("invalid bitmap"));
+    }
+    if map.size() as u64 != stores.status.next_txo.0 {
+        return Err(eg!(format!(
+            "{} bits, but {} txos are committed",
+            map.size(),
+            stores.status.next_txo.0
+        )));
+    }
+    if let Some(state) = stores.status.state_commitment_data.as_ref() {
+        if map.compute_checksum() != state.bitmap {
+            return Err(eg!("the checksum mismatches the snapshot"));
+        }
+    }
+
+    Ok(())
+}
+
+// The signatures are excluded from the hashes of early blocks.
+fn txns_hash(block: &FinalizedBlock, no_sigs: bool) -> HashOf<Vec<Transaction>> {
+    HashOf::new(
+        &block
+            .txns
+            .iter()
+            .map(|tx| strip_sigs(&tx.txn, no_sigs))
+            .collect::<Vec<_>>(),
+    )
+}
+
+fn strip_sigs(txn: &Transaction, no_sigs: bool) -> Transaction {
+    let mut txn = txn.clone();
+    if no_sigs {
+        txn.pubkey_sign_map = Default::default();
+    }
+    txn
+}
+
+fn truncate_blocks(stores: &mut Stores) -> Result<()> {
+    let cnt = stores.status.block_commit_count as usize;
+
+    while stores.blocks.len() > cnt {
+        let b = stores.blocks.pop().c(d!())?;
+        for tx in b.txns.iter() {
+            stores.tx_to_block_location.remove(&tx.tx_id);
+        }
+    }
+    while stores.status.state_commitment_versions.len() > cnt {
+        stores.status.state_commitment_versions.pop();
+    }
+
+    Ok(())
+}
+
+// The new tree is written to `TMP_DIR`, it must match the snapshot.
+fn derive_merkle(basedir: &str, stores: &Stores, kind: MerkleKind) -> Result<()> {
+    if MerkleKind::Txn == kind && 0 < stores.status.pruned_blocks {
+        return Err(eg!("the transactions have been pruned"));
+    }
+
+    let tmp = format!("{basedir}/{TMP_DIR}");
+    omit!(fs::remove_dir_all(&tmp));
+    fs::create_dir_all(&tmp).c(d!())?;
+
+    let mut tree =
+        AppendOnlyMerkle::create(&format!("{}/{}", tmp, kind.file_name())).c(d!())?;
+    for b in stores.blocks() {
+        match kind {
+            MerkleKind::Block => {
+                tree.append_hash(&b.state.txns_in_block_hash.0.hash.into())
+                    .c(d!())?;
+            }
+            MerkleKind::Txn => {
+                let no_sigs = txns_hash(&b, false) != b.state.txns_in_block_hash;
+                for tx in b.txns.iter() {
+                    let hash = HashOf::new(&(tx.tx_id, strip_sigs(&tx.txn, no_sigs)))
+                        .0
+                        .hash;
+                    tree.append_hash(&hash.into()).c(d!())?;
+                }
+            }
+        }
+    }
+
+    if (tree.state(), tree.get_root_hash()) != stores.expected_merkle(kind) {
+        return Err(eg!(format!(
+            "the {} derived from blocks mismatches the snapshot",
+            kind.file_name()
+        )));
+    }
+
+    tree.write().c(d!())
+}
+
+// The new bitmap is written to `TMP_DIR`, it must match the snapshot.
+fn derive_utxo_map(basedir: &str, stores: &Stores) -> Result<()> {
+    if 0 < stores.status.pruned_blocks {
+        return Err(eg!("the transactions have been pruned"));
+    }
+
+    let mut live = HashSet::new();
+    let mut spent = HashSet::new();
+    for b in stores.blocks() {
+        for tx in b.txns.iter() {
+            live.extend(tx.txo_ids.iter().copied());
+            for op in tx.txn.body.operations.iter() {
+                if let Operation::TransferAsset(t) = op {
+                    spent.extend(t.body.inputs.iter().filter_map(|i| match i {
+                        TxoRef::Absolute(sid) => Some(*sid),
+                        TxoRef::Relative(_) => None,
+                    }));
+                }
+            }
+        }
+    }
+
+    let tmp = format!("{basedir}/{TMP_DIR}");
+    omit!(fs::remove_dir_all(&tmp));
+    fs::create_dir_all(&tmp).c(d!())?;
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(format!("{tmp}/{UTXO_MAP}"))
+        .c(d!())?;
+    let mut map = BitMap::create(file).c(d!())?;
+
+    for ix in 0..stores.status.next_txo.0 {
+        map.set(ix as usize).c(d!())?;
+        let sid = TxoSID(ix);
+        if !live.contains(&sid) || spent.contains(&sid) {
+            map.clear(ix as usize).c(d!())?;
+        }
+    }
+
+    if let Some(state) = stores.status.state_commitment_data.as_ref() {
+        if map.compute_checksum() != state.bitmap {
+            return Err(eg!(
+                "the utxo_map derived from blocks mismatches the snapshot"
+            ));
+        }
+    }
+
+    map.write().c(d!())
+}
+
+// Move the files of `name` to `backup`, then move the new ones from `TMP_DIR`.
+fn replace_files(basedir: &str, backup: &str, name: &str) -> Result<()> {
+    let tmp = format!("{basedir}/{TMP_DIR}");
+    fs::create_dir_all(backup).c(d!())?;
+
+    let is_file_of = |f: &str| {
+        f == name
+            || f.strip_prefix(name)
+                .and_then(|s| s.strip_prefix('.'))
+                .map_or(false, |l| l.parse::<u64>().is_ok())
+    };
+
+    for (from, to) in [(basedir, backup), (tmp.as_str(), basedir)] {
+        for entry in fs::read_dir(from).c(d!())? {
+            let f = entry.c(d!())?.file_name();
+            let f = f.to_string_lossy();
+            if is_file_of(&f) {
+                fs::rename(format!("{from}/{f}"), format!("{to}/{f}")).c(d!())?;
+            }
+        }
+    }
+
+    fs::remove_dir_all(&tmp).c(d!())
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::{
+            data_model::{AssetRules, AssetTypeCode},
+            store::{helpers::*, LedgerState},
+        },
+        zei::{setup::PublicParams, xfr::sig::XfrKeyPair},
+    };
+
+    // A ledger of three blocks, the last of which is missing in the snapshot
+    // if `extra_block`, as if the node crashed in the middle of a commit.
+    fn tmp_ledger(extra_block: bool) -> (String, LedgerState) {
+        fbnc::clear();
+        let basedir = globutils::fresh_tmp_dir().to_string_lossy().into_owned();
+        let mut ledger = pnk!(LedgerState::new(&basedir, None));
+        let params = PublicParams::default();
+        let issuer = XfrKeyPair::generate(&mut ledger.get_prng());
+        let code = AssetTypeCode::gen_random();
+
+        let tx = pnk!(create_definition_transaction(
+            &code,
+            &issuer,
+            AssetRules::default(),
+            None,
+            ledger.get_block_commit_count(),
+        ));
+        apply_transaction(&mut ledger, tx);
+
+        let issue = |ledger: &mut LedgerState, seq_num| {
+            let (tx, _) = create_issue_and_transfer_txn(
+                ledger,
+                &params,
+                &code,
+                100,
+                &issuer,
+                &issuer.get_pk(),
+                seq_num,
+            );
+            apply_transaction(ledger, tx);
+        };
+
+        issue(&mut ledger, 0);
+        let snapshot = pnk!(serde_json::to_vec(ledger.get_status()));
+        if extra_block {
+            issue(&mut ledger, 1);
+        }
+        pnk!(fs::write(format!("{basedir}/{SNAPSHOT_FILE}"), snapshot));
+        fbnc::flush_data();
+
+        (basedir, ledger)
+    }
+
+    // Apply the repairs of `diagnose`, then the ledger must be healthy and loadable.
+    fn repair_and_check(basedir: &str, repairs: &[Repair]) {
+        let diagnosis = pnk!(diagnose(basedir, 8));
+        assert!(!diagnosis.is_healthy());
+        assert_eq!(diagnosis.repairs, repairs);
+
+        pnk!(repair(basedir, &diagnosis.repairs));
+        let diagnosis = pnk!(diagnose(basedir, 8));
+        assert!(diagnosis.is_healthy(), "{:?}", diagnosis.checks);
+        assert!(diagnosis.repairs.is_empty());
+
+        let ledger = pnk!(LedgerState::load_or_init(basedir));
+        pnk!(ledger.fast_invariant_check());
+        assert_eq!(ledger.get_block_commit_count(), 2);
+        assert!(fs::read_dir(format!("{basedir}/{BACKUP_DIR}")).is_ok());
+    }
+
+    #[test]
+    fn test_doctor_healthy() {
+        let (basedir, ledger) = tmp_ledger(false);
+        drop(ledger);
+
+        let diagnosis = pnk!(diagnose(&basedir, 8));
+        assert!(diagnosis.is_healthy(), "{:?}", diagnosis.checks);
+        assert!(diagnosis.repairs.is_empty());
+    }
+
+    #[test]
+    fn test_doctor_extra_block() {
+        let (basedir, ledger) = tmp_ledger(true);
+        drop(ledger);
+
+        repair_and_check(
+            &basedir,
+            &[
+                Repair::TruncateBlocks,
+                Repair::DeriveMerkle(MerkleKind::Block),
+                Repair::DeriveMerkle(MerkleKind::Txn),
+                Repair::DeriveUtxoMap,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_doctor_deleted_merkle() {
+        let (basedir, ledger) = tmp_ledger(false);
+        drop(ledger);
+        pnk!(fs::remove_file(format!("{basedir}/{TXN_MERKLE}")));
+
+        repair_and_check(&basedir, &[Repair::DeriveMerkle(MerkleKind::Txn)]);
+    }
+
+    #[test]
+    fn test_doctor_flipped_bitmap_bit() {
+        let (basedir, ledger) = tmp_ledger(false);
+        drop(ledger);
+        {
+            let file = pnk!(OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open(format!("{basedir}/{UTXO_MAP}")));
+            let mut map = pnk!(BitMap::open(file));
+            if pnk!(map.query(0)) {
+                pnk!(map.clear(0));
+            } else {
+                pnk!(map.set(0));
+            }
+            pnk!(map.write());
+        }
+
+        repair_and_check(&basedir, &[Repair::DeriveUtxoMap]);
+    }
+}
@@ -0,0 +1,167 @@
+//!
+//! # Ledger genesis
+//!
+//! Export the unspent TXOs, the asset types and the staking state of a ledger,
+//! and import them into a fresh ledger of a new chain.
+//!
+//! The transactions are not exported, so the imported ledger works
+//! like a pruned one: the TXO and transaction ids keep going on from
+//! the exported ones, and the owner memos are kept aside.
+//!
+//! NOTE: the node must be stopped before exporting.
+//!
+
+use {
+    super::LedgerState,
+    crate::{
+        data_model::{AssetType, AssetTypeCode, TxnSID, TxoSID, Utxo},
+        staking::{genesis::StakingGenesis, Staking},
+    },
+    credentials::CredIssuerPublicKey,
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    std::collections::HashSet,
+    zei::xfr::{sig::XfrPublicKey, structs::OwnerMemo},
+};
+
+/// An unspent TXO in a genesis file.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GenesisUtxo {
+    #[allow(missing_docs)]
+    pub sid: TxoSID,
+    #[allow(missing_docs)]
+    pub utxo: Utxo,
+    #[allow(missing_docs)]
+    pub owner_memo: Option<OwnerMemo>,
+}
+
+/// The ledger state in a genesis file.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct LedgerGenesis {
+    /// the id of the next transaction
+    pub next_txn: TxnSID,
+    /// the id of the next TXO
+    pub next_txo: TxoSID,
+    #[allow(missing_docs)]
+    pub utxos: Vec<GenesisUtxo>,
+    #[allow(missing_docs)]
+    pub asset_types: Vec<(AssetTypeCode, AssetType)>,
+    #[allow(missing_docs)]
+    pub issuance_num: Vec<(AssetTypeCode, u64)>,
+    #[allow(missing_docs)]
+    pub issuance_amounts: Vec<(AssetTypeCode, u64)>,
+    #[allow(missing_docs)]
+    pub cred_issuers: Vec<(XfrPublicKey, CredIssuerPublicKey)>,
+    #[allow(missing_docs)]
+    pub staking: StakingGenesis,
+}
+
+impl LedgerState {
+    /// Export the committed state of the ledger.
+    pub fn export_genesis(&self) -> Result<LedgerGenesis> {
+        let status = &self.status;
+
+        let utxos = status
+            .utxos
+            .iter()
+            .map(|(sid, utxo)| {
+                let owner_memo = if self.is_pruned_txo(sid) {
+                    self.pruned_owner_memos.get(&sid)
+                } else {
+                    let (txn, position) =
+                        status.txo_to_txn_location.get(&sid).c(d!())?;
+                    self.get_transaction_light(txn)
+                        .c(d!())?
+                        .txn
+                        .get_owner_memos_ref()
+                        .get(position.0)
+                        .and_then(|m| m.cloned())
+                };
+                Ok(GenesisUtxo {
+                    sid,
+                    utxo,
+                    owner_memo,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(LedgerGenesis {
+            next_txn: status.next_txn,
+            next_txo: status.next_txo,
+            utxos,
+            asset_types: status.asset_types.iter().collect(),
+            issuance_num: status.issuance_num.iter().collect(),
+            issuance_amounts: status.issuance_amounts.iter().collect(),
+            cred_issuers: status
+                .cred_issuers
+                .iter()
+                .map(|(k, v)| (*k, v.clone()))
+                .collect(),
+            staking: status.staking.export_genesis().c(d!())?,
+        })
+    }
+
+    /// Import an exported state into a fresh ledger,
+    /// it is committed along with the first block.
+    pub fn import_genesis(&mut self, genesis: LedgerGenesis) -> Result<()> {
+        if 0 != self.status.next_txo.0 || 0 != self.status.block_commit_count {
+            return Err(eg!("the ledger is not empty"));
+        }
+
+        let status = &mut self.status;
+        status.next_txn = genesis.next_txn;
+        status.next_txo = genesis.next_txo;
+        status.pruned_txn = genesis.next_txn;
+        status.pruned_txo = genesis.next_txo;
+
+        let mut unspent = HashSet::new();
+        for GenesisUtxo {
+            sid,
+            utxo,
+            owner_memo,
+        } in genesis.utxos.into_iter()
+        {
+            if sid.0 >= genesis.next_txo.0 {
+                return Err(eg!(format!("invalid TXO id: {}", sid.0)));
+            }
+            status
+                .owned_utxos
+                .entry(utxo.0.record.public_key)
+                .or_insert_with(HashSet::new)
+                .insert(sid);
+            status.utxos.insert(sid, utxo);
+            if let Some(memo) = owner_memo {
+                self.pruned_owner_memos.insert(sid, memo);
+            }
+            unspent.insert(sid.0);
+        }
+
+        for (code, asset_type) in genesis.asset_types.into_iter() {
+            status.asset_types.insert(code, asset_type);
+        }
+        for (code, num) in genesis.issuance_num.into_iter() {
+            status.issuance_num.insert(code, num);
+        }
+        for (code, amount) in genesis.issuance_amounts.into_iter() {
+            status.issuance_amounts.insert(code, amount);
+        }
+        status.cred_issuers = genesis.cred_issuers.into_iter().collect();
+        status.staking = Staking::from_genesis(genesis.staking).c(d!())?;
+
+        // the balances of a fresh ledger are empty,
+        // so they are recomputed from the imported utxos
+        status.refresh_data();
+
+        // Only .set() extends the bitmap, so to append a 0 we currently
+        // nead to .set() then .clear().
+        let mut utxo_map = self.utxo_map.write();
+        for ix in 0..genesis.next_txo.0 {
+            utxo_map.set(ix as usize).c(d!())?;
+            if !unspent.contains(&ix) {
+                utxo_map.clear(ix as usize).c(d!())?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -3,7 +3,10 @@
 //!
 
 pub mod api_cache;
+pub mod doctor;
+pub mod genesis;
 pub mod helpers;
+pub mod simulation;
 mod test;
 pub mod utils;
 
@@ -28,13 +31,14 @@ use {
     api_cache::ApiCache,
     bitmap::{BitMap, SparseMap},
     config::abci::global_cfg::CFG,
+    credentials::CredIssuerPublicKey,
     cryptohash::sha256::Digest as BitDigest,
     fbnc::{new_mapx, new_mapxnk, new_vecx, Mapx, Mapxnk, Vecx},
     globutils::{HashOf, ProofOf},
     merkle_tree::AppendOnlyMerkle,
     parking_lot::RwLock,
     rand_chacha::ChaChaRng,
-    rand_core::SeedableRng,
+    rand_core::{RngCore, SeedableRng},
     ruc::*,
     serde::{Deserialize, Serialize},
     sliding_set::SlidingSet,
@@ -54,7 +58,19 @@ use {
     },
 };
 
-const TRANSACTION_WINDOW_WIDTH: u64 = 128;
+/// A transaction is rejected once the block count is more than this
+/// ahead of the seq_id in its `NoReplayToken`
+pub const TRANSACTION_WINDOW_WIDTH: u64 = 128;
+
+/// The last block count at which a transaction built at `seq_id` is accepted
+#[inline(always)]
+pub fn seq_id_deadline(seq_id: u64) -> u64 {
+    seq_id + TRANSACTION_WINDOW_WIDTH
+}
+
+/// At most this many transactions are pruned in one commit,
+/// so enabling pruning on a long ledger does not stall the consensus
+pub const PRUNE_TXNS_PER_COMMIT: usize = 2000;
 
 type TmpSidMap = HashMap<TxnTempSID, (TxnSID, Vec<TxoSID>)>;
 
@@ -71,6 +87,8 @@ pub struct LedgerState {
     pub tx_to_block_location: Mapxnk<TxnSID, [usize; 2]>,
     /// cache used in APIs
     pub api_cache: Option<ApiCache>,
+    // owner memos of the unspent TXOs in pruned transactions
+    pruned_owner_memos: Mapxnk<TxoSID, OwnerMemo>,
 
     // current block effect (middle cache)
     block_ctx: Option<BlockEffect>,
@@ -83,6 +101,9 @@ pub struct LedgerState {
     txn_merkle: Arc<RwLock<AppendOnlyMerkle>>,
     // Bitmap tracing all the live TXOs
     utxo_map: Arc<RwLock<BitMap>>,
+
+    // the source of `get_prng`, only set by `set_prng_seed`
+    prng: Option<ChaChaRng>,
 }
 
 impl LedgerState {
@@ -91,10 +112,24 @@ impl LedgerState {
         self.status.fast_invariant_check().c(d!())
     }
 
+    /// A random generator seeded from the entropy,
+    /// or derived from the seed of `set_prng_seed`.
     #[inline(always)]
-    #[allow(missing_docs)]
     pub fn get_prng(&mut self) -> ChaChaRng {
-        ChaChaRng::from_entropy()
+        if let Some(prng) = self.prng.as_mut() {
+            let mut seed = [0u8; 32];
+            prng.fill_bytes(&mut seed);
+            ChaChaRng::from_seed(seed)
+        } else {
+            ChaChaRng::from_entropy()
+        }
+    }
+
+    /// Make the generators of `get_prng` deterministic,
+    /// so that the transactions built with them are reproducible in tests.
+    #[inline(always)]
+    pub fn set_prng_seed(&mut self, seed: u64) {
+        self.prng = Some(ChaChaRng::seed_from_u64(seed));
     }
 
     /// Consume a block context and assemble a BlockEffect
@@ -239,6 +274,10 @@ impl LedgerState {
             state: self.status.state_commitment_data.clone().c(d!())?,
         });
 
+        if let Some(keep) = CFG.prune_keep_blocks {
+            self.prune_history(keep, PRUNE_TXNS_PER_COMMIT).c(d!())?;
+        }
+
         mem::swap(
             &mut block.staking_simulator,
             self.get_staking_mut().deref_mut(),
@@ -254,21 +293,93 @@ impl LedgerState {
     ///    Apply current block to ledger status
     ///    Update Utxo map
     pub fn finish_block(&mut self, mut block: BlockEffect) -> Result<TmpSidMap> {
+        let mut pruned_inputs = vec![];
         {
             let mut utxo_map = self.utxo_map.write();
             for (inp_sid, _) in block.input_txos.iter() {
                 utxo_map.clear(inp_sid.0 as usize).c(d!())?;
+                if self.is_pruned_txo(*inp_sid) {
+                    pruned_inputs.push(*inp_sid);
+                }
             }
         }
 
         let (tsm, base_sid, max_sid) = self.status.apply_block_effects(&mut block);
 
+        // the transactions of these TXOs have been pruned,
+        // so they are dropped instead of being kept as spent ones
+        for sid in pruned_inputs {
+            self.status.spent_utxos.remove(&sid);
+            self.status.txo_to_txn_location.remove(&sid);
+            self.pruned_owner_memos.remove(&sid);
+        }
+
         self.update_utxo_map(base_sid, max_sid, &block.temp_sids, &tsm)
             .c(d!())
             .and_then(|_| self.update_state(block, &tsm).c(d!()))
             .map(|_| tsm)
     }
 
+    /// Drop the transaction bodies of the blocks except the last `keep` ones,
+    /// the merkle trees, the utxo bitmap, the state commitments and the unspent utxos are kept,
+    /// so the pruned ledger still produces the same `StateCommitmentData`.
+    ///
+    /// Blocks are pruned as a whole until at least `max_txns` transactions have been dropped,
+    /// the remaining ones are left to the next call. Return the number of dropped transactions.
+    pub fn prune_history(&mut self, keep: usize, max_txns: usize) -> Result<usize> {
+        let end = self.blocks.len().saturating_sub(keep);
+        let mut pruned_txns = 0;
+
+        while self.status.pruned_blocks < end && pruned_txns < max_txns {
+            let idx = self.status.pruned_blocks;
+            let txns = {
+                let mut b = self.blocks.get_mut(idx).c(d!())?;
+                mem::take(&mut b.txns)
+            };
+
+            for tx in txns.iter() {
+                self.tx_to_block_location.remove(&tx.tx_id);
+                let memos = tx.txn.get_owner_memos_ref();
+                for (position, sid) in tx.txo_ids.iter().enumerate() {
+                    if self.status.is_unspent_txo(*sid) {
+                        if let Some(memo) = memos.get(position).and_then(|m| m.cloned())
+                        {
+                            self.pruned_owner_memos.insert(*sid, memo);
+                        }
+                    } else {
+                        self.status.spent_utxos.remove(sid);
+                        self.status.txo_to_txn_location.remove(sid);
+                    }
+                    self.status.pruned_txo = TxoSID(sid.0 + 1);
+                }
+                self.status.pruned_txn = TxnSID(tx.tx_id.0 + 1);
+            }
+
+            pruned_txns += txns.len();
+            self.status.pruned_blocks += 1;
+        }
+
+        Ok(pruned_txns)
+    }
+
+    /// Check if the transactions of a block have been pruned
+    #[inline(always)]
+    pub fn is_pruned_block(&self, id: BlockSID) -> bool {
+        id.0 < self.status.pruned_blocks
+    }
+
+    /// Check if a transaction has been pruned
+    #[inline(always)]
+    pub fn is_pruned_txn(&self, id: TxnSID) -> bool {
+        id.0 < self.status.pruned_txn.0
+    }
+
+    /// Check if the transaction of a TXO has been pruned
+    #[inline(always)]
+    pub fn is_pruned_txo(&self, id: TxoSID) -> bool {
+        id.0 < self.status.pruned_txo.0
+    }
+
     #[inline(always)]
     #[allow(missing_docs)]
     pub fn get_staking_mut(&mut self) -> &mut Staking {
@@ -403,6 +514,7 @@ impl LedgerState {
 
         let blocks_path = prefix.clone() + "blocks";
         let tx_to_block_location_path = prefix.clone() + "tx_to_block_location";
+        let pruned_owner_memos_path = prefix.clone() + "pruned_owner_memos";
 
         let mut ledger = LedgerState {
             status: LedgerStatus::new(&basedir, &snapshot_file).c(d!())?,
@@ -414,11 +526,13 @@ impl LedgerState {
             )),
             blocks: new_vecx!(&blocks_path),
             tx_to_block_location: new_mapxnk!(&tx_to_block_location_path),
+            pruned_owner_memos: new_mapxnk!(&pruned_owner_memos_path),
             utxo_map: Arc::new(RwLock::new(
                 LedgerState::init_utxo_map(&utxo_map_path).c(d!())?,
             )),
             block_ctx: Some(BlockEffect::default()),
             api_cache: alt!(*KEEP_HIST, Some(ApiCache::new(&prefix)), None),
+            prng: None,
         };
 
         ledger.status.refresh_data();
@@ -645,47 +759,56 @@ impl LedgerState {
         }
     }
 
-    /// Get a utxo along with the transaction, spent status and commitment data which it belongs
+    /// Get a utxo along with the transaction, spent status and commitment data which it belongs,
+    /// the transaction of a pruned utxo is replaced by its owner memo
     pub fn get_utxo(&self, id: TxoSID) -> Option<AuthenticatedUtxo> {
-        if let Some(utxo) = self.status.get_utxo(id) {
-            let txn_location = self.status.txo_to_txn_location.get(&id).unwrap();
-            let authenticated_txn = self.get_transaction(txn_location.0).unwrap();
-            let authenticated_spent_status = self.get_utxo_status(id);
-            let state_commitment_data =
-                self.status.state_commitment_data.as_ref().unwrap().clone();
-            let utxo_location = txn_location.1;
-            Some(AuthenticatedUtxo {
-                utxo,
-                authenticated_txn,
-                authenticated_spent_status,
-                utxo_location,
-                state_commitment_data,
-            })
+        let utxo = self.status.get_utxo(id)?;
+        let txn_location = self.status.txo_to_txn_location.get(&id).unwrap();
+        let (authenticated_txn, owner_memo) = if self.is_pruned_txo(id) {
+            (None, self.pruned_owner_memos.get(&id))
         } else {
-            None
-        }
+            (Some(self.get_transaction(txn_location.0).unwrap()), None)
+        };
+        let authenticated_spent_status = self.get_utxo_status(id);
+        let state_commitment_data =
+            self.status.state_commitment_data.as_ref().unwrap().clone();
+        Some(AuthenticatedUtxo {
+            utxo,
+            authenticated_txn,
+            authenticated_spent_status,
+            utxo_location: txn_location.1,
+            state_commitment_data,
+            owner_memo,
+        })
     }
 
-    /// Get a utxo along with the transaction which it belongs
+    /// Get a utxo along with the transaction which it belongs,
+    /// the transaction of a pruned utxo is replaced by its owner memo
     /// Avoid ledger query operation to reduce latency
     pub fn get_utxo_light(&self, id: TxoSID) -> Option<UnAuthenticatedUtxo> {
-        let utxo = self.status.get_utxo(id);
-        if let Some(utxo) = utxo {
-            let txn_location = self.status.txo_to_txn_location.get(&id).unwrap();
-            let txn = self.get_transaction_light(txn_location.0).unwrap();
-            let utxo_location = txn_location.1;
-            Some(UnAuthenticatedUtxo {
-                utxo,
-                txn,
-                utxo_location,
-            })
+        let utxo = self.status.get_utxo(id)?;
+        let txn_location = self.status.txo_to_txn_location.get(&id).unwrap();
+        let (txn, owner_memo) = if self.is_pruned_txo(id) {
+            (None, self.pruned_owner_memos.get(&id))
         } else {
-            None
-        }
+            (
+                Some(self.get_transaction_light(txn_location.0).unwrap()),
+                None,
+            )
+        };
+        Some(UnAuthenticatedUtxo {
+            utxo,
+            txn,
+            utxo_location: txn_location.1,
+            owner_memo,
+        })
     }
 
     /// Get a spent utxo along with the transaction, spent status and commitment data which it belongs
     pub fn get_spent_utxo(&self, addr: TxoSID) -> Option<AuthenticatedUtxo> {
+        if self.is_pruned_txo(addr) {
+            return None;
+        }
         let utxo = self.status.get_spent_utxo(addr);
         if let Some(utxo) = utxo {
             let txn_location = self.status.txo_to_txn_location.get(&addr).unwrap();
@@ -696,10 +819,11 @@ impl LedgerState {
             let utxo_location = txn_location.1;
             Some(AuthenticatedUtxo {
                 utxo,
-                authenticated_txn,
+                authenticated_txn: Some(authenticated_txn),
                 authenticated_spent_status,
                 utxo_location,
                 state_commitment_data,
+                owner_memo: None,
             })
         } else {
             None
@@ -709,6 +833,9 @@ impl LedgerState {
     /// Get a spent utxo along with the transaction which it belongs
     /// Avoid ledger query operation to reduce latency
     pub fn get_spent_utxo_light(&self, addr: TxoSID) -> Option<UnAuthenticatedUtxo> {
+        if self.is_pruned_txo(addr) {
+            return None;
+        }
         let utxo = self.status.get_spent_utxo(addr);
         if let Some(utxo) = utxo {
             let txn_location = self.status.txo_to_txn_location.get(&addr).unwrap();
@@ -716,8 +843,9 @@ impl LedgerState {
             let utxo_location = txn_location.1;
             Some(UnAuthenticatedUtxo {
                 utxo,
-                txn,
+                txn: Some(txn),
                 utxo_location,
+                owner_memo: None,
             })
         } else {
             None
@@ -726,30 +854,7 @@ impl LedgerState {
 
     #[allow(missing_docs)]
     pub fn get_utxos(&self, sid_list: &[TxoSID]) -> Vec<Option<AuthenticatedUtxo>> {
-        let mut utxos = vec![];
-        for sid in sid_list.iter() {
-            let utxo = self.status.get_utxo(*sid);
-            if let Some(utxo) = utxo {
-                let txn_location = self.status.txo_to_txn_location.get(sid).unwrap();
-                let authenticated_txn = self.get_transaction(txn_location.0).unwrap();
-                let authenticated_spent_status = self.get_utxo_status(*sid);
-                let state_commitment_data =
-                    self.status.state_commitment_data.as_ref().unwrap().clone();
-                let utxo_location = txn_location.1;
-                let auth_utxo = AuthenticatedUtxo {
-                    utxo,
-                    authenticated_txn,
-                    authenticated_spent_status,
-                    utxo_location,
-                    state_commitment_data,
-                };
-                utxos.push(Some(auth_utxo))
-            } else {
-                utxos.push(None)
-            }
-        }
-
-        utxos
+        sid_list.iter().map(|sid| self.get_utxo(*sid)).collect()
     }
 
     #[allow(missing_docs)]
@@ -762,12 +867,19 @@ impl LedgerState {
             let utxo = self.status.get_utxo(*sid);
             if let Some(utxo) = utxo {
                 let txn_location = self.status.txo_to_txn_location.get(sid).c(d!())?;
-                let txn = self.get_transaction_light(txn_location.0).c(d!())?;
-                let utxo_location = txn_location.1;
+                let (txn, owner_memo) = if self.is_pruned_txo(*sid) {
+                    (None, self.pruned_owner_memos.get(sid))
+                } else {
+                    (
+                        Some(self.get_transaction_light(txn_location.0).c(d!())?),
+                        None,
+                    )
+                };
                 let auth_utxo = UnAuthenticatedUtxo {
                     utxo,
                     txn,
-                    utxo_location,
+                    utxo_location: txn_location.1,
+                    owner_memo,
                 };
                 utxos.push(Some(auth_utxo))
             } else {
@@ -791,26 +903,15 @@ impl LedgerState {
         let sids = self.status.get_owned_utxos(addr);
         let aus = self.get_utxos_light(&sids).c(d!())?;
 
-        let res = sids
+        Ok(sids
             .into_iter()
             .zip(aus.into_iter())
             .filter_map(|(sid, au)| au.map(|au| (sid, au)))
             .map(|(sid, au)| {
-                (
-                    sid,
-                    (
-                        au.utxo,
-                        au.txn
-                            .txn
-                            .get_owner_memos_ref()
-                            .get(au.utxo_location.0)
-                            .and_then(|i| i.cloned()),
-                    ),
-                )
+                let memo = au.get_owner_memo();
+                (sid, (au.utxo, memo))
             })
-            .collect();
-
-        Ok(res)
+            .collect())
     }
 
     #[inline(always)]
@@ -825,6 +926,12 @@ impl LedgerState {
         self.status.get_asset_type(code)
     }
 
+    /// The credential public key registered by `issuer`
+    #[inline(always)]
+    pub fn get_cred_issuer(&self, issuer: &XfrPublicKey) -> Option<CredIssuerPublicKey> {
+        self.status.get_cred_issuer(issuer)
+    }
+
     #[inline(always)]
     #[allow(missing_docs)]
     pub fn get_block_commit_count(&self) -> u64 {
@@ -896,6 +1003,9 @@ impl LedgerState {
 
     /// Query the transaction by a TxnSID without its proof data to reduce latency
     pub fn get_transaction_light(&self, id: TxnSID) -> Result<FinalizedTransaction> {
+        if self.is_pruned_txn(id) {
+            return Err(eg!(format!("transaction {} has been pruned", id.0)));
+        }
         self.tx_to_block_location
             .get(&id)
             .c(d!())
@@ -909,6 +1019,9 @@ impl LedgerState {
 
     /// Query the Block by a BlockSID along with its proof data
     pub fn get_block(&self, addr: BlockSID) -> Option<AuthenticatedBlock> {
+        if self.is_pruned_block(addr) {
+            return None;
+        }
         match self.blocks.get(addr.0) {
             None => None,
             Some(finalized_block) => {
@@ -936,6 +1049,21 @@ impl LedgerState {
         self.blocks.len()
     }
 
+    /// The first transaction committed at or after a block,
+    /// `None` if the block has been pruned or is beyond the next one.
+    pub fn get_first_txn_since(&self, addr: BlockSID) -> Option<TxnSID> {
+        if self.is_pruned_block(addr) || addr.0 > self.blocks.len() {
+            return None;
+        }
+        (addr.0..self.blocks.len())
+            .find_map(|i| {
+                self.blocks
+                    .get(i)
+                    .and_then(|b| b.txns.first().map(|tx| tx.tx_id))
+            })
+            .or_else(|| Some(self.get_next_txn()))
+    }
+
     #[inline(always)]
     #[allow(missing_docs)]
     pub fn get_transaction_count(&self) -> usize {
@@ -992,6 +1120,18 @@ pub struct LedgerStatus {
     staking: Staking,
     // tendermint commit height
     td_commit_height: u64,
+    // Registered credential issuers
+    #[serde(default)]
+    cred_issuers: HashMap<XfrPublicKey, CredIssuerPublicKey>,
+    // Transaction bodies of the blocks before it have been pruned
+    #[serde(default)]
+    pruned_blocks: usize,
+    // Transactions before it have been pruned
+    #[serde(default)]
+    pruned_txn: TxnSID,
+    // TXOs before it belong to pruned transactions
+    #[serde(default)]
+    pruned_txo: TxoSID,
 
     // An obsolete feature, ignore it!
     tracing_policies: HashMap<AssetTypeCode, TracingPolicy>,
@@ -1031,6 +1171,12 @@ impl LedgerStatus {
         self.asset_types.get(code)
     }
 
+    #[inline(always)]
+    #[allow(missing_docs)]
+    fn get_cred_issuer(&self, issuer: &XfrPublicKey) -> Option<CredIssuerPublicKey> {
+        self.cred_issuers.get(issuer).cloned()
+    }
+
     fn fast_invariant_check(&self) -> Result<()> {
         let cnt_eq =
             self.block_commit_count == self.state_commitment_versions.len() as u64;
@@ -1102,6 +1248,10 @@ impl LedgerStatus {
             block_commit_count: 0,
             staking: Staking::new(),
             td_commit_height: 0,
+            cred_issuers: map! {},
+            pruned_blocks: 0,
+            pruned_txn: TxnSID(0),
+            pruned_txo: TxoSID(0),
         };
 
         Ok(ledger)
@@ -1130,7 +1280,7 @@ impl LedgerStatus {
         );
         if seq_id > self.block_commit_count {
             return Err(eg!(("Transaction seq_id ahead of block_count")));
-        } else if seq_id + TRANSACTION_WINDOW_WIDTH < self.block_commit_count {
+        } else if seq_id_deadline(seq_id) < self.block_commit_count {
             return Err(eg!(("Transaction seq_id too far behind block_count")));
         } else {
             // Check to see that this nrpt has not been seen before
@@ -1293,6 +1443,67 @@ impl LedgerStatus {
             }
         }
 
+        // Credential-gated assets
+        // (1) Each recipient other than the asset issuer must present a credential proof
+        // (2) The proof must reveal the required attributes and be signed by the registered issuer
+        //  - NOTE: the proofs themselves have been verified in TxnEffect
+        // (3) The outputs must not hide their asset type, or they would escape the check
+        //  - NOTE: issued records never hide it, as checked in TxnEffect
+        for code in txn_effect.confidential_transfer_inputs.iter() {
+            let gated = self
+                .asset_types
+                .get(code)
+                .or_else(|| txn_effect.new_asset_codes.get(code).cloned())
+                .map(|t| t.properties.asset_rules.required_credentials.is_some())
+                .unwrap_or(false);
+            if gated {
+                return Err(eg!("credential-gated assets can not be confidential"));
+            }
+        }
+        for out in txn_effect
+            .txos
+            .iter()
+            .flatten()
+            .chain(txn_effect.internally_spent_txos.iter())
+        {
+            let asset_type = match out.record.asset_type.get_asset_type().and_then(|v| {
+                let code = AssetTypeCode { val: v };
+                self.asset_types
+                    .get(&code)
+                    .or_else(|| txn_effect.new_asset_codes.get(&code).cloned())
+            }) {
+                Some(t) => t,
+                // confidential types are rejected by (3) for gated assets
+                None => continue,
+            };
+            let rules = match asset_type.properties.asset_rules.required_credentials {
+                Some(ref r) => r,
+                None => continue,
+            };
+            // (1)
+            let owner = &out.record.public_key;
+            if asset_type.properties.issuer.key == *owner {
+                continue;
+            }
+            // (2)
+            let issuer_pub_key = self
+                .cred_issuers
+                .get(&rules.issuer)
+                .c(d!("credential issuer is not registered"))?;
+            if !txn_effect
+                .txn
+                .body
+                .credentials
+                .iter()
+                .any(|p| p.satisfies(owner, rules, issuer_pub_key))
+            {
+                return Err(eg!(format!(
+                    "recipient {} has no valid credential",
+                    globutils::wallet::public_key_to_base64(owner)
+                )));
+            }
+        }
+
         // Until we can distinguish assets that have policies that invoke transfer restrictions
         // from those that don't, prevent any non-confidential assets with transfer restrictions
         // from becoming confidential
@@ -1355,6 +1566,11 @@ impl LedgerStatus {
             asset.properties.memo = memo;
         }
 
+        // Register credential issuers, replacing the old keys
+        for (issuer, key) in block.cred_issuers.drain() {
+            self.cred_issuers.insert(issuer, key);
+        }
+
         for (code, amount) in block.issuance_amounts.drain() {
             let mut amt = self.issuance_amounts.entry(code).or_insert(0);
             *amt.deref_mut() += amount;
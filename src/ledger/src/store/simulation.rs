@@ -0,0 +1,203 @@
+//!
+//! # Dry-run of transactions
+//!
+//! A transaction is checked and applied to a copy of a block,
+//! nothing will be written to the ledger.
+//!
+
+use {
+    super::LedgerState,
+    crate::{
+        data_model::{
+            BlockEffect, Operation, Transaction, TxnEffect, TxoSID, ASSET_TYPE_FRA,
+            BLACK_HOLE_PUBKEY,
+        },
+        staking::{Amount, Staking, BLOCK_HEIGHT_MAX},
+    },
+    serde::{Deserialize, Serialize},
+    std::collections::BTreeSet,
+    zei::xfr::sig::XfrPublicKey,
+};
+
+/// The stage where a simulated transaction is rejected.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum SimulationStage {
+    /// The transaction is invalid by itself
+    ComputeEffect,
+    /// The transaction conflicts with the ledger, eg. spending a spent utxo
+    CheckLedger,
+    /// The transaction conflicts with the current block, or the staking state
+    CheckBlock,
+}
+
+/// Why a simulated transaction is rejected.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SimulationRejection {
+    #[allow(missing_docs)]
+    pub stage: SimulationStage,
+    #[allow(missing_docs)]
+    pub reason: String,
+}
+
+/// Changes of the staking state of an address.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct StakingDelta {
+    #[allow(missing_docs)]
+    pub pubkey: XfrPublicKey,
+    /// bonded FRAs before the transaction
+    pub bonded_before: Amount,
+    /// bonded FRAs after the transaction
+    pub bonded_after: Amount,
+    /// unclaimed rewards before the transaction
+    pub rewards_before: Amount,
+    /// unclaimed rewards after the transaction
+    pub rewards_after: Amount,
+    #[allow(missing_docs)]
+    pub delegated: Amount,
+    #[allow(missing_docs)]
+    pub undelegated: Amount,
+    #[allow(missing_docs)]
+    pub claimed: Amount,
+}
+
+/// The result of a dry-run.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct TxnSimulation {
+    #[allow(missing_docs)]
+    pub handle: String,
+    #[allow(missing_docs)]
+    pub accepted: bool,
+    #[allow(missing_docs)]
+    pub rejection: Option<SimulationRejection>,
+    /// SIDs of the new utxos if the transaction is the next one in the block
+    pub new_txo_sids: Vec<TxoSID>,
+    /// SIDs of the spent utxos
+    pub spent_txo_sids: Vec<TxoSID>,
+    /// FRAs paid to the fee address
+    pub fee: u64,
+    #[allow(missing_docs)]
+    pub staking_deltas: Vec<StakingDelta>,
+}
+
+impl LedgerState {
+    /// Check and apply `tx` to a copy of `block`, or of an empty block if it is `None`,
+    /// without touching the ledger.
+    pub fn simulate_transaction(
+        &self,
+        block: Option<&BlockEffect>,
+        tx: Transaction,
+    ) -> TxnSimulation {
+        let mut block = block.cloned().unwrap_or_else(|| {
+            let mut b = BlockEffect::default();
+            *b.get_staking_simulator_mut() = self.get_staking().clone();
+            b
+        });
+        let mut res = TxnSimulation {
+            handle: tx.handle(),
+            fee: get_fee(&tx),
+            ..Default::default()
+        };
+
+        macro_rules! reject {
+            ($stage: expr, $e: expr) => {{
+                res.rejection = Some(SimulationRejection {
+                    stage: $stage,
+                    reason: $e.to_string(),
+                });
+                return res;
+            }};
+        }
+
+        let txe = match TxnEffect::compute_effect(tx.clone()) {
+            Ok(txe) => txe,
+            Err(e) => reject!(SimulationStage::ComputeEffect, e),
+        };
+        if let Err(e) = self.status.check_txn_effects(&txe) {
+            reject!(SimulationStage::CheckLedger, e);
+        }
+
+        let base_sid = self.get_next_txo().0
+            + block.txos.iter().flatten().filter(|o| o.is_some()).count() as u64;
+        res.new_txo_sids = txe
+            .txos
+            .iter()
+            .filter(|o| o.is_some())
+            .enumerate()
+            .map(|(i, _)| TxoSID(base_sid + i as u64))
+            .collect();
+        res.spent_txo_sids = txe.input_txos.keys().copied().collect();
+        res.spent_txo_sids.sort();
+
+        let pubkeys = txe
+            .delegations
+            .iter()
+            .flat_map(|i| i.get_related_pubkeys())
+            .chain(
+                txe.undelegations
+                    .iter()
+                    .flat_map(|i| i.get_related_pubkeys()),
+            )
+            .chain(txe.claims.iter().flat_map(|i| i.get_related_pubkeys()))
+            .collect::<BTreeSet<_>>();
+        let before = pubkeys
+            .iter()
+            .map(|pk| staking_state(&block.staking_simulator, pk))
+            .collect::<Vec<_>>();
+
+        if let Err(e) = block.add_txn_effect(txe) {
+            reject!(SimulationStage::CheckBlock, e);
+        }
+        block.staking_simulator.coinbase_check_and_pay(&tx);
+
+        res.staking_deltas = pubkeys
+            .into_iter()
+            .zip(before)
+            .map(|(pk, (bonded_before, rewards_before))| {
+                let (bonded_after, rewards_after) =
+                    staking_state(&block.staking_simulator, &pk);
+                StakingDelta {
+                    pubkey: pk,
+                    bonded_before,
+                    bonded_after,
+                    rewards_before,
+                    rewards_after,
+                    delegated: bonded_after.saturating_sub(bonded_before),
+                    undelegated: bonded_before.saturating_sub(bonded_after),
+                    claimed: rewards_before.saturating_sub(rewards_after),
+                }
+            })
+            .collect();
+        res.accepted = true;
+
+        res
+    }
+}
+
+// bonded amount and unclaimed rewards
+fn staking_state(staking: &Staking, pk: &XfrPublicKey) -> (Amount, Amount) {
+    staking.delegation_get(pk).map_or((0, 0), |d| {
+        let bonded = if BLOCK_HEIGHT_MAX == d.end_height() {
+            d.amount()
+        } else {
+            0
+        };
+        (bonded, d.rwd_amount)
+    })
+}
+
+fn get_fee(tx: &Transaction) -> u64 {
+    tx.body
+        .operations
+        .iter()
+        .filter_map(|op| match op {
+            Operation::TransferAsset(t) => Some(t.body.outputs.iter()),
+            _ => None,
+        })
+        .flatten()
+        .filter(|o| {
+            *BLACK_HOLE_PUBKEY == o.record.public_key
+                && Some(ASSET_TYPE_FRA) == o.record.asset_type.get_asset_type()
+        })
+        .filter_map(|o| o.record.amount.get_amount())
+        .sum()
+}
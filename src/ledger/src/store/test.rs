@@ -4,16 +4,35 @@
 use {
     super::{helpers::*, *},
     crate::data_model::{
-        AssetRules, AssetTypeCode, IssueAsset, IssueAssetBody, Memo, Operation,
+        AssetRules, AssetTypeCode, CredentialProof, CredentialRules, IssueAsset,
+        IssueAssetBody, Memo, Operation, RegisterCredIssuer, RegisterCredIssuerBody,
         Transaction, TransferAsset, TransferAssetBody, TxOutput, TxnEffect, TxoRef,
-        TxoSID, ASSET_TYPE_FRA, BLACK_HOLE_PUBKEY, TX_FEE_MIN,
+        TxoSID, ASSET_TYPE_FRA, BLACK_HOLE_PUBKEY, BLACK_HOLE_PUBKEY_STAKING,
+        TX_FEE_MIN,
+    },
+    crate::{
+        converter::{check_confidential_outputs, check_convert_account, ConvertAccount},
+        staking::{
+            ops::{
+                delegation::DelegationOps,
+                mint_fra::{MintEntry, MintFraOps, MintKind},
+                vesting::VestingSchedule,
+            },
+            td_addr_to_string, StakerMemo, Validator, ValidatorData, ValidatorKind,
+        },
+    },
+    credentials::{
+        credential_issuer_key_gen, credential_sign, credential_user_key_gen, Credential,
     },
+    fp_types::{crypto::MultiSigner, H160},
+    globutils::{amount_proof::AmountProof, SignatureOf},
     rand_core::SeedableRng,
     zei::{
         setup::PublicParams,
         xfr::{
             asset_record::{
-                build_blind_asset_record, open_blind_asset_record, AssetRecordType,
+                build_blind_asset_record, build_open_asset_record,
+                open_blind_asset_record, AssetRecordType,
             },
             sig::XfrKeyPair,
             structs::{AssetRecord, AssetRecordTemplate},
@@ -113,6 +132,255 @@ fn test_asset_creation_valid() {
     assert_eq!(0, state.get_asset_type(&token_code1).unwrap().units);
 }
 
+#[test]
+fn test_simulate_transaction() {
+    let mut prng = ChaChaRng::from_entropy();
+    let state = LedgerState::tmp_ledger();
+
+    let token_code = AssetTypeCode::gen_random();
+    let keypair = build_keys(&mut prng);
+    let asset_body = asset_creation_body(
+        &token_code,
+        keypair.get_pk_ref(),
+        AssetRules::default(),
+        None,
+        None,
+    );
+    let asset_create = asset_creation_operation(&asset_body, &keypair);
+    let seq_id = state.get_block_commit_count();
+
+    let tx = Transaction::from_operation(
+        Operation::DefineAsset(asset_create.clone()),
+        seq_id,
+    );
+    let res = state.simulate_transaction(None, tx.clone());
+    assert!(res.accepted);
+    assert_eq!(res.handle, tx.handle());
+    assert!(res.rejection.is_none());
+    assert!(res.new_txo_sids.is_empty() && res.spent_txo_sids.is_empty());
+    assert!(state.get_asset_type(&token_code).is_none());
+
+    let tx = Transaction::from_operation(
+        Operation::DefineAsset(asset_create),
+        seq_id + TRANSACTION_WINDOW_WIDTH,
+    );
+    let res = state.simulate_transaction(None, tx);
+    assert!(!res.accepted);
+    assert_eq!(
+        res.rejection.unwrap().stage,
+        simulation::SimulationStage::CheckLedger
+    );
+}
+
+#[test]
+fn test_simulate_delegation() {
+    let mut ledger = LedgerState::tmp_ledger();
+    let kp = XfrKeyPair::generate(&mut ChaChaRng::from_entropy());
+
+    let tx = utils::fra_gen_initial_tx(&kp);
+    let effect = TxnEffect::compute_effect(tx).unwrap();
+    let mut block = ledger.start_block().unwrap();
+    let tmp_sid = ledger.apply_transaction(&mut block, effect).unwrap();
+    let txo_sid = ledger
+        .finish_block(block)
+        .unwrap()
+        .remove(&tmp_sid)
+        .unwrap()
+        .1[0];
+
+    // the delegator is the only validator
+    let v = Validator::new(
+        vec![7; 32],
+        1,
+        kp.get_pk(),
+        [1, 100],
+        StakerMemo::default(),
+        ValidatorKind::Initiator,
+    )
+    .unwrap();
+    let td_addr = td_addr_to_string(&v.td_addr);
+    let staking = ledger.get_staking_mut();
+    staking.set_custom_block_height(1);
+    staking.validator_set_at_height_force(1, ValidatorData::new(1, vec![v]).unwrap());
+
+    let am = 1_000_000;
+    let mut tx = Transaction::from_operation(
+        gen_transfer_operation(
+            &mut ledger,
+            txo_sid,
+            &kp,
+            &[
+                (*BLACK_HOLE_PUBKEY_STAKING, am),
+                (*BLACK_HOLE_PUBKEY, TX_FEE_MIN),
+            ],
+        ),
+        ledger.get_block_commit_count(),
+    );
+    tx.add_operation(Operation::Delegation(DelegationOps::new(
+        &kp,
+        None,
+        am,
+        td_addr,
+        None,
+        tx.body.no_replay_token,
+    )));
+
+    let next_txo = ledger.get_next_txo().0;
+    let staking = ledger.get_staking().clone();
+    let commitment = ledger.get_state_commitment();
+    let next_txn = ledger.get_next_txn();
+    let owned = ledger.get_owned_utxos(kp.get_pk_ref()).unwrap();
+    let balance = ledger.get_nonconfidential_balance(kp.get_pk_ref());
+    let res = ledger.simulate_transaction(None, tx.clone());
+    assert!(res.accepted, "{:?}", res.rejection);
+    assert_eq!(res.fee, TX_FEE_MIN);
+    assert_eq!(res.spent_txo_sids, vec![txo_sid]);
+    assert_eq!(
+        res.new_txo_sids,
+        (next_txo..next_txo + 3).map(TxoSID).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        res.staking_deltas,
+        vec![simulation::StakingDelta {
+            pubkey: kp.get_pk(),
+            bonded_before: 0,
+            bonded_after: am,
+            rewards_before: 0,
+            rewards_after: 0,
+            delegated: am,
+            undelegated: 0,
+            claimed: 0,
+        }]
+    );
+
+    // nothing is applied
+    assert!(ledger
+        .get_staking()
+        .delegation_get(kp.get_pk_ref())
+        .is_none());
+    assert_eq!(ledger.get_staking(), &staking);
+    assert_eq!(ledger.get_next_txo().0, next_txo);
+    assert_eq!(ledger.get_next_txn(), next_txn);
+    assert_eq!(ledger.get_state_commitment(), commitment);
+    assert!(ledger.get_utxo_light(txo_sid).is_some());
+    assert!(ledger.get_spent_utxo_light(txo_sid).is_none());
+    assert_eq!(ledger.get_owned_utxos(kp.get_pk_ref()).unwrap(), owned);
+    assert_eq!(ledger.get_nonconfidential_balance(kp.get_pk_ref()), balance);
+
+    // the block being built is not changed either,
+    // and the transaction gets the predicted utxos when it is applied
+    let mut block = ledger.start_block().unwrap();
+    let block_copy = block.clone();
+    let res = ledger.simulate_transaction(Some(&block), tx.clone());
+    assert!(res.accepted, "{:?}", res.rejection);
+    assert_eq!(block, block_copy);
+
+    let effect = TxnEffect::compute_effect(tx).unwrap();
+    let tmp_sid = ledger.apply_transaction(&mut block, effect).unwrap();
+    let txo_sids = ledger
+        .finish_block(block)
+        .unwrap()
+        .remove(&tmp_sid)
+        .unwrap()
+        .1;
+    assert_eq!(res.new_txo_sids, txo_sids);
+    assert!(ledger.get_spent_utxo_light(txo_sid).is_some());
+}
+
+#[test]
+fn test_vesting_delegation() {
+    let mut ledger = LedgerState::tmp_ledger();
+    let kp = XfrKeyPair::generate(&mut ChaChaRng::from_entropy());
+
+    let tx = utils::fra_gen_initial_tx(&kp);
+    let effect = TxnEffect::compute_effect(tx).unwrap();
+    let mut block = ledger.start_block().unwrap();
+    let tmp_sid = ledger.apply_transaction(&mut block, effect).unwrap();
+    let txo_sid = ledger
+        .finish_block(block)
+        .unwrap()
+        .remove(&tmp_sid)
+        .unwrap()
+        .1[0];
+
+    let v = Validator::new(
+        vec![7; 32],
+        1,
+        kp.get_pk(),
+        [1, 100],
+        StakerMemo::default(),
+        ValidatorKind::Initiator,
+    )
+    .unwrap();
+    let td_addr = td_addr_to_string(&v.td_addr);
+    let h = CFG.checkpoint.vesting_height + 1;
+    let staking = ledger.get_staking_mut();
+    staking.set_custom_block_height(h);
+    staking.validator_set_at_height_force(h, ValidatorData::new(h, vec![v]).unwrap());
+
+    let am = 1_000_000;
+    staking
+        .vesting_create(
+            *BLACK_HOLE_PUBKEY,
+            kp.get_pk(),
+            am,
+            VestingSchedule::Cliff { height: h + 10 },
+        )
+        .unwrap();
+
+    let gen_tx =
+        |ledger: &mut LedgerState, txo_sid: TxoSID, payments: &[(XfrPublicKey, u64)]| {
+            let mut tx = Transaction::from_operation(
+                gen_transfer_operation(ledger, txo_sid, &kp, payments),
+                ledger.get_block_commit_count(),
+            );
+            tx.add_operation(Operation::Delegation(DelegationOps::new_from_vesting(
+                &kp,
+                am,
+                td_addr.clone(),
+                tx.body.no_replay_token,
+            )));
+            tx
+        };
+
+    // no principal should be paid for locked FRAs
+    let tx = gen_tx(
+        &mut ledger,
+        txo_sid,
+        &[
+            (*BLACK_HOLE_PUBKEY_STAKING, am),
+            (*BLACK_HOLE_PUBKEY, TX_FEE_MIN),
+        ],
+    );
+    assert!(!ledger.simulate_transaction(None, tx).accepted);
+
+    let tx = gen_tx(&mut ledger, txo_sid, &[(*BLACK_HOLE_PUBKEY, TX_FEE_MIN)]);
+    let res = ledger.simulate_transaction(None, tx.clone());
+    assert!(res.accepted, "{:?}", res.rejection);
+    assert_eq!(res.staking_deltas[0].bonded_after, am);
+
+    let effect = TxnEffect::compute_effect(tx).unwrap();
+    let mut block = ledger.start_block().unwrap();
+    let tmp_sid = ledger.apply_transaction(&mut block, effect).unwrap();
+    let txo_sid = ledger
+        .finish_block(block)
+        .unwrap()
+        .remove(&tmp_sid)
+        .unwrap()
+        .1[0];
+
+    let staking = ledger.get_staking();
+    assert_eq!(staking.vesting_get_locked(kp.get_pk_ref()), 0);
+    assert_eq!(
+        staking.delegation_get(kp.get_pk_ref()).unwrap().amount(),
+        am
+    );
+
+    // all locked FRAs have been delegated
+    let tx = gen_tx(&mut ledger, txo_sid, &[(*BLACK_HOLE_PUBKEY, TX_FEE_MIN)]);
+    assert!(!ledger.simulate_transaction(None, tx).accepted);
+}
+
 // Change the signature to have the wrong public key
 #[test]
 fn test_asset_creation_invalid_public_key() {
@@ -699,6 +967,21 @@ fn gen_fee_operation(
     l: &mut LedgerState,
     txo_sid: TxoSID,
     fra_owner_kp: &XfrKeyPair,
+) -> Operation {
+    gen_transfer_operation(
+        l,
+        txo_sid,
+        fra_owner_kp,
+        &[(*BLACK_HOLE_PUBKEY, TX_FEE_MIN)],
+    )
+}
+
+// Spend a FRA utxo, the change is the first output.
+fn gen_transfer_operation(
+    l: &mut LedgerState,
+    txo_sid: TxoSID,
+    fra_owner_kp: &XfrKeyPair,
+    payments: &[(XfrPublicKey, u64)],
 ) -> Operation {
     let fra_code = &AssetTypeCode {
         val: ASSET_TYPE_FRA,
@@ -707,30 +990,25 @@ fn gen_fee_operation(
     let input_bar_proof = l.get_utxo_light(txo_sid).unwrap();
     let input_bar = (input_bar_proof.utxo.0).record;
     let input_oar = open_blind_asset_record(&input_bar, &None, &fra_owner_kp).unwrap();
-
-    let output_template = AssetRecordTemplate::with_no_asset_tracing(
-        input_oar.amount - TX_FEE_MIN,
-        fra_code.val,
-        AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType,
-        fra_owner_kp.get_pk(),
-    );
-    let output_ar = AssetRecord::from_template_no_identity_tracing(
-        &mut l.get_prng(),
-        &output_template,
-    )
-    .unwrap();
-
-    let output_template = AssetRecordTemplate::with_no_asset_tracing(
-        TX_FEE_MIN,
-        fra_code.val,
-        AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType,
-        *BLACK_HOLE_PUBKEY,
-    );
-    let output_ar_fee = AssetRecord::from_template_no_identity_tracing(
-        &mut l.get_prng(),
-        &output_template,
-    )
-    .unwrap();
+    let change = input_oar.amount - payments.iter().map(|(_, n)| n).sum::<u64>();
+
+    let output_ars = [(fra_owner_kp.get_pk(), change)]
+        .iter()
+        .chain(payments.iter())
+        .map(|(pk, n)| {
+            let output_template = AssetRecordTemplate::with_no_asset_tracing(
+                *n,
+                fra_code.val,
+                AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType,
+                *pk,
+            );
+            AssetRecord::from_template_no_identity_tracing(
+                &mut l.get_prng(),
+                &output_template,
+            )
+            .unwrap()
+        })
+        .collect::<Vec<_>>();
 
     let input_ar = AssetRecord::from_open_asset_record_no_asset_tracing(input_oar);
 
@@ -739,7 +1017,7 @@ fn gen_fee_operation(
             &mut l.get_prng(),
             vec![TxoRef::Absolute(txo_sid)],
             &[input_ar],
-            &[output_ar, output_ar_fee],
+            &output_ars,
             None,
             vec![],
             TransferType::Standard,
@@ -753,6 +1031,25 @@ fn gen_fee_operation(
     Operation::TransferAsset(transfer)
 }
 
+#[test]
+fn test_set_prng_seed() {
+    let mut ledger = LedgerState::tmp_ledger();
+    let mut other = LedgerState::tmp_ledger();
+    assert_ne!(ledger.get_prng().next_u64(), other.get_prng().next_u64());
+
+    // the generators of the same seed are the same on any ledger,
+    // and each `get_prng` derives a new one
+    ledger.set_prng_seed(42);
+    other.set_prng_seed(42);
+    let first = ledger.get_prng().next_u64();
+    assert_eq!(first, 11399879390506077148);
+    assert_eq!(first, other.get_prng().next_u64());
+    assert_eq!(ledger.get_prng().next_u64(), 1982106646914376929);
+
+    other.set_prng_seed(43);
+    assert_ne!(first, other.get_prng().next_u64());
+}
+
 #[test]
 fn test_check_fee_with_ledger() {
     let mut ledger = LedgerState::tmp_ledger();
@@ -787,3 +1084,606 @@ fn test_check_fee_with_ledger() {
     let mut block = ledger.start_block().unwrap();
     assert!(ledger.apply_transaction(&mut block, effect).is_err());
 }
+
+#[test]
+fn test_prune_history() {
+    let mut ledger = LedgerState::tmp_ledger();
+    let fra_owner_kp = XfrKeyPair::generate(&mut ChaChaRng::from_entropy());
+
+    let apply = |ledger: &mut LedgerState, tx: Option<Transaction>| {
+        let mut block = ledger.start_block().unwrap();
+        let tmp_sid = tx.map(|tx| {
+            let effect = TxnEffect::compute_effect(tx).unwrap();
+            ledger.apply_transaction(&mut block, effect).unwrap()
+        });
+        let mut tsm = ledger.finish_block(block).unwrap();
+        tmp_sid.and_then(|sid| tsm.remove(&sid))
+    };
+
+    let tx = utils::fra_gen_initial_tx(&fra_owner_kp);
+    let txo_sid = apply(&mut ledger, Some(tx)).unwrap().1[0];
+    let tx = Transaction::from_operation(
+        gen_fee_operation(&mut ledger, txo_sid, &fra_owner_kp),
+        ledger.get_block_commit_count(),
+    );
+    let (txn_sid, txo_sids) = apply(&mut ledger, Some(tx)).unwrap();
+    let change_sid = txo_sids[0];
+    let tx = Transaction::from_operation(
+        gen_fee_operation(&mut ledger, change_sid, &fra_owner_kp),
+        ledger.get_block_commit_count(),
+    );
+    apply(&mut ledger, None);
+
+    // the empty block and the next one start at the next transaction
+    assert_eq!(Some(txn_sid), ledger.get_first_txn_since(BlockSID(1)));
+    assert_eq!(Some(TxnSID(2)), ledger.get_first_txn_since(BlockSID(2)));
+    assert_eq!(Some(TxnSID(2)), ledger.get_first_txn_since(BlockSID(3)));
+    assert_eq!(None, ledger.get_first_txn_since(BlockSID(4)));
+
+    let commitment = ledger.get_state_commitment();
+    // the work is bounded, only the first block is pruned
+    assert_eq!(1, ledger.prune_history(1, 1).unwrap());
+    assert!(ledger.is_pruned_block(BlockSID(0)));
+    assert!(!ledger.is_pruned_block(BlockSID(1)));
+    assert_eq!(1, ledger.prune_history(1, usize::MAX).unwrap());
+    assert_eq!(0, ledger.prune_history(1, usize::MAX).unwrap());
+    assert_eq!(commitment, ledger.get_state_commitment());
+    assert_eq!(3, ledger.get_block_count());
+
+    assert!(ledger.is_pruned_block(BlockSID(1)));
+    assert!(ledger.get_block(BlockSID(1)).is_none());
+    assert!(ledger.get_first_txn_since(BlockSID(1)).is_none());
+    assert!(ledger.get_block(BlockSID(2)).is_some());
+    assert!(ledger.is_pruned_txn(txn_sid));
+    assert!(ledger.get_transaction_light(txn_sid).is_err());
+    assert!(ledger.get_spent_utxo(txo_sid).is_none());
+    assert!(ledger.status.spent_utxos.get(&txo_sid).is_none());
+
+    // the unspent TXOs of pruned transactions are kept
+    assert!(ledger.is_pruned_txo(change_sid));
+    let utxo = ledger.get_utxo(change_sid).unwrap();
+    assert!(utxo.authenticated_txn.is_none());
+    assert!(utxo.owner_memo.is_none());
+    assert_eq!(utxo.authenticated_spent_status.status, UtxoStatus::Unspent);
+    assert!(!utxo.is_valid(ledger.get_state_commitment().0));
+    let utxo_light = ledger.get_utxo_light(change_sid).unwrap();
+    assert!(utxo_light.txn.is_none());
+    assert_eq!(utxo_light.utxo, utxo.utxo);
+    assert_eq!(utxo_light.utxo_location, utxo.utxo_location);
+    assert_eq!(
+        ledger.get_utxos(&[change_sid])[0].as_ref().unwrap().utxo,
+        utxo.utxo
+    );
+    let owned = ledger.get_owned_utxos(fra_owner_kp.get_pk_ref()).unwrap();
+    assert!(owned.contains_key(&change_sid));
+
+    // and still spendable
+    let (_, txo_sids) = apply(&mut ledger, Some(tx)).unwrap();
+    assert!(ledger.status.spent_utxos.get(&change_sid).is_none());
+    let owned = ledger.get_owned_utxos(fra_owner_kp.get_pk_ref()).unwrap();
+    assert!(!owned.contains_key(&change_sid));
+    assert!(owned.contains_key(&txo_sids[0]));
+}
+
+#[test]
+fn test_genesis_export_import() {
+    let mut ledger = LedgerState::tmp_ledger();
+    let fra_owner_kp = XfrKeyPair::generate(&mut ChaChaRng::from_entropy());
+    let pk = fra_owner_kp.get_pk();
+
+    let apply = |ledger: &mut LedgerState, tx: Option<Transaction>| {
+        let mut block = ledger.start_block().unwrap();
+        let tmp_sid = tx.map(|tx| {
+            let effect = TxnEffect::compute_effect(tx).unwrap();
+            ledger.apply_transaction(&mut block, effect).unwrap()
+        });
+        let mut tsm = ledger.finish_block(block).unwrap();
+        tmp_sid.and_then(|sid| tsm.remove(&sid))
+    };
+
+    let tx = utils::fra_gen_initial_tx(&fra_owner_kp);
+    let txo_sid = apply(&mut ledger, Some(tx)).unwrap().1[0];
+    let tx = Transaction::from_operation(
+        gen_fee_operation(&mut ledger, txo_sid, &fra_owner_kp),
+        ledger.get_block_commit_count(),
+    );
+    let change_sid = apply(&mut ledger, Some(tx)).unwrap().1[0];
+
+    let staking = ledger.get_staking_mut();
+    staking.set_custom_block_height(10);
+    staking.validator_remove_at_height(0).unwrap();
+    staking
+        .validator_set_at_height(1, ValidatorData::new(1, vec![]).unwrap())
+        .unwrap();
+    staking
+        .validator_set_at_height(12, ValidatorData::new(12, vec![]).unwrap())
+        .unwrap();
+
+    let next_txo = ledger.get_next_txo();
+    let balance = ledger.get_nonconfidential_balance(&pk);
+    let genesis = ledger.export_genesis().unwrap();
+    assert!(!genesis.utxos.iter().any(|u| u.sid == txo_sid));
+    let genesis = serde_json::to_vec(&genesis).unwrap();
+    drop(ledger);
+
+    let mut ledger = LedgerState::tmp_ledger();
+    ledger
+        .import_genesis(serde_json::from_slice(&genesis).unwrap())
+        .unwrap();
+    assert!(ledger
+        .import_genesis(serde_json::from_slice(&genesis).unwrap())
+        .is_err());
+    apply(&mut ledger, None);
+
+    assert_eq!(next_txo, ledger.get_next_txo());
+    assert_eq!(balance, ledger.get_nonconfidential_balance(&pk));
+    assert!(ledger.is_pruned_txo(change_sid));
+    assert_eq!(UtxoStatus::Spent, ledger.get_utxo_status(txo_sid).status);
+    assert_eq!(
+        UtxoStatus::Unspent,
+        ledger.get_utxo_status(change_sid).status
+    );
+    let owned = ledger.get_owned_utxos(&pk).unwrap();
+    assert!(owned.contains_key(&change_sid));
+
+    // the heights of the staking are rebased
+    let staking = ledger.get_staking();
+    assert!(staking.has_been_inited());
+    assert!(staking.validator_get_at_height(1).is_some());
+    assert!(staking.validator_get_at_height(2).is_some());
+    assert!(staking.validator_get_at_height(12).is_none());
+}
+
+#[test]
+fn test_cred_gated_asset() {
+    let mut ledger = LedgerState::tmp_ledger();
+    let params = PublicParams::default();
+    let mut prng = ChaChaRng::from_entropy();
+
+    let issuer_kp = XfrKeyPair::generate(&mut prng);
+    let cred_issuer_kp = XfrKeyPair::generate(&mut prng);
+    let recipient_kp = XfrKeyPair::generate(&mut prng);
+
+    // Register the credential issuer
+    let (cred_issuer_pk, cred_issuer_sk) =
+        credential_issuer_key_gen(&mut prng, &[("kyc".to_owned(), 1)]);
+    let reg = RegisterCredIssuer::new(
+        RegisterCredIssuerBody {
+            issuer_pub_key: cred_issuer_pk.clone(),
+            no_replay_token: Default::default(),
+        },
+        &cred_issuer_kp,
+    );
+    let tx = Transaction::from_operation(
+        Operation::RegisterCredIssuer(reg),
+        ledger.get_block_commit_count(),
+    );
+    apply_transaction(&mut ledger, tx);
+    assert_eq!(
+        Some(cred_issuer_pk.clone()),
+        ledger.get_cred_issuer(cred_issuer_kp.get_pk_ref())
+    );
+
+    // Define a credential-gated asset
+    let code = AssetTypeCode::gen_random();
+    let mut rules = AssetRules::default();
+    rules.set_required_credentials(Some(CredentialRules {
+        issuer: cred_issuer_kp.get_pk(),
+        attributes: vec!["kyc".to_owned()],
+        values: vec![("kyc".to_owned(), vec![1u8])],
+    }));
+    let tx = create_definition_transaction(
+        &code,
+        &issuer_kp,
+        rules,
+        None,
+        ledger.get_block_commit_count(),
+    )
+    .unwrap();
+    apply_transaction(&mut ledger, tx);
+
+    // The recipient holds a credential
+    let (user_pk, user_sk) = credential_user_key_gen(&mut prng, &cred_issuer_pk);
+    let signature = credential_sign(
+        &mut prng,
+        &cred_issuer_sk,
+        &user_pk,
+        &[("kyc".to_owned(), &[1u8][..])],
+    )
+    .unwrap();
+    let credential = Credential {
+        attributes: vec![("kyc".to_owned(), vec![1u8])],
+        issuer_pub_key: cred_issuer_pk.clone(),
+        signature,
+    };
+
+    let (tx, _) = create_issue_and_transfer_txn(
+        &mut ledger,
+        &params,
+        &code,
+        100,
+        &issuer_kp,
+        recipient_kp.get_pk_ref(),
+        0,
+    );
+
+    let mut try_apply = |tx: Transaction| {
+        let effect = TxnEffect::compute_effect(tx)?;
+        let mut block = ledger.start_block().unwrap();
+        let res = ledger.apply_transaction(&mut block, effect);
+        ledger.finish_block(block).unwrap();
+        res
+    };
+
+    // No credential is presented
+    assert!(try_apply(tx.clone()).is_err());
+
+    // The credential is presented for another address
+    let mut bad_tx = tx.clone();
+    bad_tx.body.credentials.push(
+        CredentialProof::new(
+            &mut prng,
+            &user_sk,
+            &credential,
+            &issuer_kp,
+            &["kyc".to_owned()],
+        )
+        .unwrap(),
+    );
+    assert!(try_apply(bad_tx).is_err());
+
+    // The required attribute is not revealed
+    let mut bad_tx = tx.clone();
+    bad_tx.body.credentials.push(
+        CredentialProof::new(&mut prng, &user_sk, &credential, &recipient_kp, &[])
+            .unwrap(),
+    );
+    assert!(try_apply(bad_tx).is_err());
+
+    // The attribute does not hold the required value
+    let other_credential = Credential {
+        attributes: vec![("kyc".to_owned(), vec![2u8])],
+        issuer_pub_key: cred_issuer_pk,
+        signature: credential_sign(
+            &mut prng,
+            &cred_issuer_sk,
+            &user_pk,
+            &[("kyc".to_owned(), &[2u8][..])],
+        )
+        .unwrap(),
+    };
+    let mut bad_tx = tx.clone();
+    bad_tx.body.credentials.push(
+        CredentialProof::new(
+            &mut prng,
+            &user_sk,
+            &other_credential,
+            &recipient_kp,
+            &["kyc".to_owned()],
+        )
+        .unwrap(),
+    );
+    assert!(try_apply(bad_tx).is_err());
+
+    // The proof is bound to the recipient
+    let mut bad_tx = tx.clone();
+    let mut proof = CredentialProof::new(
+        &mut prng,
+        &user_sk,
+        &credential,
+        &recipient_kp,
+        &["kyc".to_owned()],
+    )
+    .unwrap();
+    proof.owner = issuer_kp.get_pk();
+    bad_tx.body.credentials.push(proof.clone());
+    assert!(TxnEffect::compute_effect(bad_tx).is_err());
+    proof.owner = recipient_kp.get_pk();
+
+    // and signed by the recipient
+    let mut bad_tx = tx.clone();
+    let mut bad_proof = proof.clone();
+    bad_proof.owner_signature = SignatureOf::new(
+        &issuer_kp,
+        &(
+            proof.attributes.clone(),
+            proof.commitment.clone(),
+            proof.reveal_pok.clone(),
+        ),
+    );
+    bad_tx.body.credentials.push(bad_proof);
+    assert!(TxnEffect::compute_effect(bad_tx).is_err());
+
+    // The asset type can not be hidden from the check
+    let issued = match &tx.body.operations[0] {
+        Operation::IssueAsset(i) => i.body.records[0].0.record.clone(),
+        _ => unreachable!(),
+    };
+    let output = AssetRecord::from_template_no_identity_tracing(
+        &mut prng,
+        &AssetRecordTemplate::with_no_asset_tracing(
+            100,
+            code.val,
+            AssetRecordType::ConfidentialAmount_ConfidentialAssetType,
+            recipient_kp.get_pk(),
+        ),
+    )
+    .unwrap();
+    let mut transfer = TransferAsset::new(
+        TransferAssetBody::new(
+            &mut prng,
+            vec![TxoRef::Relative(0)],
+            &[AssetRecord::from_open_asset_record_no_asset_tracing(
+                open_blind_asset_record(&issued, &None, &issuer_kp).unwrap(),
+            )],
+            &[output],
+            None,
+            vec![],
+            TransferType::Standard,
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    transfer.sign(&issuer_kp);
+    let mut conf_tx = Transaction::from_operation(
+        tx.body.operations[0].clone(),
+        tx.body.no_replay_token.get_seq_id(),
+    );
+    conf_tx.add_operation(Operation::TransferAsset(transfer));
+    conf_tx.body.credentials.push(proof.clone());
+    let e = try_apply(conf_tx).err().unwrap();
+    assert!(e.to_string().contains("can not be confidential"));
+
+    // Nor hidden at the issuance
+    let (conf_record, _, _) = build_blind_asset_record(
+        &mut prng,
+        &params.pc_gens,
+        &AssetRecordTemplate::with_no_asset_tracing(
+            100,
+            code.val,
+            AssetRecordType::NonConfidentialAmount_ConfidentialAssetType,
+            recipient_kp.get_pk(),
+        ),
+        vec![],
+    );
+    let issue = IssueAsset::new(
+        IssueAssetBody::new(
+            &code,
+            1,
+            &[(
+                TxOutput {
+                    id: None,
+                    record: conf_record,
+                    lien: None,
+                },
+                None,
+            )],
+        )
+        .unwrap(),
+        &IssuerKeyPair {
+            keypair: &issuer_kp,
+        },
+    )
+    .unwrap();
+    let mut conf_tx = Transaction::from_operation(
+        Operation::IssueAsset(issue),
+        tx.body.no_replay_token.get_seq_id(),
+    );
+    conf_tx.body.credentials.push(proof.clone());
+    assert!(TxnEffect::compute_effect(conf_tx).is_err());
+
+    let mut tx = tx;
+    tx.body.credentials.push(proof);
+    assert!(try_apply(tx).is_ok());
+}
+
+#[test]
+fn test_confidential_convert_account() {
+    let mut ledger = LedgerState::tmp_ledger();
+    let mut prng = ChaChaRng::from_entropy();
+    let fra_owner_kp = XfrKeyPair::generate(&mut prng);
+    let height = CFG
+        .checkpoint
+        .confidential_convert_height
+        .max(CFG.checkpoint.utxo_checktx_height);
+
+    let tx = utils::fra_gen_initial_tx(&fra_owner_kp);
+    let (_, txos) = apply_transaction(&mut ledger, tx);
+
+    // Send a confidential amount to the black hole, the change is non-confidential
+    let amount = 100 * TX_FEE_MIN;
+    let input_bar = ledger.get_utxo_light(txos[0]).unwrap().utxo.0.record;
+    let input_oar = open_blind_asset_record(&input_bar, &None, &fra_owner_kp).unwrap();
+    let output = |amount, record_type, pk| {
+        AssetRecord::from_template_no_identity_tracing(
+            &mut ChaChaRng::from_entropy(),
+            &AssetRecordTemplate::with_no_asset_tracing(
+                amount,
+                ASSET_TYPE_FRA,
+                record_type,
+                pk,
+            ),
+        )
+        .unwrap()
+    };
+    let converted = output(
+        amount,
+        AssetRecordType::ConfidentialAmount_NonConfidentialAssetType,
+        *BLACK_HOLE_PUBKEY_STAKING,
+    );
+    let change = output(
+        input_oar.amount - amount,
+        AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType,
+        fra_owner_kp.get_pk(),
+    );
+    let blinds = (
+        converted.open_asset_record.amount_blinds.0 .0,
+        converted.open_asset_record.amount_blinds.1 .0,
+    );
+    let mut transfer = TransferAsset::new(
+        TransferAssetBody::new(
+            &mut prng,
+            vec![TxoRef::Absolute(txos[0])],
+            &[AssetRecord::from_open_asset_record_no_asset_tracing(
+                input_oar,
+            )],
+            &[change, converted],
+            None,
+            vec![],
+            TransferType::Standard,
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    transfer.sign(&fra_owner_kp);
+
+    let receiver = MultiSigner::Ethereum(H160::repeat_byte(1));
+    let convert_tx = |value, amount_proofs| {
+        let mut tx = Transaction::from_operation(
+            Operation::TransferAsset(transfer.clone()),
+            ledger.get_block_commit_count(),
+        );
+        tx.add_operation(Operation::ConvertAccount(ConvertAccount {
+            signer: fra_owner_kp.get_pk(),
+            nonce: tx.body.no_replay_token,
+            receiver: receiver.clone(),
+            value,
+            amount_proofs,
+        }));
+        tx.sign_to_map(&fra_owner_kp);
+        tx
+    };
+
+    // A valid convert
+    let proof = AmountProof::prove(&mut prng, amount, blinds);
+    let tx = convert_tx(amount, vec![proof.clone()]);
+    assert_eq!(
+        (receiver.clone(), amount),
+        check_convert_account(&tx, height).unwrap()
+    );
+
+    // The proof does not match the record
+    let bad_proof = AmountProof::prove(&mut prng, amount + 1, blinds);
+    assert!(
+        check_convert_account(&convert_tx(amount + 1, vec![bad_proof]), height).is_err()
+    );
+    let mut bad_proof = proof.clone();
+    bad_proof.amount += 1;
+    assert!(
+        check_convert_account(&convert_tx(amount + 1, vec![bad_proof]), height).is_err()
+    );
+
+    // The value does not match the proof
+    assert!(
+        check_convert_account(&convert_tx(amount + 1, vec![proof.clone()]), height)
+            .is_err()
+    );
+
+    // A proof is missing or redundant
+    assert!(check_convert_account(&convert_tx(amount, vec![]), height).is_err());
+    assert!(check_convert_account(
+        &convert_tx(amount, vec![proof.clone(), proof]),
+        height
+    )
+    .is_err());
+
+    // The transfer itself is still valid
+    assert!(tx.check_tx().is_ok());
+    apply_transaction(&mut ledger, tx);
+}
+
+#[test]
+fn test_confidential_mint() {
+    let mut ledger = LedgerState::tmp_ledger();
+    let mut prng = ChaChaRng::from_entropy();
+    let params = PublicParams::default();
+    let recipient_kp = XfrKeyPair::generate(&mut prng);
+    let height = CFG.checkpoint.confidential_convert_height;
+    let amount = (7 << 32) + 123;
+
+    let record = |record_type| {
+        build_open_asset_record(
+            &mut ChaChaRng::from_entropy(),
+            &params.pc_gens,
+            &AssetRecordTemplate::with_no_asset_tracing(
+                amount,
+                ASSET_TYPE_FRA,
+                record_type,
+                recipient_kp.get_pk(),
+            ),
+            vec![],
+        )
+    };
+
+    // A non-confidential record is rejected
+    let (oar, _, _) =
+        record(AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType);
+    let proof = AmountProof::prove(
+        &mut prng,
+        amount,
+        (oar.amount_blinds.0 .0, oar.amount_blinds.1 .0),
+    );
+    assert!(
+        check_confidential_outputs(&[(&oar.blind_asset_record, &proof)], height)
+            .is_err()
+    );
+
+    // So is a record of a hidden asset type
+    let (oar, _, _) = record(AssetRecordType::ConfidentialAmount_ConfidentialAssetType);
+    let proof = AmountProof::prove(
+        &mut prng,
+        amount,
+        (oar.amount_blinds.0 .0, oar.amount_blinds.1 .0),
+    );
+    assert!(
+        check_confidential_outputs(&[(&oar.blind_asset_record, &proof)], height)
+            .is_err()
+    );
+
+    // A confidential record is minted with its owner memo
+    let (oar, _, owner_memo) =
+        record(AssetRecordType::ConfidentialAmount_NonConfidentialAssetType);
+    let proof = AmountProof::prove(
+        &mut prng,
+        amount,
+        (oar.amount_blinds.0 .0, oar.amount_blinds.1 .0),
+    );
+    let record = oar.blind_asset_record;
+    assert_eq!(
+        amount,
+        check_confidential_outputs(&[(&record, &proof)], height).unwrap()
+    );
+    let entry = MintEntry::with_record(
+        MintKind::Other,
+        record.public_key,
+        proof.amount,
+        record,
+        owner_memo,
+    );
+    let tx = Transaction::from_operation_coinbase_mint(
+        Operation::MintFra(MintFraOps::new(height as u64, vec![entry])),
+        ledger.get_block_commit_count(),
+    );
+    let (txn_sid, txos) = apply_transaction(&mut ledger, tx);
+
+    // The receiver opens it to the proved amount
+    let utxo = ledger.get_utxo_light(txos[0]).unwrap().utxo.0.record;
+    let memos = match &ledger
+        .get_transaction_light(txn_sid)
+        .unwrap()
+        .txn
+        .body
+        .operations[0]
+    {
+        Operation::MintFra(m) => m
+            .get_owner_memos_ref()
+            .into_iter()
+            .map(|m| m.cloned())
+            .collect::<Vec<_>>(),
+        _ => unreachable!(),
+    };
+    assert!(utxo.amount.is_confidential());
+    let oar = open_blind_asset_record(&utxo, &memos[0], &recipient_kp).unwrap();
+    assert_eq!(proof.amount, oar.amount);
+    assert_eq!(ASSET_TYPE_FRA, oar.asset_type);
+}
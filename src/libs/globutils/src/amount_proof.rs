@@ -0,0 +1,195 @@
+//!
+//! # Proof of a confidential amount
+//!
+//! Zei commits a confidential amount as two Pedersen commitments,
+//! one for the lower 32 bits and one for the higher 32 bits.
+//!
+//! Given a public amount `a`, `C - a * B` must be `r * B_blinding`,
+//! so a Schnorr proof of knowing `r` proves the amount without revealing `r`.
+//!
+
+use {
+    bulletproofs::PedersenGens,
+    curve25519_dalek::{
+        ristretto::{CompressedRistretto, RistrettoPoint},
+        scalar::Scalar,
+    },
+    rand_core::{CryptoRng, RngCore},
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    sha2::{Digest, Sha512},
+    zei::xfr::structs::XfrAmount,
+};
+
+const DOMAIN: &[u8] = b"Findora AmountProof";
+
+/// Proof that a confidential `XfrAmount` holds `amount`
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct AmountProof {
+    /// the proved amount
+    pub amount: u64,
+    lo: DlogProof,
+    hi: DlogProof,
+}
+
+impl AmountProof {
+    /// `blinds` are the blinds of the lower and the higher parts,
+    /// aka the `amount_blinds` of an `OpenAssetRecord`.
+    pub fn prove<R: CryptoRng + RngCore>(
+        prng: &mut R,
+        amount: u64,
+        blinds: (Scalar, Scalar),
+    ) -> AmountProof {
+        let pc_gens = PedersenGens::default();
+        let (lo, hi) = split(amount);
+
+        AmountProof {
+            amount,
+            lo: DlogProof::prove(
+                prng,
+                &pc_gens,
+                pc_gens.commit(lo, blinds.0),
+                lo,
+                blinds.0,
+            ),
+            hi: DlogProof::prove(
+                prng,
+                &pc_gens,
+                pc_gens.commit(hi, blinds.1),
+                hi,
+                blinds.1,
+            ),
+        }
+    }
+
+    /// Check the proof against a confidential amount.
+    pub fn verify(&self, amount: &XfrAmount) -> Result<()> {
+        let (lo_com, hi_com) = match amount {
+            XfrAmount::Confidential((lo, hi)) => (lo.0, hi.0),
+            XfrAmount::NonConfidential(_) => {
+                return Err(eg!("not a confidential amount"));
+            }
+        };
+
+        let pc_gens = PedersenGens::default();
+        let (lo, hi) = split(self.amount);
+
+        self.lo
+            .verify(&pc_gens, lo_com.decompress().c(d!())?, lo)
+            .c(d!())
+            .and_then(|_| {
+                self.hi
+                    .verify(&pc_gens, hi_com.decompress().c(d!())?, hi)
+                    .c(d!())
+            })
+    }
+}
+
+// Knowledge of `r` in `C - v * B = r * B_blinding`
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+struct DlogProof {
+    r: CompressedRistretto,
+    s: Scalar,
+}
+
+impl DlogProof {
+    fn prove<R: CryptoRng + RngCore>(
+        prng: &mut R,
+        pc_gens: &PedersenGens,
+        commitment: RistrettoPoint,
+        value: Scalar,
+        blind: Scalar,
+    ) -> DlogProof {
+        let p = commitment - value * pc_gens.B;
+        let k = Scalar::random(prng);
+        let r = (k * pc_gens.B_blinding).compress();
+        let c = challenge(&p, &r);
+
+        DlogProof {
+            r,
+            s: k + c * blind,
+        }
+    }
+
+    fn verify(
+        &self,
+        pc_gens: &PedersenGens,
+        commitment: RistrettoPoint,
+        value: Scalar,
+    ) -> Result<()> {
+        let p = commitment - value * pc_gens.B;
+        let c = challenge(&p, &self.r);
+        let r = self.r.decompress().c(d!())?;
+
+        if self.s * pc_gens.B_blinding == r + c * p {
+            Ok(())
+        } else {
+            Err(eg!("invalid amount proof"))
+        }
+    }
+}
+
+fn challenge(p: &RistrettoPoint, r: &CompressedRistretto) -> Scalar {
+    let mut hasher = Sha512::new();
+    hasher.update(DOMAIN);
+    hasher.update(p.compress().as_bytes());
+    hasher.update(r.as_bytes());
+    Scalar::from_hash(hasher)
+}
+
+// The same split as zei
+fn split(amount: u64) -> (Scalar, Scalar) {
+    (
+        Scalar::from(amount & 0xFFFF_FFFF),
+        Scalar::from(amount >> 32),
+    )
+}
+
+#[cfg(test)]
+#[allow(missing_docs)]
+mod test {
+    use {
+        super::*,
+        rand_chacha::ChaChaRng,
+        rand_core::SeedableRng,
+        zei::{
+            setup::PublicParams,
+            xfr::{
+                asset_record::{build_open_asset_record, AssetRecordType},
+                sig::XfrKeyPair,
+                structs::{AssetRecordTemplate, AssetType},
+            },
+        },
+    };
+
+    #[test]
+    fn t_amount_proof() {
+        let mut prng = ChaChaRng::from_entropy();
+        let amount = (7 << 32) + 123;
+        let template = AssetRecordTemplate::with_no_asset_tracing(
+            amount,
+            AssetType([0; 32]),
+            AssetRecordType::ConfidentialAmount_NonConfidentialAssetType,
+            XfrKeyPair::generate(&mut prng).get_pk(),
+        );
+        let (oar, _, _) = build_open_asset_record(
+            &mut prng,
+            &PublicParams::default().pc_gens,
+            &template,
+            vec![],
+        );
+        let blinds = (oar.amount_blinds.0 .0, oar.amount_blinds.1 .0);
+        let record_amount = &oar.blind_asset_record.amount;
+
+        let proof = AmountProof::prove(&mut prng, amount, blinds);
+        assert!(proof.verify(record_amount).is_ok());
+        assert!(proof.verify(&XfrAmount::NonConfidential(amount)).is_err());
+
+        let mut bad_proof = proof;
+        bad_proof.amount += 1;
+        assert!(bad_proof.verify(record_amount).is_err());
+
+        let bad_proof = AmountProof::prove(&mut prng, amount + 1, blinds);
+        assert!(bad_proof.verify(record_amount).is_err());
+    }
+}
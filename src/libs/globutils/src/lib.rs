@@ -5,7 +5,9 @@
 #![deny(warnings)]
 #![deny(missing_docs)]
 
+pub mod amount_proof;
 pub mod logging;
+pub mod signer;
 pub mod wallet;
 
 use {
@@ -15,6 +17,7 @@ use {
     },
     ruc::*,
     serde::{Deserialize, Deserializer, Serialize, Serializer},
+    signer::Signer,
     std::{fs, marker::PhantomData, path::PathBuf, result::Result as StdResult},
     zei::xfr::sig::{XfrKeyPair, XfrPublicKey, XfrSignature},
 };
@@ -137,6 +140,19 @@ where
         Self(SignatureOfBytes::new(xfr, &Serialized::new(to_sign)))
     }
 
+    /// Serialize a data structure and sign it with a `Signer`,
+    /// `summary` is shown by the signer if it is a device
+    #[inline(always)]
+    pub fn new_with_signer<S: Signer + ?Sized>(
+        signer: &S,
+        to_sign: &T,
+        summary: &str,
+    ) -> Result<Self> {
+        SignatureOfBytes::new_with_signer(signer, &Serialized::new(to_sign), summary)
+            .c(d!())
+            .map(Self)
+    }
+
     /// Verify if a value is properly singed with the `XfrKeyPair`
     #[inline(always)]
     pub fn verify(&self, pubkey: &XfrPublicKey, val: &T) -> Result<()> {
@@ -392,6 +408,22 @@ where
         }
     }
 
+    /// Create a signature with a `Signer`
+    #[inline(always)]
+    pub fn new_with_signer<S: Signer + ?Sized>(
+        signer: &S,
+        to_sign: &T,
+        summary: &str,
+    ) -> Result<Self> {
+        signer
+            .sign_msg(to_sign.as_ref(), summary)
+            .c(d!())
+            .map(|sig| Self {
+                sig,
+                phantom: PhantomData,
+            })
+    }
+
     /// Verify a signature with specified keypair
     #[inline(always)]
     pub fn verify(&self, pubkey: &XfrPublicKey, val: &T) -> Result<()> {
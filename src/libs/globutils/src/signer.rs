@@ -0,0 +1,452 @@
+//!
+//! # Signer
+//!
+//! Sign with a key which may not be held in the memory,
+//! e.g. on a hardware wallet.
+//!
+//! The `XfrKeyPair` itself is the software implementation,
+//! and `ApduSigner` talks to a Ledger-style device through an `ApduTransport`,
+//! e.g. `TcpTransport` for an emulator, or `HidTransport` (with the `hid` feature)
+//! for a device plugged by USB.
+//!
+//! ## APDU commands
+//!
+//! The `CLA` is `0xE0`, and a response ends with the status word,
+//! `0x9000` means success.
+//!
+//! - `INS = 0x02`: get the public key at the path in the data,
+//!   `P1 = 0x01` displays the address on the device, `P1 = 0x00` does not
+//! - `INS = 0x03`: sign, the payload is sent in chunks,
+//!   `P1` is `0x00` for the first one and `0x80` for the others,
+//!   `P2` is `0x80` if more chunks follow and `0x00` for the last one,
+//!   which is answered with the signature
+//!
+//! A path is `5` followed by the five `u32` of
+//! "m/44'/coin'/account'/change/address" in big endian,
+//! and the payload of signing is `path || u16 length of summary || summary || message`,
+//! the device displays the summary and signs the message after the user approves it.
+//!
+
+use {
+    crate::wallet::BipPath,
+    ruc::*,
+    zei::{
+        serialization::ZeiFromToBytes,
+        xfr::sig::{XfrKeyPair, XfrPublicKey, XfrSignature},
+    },
+};
+
+/// Something that holds a key and signs messages with it.
+pub trait Signer {
+    /// The public key of the signing key.
+    fn public_key(&self) -> Result<XfrPublicKey>;
+
+    /// Sign `msg` with the key.
+    ///
+    /// `summary` is a readable description of `msg`,
+    /// which is shown to the user by the devices for confirmation.
+    fn sign_msg(&self, msg: &[u8], summary: &str) -> Result<XfrSignature>;
+}
+
+impl Signer for XfrKeyPair {
+    #[inline(always)]
+    fn public_key(&self) -> Result<XfrPublicKey> {
+        Ok(self.get_pk())
+    }
+
+    #[inline(always)]
+    fn sign_msg(&self, msg: &[u8], _summary: &str) -> Result<XfrSignature> {
+        Ok(self.get_sk_ref().sign(msg, self.get_pk_ref()))
+    }
+}
+
+const CLA: u8 = 0xE0;
+const INS_GET_PUBLIC_KEY: u8 = 0x02;
+const INS_SIGN: u8 = 0x03;
+
+const P1_SILENT: u8 = 0x00;
+const P1_DISPLAY: u8 = 0x01;
+const P1_FIRST: u8 = 0x00;
+const P1_MORE: u8 = 0x80;
+const P2_MORE: u8 = 0x80;
+const P2_LAST: u8 = 0x00;
+
+const SW_OK: u16 = 0x9000;
+const SW_DENIED: u16 = 0x6985;
+
+// the max length of the data of a command
+const CHUNK_SIZE: usize = 250;
+
+/// The channel between the host and a device.
+pub trait ApduTransport {
+    /// Send a command to the device,
+    /// the response includes the status word at the end.
+    fn exchange(&self, command: &[u8]) -> Result<Vec<u8>>;
+}
+
+/// A signer whose key is derived at a `BipPath` on a device.
+pub struct ApduSigner<T: ApduTransport> {
+    transport: T,
+    path: BipPath,
+    pk: XfrPublicKey,
+}
+
+impl<T: ApduTransport> ApduSigner<T> {
+    /// Connect to the key derived at `path` on the device.
+    pub fn new(transport: T, path: BipPath) -> Result<Self> {
+        let pk = get_public_key(&transport, &path, P1_SILENT).c(d!())?;
+        Ok(ApduSigner {
+            transport,
+            path,
+            pk,
+        })
+    }
+
+    /// Show the address on the device, and wait for the user to confirm it.
+    pub fn confirm_address(&self) -> Result<()> {
+        let pk = get_public_key(&self.transport, &self.path, P1_DISPLAY).c(d!())?;
+        if pk != self.pk {
+            return Err(eg!("the device returned a different key"));
+        }
+        Ok(())
+    }
+}
+
+impl<T: ApduTransport> Signer for ApduSigner<T> {
+    #[inline(always)]
+    fn public_key(&self) -> Result<XfrPublicKey> {
+        Ok(self.pk)
+    }
+
+    fn sign_msg(&self, msg: &[u8], summary: &str) -> Result<XfrSignature> {
+        let summary = summary.as_bytes();
+        if summary.len() > u16::MAX as usize {
+            return Err(eg!("the summary is too long"));
+        }
+
+        let mut payload = encode_path(&self.path);
+        payload.extend_from_slice(&(summary.len() as u16).to_be_bytes());
+        payload.extend_from_slice(summary);
+        payload.extend_from_slice(msg);
+
+        let chunks = payload.chunks(CHUNK_SIZE).collect::<Vec<_>>();
+        let mut resp = vec![];
+        for (i, chunk) in chunks.iter().enumerate() {
+            let p1 = if 0 == i { P1_FIRST } else { P1_MORE };
+            let p2 = if chunks.len() == i + 1 {
+                P2_LAST
+            } else {
+                P2_MORE
+            };
+            resp = send(&self.transport, INS_SIGN, p1, p2, chunk).c(d!())?;
+        }
+
+        let sig = XfrSignature::zei_from_bytes(&resp).c(d!("invalid signature"))?;
+        self.pk
+            .verify(msg, &sig)
+            .c(d!("the device signed with a different key"))?;
+        Ok(sig)
+    }
+}
+
+fn get_public_key<T: ApduTransport>(
+    transport: &T,
+    path: &BipPath,
+    p1: u8,
+) -> Result<XfrPublicKey> {
+    send(transport, INS_GET_PUBLIC_KEY, p1, 0, &encode_path(path))
+        .c(d!())
+        .and_then(|pk| XfrPublicKey::zei_from_bytes(&pk).c(d!("invalid public key")))
+}
+
+// send a command and check the status word of the response
+fn send<T: ApduTransport>(
+    transport: &T,
+    ins: u8,
+    p1: u8,
+    p2: u8,
+    data: &[u8],
+) -> Result<Vec<u8>> {
+    let mut command = vec![CLA, ins, p1, p2, data.len() as u8];
+    command.extend_from_slice(data);
+
+    let mut resp = transport.exchange(&command).c(d!())?;
+    if resp.len() < 2 {
+        return Err(eg!("invalid response"));
+    }
+    let sw = resp.split_off(resp.len() - 2);
+    match u16::from_be_bytes([sw[0], sw[1]]) {
+        SW_OK => Ok(resp),
+        SW_DENIED => Err(eg!("rejected on the device")),
+        sw => Err(eg!(format!("device error: 0x{sw:04x}"))),
+    }
+}
+
+fn encode_path(path: &BipPath) -> Vec<u8> {
+    let mut bytes = vec![5];
+    for i in path.to_indexes() {
+        bytes.extend_from_slice(&i.to_be_bytes());
+    }
+    bytes
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use tcp::TcpTransport;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod tcp {
+    use {
+        super::ApduTransport,
+        ruc::*,
+        std::{
+            io::{Read, Write},
+            net::TcpStream,
+            sync::Mutex,
+        },
+    };
+
+    /// The APDU port of a speculos-style emulator,
+    /// a command or a response is prefixed with its length in 4 bytes,
+    /// the status word of a response is not counted.
+    pub struct TcpTransport {
+        stream: Mutex<TcpStream>,
+    }
+
+    impl TcpTransport {
+        /// Connect to the emulator, e.g. "127.0.0.1:9999".
+        pub fn connect(addr: &str) -> Result<Self> {
+            TcpStream::connect(addr).c(d!()).map(|s| TcpTransport {
+                stream: Mutex::new(s),
+            })
+        }
+    }
+
+    impl ApduTransport for TcpTransport {
+        fn exchange(&self, command: &[u8]) -> Result<Vec<u8>> {
+            let mut stream = self.stream.lock().map_err(|e| eg!(e))?;
+            stream
+                .write_all(&(command.len() as u32).to_be_bytes())
+                .c(d!())?;
+            stream.write_all(command).c(d!())?;
+
+            let mut len = [0u8; 4];
+            stream.read_exact(&mut len).c(d!())?;
+            let mut resp = vec![0u8; u32::from_be_bytes(len) as usize + 2];
+            stream.read_exact(&mut resp).c(d!())?;
+            Ok(resp)
+        }
+    }
+}
+
+#[cfg(all(feature = "hid", not(target_arch = "wasm32")))]
+pub use hid::HidTransport;
+
+#[cfg(all(feature = "hid", not(target_arch = "wasm32")))]
+mod hid {
+    use {
+        super::ApduTransport,
+        hidapi::{HidApi, HidDevice},
+        ruc::*,
+        std::sync::Mutex,
+    };
+
+    const LEDGER_VID: u16 = 0x2c97;
+    const LEDGER_USAGE_PAGE: u16 = 0xffa0;
+
+    const CHANNEL: u16 = 0x0101;
+    const TAG_APDU: u8 = 0x05;
+    const PACKET_SIZE: usize = 64;
+    // channel, tag and sequence index
+    const HEADER_SIZE: usize = 5;
+
+    /// A Ledger device plugged by USB,
+    /// a command or a response is prefixed with its length in 2 bytes,
+    /// and sent in HID packets of 64 bytes.
+    pub struct HidTransport {
+        device: Mutex<HidDevice>,
+    }
+
+    impl HidTransport {
+        /// Open the first Ledger device found.
+        pub fn open() -> Result<Self> {
+            let api = HidApi::new().c(d!())?;
+            let device = api
+                .device_list()
+                .find(|d| {
+                    d.vendor_id() == LEDGER_VID
+                        && (d.usage_page() == LEDGER_USAGE_PAGE
+                            || d.interface_number() == 0)
+                })
+                .c(d!("no Ledger device found"))?
+                .open_device(&api)
+                .c(d!())?;
+            Ok(HidTransport {
+                device: Mutex::new(device),
+            })
+        }
+    }
+
+    impl ApduTransport for HidTransport {
+        fn exchange(&self, command: &[u8]) -> Result<Vec<u8>> {
+            let device = self.device.lock().map_err(|e| eg!(e))?;
+
+            let mut data = (command.len() as u16).to_be_bytes().to_vec();
+            data.extend_from_slice(command);
+            for (seq, chunk) in data.chunks(PACKET_SIZE - HEADER_SIZE).enumerate() {
+                // the leading 0 is the report id
+                let mut packet = vec![0];
+                packet.extend_from_slice(&CHANNEL.to_be_bytes());
+                packet.push(TAG_APDU);
+                packet.extend_from_slice(&(seq as u16).to_be_bytes());
+                packet.extend_from_slice(chunk);
+                packet.resize(PACKET_SIZE + 1, 0);
+                device.write(&packet).c(d!())?;
+            }
+
+            // blocks until the user approves or rejects on the device
+            let mut resp = vec![];
+            let mut len = 0;
+            for seq in 0u16.. {
+                let mut packet = [0u8; PACKET_SIZE];
+                let n = device.read(&mut packet).c(d!())?;
+                if n < HEADER_SIZE + 2
+                    || packet[..2] != CHANNEL.to_be_bytes()
+                    || packet[2] != TAG_APDU
+                    || packet[3..5] != seq.to_be_bytes()
+                {
+                    return Err(eg!("invalid HID packet"));
+                }
+                let mut body = &packet[HEADER_SIZE..n];
+                if 0 == seq {
+                    len = u16::from_be_bytes([body[0], body[1]]) as usize;
+                    body = &body[2..];
+                }
+                resp.extend_from_slice(body);
+                if resp.len() >= len {
+                    break;
+                }
+            }
+            resp.truncate(len);
+            Ok(resp)
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(missing_docs)]
+mod tests {
+    use {
+        super::*,
+        crate::wallet::{
+            generate_mnemonic_default, restore_keypair_from_mnemonic_bip44,
+            restore_keypair_from_mnemonic_default,
+        },
+        std::cell::RefCell,
+    };
+
+    // a device holding the key of a mnemonic
+    struct MockTransport {
+        phrase: String,
+        reject: bool,
+        payload: RefCell<Vec<u8>>,
+        summaries: RefCell<Vec<String>>,
+    }
+
+    impl MockTransport {
+        fn new(phrase: &str, reject: bool) -> Self {
+            MockTransport {
+                phrase: phrase.to_owned(),
+                reject,
+                payload: RefCell::new(vec![]),
+                summaries: RefCell::new(vec![]),
+            }
+        }
+
+        fn derive(&self, path: &[u8]) -> XfrKeyPair {
+            let i = path[1..21]
+                .chunks(4)
+                .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+                .collect::<Vec<_>>();
+            let path = BipPath::new(i[1] & 0x7FFF_FFFF, i[2] & 0x7FFF_FFFF, i[3], i[4]);
+            pnk!(restore_keypair_from_mnemonic_bip44(
+                &self.phrase,
+                "en",
+                &path
+            ))
+        }
+    }
+
+    impl ApduTransport for MockTransport {
+        fn exchange(&self, command: &[u8]) -> Result<Vec<u8>> {
+            assert_eq!(CLA, command[0]);
+            assert_eq!(command.len(), 5 + command[4] as usize);
+            let (ins, p1, p2, data) =
+                (command[1], command[2], command[3], &command[5..]);
+
+            let mut resp = match ins {
+                INS_GET_PUBLIC_KEY => self.derive(data).get_pk().zei_to_bytes(),
+                INS_SIGN => {
+                    let mut payload = self.payload.borrow_mut();
+                    if P1_FIRST == p1 {
+                        payload.clear();
+                    }
+                    payload.extend_from_slice(data);
+                    if P2_MORE == p2 {
+                        vec![]
+                    } else if self.reject {
+                        return Ok(SW_DENIED.to_be_bytes().to_vec());
+                    } else {
+                        let kp = self.derive(&payload[..21]);
+                        let len =
+                            u16::from_be_bytes([payload[21], payload[22]]) as usize;
+                        let summary = &payload[23..23 + len];
+                        self.summaries
+                            .borrow_mut()
+                            .push(String::from_utf8(summary.to_vec()).unwrap());
+                        let msg = &payload[23 + len..];
+                        kp.get_sk_ref().sign(msg, kp.get_pk_ref()).zei_to_bytes()
+                    }
+                }
+                _ => return Ok(0x6D00u16.to_be_bytes().to_vec()),
+            };
+            resp.extend_from_slice(&SW_OK.to_be_bytes());
+            Ok(resp)
+        }
+    }
+
+    #[test]
+    fn test_apdu_signer() {
+        let phrase = generate_mnemonic_default();
+        let kp = pnk!(restore_keypair_from_mnemonic_default(&phrase));
+        let signer = pnk!(ApduSigner::new(
+            MockTransport::new(&phrase, false),
+            BipPath::new(917, 0, 0, 0)
+        ));
+        assert_eq!(kp.get_pk(), pnk!(signer.public_key()));
+        pnk!(signer.confirm_address());
+
+        // long enough to be sent in several commands
+        let msg = (0..1000).map(|i| i as u8).collect::<Vec<_>>();
+        let sig = pnk!(signer.sign_msg(&msg, "transfer 1 FRA"));
+        assert_eq!(sig, pnk!(kp.sign_msg(&msg, "")));
+        assert_eq!(
+            vec!["transfer 1 FRA".to_owned()],
+            *signer.transport.summaries.borrow()
+        );
+
+        let other = pnk!(ApduSigner::new(
+            MockTransport::new(&phrase, false),
+            BipPath::new(917, 1, 0, 0)
+        ));
+        assert_ne!(kp.get_pk(), pnk!(other.public_key()));
+    }
+
+    #[test]
+    fn test_apdu_signer_rejected() {
+        let signer = pnk!(ApduSigner::new(
+            MockTransport::new(&generate_mnemonic_default(), true),
+            BipPath::new(917, 0, 0, 0)
+        ));
+        assert!(signer.sign_msg(b"msg", "").is_err());
+    }
+}
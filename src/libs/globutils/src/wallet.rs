@@ -71,6 +71,7 @@ macro_rules! restore_keypair_from_mnemonic {
 }
 
 /// Use this struct to express a Bip44/Bip49 path.
+#[derive(Clone, Debug)]
 pub struct BipPath {
     coin: u32,
     account: u32,
@@ -89,6 +90,20 @@ impl BipPath {
             address,
         }
     }
+
+    /// The indexes of "m/44'/coin'/account'/change/address",
+    /// the hardened ones are offset by `2^31`.
+    #[inline(always)]
+    pub fn to_indexes(&self) -> [u32; 5] {
+        const H: u32 = 0x8000_0000;
+        [
+            44 | H,
+            self.coin | H,
+            self.account | H,
+            self.change,
+            self.address,
+        ]
+    }
 }
 
 /// Restore the XfrKeyPair from a mnemonic with a default bip44-path,